@@ -0,0 +1,222 @@
+use std::{error::Error, path::Path};
+
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{GenericArgument, Lit, Meta, NestedMeta, PathArguments, Type};
+use synstructure::Structure;
+use welkin::{Parameter, SerializableData, SerializableVariant};
+use welkin_binding_lib::{check_canonical, welkin_core::term::Term, AbsolutePath, CheckError};
+
+use crate::{adt::unraw, bind::load_defs};
+
+fn reference(name: &str) -> Term<AbsolutePath> {
+    Term::Reference(AbsolutePath(vec![name.to_owned()]))
+}
+
+fn apply(function: Term<AbsolutePath>, arguments: Vec<Term<AbsolutePath>>) -> Term<AbsolutePath> {
+    arguments
+        .into_iter()
+        .fold(function, |function, argument| Term::Apply {
+            function: Box::new(function),
+            argument: Box::new(argument),
+            erased: true,
+        })
+}
+
+/// Builds the welkin type a field of the given Rust type binds, naming type parameters
+/// positionally as bindings do and mapping std types to the canonical data they convert to.
+fn field_term(ty: &Type, params: &[String], this: &str) -> Result<Term<AbsolutePath>, String> {
+    match ty {
+        Type::Paren(ty) => field_term(&ty.elem, params, this),
+        Type::Group(ty) => field_term(&ty.elem, params, this),
+        Type::Tuple(tuple) if tuple.elems.is_empty() => Ok(reference("Unit")),
+        Type::Tuple(tuple) if tuple.elems.len() == 2 => Ok(apply(
+            reference("Pair"),
+            tuple
+                .elems
+                .iter()
+                .map(|ty| field_term(ty, params, this))
+                .collect::<Result<_, _>>()?,
+        )),
+        Type::Path(path) if path.qself.is_none() => {
+            let segment = path.path.segments.last().unwrap();
+            let name = unraw(&segment.ident);
+
+            if path.path.segments.len() == 1 {
+                if let Some(idx) = params.iter().position(|param| param == &name) {
+                    return Ok(reference(&format!("T{}", idx)));
+                }
+            }
+
+            let mut arguments = vec![];
+
+            if let PathArguments::AngleBracketed(args) = &segment.arguments {
+                for arg in &args.args {
+                    if let GenericArgument::Type(ty) = arg {
+                        arguments.push(ty);
+                    }
+                }
+            }
+
+            Ok(match name.as_str() {
+                "Box" if arguments.len() == 1 => field_term(arguments[0], params, this)?,
+                "Self" => apply(
+                    reference(this),
+                    (0..params.len())
+                        .map(|idx| reference(&format!("T{}", idx)))
+                        .collect(),
+                ),
+                "bool" => reference("Bool"),
+                "char" => reference("Char"),
+                "u8" | "u16" | "u32" | "u64" | "usize" => reference("Size"),
+                // the width of a word is an index, which bindings erase
+                "Word" => reference("Word"),
+                name => apply(
+                    reference(match name {
+                        "Option" => "Maybe",
+                        "Result" => "Either",
+                        "Vec" => "Vector",
+                        name => name,
+                    }),
+                    arguments
+                        .into_iter()
+                        .map(|ty| field_term(ty, params, this))
+                        .collect::<Result<_, _>>()?,
+                ),
+            })
+        }
+        _ => Err(format!(
+            "cannot check field of type `{}` against welkin source",
+            quote!(#ty)
+        )),
+    }
+}
+
+fn check_path(structure: &Structure) -> Result<Option<String>, Box<dyn Error>> {
+    let mut path = None;
+
+    for attr in &structure.ast().attrs {
+        if !attr.path.is_ident("welkin") {
+            continue;
+        }
+
+        if let Meta::List(list) = attr.parse_meta()? {
+            for item in list.nested {
+                match item {
+                    NestedMeta::Meta(Meta::NameValue(item)) if item.path.is_ident("check") => {
+                        if let Lit::Str(lit) = item.lit {
+                            path = Some(lit.value());
+                            continue;
+                        }
+                        Err("expected a string path in `check`")?;
+                    }
+                    _ => Err("unknown argument in `welkin` attribute")?,
+                }
+            }
+        } else {
+            Err("expected `#[welkin(check = \"...\")]`")?;
+        }
+    }
+
+    Ok(path)
+}
+
+/// Checks the type against the data of the same name in welkin source when it is annotated with
+/// `#[welkin(check = "path")]`, failing the derive on any mismatch. The path is relative to
+/// `root`, and the files read are only included when `track` is set.
+pub fn derive(
+    structure: &Structure,
+    root: &Path,
+    track: bool,
+) -> Result<TokenStream, Box<dyn Error>> {
+    let path = match check_path(structure)? {
+        Some(path) => path,
+        None => return Ok(quote!()),
+    };
+
+    let (defs, mut tracked) = load_defs(root, &path)?;
+
+    if !track {
+        tracked.clear();
+    }
+
+    let ident = unraw(&structure.ast().ident);
+
+    let params: Vec<_> = structure
+        .ast()
+        .generics
+        .type_params()
+        .map(|param| unraw(&param.ident))
+        .collect();
+
+    let mut variants = vec![];
+    let mut named = vec![];
+
+    for variant in structure.variants() {
+        let mut inhabitants = vec![];
+
+        for (idx, binding) in variant.bindings().iter().enumerate() {
+            let field = match &binding.ast().ident {
+                Some(field) => {
+                    named.push((unraw(&variant.ast().ident), idx));
+                    unraw(field)
+                }
+                None => format!("{}", idx),
+            };
+
+            inhabitants.push((field, field_term(&binding.ast().ty, &params, &ident)?));
+        }
+
+        variants.push((
+            unraw(&variant.ast().ident),
+            SerializableVariant {
+                inhabitants,
+                indices: vec![],
+            },
+        ));
+    }
+
+    let data = SerializableData {
+        variants,
+        ident,
+        type_arguments: params.len(),
+        indices: 0,
+        parameters: vec![Parameter::Type; params.len()],
+    };
+
+    check_canonical(&data, &defs)?;
+
+    // the canonical check is structural, so the names of named fields are compared here; tuple
+    // fields have no names to compare
+    let welkin = defs.iter().find(|def| def.ident == data.ident).unwrap();
+
+    for (variant, idx) in named {
+        let (got, _) = &data
+            .variants
+            .iter()
+            .find(|(name, _)| name == &variant)
+            .unwrap()
+            .1
+            .inhabitants[idx];
+        let (expected, _) = &welkin
+            .variants
+            .iter()
+            .find(|(name, _)| name == &variant)
+            .unwrap()
+            .1
+            .inhabitants[idx];
+
+        if got != expected {
+            Err(CheckError::FieldName {
+                data: data.ident.clone(),
+                variant,
+                expected: expected.clone(),
+                got: got.clone(),
+            })?;
+        }
+    }
+
+    Ok(quote! {
+        #(const _: &[u8] = include_bytes!(#tracked);)*
+    })
+}
@@ -5,7 +5,7 @@ use quote::{format_ident, quote};
 use syn::{parse_quote, ItemEnum};
 use synstructure::{AddBounds, Structure};
 
-use crate::adt::is_inductive;
+use crate::adt::{is_inductive, unraw};
 
 pub fn derive(structure: &Structure) -> (TokenStream, Ident, Vec<Ident>) {
     let vis = &structure.ast().vis;
@@ -39,6 +39,8 @@ pub fn derive(structure: &Structure) -> (TokenStream, Ident, Vec<Ident>) {
     }
 
     let mut error_variant_idents = vec![];
+    let mut params = vec![];
+    let mut diagnose_arms = quote!();
 
     for (idx, ((binding_idx, binding), variant)) in structure
         .variants()
@@ -85,6 +87,25 @@ pub fn derive(structure: &Structure) -> (TokenStream, Ident, Vec<Ident>) {
             )
         };
 
+        let variant_name = unraw(&variant.ast().ident);
+        let field_name = binding
+            .ast()
+            .ident
+            .as_ref()
+            .map(unraw)
+            .unwrap_or_else(|| format!("{}", binding_idx));
+        let ty = &binding.ast().ty;
+        let ty_name = quote!(#ty).to_string().replace(' ', "");
+
+        diagnose_arms = quote! {
+            #diagnose_arms
+            #from_welkin_error_ident::#variant_ident(error) => error.diagnose().within(
+                Segment::Field { variant: #variant_name, field: #field_name },
+                #ty_name,
+            ),
+        };
+
+        params.push(ident.clone());
         error_variant_idents.push(variant_ident.clone());
 
         from_welkin_error.variants.push(parse_quote! {
@@ -119,12 +140,30 @@ pub fn derive(structure: &Structure) -> (TokenStream, Ident, Vec<Ident>) {
     from_welkin_error.variants.push(parse_quote! {
         InvalidVariant {
             got: usize,
-            expected_at_most: usize
+            expected_at_most: usize,
+            term: Term<::std::string::String>
         }
     });
     from_welkin_error.variants.push(parse_quote! {
-        InsufficientFields
+        InsufficientFields(Term<::std::string::String>)
     });
+    from_welkin_error.variants.push(parse_quote! {
+        IndexMismatch {
+            variant: &'static str,
+            field: &'static str,
+            got: usize,
+            term: Term<::std::string::String>
+        }
+    });
+    from_welkin_error.variants.push(parse_quote! {
+        Inductive {
+            variant: &'static str,
+            field: &'static str,
+            error: ::std::boxed::Box<Self>
+        }
+    });
+
+    let ident_name = unraw(ident);
 
     let display_impl = error_structure.gen_impl(quote! {
         use ::std::fmt::{self, Formatter, Display};
@@ -139,12 +178,18 @@ pub fn derive(structure: &Structure) -> (TokenStream, Ident, Vec<Ident>) {
                     #from_welkin_error_ident::ExpectedApplyOrVariable(got) => {
                         write!(f, "expected apply or variable, got {:?}", got)?;
                     }
-                    #from_welkin_error_ident::InvalidVariant { got, expected_at_most } => {
+                    #from_welkin_error_ident::InvalidVariant { got, expected_at_most, .. } => {
                         write!(f, "invalid variant {}, expected at most {}", got, expected_at_most)?;
                     }
-                    #from_welkin_error_ident::InsufficientFields => {
+                    #from_welkin_error_ident::InsufficientFields(_) => {
                         write!(f, "insufficient fields present in term")?;
                     }
+                    #from_welkin_error_ident::IndexMismatch { variant, field, got, .. } => {
+                        write!(f, "field \"{}.{}\" of type \"{}\" holds variant {}, which the indices of its type rule out", variant, field, #ident_name, got)?;
+                    }
+                    #from_welkin_error_ident::Inductive { variant, field, error } => {
+                        write!(f, "error reading field \"{}.{}\" of type \"{}\": {}", variant, field, #ident_name, error)?;
+                    }
                 }
 
                 Ok(())
@@ -152,10 +197,47 @@ pub fn derive(structure: &Structure) -> (TokenStream, Ident, Vec<Ident>) {
         }
     });
 
+    let diagnose_impl = quote! {
+        const _: () = {
+            extern crate welkin_binding;
+            use welkin_binding::{Diagnose, DecodeError, Segment};
+
+            impl<#(#params: Diagnose),*> Diagnose for #from_welkin_error_ident<#(#params),*> {
+                fn diagnose(&self) -> DecodeError {
+                    match self {
+                        #diagnose_arms
+                        #from_welkin_error_ident::ExpectedLambda(term) => {
+                            DecodeError::new("expected lambda").with_term(term).expecting(#ident_name)
+                        }
+                        #from_welkin_error_ident::ExpectedApplyOrVariable(term) => {
+                            DecodeError::new("expected apply or variable").with_term(term).expecting(#ident_name)
+                        }
+                        #from_welkin_error_ident::InvalidVariant { got, expected_at_most, term } => {
+                            DecodeError::new(format!("invalid variant {}, expected at most {}", got, expected_at_most)).with_term(term).expecting(#ident_name)
+                        }
+                        #from_welkin_error_ident::InsufficientFields(term) => {
+                            DecodeError::new("insufficient fields present in term").with_term(term).expecting(#ident_name)
+                        }
+                        #from_welkin_error_ident::IndexMismatch { variant, field, got, term } => {
+                            DecodeError::new(format!("variant {} is ruled out by the indices of the field's type", got))
+                                .with_term(term)
+                                .within(Segment::Field { variant: *variant, field: *field }, #ident_name)
+                        }
+                        #from_welkin_error_ident::Inductive { variant, field, error } => error.diagnose().within(
+                            Segment::Field { variant: *variant, field: *field },
+                            #ident_name,
+                        ),
+                    }
+                }
+            }
+        };
+    };
+
     (
         quote! {
             #from_welkin_error
             #display_impl
+            #diagnose_impl
         },
         from_welkin_error_ident,
         error_variant_idents,
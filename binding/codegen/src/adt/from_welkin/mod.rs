@@ -1,8 +1,8 @@
 mod error;
 
-use std::collections::VecDeque;
+use std::{collections::VecDeque, error::Error};
 
-use proc_macro2::TokenStream;
+use proc_macro2::{Literal, TokenStream};
 use quote::quote;
 use syn::parse_quote;
 use syn::punctuated::Punctuated;
@@ -11,10 +11,12 @@ use syn::Token;
 use syn::WhereClause;
 use synstructure::Structure;
 
+use crate::adt::field_variants;
 use crate::adt::is_field_inductive;
 use crate::adt::is_inductive;
+use crate::adt::unraw;
 
-pub fn derive(structure: &Structure) -> TokenStream {
+pub fn derive(structure: &Structure) -> Result<TokenStream, Box<dyn Error>> {
     let variant_count = structure.variants().len();
 
     let (from_welkin_error, from_welkin_error_ident, error_variant_idents) =
@@ -51,21 +53,84 @@ pub fn derive(structure: &Structure) -> TokenStream {
             .count();
         let rem_bindings = error_variant_idents.split_off(n_bindings);
 
-        let construct = variant.construct(|field, _| {
-            let mut error_transform = quote! {};
+        let variant_name = unraw(&variant.ast().ident);
 
-            if !is_field_inductive(field) {
+        let allowed = variant
+            .ast()
+            .fields
+            .iter()
+            .map(field_variants)
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let construct = variant.construct(|field, idx| {
+            let field_name = field
+                .ident
+                .as_ref()
+                .map(unraw)
+                .unwrap_or_else(|| format!("{}", idx));
+
+            let error_transform = if is_field_inductive(field) {
+                quote! {
+                    .map_err(|error| #from_welkin_error_ident::Inductive {
+                        variant: #variant_name,
+                        field: #field_name,
+                        error: Box::new(error),
+                    })
+                }
+            } else {
                 let error_variant_ident = error_variant_idents.pop_back().unwrap();
 
-                error_transform = quote! {
+                quote! {
                     .map_err(#from_welkin_error_ident::#error_variant_ident)
-                };
+                }
+            };
+
+            // the arm checks first that the variant has all its fields
+            let mut read = quote! {
+                fields.next().unwrap()
+            };
+
+            // the indices of the field's type can rule out variants its binding could otherwise
+            // hold, which bindings have no index values to check by
+            if let Some(allowed) = &allowed[idx] {
+                let allowed = allowed.iter().copied().map(Literal::usize_unsuffixed);
+
+                read = quote! {{
+                    let field = #read;
+                    if let Some(got) = welkin_binding::variant_position(&field) {
+                        if ![#(#allowed),*].contains(&got) {
+                            return Err(#from_welkin_error_ident::IndexMismatch {
+                                variant: #variant_name,
+                                field: #field_name,
+                                got,
+                                term: field,
+                            });
+                        }
+                    }
+                    field
+                }};
             }
 
             quote! {
-                FromAnalogue::from_analogue(FromWelkin::from_welkin(fields.next().ok_or(#from_welkin_error_ident::InsufficientFields)?)#error_transform?)
+                FromAnalogue::from_analogue(FromWelkin::from_welkin(#read)#error_transform?)
             }
         });
+        let arity = variant.bindings().len();
+
+        let construct = if arity == 0 {
+            construct
+        } else {
+            quote! {
+                if fields.len() < #arity {
+                    return Err(#from_welkin_error_ident::InsufficientFields(
+                        welkin_binding::variant_term(#variant_count, index, fields)
+                    ));
+                }
+                let mut fields = fields.into_iter();
+                #construct
+            }
+        };
+
         from_welkin = quote! {
             #from_welkin
             #idx => {
@@ -76,7 +141,7 @@ pub fn derive(structure: &Structure) -> TokenStream {
         error_variant_idents = rem_bindings;
     }
 
-    structure.gen_impl(quote! {
+    Ok(structure.gen_impl(quote! {
         extern crate welkin_binding;
         use welkin_binding::{welkin_core, FromWelkin, FromAnalogue, Error};
         use welkin_core::term::{Term, Index};
@@ -109,16 +174,17 @@ pub fn derive(structure: &Structure) -> TokenStream {
                     }
                 };
 
-                let mut fields = fields.into_iter().rev();
+                fields.reverse();
 
                 Ok(match index {
                     #from_welkin
                     index => return Err(#from_welkin_error_ident::InvalidVariant {
                         expected_at_most: #variant_count,
-                        got: index
+                        got: index,
+                        term: welkin_binding::variant_term(#variant_count, index, fields)
                     })
                 })
             }
         }
-    })
+    }))
 }
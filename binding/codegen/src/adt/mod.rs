@@ -0,0 +1,102 @@
+use std::{error::Error, path::Path};
+
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{parse_quote, Field, Lit, Meta, NestedMeta};
+use synstructure::{AddBounds, BindStyle, BindingInfo, Structure};
+
+mod analogous;
+mod check;
+mod from_analogue;
+mod from_welkin;
+mod to_analogue;
+mod to_welkin;
+
+mod derive;
+
+use crate::bind::manifest_dir;
+
+/// The name of an identifier as written in welkin, without any raw prefix.
+pub fn unraw(ident: &impl ToString) -> String {
+    let ident = ident.to_string();
+    ident.strip_prefix("r#").map(str::to_owned).unwrap_or(ident)
+}
+
+pub fn is_inductive(binding: &BindingInfo) -> bool {
+    is_field_inductive(binding.ast())
+}
+
+pub fn is_field_inductive(field: &Field) -> bool {
+    field
+        .attrs
+        .iter()
+        .any(|attr| attr.path == parse_quote!(inductive))
+}
+
+/// The declaration positions of the variants a field can hold when its indices rule out others,
+/// as given by `#[variants(..)]`.
+pub fn field_variants(field: &Field) -> Result<Option<Vec<usize>>, Box<dyn Error>> {
+    let attr = match field
+        .attrs
+        .iter()
+        .find(|attr| attr.path == parse_quote!(variants))
+    {
+        Some(attr) => attr,
+        None => return Ok(None),
+    };
+
+    match attr.parse_meta()? {
+        Meta::List(list) => list
+            .nested
+            .iter()
+            .map(|nested| match nested {
+                NestedMeta::Lit(Lit::Int(position)) => Ok(position.base10_parse()?),
+                _ => Err("expected variant positions in `variants` attribute".into()),
+            })
+            .collect::<Result<_, _>>()
+            .map(Some),
+        _ => Err("expected variant positions in `variants` attribute".into()),
+    }
+}
+
+pub fn derive(structure: Structure) -> Result<TokenStream, Box<dyn Error>> {
+    derive_in(structure, Path::new(&manifest_dir()?), true)
+}
+
+/// Derives `Adt` with any source to check against read relative to `root`. When `track` is set,
+/// the files read are included so that cargo rebuilds the impls when they change.
+pub fn derive_in(
+    mut structure: Structure,
+    root: &Path,
+    track: bool,
+) -> Result<TokenStream, Box<dyn Error>> {
+    structure.add_bounds(AddBounds::None);
+    structure.bind_with(|_| BindStyle::Move);
+
+    let check_impl = check::derive(&structure, root, track)?;
+
+    let analogous_impl = analogous::derive(&structure);
+
+    let to_analogue_impl = to_analogue::derive(&structure);
+    let from_analogue_impl = from_analogue::derive(&structure);
+
+    let to_welkin_impl = to_welkin::derive(&structure);
+    let from_welkin_impl = from_welkin::derive(&structure)?;
+
+    let adt_impl = derive::derive(&structure);
+
+    Ok(quote! {
+        #check_impl
+
+        #analogous_impl
+
+        #to_analogue_impl
+        #from_analogue_impl
+
+        #to_welkin_impl
+        #from_welkin_impl
+
+        #adt_impl
+
+    })
+}
@@ -1,8 +1,12 @@
-use std::{collections::HashMap, path::PathBuf};
+use std::{
+    cell::RefCell,
+    collections::{HashMap, HashSet},
+    path::{Path, PathBuf},
+};
 
 use anyhow::{anyhow, Error};
 
-use proc_macro2::TokenStream;
+use proc_macro2::{Literal, TokenStream};
 use quote::{format_ident, quote};
 use regex::Regex;
 use syn::{
@@ -10,11 +14,12 @@ use syn::{
     Item, ItemStruct, LitInt, LitStr, Token, Variant,
 };
 
+use welkin::{mentioned_types, source::load};
 use welkin_binding_lib::{
     deserialize_defs, welkin_core::term::Term, AbsolutePath, SerializableData,
 };
 
-fn error(err: Error) -> TokenStream {
+pub(crate) fn error(err: Error) -> TokenStream {
     let err = format!("{}", err);
 
     quote! {
@@ -26,15 +31,15 @@ fn error(err: Error) -> TokenStream {
 }
 
 pub fn bind(mod_declaration: TokenStream) -> TokenStream {
-    match bind_inner(mod_declaration) {
+    match manifest_dir().and_then(|root| bind_inner(mod_declaration, Path::new(&root), true)) {
         Ok(data) => data,
         Err(err) => error(err),
     }
 }
 
-struct PathArg {
+pub(crate) struct PathArg {
     _eq_token: Token![=],
-    path: LitStr,
+    pub(crate) path: LitStr,
 }
 
 impl Parse for PathArg {
@@ -67,8 +72,13 @@ struct IdentList {
 
 #[derive(Debug)]
 enum OverrideData {
-    Override { indices: usize },
-    Wrapper { ident: String },
+    Override {
+        indices: Option<usize>,
+        params: usize,
+    },
+    Wrapper {
+        ident: String,
+    },
 }
 
 impl Parse for IdentList {
@@ -81,7 +91,62 @@ impl Parse for IdentList {
     }
 }
 
-fn bind_inner(mod_declaration: TokenStream) -> Result<TokenStream, Error> {
+/// The root of the crate being expanded, which paths in attributes are relative to.
+pub(crate) fn manifest_dir() -> Result<String, Error> {
+    Ok(std::env::var("CARGO_MANIFEST_DIR")?)
+}
+
+type Loaded = (Vec<SerializableData>, Vec<String>);
+
+thread_local! {
+    // a proc macro process expands every derive and bind! of a crate, many of them reading the
+    // same source, which doesn't change while the crate is compiled
+    static LOADED: RefCell<HashMap<PathBuf, Loaded>> = RefCell::new(HashMap::new());
+}
+
+/// Reads data declarations from a path relative to `root`, returning them along with the files
+/// read. Each path is read once per process.
+pub(crate) fn load_defs(root: &Path, path: &str) -> Result<Loaded, Error> {
+    let path = root.join(path);
+
+    if let Some(loaded) = LOADED.with(|loaded| loaded.borrow().get(&path).cloned()) {
+        return Ok(loaded);
+    }
+
+    // a directory is read as welkin source, and anything else as exported definitions
+    let (defs, tracked) = if path.is_dir() {
+        let library = load(&path, &HashSet::new()).map_err(|e| anyhow!(e))?;
+        (library.data_declarations, library.files)
+    } else {
+        (
+            deserialize_defs(&std::fs::read(&path)?)?,
+            vec![path.clone()],
+        )
+    };
+
+    // proc macros can't emit rerun-if-changed, so every file read is included to have cargo
+    // rebuild the bindings when it changes
+    let tracked = tracked
+        .iter()
+        .map(|path| path.to_string_lossy().into_owned())
+        .collect();
+
+    LOADED.with(|loaded| {
+        loaded
+            .borrow_mut()
+            .insert(path, (defs.clone(), tracked.clone()));
+    });
+
+    Ok((defs, tracked))
+}
+
+/// Expands bind declarations with source paths relative to `root`. When `track` is set, the files
+/// read are included so that cargo rebuilds the bindings when they change.
+pub(crate) fn bind_inner(
+    mod_declaration: TokenStream,
+    root: &Path,
+    track: bool,
+) -> Result<TokenStream, Error> {
     let items: File = parse2(mod_declaration)?;
 
     if !items.shebang.is_none() {
@@ -162,14 +227,14 @@ fn bind_inner(mod_declaration: TokenStream) -> Result<TokenStream, Error> {
             {
                 let attr: PathArg = parse2(attr.tokens.clone())?;
 
-                let manifest_dir = std::env::var("CARGO_MANIFEST_DIR")?;
+                let (mut defs, mut tracked) = load_defs(root, &attr.path.value())?;
 
-                let path = PathBuf::from(manifest_dir);
-                let path = path.join(attr.path.value());
-
-                let data = std::fs::read(path)?;
+                if !track {
+                    tracked.clear();
+                }
 
-                let mut defs = deserialize_defs(&data)?;
+                // parameter information is needed for every source type, including those not bound
+                let all = defs.clone();
 
                 let mut includes = vec![];
 
@@ -194,29 +259,31 @@ fn bind_inner(mod_declaration: TokenStream) -> Result<TokenStream, Error> {
                 }
 
                 for (name, en) in &override_enums {
-                    let mut indices = 0;
+                    let mut indices = None;
                     if let Some(attr) = en
                         .attrs
                         .iter()
                         .find(|attr| attr.path == parse_quote!(indices))
                     {
                         let attr: IndicesArg = parse2(attr.tokens.clone())?;
-                        indices = attr.num.base10_digits().parse()?;
+                        indices = Some(attr.num.base10_digits().parse()?);
                     }
-                    override_data.insert(name.clone(), OverrideData::Override { indices });
+                    let params = en.generics.type_params().count();
+                    override_data.insert(name.clone(), OverrideData::Override { indices, params });
                 }
 
                 for (name, ty) in &override_types {
-                    let mut indices = 0;
+                    let mut indices = None;
                     if let Some(attr) = ty
                         .attrs
                         .iter()
                         .find(|attr| attr.path == parse_quote!(indices))
                     {
                         let attr: IndicesArg = parse2(attr.tokens.clone())?;
-                        indices = attr.num.base10_digits().parse()?;
+                        indices = Some(attr.num.base10_digits().parse()?);
                     }
-                    override_data.insert(name.clone(), OverrideData::Override { indices });
+                    let params = ty.generics.type_params().count();
+                    override_data.insert(name.clone(), OverrideData::Override { indices, params });
 
                     match &*ty.ty {
                         syn::Type::Path(path) => {
@@ -285,7 +352,7 @@ fn bind_inner(mod_declaration: TokenStream) -> Result<TokenStream, Error> {
                     defs.retain(|def| includes.contains(&def.ident));
                 }
 
-                let defs = generate_defs(&defs, &override_data)?;
+                let defs = generate_defs(&defs, &all, &override_data)?;
 
                 defs_stream = quote! {
                     #defs_stream
@@ -293,6 +360,8 @@ fn bind_inner(mod_declaration: TokenStream) -> Result<TokenStream, Error> {
                     #vis mod #ident {
                         extern crate welkin_binding;
 
+                        #(const _: &[u8] = include_bytes!(#tracked);)*
+
                         use welkin_binding::{Adt, AbsolutePath, welkin_core::term::Term};
 
                         #override_stream
@@ -315,6 +384,7 @@ fn bind_inner(mod_declaration: TokenStream) -> Result<TokenStream, Error> {
 
 fn generate_defs(
     defs: &[SerializableData],
+    all: &[SerializableData],
     overrides: &HashMap<String, OverrideData>,
 ) -> Result<TokenStream, Error> {
     let mut defs_stream = quote!();
@@ -332,6 +402,11 @@ fn generate_defs(
 
         let mut variants: Punctuated<Variant, Token![,]> = parse_quote!();
 
+        // type arguments that no bound field is built from, such as those only wrapped fields use
+        let mut unused: Vec<_> = (0..def.type_arguments)
+            .map(|arg| format!("T{}", arg))
+            .collect();
+
         for (ident, variant) in &def.variants {
             let v_ident = &ident;
             let ident = format_ident!("r#{}", ident);
@@ -353,7 +428,11 @@ fn generate_defs(
                     continue;
                 }
 
-                let (mut ty, is_inductive) = term_to_ty(field, defs, overrides, &def.ident)?;
+                let mut used = HashSet::new();
+                mentioned_types(field, &mut used);
+                unused.retain(|arg| !used.contains(arg));
+
+                let (mut ty, is_inductive) = term_to_ty(field, defs, all, overrides, &def.ident)?;
 
                 if is_inductive {
                     ty = quote!(Box<#ty>);
@@ -365,8 +444,17 @@ fn generate_defs(
                     quote!()
                 };
 
+                let variants_attr = match allowed_variants(field, all, overrides) {
+                    Some(allowed) => {
+                        let allowed = allowed.into_iter().map(Literal::usize_unsuffixed);
+                        quote!(#[variants(#(#allowed),*)])
+                    }
+                    None => quote!(),
+                };
+
                 fields.push(quote! {
                     #inductive_attr
+                    #variants_attr
                     #ident: #ty
                 });
             }
@@ -384,6 +472,17 @@ fn generate_defs(
             }
         }
 
+        // parameters no field is built from are erased from the data, so one that only wrapped
+        // fields are built from would be left unused by the binding
+        if let Some(arg) = unused.first() {
+            let arg: u8 = arg[1..].parse()?;
+            return Err(anyhow!(
+                "type parameter {} of {} is only used by wrapped fields",
+                ('A' as u8 + arg) as char,
+                def.ident
+            ));
+        }
+
         defs_stream = quote! {
             #defs_stream
 
@@ -398,34 +497,97 @@ fn generate_defs(
     Ok(defs_stream)
 }
 
+/// Splits an application into its head and arguments, whether they are erased or not.
+fn spine(term: &Term<AbsolutePath>) -> (&Term<AbsolutePath>, Vec<&Term<AbsolutePath>>) {
+    let mut head = term;
+    let mut arguments = vec![];
+
+    while let Term::Apply {
+        function, argument, ..
+    } = head
+    {
+        arguments.push(&**argument);
+        head = &**function;
+    }
+
+    arguments.reverse();
+
+    (head, arguments)
+}
+
+/// The data and variant a path names, if it names a variant of bound data.
+fn constructor<'a>(path: &'a AbsolutePath, all: &[SerializableData]) -> Option<(&'a str, &'a str)> {
+    let (data, variant) = match path.0.as_slice() {
+        [.., data, variant] => (data, variant),
+        _ => return None,
+    };
+
+    all.iter()
+        .find(|def| &def.ident == data)?
+        .variants
+        .iter()
+        .find(|(ident, _)| ident == variant)?;
+
+    Some((data.as_str(), variant.as_str()))
+}
+
+/// The declaration positions of the variants a field of the given type can hold, where the
+/// indices its type is applied to are headed by constructors that rule out some variants.
+fn allowed_variants(
+    term: &Term<AbsolutePath>,
+    all: &[SerializableData],
+    overrides: &HashMap<String, OverrideData>,
+) -> Option<Vec<usize>> {
+    let (head, arguments) = spine(term);
+
+    let data = match head {
+        Term::Reference(path) if path.0.len() == 1 && !overrides.contains_key(&path.0[0]) => {
+            all.iter().find(|def| def.ident == path.0[0])?
+        }
+        _ => return None,
+    };
+
+    let indices = arguments.get(data.parameters.len()..)?;
+
+    let allowed: Vec<_> = data
+        .variants
+        .iter()
+        .enumerate()
+        .filter(|(_, (_, variant))| {
+            indices.iter().zip(&variant.indices).all(|(index, head)| {
+                let index = match spine(index).0 {
+                    Term::Reference(path) => constructor(path, all),
+                    _ => None,
+                };
+
+                match (index, head.as_ref().and_then(|head| constructor(head, all))) {
+                    (Some(index), Some(head)) => index == head,
+                    _ => true,
+                }
+            })
+        })
+        .map(|(position, _)| position)
+        .collect();
+
+    if allowed.len() < data.variants.len() {
+        Some(allowed)
+    } else {
+        None
+    }
+}
+
 fn term_to_ty(
     term: &Term<AbsolutePath>,
     defs: &[SerializableData],
+    all: &[SerializableData],
     overrides: &HashMap<String, OverrideData>,
     this: &str,
 ) -> Result<(TokenStream, bool), Error> {
     let expr = Regex::new("^T[0-9]*$").unwrap();
 
     Ok(match term {
-        Term::Apply {
-            function,
-            argument,
-            erased: true,
-        } => {
-            let mut arguments = vec![&**argument];
-            let mut function = &**function;
-
-            while let Term::Apply {
-                function: f,
-                argument,
-                erased: true,
-            } = function
-            {
-                function = &**f;
-                arguments.push(&**argument);
-            }
-
-            arguments.reverse();
+        Term::Apply { .. } => {
+            let (function, mut arguments) = spine(term);
 
             let name = if let Term::Reference(path) = function {
                 if let Some(ident) = path.0.first() {
@@ -441,20 +603,38 @@ fn term_to_ty(
                 None
             };
 
-            let (function, _) = term_to_ty(function, defs, overrides, this)?;
+            let (function, _) = term_to_ty(function, defs, all, overrides, this)?;
 
             let mut args: Punctuated<TokenStream, Token![,]> = parse_quote!();
 
-            if let Some(def) = name.and_then(|name| defs.iter().find(|def| &def.ident == name)) {
-                arguments.truncate(arguments.len() - def.indices);
-            } else if let Some(&OverrideData::Override { indices }) =
-                name.and_then(|name| overrides.get(name.as_str()))
+            let data = name.and_then(|name| all.iter().find(|def| &def.ident == name));
+            let override_data = name.and_then(|name| overrides.get(name.as_str()));
+
+            // an explicit index count takes precedence, then the parameters of the source data,
+            // then the arity of the overriding type
+            if let Some(&OverrideData::Override {
+                indices: Some(indices),
+                ..
+            }) = override_data
             {
-                arguments.truncate(arguments.len() - indices);
+                arguments.truncate(arguments.len().saturating_sub(indices));
+            } else if let Some(data) = data {
+                let count = arguments.len();
+                arguments = data.carried_arguments(arguments).ok_or_else(|| {
+                    anyhow!(
+                        "{} is applied to {} arguments, but takes {} parameters and {} indices",
+                        data.ident,
+                        count,
+                        data.parameters.len(),
+                        data.indices
+                    )
+                })?;
+            } else if let Some(&OverrideData::Override { params, .. }) = override_data {
+                arguments.truncate(params);
             }
 
             for argument in arguments {
-                args.push(term_to_ty(argument, defs, overrides, this)?.0);
+                args.push(term_to_ty(argument, defs, all, overrides, this)?.0);
             }
 
             (
@@ -0,0 +1,111 @@
+use std::path::Path;
+
+use anyhow::{anyhow, Error};
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{
+    parse2, parse_quote, punctuated::Punctuated, Attribute, DeriveInput, File, Item, Meta,
+    NestedMeta, Token,
+};
+use synstructure::Structure;
+
+use crate::{adt, bind::bind_inner};
+
+/// Removes `Adt` from the `derive` attributes, returning whether it was present.
+fn take_adt_derive(attrs: &mut Vec<Attribute>) -> Result<bool, Error> {
+    let mut found = false;
+    let mut retained = vec![];
+
+    for mut attr in attrs.drain(..) {
+        if attr.path.is_ident("derive") {
+            if let Meta::List(list) = attr.parse_meta()? {
+                let derives: Punctuated<NestedMeta, Token![,]> = list
+                    .nested
+                    .into_iter()
+                    .filter(|item| match item {
+                        NestedMeta::Meta(Meta::Path(path)) if path.is_ident("Adt") => {
+                            found = true;
+                            false
+                        }
+                        _ => true,
+                    })
+                    .collect();
+
+                if derives.is_empty() {
+                    continue;
+                }
+
+                attr = parse_quote!(#[derive(#derives)]);
+            }
+        }
+
+        retained.push(attr);
+    }
+
+    *attrs = retained;
+
+    Ok(found)
+}
+
+/// Replaces `#[derive(Adt)]` on the items with the impls it expands to, stripping the helper
+/// attributes the derive reads. Sources checked against are read relative to `root`.
+fn expand_derives(items: &mut Vec<Item>, root: &Path) -> Result<(), Error> {
+    let mut expanded = vec![];
+
+    for mut item in items.drain(..) {
+        let mut derived = None;
+
+        match &mut item {
+            Item::Mod(module) => {
+                if let Some((_, items)) = &mut module.content {
+                    expand_derives(items, root)?;
+                }
+            }
+            Item::Enum(data) => {
+                if take_adt_derive(&mut data.attrs)? {
+                    let input: DeriveInput = parse2(quote!(#data))?;
+                    derived = Some(
+                        adt::derive_in(Structure::new(&input), root, false)
+                            .map_err(|e| anyhow!("{}", e))?,
+                    );
+
+                    data.attrs.retain(|attr| {
+                        !attr.path.is_ident("inductive") && !attr.path.is_ident("welkin")
+                    });
+                    for variant in &mut data.variants {
+                        for field in &mut variant.fields {
+                            field.attrs.retain(|attr| {
+                                !attr.path.is_ident("inductive") && !attr.path.is_ident("variants")
+                            });
+                        }
+                    }
+                }
+            }
+            _ => {}
+        }
+
+        expanded.push(item);
+
+        if let Some(derived) = derived {
+            let derived: File = parse2(derived)?;
+            expanded.extend(derived.items);
+        }
+    }
+
+    *items = expanded;
+
+    Ok(())
+}
+
+/// Expands bind declarations, as given to `bind!`, into a standalone source file with the `Adt`
+/// derives expanded in place. Source paths are relative to `root`.
+pub fn bindgen(declarations: TokenStream, root: &Path) -> Result<String, Error> {
+    let mut file: File = parse2(bind_inner(declarations, root, false)?)?;
+
+    expand_derives(&mut file.items, root)?;
+
+    Ok(format!(
+        "// generated by `welkin bindgen`, do not edit\n\n{}",
+        prettyplease::unparse(&file)
+    ))
+}
@@ -0,0 +1,229 @@
+use std::{collections::HashSet, path::Path};
+
+use anyhow::{anyhow, Error};
+
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use syn::{
+    parse::{Parse, ParseStream},
+    parse2, parse_quote, Attribute, FnArg, ForeignItemFn, Pat, ReturnType,
+};
+use welkin::source::load;
+use welkin_binding_lib::{welkin_core::term::Term, AbsolutePath};
+
+use crate::bind::{error, manifest_dir, PathArg};
+
+struct Functions {
+    attrs: Vec<Attribute>,
+    items: Vec<ForeignItemFn>,
+}
+
+impl Parse for Functions {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let attrs = input.call(Attribute::parse_inner)?;
+        let mut items = vec![];
+
+        while !input.is_empty() {
+            items.push(input.parse()?);
+        }
+
+        Ok(Functions { attrs, items })
+    }
+}
+
+/// Whether a type classifies types, such as `*` or `Size |-> *`.
+fn is_kind(term: &Term<AbsolutePath>) -> bool {
+    match term {
+        Term::Universe => true,
+        Term::Function { return_type, .. } => is_kind(return_type),
+        _ => false,
+    }
+}
+
+/// How each parameter of a definition of the given type is applied: whether it is erased, and
+/// whether it is a type, which Rust callers don't supply.
+fn parameters(mut ty: &Term<AbsolutePath>) -> Vec<(bool, bool)> {
+    let mut parameters = vec![];
+
+    while let Term::Function {
+        argument_type,
+        return_type,
+        erased,
+    } = ty
+    {
+        parameters.push((*erased, *erased && is_kind(argument_type)));
+        ty = return_type;
+    }
+
+    parameters
+}
+
+pub fn welkin_fn(declarations: TokenStream) -> TokenStream {
+    match manifest_dir().and_then(|root| welkin_fn_in(declarations, Path::new(&root))) {
+        Ok(data) => data,
+        Err(err) => error(err),
+    }
+}
+
+/// Expands `welkin_fn!` declarations with a source path relative to `root`.
+pub fn welkin_fn_in(declarations: TokenStream, root: &Path) -> Result<TokenStream, Error> {
+    let functions: Functions = parse2(declarations)?;
+
+    // the types of the definitions say which arguments are erased
+    let path = match functions
+        .attrs
+        .iter()
+        .find(|attr| attr.path == parse_quote!(path))
+    {
+        Some(attr) => parse2::<PathArg>(attr.tokens.clone())?.path.value(),
+        None => Err(anyhow!(
+            "welkin_fn! needs a `#![path = \"...\"]` naming the welkin source of its definitions"
+        ))?,
+    };
+
+    let library = load(root.join(path), &HashSet::new()).map_err(|e| anyhow!(e))?;
+
+    // as with bind!, the files read are included so cargo rebuilds the functions when they change
+    let tracked: Vec<_> = library
+        .files
+        .iter()
+        .map(|path| path.to_string_lossy().into_owned())
+        .collect();
+
+    let mut stream = quote! {
+        #(const _: &[u8] = include_bytes!(#tracked);)*
+    };
+
+    for mut item in functions.items {
+        let sig = &item.sig;
+        let ident = &sig.ident;
+
+        if !sig.generics.params.is_empty() {
+            Err(anyhow!("welkin function \"{}\" cannot be generic", ident))?;
+        }
+
+        if sig.asyncness.is_some() || sig.unsafety.is_some() || sig.variadic.is_some() {
+            Err(anyhow!(
+                "welkin function \"{}\" should be a plain fn declaration",
+                ident
+            ))?;
+        }
+
+        // the definition defaults to the name of the function
+        let definition = match item
+            .attrs
+            .iter()
+            .position(|attr| attr.path == parse_quote!(definition))
+        {
+            Some(position) => {
+                let attr = item.attrs.remove(position);
+                let attr: PathArg = parse2(attr.tokens)?;
+                attr.path.value()
+            }
+            None => format!("{}", ident),
+        };
+
+        let mut arguments = vec![];
+        let mut types = vec![];
+
+        for input in &sig.inputs {
+            match input {
+                FnArg::Typed(input) => match &*input.pat {
+                    Pat::Ident(pat) => {
+                        arguments.push(pat.ident.clone());
+                        types.push(input.ty.clone());
+                    }
+                    _ => Err(anyhow!(
+                        "arguments of welkin function \"{}\" should be plain identifiers",
+                        ident
+                    ))?,
+                },
+                FnArg::Receiver(_) => {
+                    Err(anyhow!("welkin function \"{}\" cannot take self", ident))?
+                }
+            }
+        }
+
+        let ty = library
+            .declarations
+            .iter()
+            .find(|(path, _, _)| format!("{:?}", path) == definition)
+            .map(|(_, ty, _)| ty)
+            .ok_or_else(|| anyhow!("unknown definition \"{}\"", definition))?;
+
+        let parameters = parameters(ty);
+        let supplied = parameters.iter().filter(|(_, is_type)| !is_type).count();
+
+        if supplied != arguments.len() {
+            Err(anyhow!(
+                "welkin function \"{}\" takes {} arguments, but \"{}\" takes {}",
+                ident,
+                arguments.len(),
+                definition,
+                supplied
+            ))?;
+        }
+
+        let mut supplied = arguments.iter();
+        let mut applications = vec![];
+
+        for (erased, is_type) in parameters {
+            // type arguments are erased, so any term stands in for them
+            applications.push(if is_type {
+                quote! {
+                    let term = ::welkin_binding::welkin_core::term::Term::Apply {
+                        erased: true,
+                        function: ::std::boxed::Box::new(term),
+                        argument: ::std::boxed::Box::new(
+                            ::welkin_binding::welkin_core::term::Term::Universe
+                        ),
+                    };
+                }
+            } else {
+                let argument = supplied.next().unwrap();
+                let mut name = format!("{}", argument);
+                if name.starts_with("r#") {
+                    name = name.chars().skip(2).collect();
+                }
+
+                quote! {
+                    let term = ::welkin_binding::apply_argument(
+                        #definition, term, #name, #argument, #erased
+                    )?;
+                }
+            });
+        }
+
+        let output = match &sig.output {
+            ReturnType::Default => quote!(()),
+            ReturnType::Type(_, ty) => quote!(#ty),
+        };
+
+        let evaluator = format_ident!("__Evaluator");
+        let attrs = &item.attrs;
+        let vis = &item.vis;
+
+        stream = quote! {
+            #stream
+
+            #(#attrs)*
+            #vis fn #ident<#evaluator: ::welkin_binding::Evaluator>(
+                evaluator: &#evaluator,
+                #(#arguments: #types),*
+            ) -> ::std::result::Result<#output, ::welkin_binding::CallError<#evaluator::Error>>
+            where
+                #evaluator::Error: ::std::fmt::Debug
+            {
+                let term = ::welkin_binding::welkin_core::term::Term::Reference(
+                    ::std::string::String::from(#definition)
+                );
+
+                #(#applications)*
+
+                ::welkin_binding::evaluate_call(evaluator, #definition, term)
+            }
+        };
+    }
+
+    Ok(stream)
+}
@@ -0,0 +1,11 @@
+//! Code generation for welkin bindings, shared by the proc macros and `welkin bindgen`.
+
+mod adt;
+mod bind;
+mod bindgen;
+mod function;
+
+pub use adt::{derive as derive_adt, derive_in as derive_adt_in};
+pub use bind::bind;
+pub use bindgen::bindgen;
+pub use function::{welkin_fn, welkin_fn_in};
@@ -0,0 +1,48 @@
+use std::{path::PathBuf, process::exit};
+
+use proc_macro2::TokenStream;
+
+fn usage() -> ! {
+    eprintln!("USAGE:\nwelkin-bindgen <INPUT> [--root <DIR>] [--output <PATH>]");
+    exit(1)
+}
+
+fn main() {
+    let mut args = std::env::args().skip(1);
+
+    let input = args.next().unwrap_or_else(|| usage());
+
+    // paths in declarations are relative to the crate root, which is usually where this is run
+    let mut root = PathBuf::from(".");
+    let mut output = None;
+
+    while let Some(a) = args.next() {
+        match a.as_str() {
+            "--root" => root = PathBuf::from(args.next().unwrap_or_else(|| usage())),
+            "--output" => output = Some(args.next().unwrap_or_else(|| usage())),
+            _ => usage(),
+        }
+    }
+
+    let declarations: TokenStream = std::fs::read_to_string(&input)
+        .unwrap_or_else(|e| {
+            eprintln!("could not read {}: {}", input, e);
+            exit(1)
+        })
+        .parse()
+        .unwrap_or_else(|e| {
+            eprintln!("could not parse {}: {:?}", input, e);
+            exit(1)
+        });
+
+    let generated = welkin_binding_codegen::bindgen(declarations, &root).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        exit(1)
+    });
+
+    if let Some(output) = output {
+        std::fs::write(output, generated).unwrap();
+    } else {
+        print!("{}", generated);
+    }
+}
@@ -0,0 +1,145 @@
+use std::fs;
+
+use welkin_binding_codegen::bindgen;
+
+mod common;
+
+const SIZE: (&str, &str) = ("Size", "~data Size { zero, succ(n: Size) }");
+
+const VECTOR: (&str, &str) = (
+    "Vector",
+    "~data Vector A ~with { _: Size } {
+        nil ~with { Size::zero },
+        cons[size: Size](head: A, tail: Vector[A, size]) ~with { Size::succ(size) }
+    }",
+);
+
+fn generate(test: &str, files: &[(&str, &str)], module: &str) -> Result<String, String> {
+    let root = common::source(test, files);
+    let generated = bindgen(module.parse().unwrap(), &root).map_err(|e| format!("{}", e));
+    fs::remove_dir_all(&root).unwrap();
+    generated
+}
+
+/// The generated source without whitespace, so that assertions don't depend on its layout.
+fn compact(test: &str, files: &[(&str, &str)], include: &str) -> String {
+    generate(
+        test,
+        files,
+        &format!("#[path = \"src\"] #[include({})] pub mod w {{}}", include),
+    )
+    .unwrap()
+    .chars()
+    .filter(|c| !c.is_whitespace())
+    .collect()
+}
+
+#[test]
+fn binds_indexed_data_by_its_parameters() {
+    let generated = compact("vector", &[SIZE, VECTOR], "Size, Vector");
+
+    assert!(generated.contains("pubenumVector<A>{"));
+    assert!(generated.contains("r#head:A"));
+    assert!(generated.contains("r#tail:Box<r#Vector<A>>"));
+    assert!(!generated.contains("__Phantom"));
+}
+
+#[test]
+fn binds_type_families_as_type_parameters() {
+    let generated = compact(
+        "sized",
+        &[
+            SIZE,
+            (
+                "Sized",
+                "~data Sized [A: Size |-> *] { new(size: Size, data: A[size]) }",
+            ),
+        ],
+        "Size, Sized",
+    );
+
+    assert!(generated.contains("pubenumSized<A>{"));
+    assert!(generated.contains("r#size:r#Size"));
+    assert!(generated.contains("r#data:A"));
+}
+
+#[test]
+fn drops_parameters_no_field_uses() {
+    let generated = compact(
+        "equal",
+        &[(
+            "Equal",
+            "~data Equal A [a: A] ~with { _: A } { refl ~with { a } }",
+        )],
+        "Equal",
+    );
+
+    assert!(generated.contains("pubenumEqual"));
+    assert!(!generated.contains("pubenumEqual<A"));
+    assert!(generated.contains("r#refl"));
+    assert!(!generated.contains("__Phantom"));
+}
+
+#[test]
+fn binds_data_with_value_parameters() {
+    let generated = compact(
+        "value",
+        &[
+            SIZE,
+            ("Tagged", "~data Tagged (tag: Size) A { new(value: A) }"),
+        ],
+        "Size, Tagged",
+    );
+
+    assert!(generated.contains("pubenumTagged<A>{"));
+    assert!(generated.contains("r#value:A"));
+}
+
+#[test]
+fn checks_index_arguments_when_reading() {
+    let generated = compact(
+        "indices",
+        &[
+            SIZE,
+            VECTOR,
+            (
+                "NonEmpty",
+                "~data NonEmpty A { new[n: Size](items: Vector[A, Size::succ(n)]) }",
+            ),
+        ],
+        "Size, Vector, NonEmpty",
+    );
+
+    // only `cons`, the second variant of `Vector`, is constructed with a successor index
+    assert!(generated.contains("r#items:r#Vector<A>"));
+    assert!(generated.contains("if![1].contains(&got)"));
+    assert!(generated.contains("NonEmptyFromWelkinError::IndexMismatch{variant:\"new\""));
+
+    // `Vector` fields whose index is a variable can hold either variant
+    assert!(!generated.contains("VectorFromWelkinError::IndexMismatch{variant:"));
+}
+
+#[test]
+fn rejects_parameters_only_wrapped_fields_use() {
+    let error = generate(
+        "wrapped",
+        &[("Boxed", "~data Boxed A { new(value: A) }")],
+        "#[path = \"src\"] pub mod w { #[wrapper = \"Boxed::new.value\"] struct Value; }",
+    )
+    .unwrap_err();
+
+    assert_eq!(
+        error,
+        "type parameter A of Boxed is only used by wrapped fields"
+    );
+}
+
+#[test]
+fn reports_the_terms_of_unreadable_variants() {
+    let generated = compact("unreadable", &[SIZE], "Size");
+
+    assert!(
+        generated.contains("InsufficientFields(welkin_binding::variant_term(2usize,index,fields))")
+    );
+    assert!(generated.contains("term:welkin_binding::variant_term(2usize,index,fields)"));
+}
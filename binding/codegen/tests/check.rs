@@ -0,0 +1,163 @@
+use std::{error::Error, fs, path::Path};
+
+use syn::{parse_quote, DeriveInput};
+use synstructure::Structure;
+use welkin_binding_codegen::derive_adt_in;
+use welkin_binding_lib::CheckError;
+
+mod common;
+
+const FLAG: (&str, &str) = ("Flag", "~data Flag { on, off }");
+
+const WRAPPED: (&str, &str) = ("Wrapped", "~data Wrapped { new(flag: Flag) }");
+
+fn derive(root: &Path, item: &DeriveInput, track: bool) -> Result<String, Box<dyn Error>> {
+    derive_adt_in(Structure::new(item), root, track).map(|derived| derived.to_string())
+}
+
+/// The error deriving `Adt` for `item` fails with when checked against `Flag` and `Wrapped`.
+fn check_error(test: &str, item: DeriveInput) -> CheckError {
+    let root = common::source(test, &[FLAG, WRAPPED]);
+    let error = derive(&root, &item, false).unwrap_err();
+    fs::remove_dir_all(&root).unwrap();
+
+    *error.downcast::<CheckError>().unwrap()
+}
+
+#[test]
+fn derives_types_matching_welkin_source() {
+    let root = common::source("matching", &[FLAG, WRAPPED]);
+
+    let flag: DeriveInput = parse_quote! {
+        #[welkin(check = "src")]
+        enum Flag { on, off }
+    };
+    let wrapped: DeriveInput = parse_quote! {
+        #[welkin(check = "src")]
+        enum Wrapped { new { flag: Flag } }
+    };
+    // tuple fields have no names to compare
+    let tuple: DeriveInput = parse_quote! {
+        #[welkin(check = "src")]
+        enum Wrapped { new(Flag) }
+    };
+
+    assert!(derive(&root, &flag, true)
+        .unwrap()
+        .contains("include_bytes"));
+    assert!(!derive(&root, &wrapped, false)
+        .unwrap()
+        .contains("include_bytes"));
+    assert!(derive(&root, &tuple, false).is_ok());
+
+    fs::remove_dir_all(&root).unwrap();
+}
+
+#[test]
+fn reads_each_source_once() {
+    let root = common::source("cached", &[FLAG]);
+    let flag: DeriveInput = parse_quote! {
+        #[welkin(check = "src")]
+        enum Flag { on, off }
+    };
+
+    let derived = derive(&root, &flag, true).unwrap();
+    fs::remove_dir_all(&root).unwrap();
+
+    // later derives are checked against the source as it was first read
+    assert_eq!(derive(&root, &flag, true).unwrap(), derived);
+}
+
+#[test]
+fn rejects_types_missing_in_welkin_source() {
+    let error = check_error(
+        "missing",
+        parse_quote! {
+            #[welkin(check = "src")]
+            enum Other { on, off }
+        },
+    );
+
+    assert!(matches!(error, CheckError::Missing(path) if path.0 == vec!["Other"]));
+}
+
+#[test]
+fn rejects_types_with_other_type_arguments() {
+    let error = check_error(
+        "arguments",
+        parse_quote! {
+            #[welkin(check = "src")]
+            enum Flag<T> { on, off }
+        },
+    );
+
+    assert!(matches!(
+        error,
+        CheckError::TypeArguments {
+            expected: 0,
+            got: 1,
+            ..
+        }
+    ));
+}
+
+#[test]
+fn rejects_types_with_other_variants() {
+    let error = check_error(
+        "unbound",
+        parse_quote! {
+            #[welkin(check = "src")]
+            enum Flag { on }
+        },
+    );
+    assert!(matches!(error, CheckError::UnboundVariant { variant, .. } if variant == "off"));
+
+    let error = check_error(
+        "variant",
+        parse_quote! {
+            #[welkin(check = "src")]
+            enum Flag { on, off, unset }
+        },
+    );
+    assert!(matches!(error, CheckError::MissingVariant { variant, .. } if variant == "unset"));
+}
+
+#[test]
+fn rejects_fields_that_do_not_match() {
+    let error = check_error(
+        "count",
+        parse_quote! {
+            #[welkin(check = "src")]
+            enum Wrapped { new { flag: Flag, other: Flag } }
+        },
+    );
+    assert!(matches!(
+        error,
+        CheckError::FieldCount {
+            expected: 1,
+            got: 2,
+            ..
+        }
+    ));
+
+    let error = check_error(
+        "field",
+        parse_quote! {
+            #[welkin(check = "src")]
+            enum Wrapped { new { flag: bool } }
+        },
+    );
+    assert!(matches!(error, CheckError::FieldMismatch { field, .. } if field == "flag"));
+
+    let error = check_error(
+        "name",
+        parse_quote! {
+            #[welkin(check = "src")]
+            enum Wrapped { new { value: Flag } }
+        },
+    );
+    assert!(matches!(
+        error,
+        CheckError::FieldName { expected, got, .. } if expected == "flag" && got == "value"
+    ));
+}
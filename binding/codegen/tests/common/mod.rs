@@ -0,0 +1,15 @@
+use std::{fs, path::PathBuf};
+
+/// Writes the files as welkin source under `src` in a fresh directory named for the test,
+/// returning that directory.
+pub fn source(test: &str, files: &[(&str, &str)]) -> PathBuf {
+    let root = std::env::temp_dir().join(format!("welkin-codegen-{}-{}", test, std::process::id()));
+    let _ = fs::remove_dir_all(&root);
+    fs::create_dir_all(root.join("src")).unwrap();
+
+    for (name, data) in files {
+        fs::write(root.join("src").join(format!("{}.w", name)), data).unwrap();
+    }
+
+    root
+}
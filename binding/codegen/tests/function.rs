@@ -0,0 +1,158 @@
+use std::{collections::HashSet, path::Path};
+
+use proc_macro2::TokenStream;
+use quote::quote;
+use welkin::source::load;
+use welkin_binding_codegen::welkin_fn_in;
+
+/// The root of the repository, whose core library the functions call into.
+fn root() -> &'static Path {
+    Path::new(concat!(env!("CARGO_MANIFEST_DIR"), "/../.."))
+}
+
+fn expand(declarations: TokenStream) -> Result<String, String> {
+    welkin_fn_in(
+        quote! {
+            #![path = ".welkin/core"]
+            #declarations
+        },
+        root(),
+    )
+    .map(|expanded| expanded.to_string())
+    .map_err(|e| format!("{}", e))
+}
+
+/// Asserts that `declarations` expand to `functions`, after the includes of the library's files.
+fn assert_expands(declarations: TokenStream, functions: TokenStream) {
+    let tracked: Vec<_> = load(root().join(".welkin/core"), &HashSet::new())
+        .unwrap()
+        .files
+        .iter()
+        .map(|path| path.to_string_lossy().into_owned())
+        .collect();
+
+    let expected = quote! {
+        #(const _: &[u8] = include_bytes!(#tracked);)*
+        #functions
+    };
+
+    assert_eq!(expand(declarations).unwrap(), expected.to_string());
+}
+
+#[test]
+fn applies_definitions_to_their_arguments() {
+    assert_expands(
+        quote! {
+            #[inline]
+            #[definition = "Bool::not"]
+            pub fn not(value: bool) -> bool;
+        },
+        quote! {
+            #[inline]
+            pub fn not<__Evaluator: ::welkin_binding::Evaluator>(
+                evaluator: &__Evaluator,
+                value: bool
+            ) -> ::std::result::Result<bool, ::welkin_binding::CallError<__Evaluator::Error>>
+            where
+                __Evaluator::Error: ::std::fmt::Debug
+            {
+                let term = ::welkin_binding::welkin_core::term::Term::Reference(
+                    ::std::string::String::from("Bool::not")
+                );
+
+                let term = ::welkin_binding::apply_argument(
+                    "Bool::not", term, "value", value, false
+                )?;
+
+                ::welkin_binding::evaluate_call(evaluator, "Bool::not", term)
+            }
+        },
+    );
+}
+
+#[test]
+fn applies_erased_type_parameters_implicitly() {
+    assert_expands(
+        quote! {
+            #[definition = "Maybe::map"]
+            fn map(call: fn(u8) -> u8, maybe: Option<u8>) -> Option<u8>;
+        },
+        quote! {
+            fn map<__Evaluator: ::welkin_binding::Evaluator>(
+                evaluator: &__Evaluator,
+                call: fn(u8) -> u8,
+                maybe: Option<u8>
+            ) -> ::std::result::Result<Option<u8>, ::welkin_binding::CallError<__Evaluator::Error>>
+            where
+                __Evaluator::Error: ::std::fmt::Debug
+            {
+                let term = ::welkin_binding::welkin_core::term::Term::Reference(
+                    ::std::string::String::from("Maybe::map")
+                );
+
+                let term = ::welkin_binding::welkin_core::term::Term::Apply {
+                    erased: true,
+                    function: ::std::boxed::Box::new(term),
+                    argument: ::std::boxed::Box::new(
+                        ::welkin_binding::welkin_core::term::Term::Universe
+                    ),
+                };
+                let term = ::welkin_binding::welkin_core::term::Term::Apply {
+                    erased: true,
+                    function: ::std::boxed::Box::new(term),
+                    argument: ::std::boxed::Box::new(
+                        ::welkin_binding::welkin_core::term::Term::Universe
+                    ),
+                };
+                let term = ::welkin_binding::apply_argument(
+                    "Maybe::map", term, "call", call, false
+                )?;
+                let term = ::welkin_binding::apply_argument(
+                    "Maybe::map", term, "maybe", maybe, false
+                )?;
+
+                ::welkin_binding::evaluate_call(evaluator, "Maybe::map", term)
+            }
+        },
+    );
+}
+
+#[test]
+fn rejects_declarations_that_do_not_match_their_definition() {
+    assert_eq!(
+        expand(quote!(
+            fn not(value: bool) -> bool;
+        ))
+        .unwrap_err(),
+        "unknown definition \"not\""
+    );
+    assert_eq!(
+        expand(quote! {
+            #[definition = "Bool::not"]
+            fn not(value: bool, other: bool) -> bool;
+        })
+        .unwrap_err(),
+        "welkin function \"not\" takes 2 arguments, but \"Bool::not\" takes 1"
+    );
+    assert_eq!(
+        expand(quote! {
+            #[definition = "Bool::not"]
+            fn not<T>(value: T) -> bool;
+        })
+        .unwrap_err(),
+        "welkin function \"not\" cannot be generic"
+    );
+}
+
+#[test]
+fn needs_the_path_of_the_source() {
+    let error = welkin_fn_in(
+        quote!(
+            fn not(value: bool) -> bool;
+        ),
+        root(),
+    )
+    .unwrap_err();
+
+    assert!(format!("{}", error).starts_with("welkin_fn! needs a `#![path = \"...\"]`"));
+}
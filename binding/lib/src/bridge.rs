@@ -0,0 +1,431 @@
+//! Reading and writing welkin values with serde, guided by the data declarations they belong to.
+//!
+//! Data with several variants maps to an externally tagged enum: a variant without fields is its
+//! name, and any other variant is a map from its name to a map of its fields. Data with a single
+//! variant is the map of its fields, or unit if it has none. `String`, `Size`, `Char` and `Bool`
+//! map to native scalars and `Vector` to a sequence.
+
+use std::{
+    collections::HashMap,
+    convert::Infallible,
+    fmt::{self, Formatter},
+};
+
+use serde::{
+    de::{self, DeserializeSeed, IgnoredAny, MapAccess, SeqAccess, Visitor},
+    ser::{self, SerializeMap, SerializeSeq},
+    Deserialize, Deserializer, Serialize, Serializer,
+};
+use welkin::{parser::AbsolutePath, SerializableData};
+use welkin_core::term::Term;
+
+use crate::{
+    conversions::{construct, read_variant},
+    FromWelkin, ToWelkin,
+};
+
+/// The data declarations values are read and written against.
+pub struct Schema<'a> {
+    definitions: &'a [SerializableData],
+}
+
+enum Shape<'a> {
+    String,
+    Size,
+    Char,
+    Bool,
+    Vector(Term<AbsolutePath>),
+    Data {
+        data: &'a SerializableData,
+        arguments: Vec<Term<AbsolutePath>>,
+    },
+}
+
+/// Replaces the positional `T{n}` type arguments of a field's type.
+fn substitute(ty: &Term<AbsolutePath>, arguments: &[Term<AbsolutePath>]) -> Term<AbsolutePath> {
+    ty.clone().map_reference(|reference| {
+        let argument = match reference.0.as_slice() {
+            [name] => name
+                .strip_prefix('T')
+                .and_then(|idx| idx.parse::<usize>().ok())
+                .and_then(|idx| arguments.get(idx).cloned()),
+            _ => None,
+        };
+
+        argument.unwrap_or(Term::Reference(reference))
+    })
+}
+
+fn read_elements(mut term: Term<String>) -> Result<Vec<Term<String>>, String> {
+    let mut elements = vec![];
+
+    loop {
+        let (variant, fields) = read_variant::<Infallible>(term, &[0, 2])
+            .map_err(|e| format!("invalid vector: {}", e))?;
+
+        if variant == 0 {
+            break Ok(elements);
+        }
+
+        // `read_variant` has checked that `cons` has both its fields
+        let mut fields = fields.into_iter();
+        elements.push(fields.next().unwrap());
+        term = fields.next().unwrap();
+    }
+}
+
+fn infallible(term: Result<Term<String>, Infallible>) -> Term<String> {
+    match term {
+        Ok(term) => term,
+        Err(e) => match e {},
+    }
+}
+
+fn write_elements(elements: Vec<Term<String>>) -> Term<String> {
+    let mut term = construct(2, 0, vec![]);
+
+    for element in elements.into_iter().rev() {
+        term = construct(2, 1, vec![element, term]);
+    }
+
+    term
+}
+
+impl<'a> Schema<'a> {
+    pub fn new(definitions: &'a [SerializableData]) -> Self {
+        Schema { definitions }
+    }
+
+    fn shape(&self, ty: &Term<AbsolutePath>) -> Result<Shape<'a>, String> {
+        let mut head = ty;
+        let mut arguments = vec![];
+
+        while let Term::Apply {
+            function, argument, ..
+        } = head
+        {
+            arguments.push((**argument).clone());
+            head = &**function;
+        }
+
+        arguments.reverse();
+
+        let name = match head {
+            Term::Reference(path) if path.0.len() == 1 => path.0[0].as_str(),
+            _ => return Err(format!("unsupported type {:?}", ty)),
+        };
+
+        Ok(match name {
+            "String" => Shape::String,
+            "Size" => Shape::Size,
+            "Char" => Shape::Char,
+            "Bool" => Shape::Bool,
+            "Vector" => Shape::Vector(
+                arguments
+                    .into_iter()
+                    .next()
+                    .ok_or_else(|| "missing element type of Vector".to_owned())?,
+            ),
+            name => {
+                let data = self
+                    .definitions
+                    .iter()
+                    .find(|data| data.ident == name)
+                    .ok_or_else(|| format!("unknown type {}", name))?;
+
+                Shape::Data {
+                    data,
+                    arguments: data.carried_arguments(arguments).ok_or_else(|| {
+                        format!("{} is applied to the wrong number of arguments", name)
+                    })?,
+                }
+            }
+        })
+    }
+
+    /// Wraps a value of type `ty` for serialization.
+    pub fn serialize<'b>(
+        &'b self,
+        ty: Term<AbsolutePath>,
+        term: &'b Term<String>,
+    ) -> SerializeTerm<'b> {
+        SerializeTerm {
+            schema: self,
+            ty,
+            term,
+        }
+    }
+
+    /// A seed deserializing a value of type `ty`.
+    pub fn seed(&self, ty: Term<AbsolutePath>) -> TermSeed<'_> {
+        TermSeed { schema: self, ty }
+    }
+}
+
+pub struct SerializeTerm<'a> {
+    schema: &'a Schema<'a>,
+    ty: Term<AbsolutePath>,
+    term: &'a Term<String>,
+}
+
+struct SerializeFields<'a> {
+    schema: &'a Schema<'a>,
+    arguments: &'a [Term<AbsolutePath>],
+    inhabitants: &'a [(String, Term<AbsolutePath>)],
+    values: &'a [Term<String>],
+}
+
+impl Serialize for SerializeFields<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(self.inhabitants.len()))?;
+
+        for ((name, ty), value) in self.inhabitants.iter().zip(self.values) {
+            map.serialize_entry(
+                name,
+                &self.schema.serialize(substitute(ty, self.arguments), value),
+            )?;
+        }
+
+        map.end()
+    }
+}
+
+impl Serialize for SerializeTerm<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let term = self.term.clone();
+
+        match self.schema.shape(&self.ty).map_err(ser::Error::custom)? {
+            Shape::String => {
+                serializer.serialize_str(&String::from_welkin(term).map_err(ser::Error::custom)?)
+            }
+            Shape::Size => {
+                serializer.serialize_u64(u64::from_welkin(term).map_err(ser::Error::custom)?)
+            }
+            Shape::Char => {
+                serializer.serialize_char(char::from_welkin(term).map_err(ser::Error::custom)?)
+            }
+            Shape::Bool => {
+                serializer.serialize_bool(bool::from_welkin(term).map_err(ser::Error::custom)?)
+            }
+            Shape::Vector(element) => {
+                let elements = read_elements(term).map_err(ser::Error::custom)?;
+                let mut seq = serializer.serialize_seq(Some(elements.len()))?;
+
+                for value in &elements {
+                    seq.serialize_element(&self.schema.serialize(element.clone(), value))?;
+                }
+
+                seq.end()
+            }
+            Shape::Data { data, arguments } => {
+                let arities: Vec<_> = data
+                    .variants
+                    .iter()
+                    .map(|(_, variant)| variant.inhabitants.len())
+                    .collect();
+                let (variant, values) =
+                    read_variant::<Infallible>(term, &arities).map_err(ser::Error::custom)?;
+                let (name, variant) = &data.variants[variant];
+
+                let fields = SerializeFields {
+                    schema: self.schema,
+                    arguments: &arguments,
+                    inhabitants: &variant.inhabitants,
+                    values: &values,
+                };
+
+                if data.variants.len() == 1 {
+                    if variant.inhabitants.is_empty() {
+                        serializer.serialize_unit()
+                    } else {
+                        fields.serialize(serializer)
+                    }
+                } else if variant.inhabitants.is_empty() {
+                    serializer.serialize_str(name)
+                } else {
+                    let mut map = serializer.serialize_map(Some(1))?;
+                    map.serialize_entry(name, &fields)?;
+                    map.end()
+                }
+            }
+        }
+    }
+}
+
+pub struct TermSeed<'a> {
+    schema: &'a Schema<'a>,
+    ty: Term<AbsolutePath>,
+}
+
+impl<'de> DeserializeSeed<'de> for TermSeed<'_> {
+    type Value = Term<String>;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        match self.schema.shape(&self.ty).map_err(de::Error::custom)? {
+            Shape::String => Ok(infallible(String::deserialize(deserializer)?.to_welkin())),
+            Shape::Size => u64::deserialize(deserializer)?
+                .to_welkin()
+                .map_err(de::Error::custom),
+            Shape::Char => Ok(infallible(char::deserialize(deserializer)?.to_welkin())),
+            Shape::Bool => Ok(infallible(bool::deserialize(deserializer)?.to_welkin())),
+            Shape::Vector(element) => deserializer.deserialize_seq(ElementsVisitor {
+                schema: self.schema,
+                element,
+            }),
+            Shape::Data { data, arguments } => {
+                if data.variants.len() == 1 {
+                    let (_, variant) = &data.variants[0];
+
+                    if variant.inhabitants.is_empty() {
+                        <()>::deserialize(deserializer)?;
+                        Ok(construct(1, 0, vec![]))
+                    } else {
+                        FieldsSeed {
+                            schema: self.schema,
+                            arguments: &arguments,
+                            inhabitants: &variant.inhabitants,
+                            variants: 1,
+                            variant: 0,
+                        }
+                        .deserialize(deserializer)
+                    }
+                } else {
+                    deserializer.deserialize_any(VariantVisitor {
+                        schema: self.schema,
+                        data,
+                        arguments: &arguments,
+                    })
+                }
+            }
+        }
+    }
+}
+
+struct ElementsVisitor<'a> {
+    schema: &'a Schema<'a>,
+    element: Term<AbsolutePath>,
+}
+
+impl<'de> Visitor<'de> for ElementsVisitor<'_> {
+    type Value = Term<String>;
+
+    fn expecting(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "a sequence")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let mut elements = vec![];
+
+        while let Some(element) = seq.next_element_seed(self.schema.seed(self.element.clone()))? {
+            elements.push(element);
+        }
+
+        Ok(write_elements(elements))
+    }
+}
+
+struct FieldsSeed<'a> {
+    schema: &'a Schema<'a>,
+    arguments: &'a [Term<AbsolutePath>],
+    inhabitants: &'a [(String, Term<AbsolutePath>)],
+    variants: usize,
+    variant: usize,
+}
+
+impl<'de> DeserializeSeed<'de> for FieldsSeed<'_> {
+    type Value = Term<String>;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_map(self)
+    }
+}
+
+impl<'de> Visitor<'de> for FieldsSeed<'_> {
+    type Value = Term<String>;
+
+    fn expecting(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "a map of fields")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+        let mut values = HashMap::new();
+
+        while let Some(name) = map.next_key::<String>()? {
+            let ty = self
+                .inhabitants
+                .iter()
+                .find(|(field, _)| field == &name)
+                .map(|(_, ty)| substitute(ty, self.arguments))
+                .ok_or_else(|| de::Error::custom(format!("unknown field {}", name)))?;
+
+            values.insert(name, map.next_value_seed(self.schema.seed(ty))?);
+        }
+
+        let fields = self
+            .inhabitants
+            .iter()
+            .map(|(name, _)| {
+                values
+                    .remove(name)
+                    .ok_or_else(|| de::Error::custom(format!("missing field {}", name)))
+            })
+            .collect::<Result<_, _>>()?;
+
+        Ok(construct(self.variants, self.variant, fields))
+    }
+}
+
+struct VariantVisitor<'a> {
+    schema: &'a Schema<'a>,
+    data: &'a SerializableData,
+    arguments: &'a [Term<AbsolutePath>],
+}
+
+impl VariantVisitor<'_> {
+    fn variant<E: de::Error>(&self, name: &str) -> Result<usize, E> {
+        self.data
+            .variants
+            .iter()
+            .position(|(variant, _)| variant == name)
+            .ok_or_else(|| E::custom(format!("unknown variant {} of {}", name, self.data.ident)))
+    }
+}
+
+impl<'de> Visitor<'de> for VariantVisitor<'_> {
+    type Value = Term<String>;
+
+    fn expecting(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "a variant of {}", self.data.ident)
+    }
+
+    fn visit_str<E: de::Error>(self, name: &str) -> Result<Self::Value, E> {
+        let variant = self.variant(name)?;
+
+        if !self.data.variants[variant].1.inhabitants.is_empty() {
+            return Err(E::custom(format!("variant {} has fields", name)));
+        }
+
+        Ok(construct(self.data.variants.len(), variant, vec![]))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+        let name: String = map
+            .next_key()?
+            .ok_or_else(|| de::Error::custom("expected a variant"))?;
+        let variant = self.variant(&name)?;
+
+        let term = map.next_value_seed(FieldsSeed {
+            schema: self.schema,
+            arguments: self.arguments,
+            inhabitants: &self.data.variants[variant].1.inhabitants,
+            variants: self.data.variants.len(),
+            variant,
+        })?;
+
+        if map.next_key::<IgnoredAny>()?.is_some() {
+            return Err(de::Error::custom("expected a single variant"));
+        }
+
+        Ok(term)
+    }
+}
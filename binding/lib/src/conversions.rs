@@ -0,0 +1,768 @@
+//! Conversions between Rust primitives and std types and the canonical data in `.welkin/core`.
+//!
+//! Unsigned integers convert to and from `Size`; wrap them in [`Word`] to use the fixed-width
+//! `Word` encoding instead. A `Size` is unary, so only integers up to [`MAX_SIZE`] are written as
+//! one.
+
+use std::{
+    convert::{Infallible, TryFrom},
+    mem::size_of,
+};
+
+use thiserror::Error;
+use welkin_core::term::{Index, Term};
+
+use crate::{
+    Adt, AdtDefinition, AdtVariant, Analogous, FromAnalogue, FromWelkin, Segment, ToAnalogue,
+    ToWelkin, Type, Typed,
+};
+
+#[derive(Debug, Error, Clone)]
+pub enum ConversionError<T = Infallible> {
+    #[error("expected lambda, got {0:?}")]
+    ExpectedLambda(Term<String>),
+    #[error("expected apply or variable, got {0:?}")]
+    ExpectedApplyOrVariable(Term<String>),
+    #[error("invalid variant {got}, expected at most {expected_at_most}")]
+    InvalidVariant {
+        got: usize,
+        expected_at_most: usize,
+        term: Term<String>,
+    },
+    #[error("insufficient fields present in term")]
+    InsufficientFields(Term<String>),
+    #[error("value does not fit in {0}")]
+    Overflow(&'static str),
+    #[error("{0} is too large to write as a Size, use a Word instead")]
+    SizeLimit(u64),
+    #[error("expected a word of {expected} bits, got {got}")]
+    WordWidth { expected: usize, got: usize },
+    #[error("invalid unicode scalar value {0:#x}")]
+    InvalidChar(u32),
+    #[error("error reading {segment}: {error}")]
+    Field {
+        segment: Segment,
+        /// The type of the field, as named in Rust.
+        expected: String,
+        error: T,
+    },
+}
+
+/// The name of a type without the module paths in it.
+pub(crate) fn type_name<T>() -> String {
+    let mut name = String::new();
+    let mut path = String::new();
+
+    for c in std::any::type_name::<T>().chars() {
+        if c.is_alphanumeric() || c == '_' || c == ':' {
+            path.push(c);
+        } else {
+            name.push_str(path.rsplit("::").next().unwrap());
+            path.clear();
+            name.push(c);
+        }
+    }
+
+    name.push_str(path.rsplit("::").next().unwrap());
+
+    name
+}
+
+impl<T> ConversionError<T> {
+    /// Wraps the error reading a field of type `F`.
+    fn at_field<F>(variant: &'static str, field: &'static str) -> impl FnOnce(T) -> Self {
+        Self::at::<F>(Segment::Field { variant, field })
+    }
+
+    fn at<F>(segment: Segment) -> impl FnOnce(T) -> Self {
+        move |error| ConversionError::Field {
+            segment,
+            expected: type_name::<F>(),
+            error,
+        }
+    }
+}
+
+/// The error of a conversion involving two independent values, such as the sides of an `Either`
+/// or the halves of a `Pair`.
+#[derive(Debug, Error, Clone)]
+pub enum EitherError<A, B> {
+    #[error("left: {0}")]
+    Left(A),
+    #[error("right: {0}")]
+    Right(B),
+}
+
+/// The largest integer written as a `Size`, which takes one application per unit.
+pub const MAX_SIZE: u64 = 1 << 12;
+
+/// An unsigned integer encoded as a `Word` of exactly its bit width, least significant bit
+/// outermost.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct Word<T>(pub T);
+
+/// Strips the lambdas of a data type whose variants have `arities` fields each and returns the
+/// declaration-order index of the variant along with its fields, of which there are at least as
+/// many as the variant has.
+pub(crate) fn read_variant<E>(
+    mut term: Term<String>,
+    arities: &[usize],
+) -> Result<(usize, Vec<Term<String>>), ConversionError<E>> {
+    let variants = arities.len();
+
+    for _ in 0..variants {
+        if let Term::Lambda { body, .. } = term {
+            term = *body;
+        } else {
+            return Err(ConversionError::ExpectedLambda(term));
+        }
+    }
+
+    let mut fields = vec![];
+
+    let index = loop {
+        match term {
+            Term::Apply {
+                argument, function, ..
+            } => {
+                term = *function;
+                fields.push(*argument);
+            }
+            Term::Variable(index) => break index.0,
+            other => return Err(ConversionError::ExpectedApplyOrVariable(other)),
+        }
+    };
+
+    fields.reverse();
+
+    if index >= variants {
+        return Err(ConversionError::InvalidVariant {
+            expected_at_most: variants,
+            got: index,
+            term: variant_term(variants, index, fields),
+        });
+    }
+
+    let variant = variants - 1 - index;
+
+    if fields.len() < arities[variant] {
+        return Err(ConversionError::InsufficientFields(variant_term(
+            variants, index, fields,
+        )));
+    }
+
+    Ok((variant, fields))
+}
+
+/// The term of data with `variants` variants that applies the variable `index` to `fields`, from
+/// which a term that could not be read is rebuilt to report it.
+#[doc(hidden)]
+pub fn variant_term(variants: usize, index: usize, fields: Vec<Term<String>>) -> Term<String> {
+    let mut term = Term::Variable(Index(index));
+
+    for field in fields {
+        term = Term::Apply {
+            erased: false,
+            function: Box::new(term),
+            argument: Box::new(field),
+        };
+    }
+
+    for _ in 0..variants {
+        term = Term::Lambda {
+            erased: false,
+            body: Box::new(term),
+        };
+    }
+
+    term
+}
+
+pub(crate) fn construct(
+    variants: usize,
+    variant: usize,
+    fields: Vec<Term<String>>,
+) -> Term<String> {
+    variant_term(variants, variants - 1 - variant, fields)
+}
+
+/// Takes the next field of a variant, which `read_variant` has checked is present.
+fn field(fields: &mut Vec<Term<String>>) -> Term<String> {
+    fields.remove(0)
+}
+
+/// Reads the bits of a `Word`, outermost first.
+fn read_word(mut term: Term<String>) -> Result<Vec<bool>, ConversionError> {
+    let mut bits = vec![];
+
+    loop {
+        let (variant, mut fields) = read_variant(term, &[0, 1, 1])?;
+
+        term = match variant {
+            0 => break Ok(bits),
+            1 => {
+                bits.push(false);
+                field(&mut fields)
+            }
+            _ => {
+                bits.push(true);
+                field(&mut fields)
+            }
+        };
+    }
+}
+
+/// Writes a `Word` from its bits, outermost first.
+fn write_word(bits: Vec<bool>) -> Term<String> {
+    let mut term = construct(3, 0, vec![]);
+
+    for bit in bits.into_iter().rev() {
+        term = construct(3, if bit { 2 } else { 1 }, vec![term]);
+    }
+
+    term
+}
+
+fn read_size(mut term: Term<String>) -> Result<u64, ConversionError> {
+    let mut size = 0u64;
+
+    for _ in 0..2 {
+        if let Term::Lambda { body, .. } = term {
+            term = *body;
+        } else {
+            return Err(ConversionError::ExpectedLambda(term));
+        }
+    }
+
+    loop {
+        match term {
+            Term::Apply { argument, .. } => {
+                term = *argument;
+                size = size
+                    .checked_add(1)
+                    .ok_or(ConversionError::Overflow("u64"))?;
+            }
+            Term::Variable(_) => break Ok(size),
+            other => return Err(ConversionError::ExpectedApplyOrVariable(other)),
+        }
+    }
+}
+
+fn write_size(size: u64) -> Term<String> {
+    let mut term = Term::Variable(Index(0));
+
+    for _ in 0..size {
+        term = Term::Apply {
+            erased: false,
+            argument: Box::new(term),
+            function: Box::new(Term::Variable(Index(1))),
+        };
+    }
+
+    for _ in 0..2 {
+        term = Term::Lambda {
+            erased: false,
+            body: Box::new(term),
+        };
+    }
+
+    term
+}
+
+fn read_vector<T: FromWelkin>(mut term: Term<String>) -> Result<Vec<T>, ConversionError<T::Error>> {
+    let mut elements = vec![];
+
+    loop {
+        let (variant, mut fields) = read_variant(term, &[0, 2])?;
+
+        if variant == 0 {
+            break Ok(elements);
+        }
+
+        let index = Segment::Index(elements.len());
+        elements.push(T::from_welkin(field(&mut fields)).map_err(ConversionError::at::<T>(index))?);
+        term = field(&mut fields);
+    }
+}
+
+fn write_vector(elements: Vec<Term<String>>) -> Term<String> {
+    let mut term = construct(2, 0, vec![]);
+
+    for element in elements.into_iter().rev() {
+        term = construct(2, 1, vec![element, term]);
+    }
+
+    term
+}
+
+macro_rules! impl_integer {
+    ($($t:ident),+) => {
+        $(
+        impl FromWelkin for $t {
+            type Error = ConversionError;
+
+            fn from_welkin(term: Term<String>) -> Result<Self, Self::Error> {
+                $t::try_from(read_size(term)?).map_err(|_| ConversionError::Overflow(stringify!($t)))
+            }
+        }
+
+        impl ToWelkin for $t {
+            type Error = ConversionError;
+
+            fn to_welkin(self) -> Result<Term<String>, Self::Error> {
+                let size = self as u64;
+
+                if size > MAX_SIZE {
+                    return Err(ConversionError::SizeLimit(size));
+                }
+
+                Ok(write_size(size))
+            }
+        }
+
+        impl FromWelkin for Word<$t> {
+            type Error = ConversionError;
+
+            fn from_welkin(term: Term<String>) -> Result<Self, Self::Error> {
+                let bits = read_word(term)?;
+                let width = size_of::<$t>() * 8;
+
+                if bits.len() != width {
+                    return Err(ConversionError::WordWidth {
+                        expected: width,
+                        got: bits.len(),
+                    });
+                }
+
+                Ok(Word(
+                    bits.into_iter()
+                        .enumerate()
+                        .filter(|(_, bit)| *bit)
+                        .fold(0, |value, (idx, _)| value | (1 << idx)),
+                ))
+            }
+        }
+
+        impl ToWelkin for Word<$t> {
+            type Error = Infallible;
+
+            fn to_welkin(self) -> Result<Term<String>, Self::Error> {
+                Ok(write_word(
+                    (0..size_of::<$t>() * 8)
+                        .map(|idx| (self.0 >> idx) & 1 == 1)
+                        .collect(),
+                ))
+            }
+        }
+        )+
+
+        impl_analogous!($($t, Word<$t>),+);
+    };
+}
+
+macro_rules! impl_analogous {
+    ($($t:ty),+) => {
+        $(
+        impl Analogous for $t {
+            type Analogue = Self;
+        }
+
+        impl FromAnalogue for $t {
+            type Analogue = Self;
+
+            fn from_analogue(analogue: Self) -> Self {
+                analogue
+            }
+        }
+
+        impl ToAnalogue for $t {
+            type Analogue = Self;
+
+            fn to_analogue(self) -> Self {
+                self
+            }
+        }
+        )+
+    };
+}
+
+impl_integer!(u8, u16, u32, u64, usize);
+
+impl_analogous!(bool, char, String, ());
+
+impl FromWelkin for bool {
+    type Error = ConversionError;
+
+    fn from_welkin(term: Term<String>) -> Result<Self, Self::Error> {
+        Ok(read_variant(term, &[0, 0])?.0 == 0)
+    }
+}
+
+impl ToWelkin for bool {
+    type Error = Infallible;
+
+    fn to_welkin(self) -> Result<Term<String>, Self::Error> {
+        Ok(construct(2, if self { 0 } else { 1 }, vec![]))
+    }
+}
+
+impl Adt for bool {
+    const DEFINITION: AdtDefinition = AdtDefinition {
+        name: "Bool",
+        variants: &[
+            AdtVariant {
+                name: "true",
+                fields: &[],
+            },
+            AdtVariant {
+                name: "false",
+                fields: &[],
+            },
+        ],
+        params: 0,
+    };
+
+    const PARAMS: &'static [Type] = &[];
+}
+
+impl FromWelkin for () {
+    type Error = ConversionError;
+
+    fn from_welkin(term: Term<String>) -> Result<Self, Self::Error> {
+        read_variant(term, &[0]).map(|_| ())
+    }
+}
+
+impl ToWelkin for () {
+    type Error = Infallible;
+
+    fn to_welkin(self) -> Result<Term<String>, Self::Error> {
+        Ok(construct(1, 0, vec![]))
+    }
+}
+
+impl Adt for () {
+    const DEFINITION: AdtDefinition = AdtDefinition {
+        name: "Unit",
+        variants: &[AdtVariant {
+            name: "new",
+            fields: &[],
+        }],
+        params: 0,
+    };
+
+    const PARAMS: &'static [Type] = &[];
+}
+
+// `Char` literals are compiled from the big-endian bytes of the scalar value with the least
+// significant bit of each byte innermost, so read outermost first the bytes are little-endian
+// and each byte is most significant bit first.
+impl FromWelkin for char {
+    type Error = ConversionError;
+
+    fn from_welkin(term: Term<String>) -> Result<Self, Self::Error> {
+        let (_, mut fields) = read_variant(term, &[1])?;
+        let bits = read_word(field(&mut fields))?;
+
+        if bits.len() != 32 {
+            return Err(ConversionError::WordWidth {
+                expected: 32,
+                got: bits.len(),
+            });
+        }
+
+        let mut bytes = [0u8; 4];
+
+        for (byte, bits) in bytes.iter_mut().zip(bits.chunks_exact(8)) {
+            for (idx, bit) in bits.iter().enumerate() {
+                if *bit {
+                    *byte |= 1 << (7 - idx);
+                }
+            }
+        }
+
+        let value = u32::from_le_bytes(bytes);
+
+        char::from_u32(value).ok_or(ConversionError::InvalidChar(value))
+    }
+}
+
+impl ToWelkin for char {
+    type Error = Infallible;
+
+    fn to_welkin(self) -> Result<Term<String>, Self::Error> {
+        let bits = (self as u32)
+            .to_le_bytes()
+            .iter()
+            .flat_map(|byte| (0..8).map(move |idx| (byte >> (7 - idx)) & 1 == 1))
+            .collect();
+
+        Ok(construct(1, 0, vec![write_word(bits)]))
+    }
+}
+
+impl FromWelkin for String {
+    type Error = ConversionError<ConversionError>;
+
+    fn from_welkin(term: Term<String>) -> Result<Self, Self::Error> {
+        let (_, mut fields) = read_variant(term, &[1])?;
+
+        Ok(read_vector::<char>(field(&mut fields))?
+            .into_iter()
+            .collect())
+    }
+}
+
+impl ToWelkin for String {
+    type Error = Infallible;
+
+    fn to_welkin(self) -> Result<Term<String>, Self::Error> {
+        let characters = self
+            .chars()
+            .map(ToWelkin::to_welkin)
+            .collect::<Result<_, _>>()?;
+
+        Ok(construct(1, 0, vec![write_vector(characters)]))
+    }
+}
+
+impl<T> Analogous for Vec<T> {
+    type Analogue = Self;
+}
+
+impl<T: FromWelkin> FromAnalogue for Vec<T> {
+    type Analogue = Self;
+
+    fn from_analogue(analogue: Self) -> Self {
+        analogue
+    }
+}
+
+impl<T: ToWelkin> ToAnalogue for Vec<T> {
+    type Analogue = Self;
+
+    fn to_analogue(self) -> Self {
+        self
+    }
+}
+
+impl<T: FromWelkin> FromWelkin for Vec<T> {
+    type Error = ConversionError<T::Error>;
+
+    fn from_welkin(term: Term<String>) -> Result<Self, Self::Error> {
+        read_vector(term)
+    }
+}
+
+impl<T: ToWelkin> ToWelkin for Vec<T> {
+    type Error = T::Error;
+
+    fn to_welkin(self) -> Result<Term<String>, Self::Error> {
+        Ok(write_vector(
+            self.into_iter()
+                .map(ToWelkin::to_welkin)
+                .collect::<Result<_, _>>()?,
+        ))
+    }
+}
+
+impl<T> Analogous for Option<T> {
+    type Analogue = Self;
+}
+
+impl<T: FromWelkin> FromAnalogue for Option<T> {
+    type Analogue = Self;
+
+    fn from_analogue(analogue: Self) -> Self {
+        analogue
+    }
+}
+
+impl<T: ToWelkin> ToAnalogue for Option<T> {
+    type Analogue = Self;
+
+    fn to_analogue(self) -> Self {
+        self
+    }
+}
+
+impl<T: FromWelkin> FromWelkin for Option<T> {
+    type Error = ConversionError<T::Error>;
+
+    fn from_welkin(term: Term<String>) -> Result<Self, Self::Error> {
+        let (variant, mut fields) = read_variant(term, &[0, 1])?;
+
+        Ok(if variant == 0 {
+            None
+        } else {
+            Some(
+                T::from_welkin(field(&mut fields))
+                    .map_err(ConversionError::at_field::<T>("just", "value"))?,
+            )
+        })
+    }
+}
+
+impl<T: ToWelkin> ToWelkin for Option<T> {
+    type Error = T::Error;
+
+    fn to_welkin(self) -> Result<Term<String>, Self::Error> {
+        Ok(match self {
+            None => construct(2, 0, vec![]),
+            Some(value) => construct(2, 1, vec![value.to_welkin()?]),
+        })
+    }
+}
+
+impl<T: Typed> Adt for Option<T> {
+    const DEFINITION: AdtDefinition = AdtDefinition {
+        name: "Maybe",
+        variants: &[
+            AdtVariant {
+                name: "nothing",
+                fields: &[],
+            },
+            AdtVariant {
+                name: "just",
+                fields: &[Type::Parameter(0)],
+            },
+        ],
+        params: 1,
+    };
+
+    const PARAMS: &'static [Type] = &[T::TYPE];
+}
+
+impl<T, E> Analogous for Result<T, E> {
+    type Analogue = Self;
+}
+
+impl<T: FromWelkin, E: FromWelkin> FromAnalogue for Result<T, E> {
+    type Analogue = Self;
+
+    fn from_analogue(analogue: Self) -> Self {
+        analogue
+    }
+}
+
+impl<T: ToWelkin, E: ToWelkin> ToAnalogue for Result<T, E> {
+    type Analogue = Self;
+
+    fn to_analogue(self) -> Self {
+        self
+    }
+}
+
+impl<T: FromWelkin, E: FromWelkin> FromWelkin for Result<T, E> {
+    type Error = ConversionError<EitherError<T::Error, E::Error>>;
+
+    fn from_welkin(term: Term<String>) -> Result<Self, Self::Error> {
+        let (variant, mut fields) = read_variant(term, &[1, 1])?;
+        let value = field(&mut fields);
+
+        Ok(if variant == 0 {
+            Ok(T::from_welkin(value)
+                .map_err(EitherError::Left)
+                .map_err(ConversionError::at_field::<T>("left", "value"))?)
+        } else {
+            Err(E::from_welkin(value)
+                .map_err(EitherError::Right)
+                .map_err(ConversionError::at_field::<E>("right", "value"))?)
+        })
+    }
+}
+
+impl<T: ToWelkin, E: ToWelkin> ToWelkin for Result<T, E> {
+    type Error = EitherError<T::Error, E::Error>;
+
+    fn to_welkin(self) -> Result<Term<String>, Self::Error> {
+        Ok(match self {
+            Ok(value) => construct(2, 0, vec![value.to_welkin().map_err(EitherError::Left)?]),
+            Err(value) => construct(2, 1, vec![value.to_welkin().map_err(EitherError::Right)?]),
+        })
+    }
+}
+
+impl<T: Typed, E: Typed> Adt for Result<T, E> {
+    const DEFINITION: AdtDefinition = AdtDefinition {
+        name: "Either",
+        variants: &[
+            AdtVariant {
+                name: "left",
+                fields: &[Type::Parameter(0)],
+            },
+            AdtVariant {
+                name: "right",
+                fields: &[Type::Parameter(1)],
+            },
+        ],
+        params: 2,
+    };
+
+    const PARAMS: &'static [Type] = &[T::TYPE, E::TYPE];
+}
+
+impl<A, B> Analogous for (A, B) {
+    type Analogue = Self;
+}
+
+impl<A: FromWelkin, B: FromWelkin> FromAnalogue for (A, B) {
+    type Analogue = Self;
+
+    fn from_analogue(analogue: Self) -> Self {
+        analogue
+    }
+}
+
+impl<A: ToWelkin, B: ToWelkin> ToAnalogue for (A, B) {
+    type Analogue = Self;
+
+    fn to_analogue(self) -> Self {
+        self
+    }
+}
+
+impl<A: FromWelkin, B: FromWelkin> FromWelkin for (A, B) {
+    type Error = ConversionError<EitherError<A::Error, B::Error>>;
+
+    fn from_welkin(term: Term<String>) -> Result<Self, Self::Error> {
+        let (_, mut fields) = read_variant(term, &[2])?;
+
+        Ok((
+            A::from_welkin(field(&mut fields))
+                .map_err(EitherError::Left)
+                .map_err(ConversionError::at_field::<A>("new", "left"))?,
+            B::from_welkin(field(&mut fields))
+                .map_err(EitherError::Right)
+                .map_err(ConversionError::at_field::<B>("new", "right"))?,
+        ))
+    }
+}
+
+impl<A: ToWelkin, B: ToWelkin> ToWelkin for (A, B) {
+    type Error = EitherError<A::Error, B::Error>;
+
+    fn to_welkin(self) -> Result<Term<String>, Self::Error> {
+        Ok(construct(
+            1,
+            0,
+            vec![
+                self.0.to_welkin().map_err(EitherError::Left)?,
+                self.1.to_welkin().map_err(EitherError::Right)?,
+            ],
+        ))
+    }
+}
+
+impl<A: Typed, B: Typed> Adt for (A, B) {
+    const DEFINITION: AdtDefinition = AdtDefinition {
+        name: "Pair",
+        variants: &[AdtVariant {
+            name: "new",
+            fields: &[Type::Parameter(0), Type::Parameter(1)],
+        }],
+        params: 2,
+    };
+
+    const PARAMS: &'static [Type] = &[A::TYPE, B::TYPE];
+}
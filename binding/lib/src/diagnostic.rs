@@ -0,0 +1,186 @@
+use std::{
+    convert::Infallible,
+    fmt::{self, Display, Formatter},
+};
+
+use welkin_core::term::Term;
+
+use crate::{ConversionError, EitherError};
+
+/// The longest snippet of a term included in a `DecodeError`.
+const SNIPPET_LENGTH: usize = 80;
+
+/// A step from a value into one of its subterms.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Segment {
+    Field {
+        variant: &'static str,
+        field: &'static str,
+    },
+    /// An element of a `Vec`, `String` or other sequence.
+    Index(usize),
+}
+
+impl Display for Segment {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Segment::Field { variant, field } => write!(f, "{}.{}", variant, field),
+            Segment::Index(idx) => write!(f, "[{}]", idx),
+        }
+    }
+}
+
+/// Why a term could not be read, located by the path from the root value to the subterm that
+/// failed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DecodeError {
+    pub path: Vec<Segment>,
+    /// The type being read at the failing subterm, where known.
+    pub expected: Option<String>,
+    pub reason: String,
+    /// The start of the failing subterm, where the failure concerns its shape.
+    pub term: Option<String>,
+}
+
+impl DecodeError {
+    pub fn new(reason: impl Into<String>) -> Self {
+        DecodeError {
+            path: vec![],
+            expected: None,
+            reason: reason.into(),
+            term: None,
+        }
+    }
+
+    pub fn with_term(mut self, term: &Term<String>) -> Self {
+        self.term = Some(snippet(term));
+        self
+    }
+
+    /// Names the type being read at the failing subterm, unless it is already known.
+    pub fn expecting(mut self, expected: &str) -> Self {
+        if self.path.is_empty() && self.expected.is_none() {
+            self.expected = Some(expected.to_owned());
+        }
+        self
+    }
+
+    /// Places the error beneath a step from an enclosing value whose subterm has type `expected`.
+    pub fn within(self, segment: Segment, expected: &str) -> Self {
+        let mut error = self.expecting(expected);
+        error.path.insert(0, segment);
+        error
+    }
+}
+
+impl Display for DecodeError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        if let Some(expected) = &self.expected {
+            write!(f, "error reading {}", expected)?;
+        } else {
+            write!(f, "error reading value")?;
+        }
+
+        if !self.path.is_empty() {
+            write!(f, " at ")?;
+            for (idx, segment) in self.path.iter().enumerate() {
+                if idx != 0 && matches!(segment, Segment::Field { .. }) {
+                    write!(f, ".")?;
+                }
+                write!(f, "{}", segment)?;
+            }
+        }
+
+        write!(f, ": {}", self.reason)?;
+
+        if let Some(term) = &self.term {
+            write!(f, ", got `{}`", term)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl std::error::Error for DecodeError {}
+
+/// Prints the start of a term for inclusion in an error.
+pub fn snippet(term: &Term<String>) -> String {
+    let printed = format!("{:?}", term);
+
+    if printed.chars().count() > SNIPPET_LENGTH {
+        let mut printed: String = printed.chars().take(SNIPPET_LENGTH).collect();
+        printed.push_str("...");
+        printed
+    } else {
+        printed
+    }
+}
+
+/// Errors from reading a term that can be located within it.
+pub trait Diagnose {
+    fn diagnose(&self) -> DecodeError;
+}
+
+impl Diagnose for Infallible {
+    fn diagnose(&self) -> DecodeError {
+        match *self {}
+    }
+}
+
+impl Diagnose for DecodeError {
+    fn diagnose(&self) -> DecodeError {
+        self.clone()
+    }
+}
+
+impl<A: Diagnose, B: Diagnose> Diagnose for EitherError<A, B> {
+    fn diagnose(&self) -> DecodeError {
+        match self {
+            EitherError::Left(error) => error.diagnose(),
+            EitherError::Right(error) => error.diagnose(),
+        }
+    }
+}
+
+impl<T: Diagnose> Diagnose for ConversionError<T> {
+    fn diagnose(&self) -> DecodeError {
+        match self {
+            ConversionError::ExpectedLambda(term) => {
+                DecodeError::new("expected lambda").with_term(term)
+            }
+            ConversionError::ExpectedApplyOrVariable(term) => {
+                DecodeError::new("expected apply or variable").with_term(term)
+            }
+            ConversionError::Field {
+                segment,
+                expected,
+                error,
+            } => error.diagnose().within(*segment, expected),
+            ConversionError::InvalidVariant {
+                got,
+                expected_at_most,
+                term,
+            } => DecodeError::new(format!(
+                "invalid variant {}, expected at most {}",
+                got, expected_at_most
+            ))
+            .with_term(term),
+            ConversionError::InsufficientFields(term) => {
+                DecodeError::new("insufficient fields present in term").with_term(term)
+            }
+            ConversionError::Overflow(ty) => {
+                DecodeError::new(format!("value does not fit in {}", ty))
+            }
+            ConversionError::SizeLimit(size) => DecodeError::new(format!(
+                "{} is too large to write as a Size, use a Word instead",
+                size
+            )),
+            ConversionError::WordWidth { expected, got } => {
+                DecodeError::new(format!("expected a word of {} bits, got {}", expected, got))
+            }
+            ConversionError::InvalidChar(value) => {
+                DecodeError::new(format!("invalid unicode scalar value {:#x}", value))
+            }
+        }
+    }
+}
@@ -0,0 +1,105 @@
+use std::{error::Error as StdError, fmt::Debug};
+
+use thiserror::Error;
+use welkin::runtime::{Runtime, RuntimeError};
+use welkin_core::term::{Definitions, NormalizationError, Term};
+
+use crate::{FromAnalogue, FromWelkin, ToAnalogue, ToWelkin};
+
+/// Normalizes the application of a welkin definition to its arguments.
+pub trait Evaluator {
+    type Error;
+
+    fn evaluate(&self, term: Term<String>) -> Result<Term<String>, Self::Error>;
+}
+
+impl<T: Evaluator + ?Sized> Evaluator for &T {
+    type Error = T::Error;
+
+    fn evaluate(&self, term: Term<String>) -> Result<Term<String>, Self::Error> {
+        T::evaluate(*self, term)
+    }
+}
+
+/// Evaluates by substitution against a set of definitions.
+pub struct Substitution<T>(pub T);
+
+impl<T: Definitions<String>> Evaluator for Substitution<T> {
+    type Error = NormalizationError;
+
+    fn evaluate(&self, mut term: Term<String>) -> Result<Term<String>, Self::Error> {
+        term.normalize(&self.0)?;
+        Ok(term)
+    }
+}
+
+impl Evaluator for Runtime {
+    type Error = RuntimeError;
+
+    fn evaluate(&self, term: Term<String>) -> Result<Term<String>, Self::Error> {
+        Runtime::evaluate(self, term)
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum CallError<E: Debug> {
+    #[error("error converting argument \"{name}\" of \"{function}\": {error}")]
+    Argument {
+        function: &'static str,
+        name: &'static str,
+        error: Box<dyn StdError + Send + Sync>,
+    },
+    #[error("error evaluating \"{function}\": {error:?}")]
+    Evaluator { function: &'static str, error: E },
+    #[error("error reading result of \"{function}\": {error}")]
+    Result {
+        function: &'static str,
+        error: Box<dyn StdError + Send + Sync>,
+    },
+}
+
+#[doc(hidden)]
+pub fn apply_argument<T: ToAnalogue, E: Debug>(
+    function: &'static str,
+    term: Term<String>,
+    name: &'static str,
+    argument: T,
+    erased: bool,
+) -> Result<Term<String>, CallError<E>>
+where
+    <<T as ToAnalogue>::Analogue as ToWelkin>::Error: StdError + Send + Sync + 'static,
+{
+    Ok(Term::Apply {
+        erased,
+        function: Box::new(term),
+        argument: Box::new(
+            ToWelkin::to_welkin(argument.to_analogue()).map_err(|error| CallError::Argument {
+                function,
+                name,
+                error: Box::new(error),
+            })?,
+        ),
+    })
+}
+
+#[doc(hidden)]
+pub fn evaluate_call<T: FromAnalogue, E: Evaluator>(
+    evaluator: &E,
+    function: &'static str,
+    term: Term<String>,
+) -> Result<T, CallError<E::Error>>
+where
+    E::Error: Debug,
+    <<T as FromAnalogue>::Analogue as FromWelkin>::Error: StdError + Send + Sync + 'static,
+{
+    let term = evaluator
+        .evaluate(term)
+        .map_err(|error| CallError::Evaluator { function, error })?;
+
+    Ok(T::from_analogue(FromWelkin::from_welkin(term).map_err(
+        |error| CallError::Result {
+            function,
+            error: Box::new(error),
+        },
+    )?))
+}
@@ -3,6 +3,7 @@ use std::{
     convert::TryInto,
     fmt::{self, Display, Formatter},
     marker::PhantomData,
+    rc::Rc,
     str::FromStr,
 };
 
@@ -20,6 +21,7 @@ use combine::{
 pub use thiserror::Error;
 use welkin::{
     compiler::{item::Compile, LocalResolver},
+    container::{self, ContainerError},
     parser::{self, BumpBox, BumpVec, Data, Ident, Path, Variant},
 };
 #[doc(hidden)]
@@ -28,6 +30,28 @@ pub use welkin::{parser::AbsolutePath, SerializableData};
 pub use welkin_core;
 use welkin_core::term::{Referent, Term};
 
+mod bridge;
+mod conversions;
+mod diagnostic;
+mod function;
+pub use bridge::{Schema, SerializeTerm, TermSeed};
+#[doc(hidden)]
+pub use conversions::variant_term;
+pub use conversions::{ConversionError, EitherError, Word, MAX_SIZE};
+pub use diagnostic::{snippet, DecodeError, Diagnose, Segment};
+#[doc(hidden)]
+pub use function::{apply_argument, evaluate_call};
+pub use function::{CallError, Evaluator, Substitution};
+
+/// Has cargo rebuild the calling crate whenever a file under any of `sources` is added, removed or
+/// changed, for use from a build script. `bind!` tracks the files it reads, but a file added to a
+/// source directory is only picked up through this.
+pub fn rerun_if_changed<P: AsRef<std::path::Path>>(sources: impl IntoIterator<Item = P>) {
+    for source in sources {
+        println!("cargo:rerun-if-changed={}", source.as_ref().display());
+    }
+}
+
 pub trait FromWelkin: Sized {
     type Error;
 
@@ -316,43 +340,36 @@ impl<T> Wrapper for Box<T> {
     type Inner = T;
 }
 
-trait ResolveParam {
-    fn resolve(&self, idx: usize) -> (Type, Box<dyn ResolveParam>);
-}
-
-impl<T: ResolveParam + ?Sized> ResolveParam for Box<T> {
-    fn resolve(&self, idx: usize) -> (Type, Box<dyn ResolveParam>) {
-        T::resolve(&**self, idx)
-    }
-}
-
+/// Resolves the parameters of a type: `Type::Parameter(n)` in the current scope stands for
+/// `params[n]`, which is itself written in the scope resolved by `parent`.
 struct SliceResolver {
     params: &'static [Type],
+    parent: Option<Rc<SliceResolver>>,
 }
 
-impl ResolveParam for SliceResolver {
-    fn resolve(&self, idx: usize) -> (Type, Box<dyn ResolveParam>) {
-        (
-            self.params[idx].clone(),
-            Box::new(SliceResolver {
-                params: match &self.params[idx] {
-                    Type::Parameter(_) => todo!(),
-                    Type::Data { params, .. } => params,
-                },
-            }),
-        )
+impl SliceResolver {
+    /// Returns the type a parameter stands for along with the scope that type is written in, or
+    /// `None` if the parameter is left free by the outermost type.
+    fn resolve(&self, idx: usize) -> Option<(&'static Type, Option<Rc<SliceResolver>>)> {
+        let params: &'static [Type] = self.params;
+
+        match &params[idx] {
+            Type::Parameter(idx) => self.parent.as_ref()?.resolve(*idx),
+            ty => Some((ty, self.parent.clone())),
+        }
     }
 }
 
 fn resolve_dependencies(
     ty: &Type,
     register: &mut impl FnMut(&'static AdtDefinition),
-    resolve_param: &impl ResolveParam,
+    scope: &Option<Rc<SliceResolver>>,
 ) {
     match ty {
         Type::Parameter(idx) => {
-            let (ty, resolver) = &resolve_param.resolve(*idx);
-            resolve_dependencies(ty, &mut *register, resolver)
+            if let Some((ty, scope)) = scope.as_ref().and_then(|scope| scope.resolve(*idx)) {
+                resolve_dependencies(ty, &mut *register, &scope)
+            }
         }
         Type::Data {
             constructor,
@@ -360,19 +377,31 @@ fn resolve_dependencies(
         } => {
             if let AdtConstructor::Other(constructor) = constructor {
                 register(constructor);
+                // the fields of the constructor are written in terms of its own parameters
+                let inner = Some(Rc::new(SliceResolver {
+                    params: *params,
+                    parent: scope.clone(),
+                }));
                 for variant in constructor.variants {
                     for field in variant.fields {
-                        resolve_dependencies(field, &mut *register, &*resolve_param)
+                        resolve_dependencies(field, &mut *register, &inner)
                     }
                 }
             }
             for param in *params {
-                resolve_dependencies(param, &mut *register, &*resolve_param)
+                resolve_dependencies(param, &mut *register, scope)
             }
         }
     }
 }
 
+fn outermost_scope<A: Adt>() -> Option<Rc<SliceResolver>> {
+    Some(Rc::new(SliceResolver {
+        params: A::PARAMS,
+        parent: None,
+    }))
+}
+
 fn generate_all_data<'a, A: Adt>(bump: &'a Bump) -> Vec<Data<'a>> {
     let mut dependencies = HashSet::new();
 
@@ -385,7 +414,7 @@ fn generate_all_data<'a, A: Adt>(bump: &'a Bump) -> Vec<Data<'a>> {
                 &mut |definition| {
                     dependencies.insert(definition);
                 },
-                &SliceResolver { params: A::PARAMS },
+                &outermost_scope::<A>(),
             )
         }
     }
@@ -408,7 +437,7 @@ fn generate_all<A: Adt>() -> Definitions {
                 &mut |definition| {
                     dependencies.insert(definition);
                 },
-                &SliceResolver { params: A::PARAMS },
+                &outermost_scope::<A>(),
             )
         }
     }
@@ -421,6 +450,28 @@ fn generate_all<A: Adt>() -> Definitions {
     }
 }
 
+/// The declaration position of the variant a term of data holds, for checking it against the
+/// variants the indices of a field's type allow.
+#[doc(hidden)]
+pub fn variant_position(term: &Term<String>) -> Option<usize> {
+    let mut term = term;
+    let mut variants = 0;
+
+    while let Term::Lambda { body, .. } = term {
+        term = &**body;
+        variants += 1;
+    }
+
+    while let Term::Apply { function, .. } = term {
+        term = &**function;
+    }
+
+    match term {
+        Term::Variable(index) => variants.checked_sub(index.0 + 1),
+        _ => None,
+    }
+}
+
 pub fn concrete_type<A: Adt>() -> Term<AbsolutePath> {
     fn helper(definition: &AdtDefinition, params: &[Type]) -> Term<AbsolutePath> {
         let mut term = Term::Reference(AbsolutePath(vec![definition.name.to_owned()]));
@@ -454,14 +505,44 @@ pub enum CheckError {
     Missing(AbsolutePath),
     #[error("definition {0:?} does not match declaration in welkin source")]
     Mismatch(AbsolutePath),
+    #[error("{data} takes {got} type arguments, but {expected} in welkin source")]
+    TypeArguments {
+        data: String,
+        expected: usize,
+        got: usize,
+    },
+    #[error("variant {variant} of {data} is missing in welkin source")]
+    MissingVariant { data: String, variant: String },
+    #[error("variant {variant} of {data} in welkin source has no corresponding variant")]
+    UnboundVariant { data: String, variant: String },
+    #[error("variant {variant} of {data} has {got} fields, but {expected} in welkin source")]
+    FieldCount {
+        data: String,
+        variant: String,
+        expected: usize,
+        got: usize,
+    },
+    #[error("field {got} of variant {variant} of {data} is named {expected} in welkin source")]
+    FieldName {
+        data: String,
+        variant: String,
+        expected: String,
+        got: String,
+    },
+    #[error("field {field} of variant {variant} of {data} does not match its declaration in welkin source")]
+    FieldMismatch {
+        data: String,
+        variant: String,
+        field: String,
+    },
 }
 
 #[derive(Debug, Error)]
 pub enum CanonicalEquivalenceError {
     #[error("check error: {0}")]
     Check(#[from] CheckError),
-    #[error("bincode error: {0}")]
-    Bincode(#[from] Box<bincode::ErrorKind>),
+    #[error("container error: {0}")]
+    Container(#[from] ContainerError),
 }
 
 #[doc(hidden)]
@@ -532,9 +613,7 @@ fn canonically_equivalent_fields(
             let mut func = &**b_function;
 
             while let Term::Apply {
-                argument,
-                function,
-                erased: true,
+                argument, function, ..
             } = func
             {
                 arguments.push(&**argument);
@@ -546,7 +625,10 @@ fn canonically_equivalent_fields(
                     if path.0.len() == 1 {
                         if let Some(data) = definitions.iter().find(|data| &data.ident == segment) {
                             arguments.reverse();
-                            arguments.truncate(arguments.len() - data.indices);
+                            let arguments = match data.carried_arguments(arguments) {
+                                Some(arguments) => arguments,
+                                None => return false,
+                            };
 
                             let mut term = func.clone();
 
@@ -623,31 +705,66 @@ fn canonically_equivalent_data(
     rust: &SerializableData,
     welkin: &SerializableData,
     definitions: &[SerializableData],
-) -> bool {
-    rust.ident == welkin.ident
-        && rust.type_arguments == welkin.type_arguments
-        && rust
-            .variants
-            .iter()
-            .map(|a| a.0.as_str())
-            .collect::<HashSet<_>>()
-            == welkin
-                .variants
-                .iter()
-                .map(|a| a.0.as_str())
-                .collect::<HashSet<_>>()
-        && rust
-            .variants
+) -> Result<(), CheckError> {
+    if rust.ident != welkin.ident {
+        return Err(CheckError::Mismatch(AbsolutePath(vec![rust.ident.clone()])));
+    }
+
+    if rust.type_arguments != welkin.type_arguments {
+        return Err(CheckError::TypeArguments {
+            data: rust.ident.clone(),
+            expected: welkin.type_arguments,
+            got: rust.type_arguments,
+        });
+    }
+
+    if let Some((variant, _)) = welkin
+        .variants
+        .iter()
+        .find(|(name, _)| !rust.variants.iter().any(|(other, _)| other == name))
+    {
+        return Err(CheckError::UnboundVariant {
+            data: rust.ident.clone(),
+            variant: variant.clone(),
+        });
+    }
+
+    for (name, rust_variant) in &rust.variants {
+        let welkin_variant = match welkin.variants.iter().find(|(other, _)| other == name) {
+            Some((_, variant)) => variant,
+            None => {
+                return Err(CheckError::MissingVariant {
+                    data: rust.ident.clone(),
+                    variant: name.clone(),
+                })
+            }
+        };
+
+        if rust_variant.inhabitants.len() != welkin_variant.inhabitants.len() {
+            return Err(CheckError::FieldCount {
+                data: rust.ident.clone(),
+                variant: name.clone(),
+                expected: welkin_variant.inhabitants.len(),
+                got: rust_variant.inhabitants.len(),
+            });
+        }
+
+        for ((field, rust_field), (_, welkin_field)) in rust_variant
+            .inhabitants
             .iter()
-            .zip(welkin.variants.iter())
-            .all(|((_, rust), (_, welkin))| {
-                rust.inhabitants.len() == welkin.inhabitants.len()
-                    && rust.inhabitants.iter().zip(welkin.inhabitants.iter()).all(
-                        |((_, rust), (_, welkin))| {
-                            canonically_equivalent_fields(rust, welkin, definitions)
-                        },
-                    )
-            })
+            .zip(welkin_variant.inhabitants.iter())
+        {
+            if !canonically_equivalent_fields(rust_field, welkin_field, definitions) {
+                return Err(CheckError::FieldMismatch {
+                    data: rust.ident.clone(),
+                    variant: name.clone(),
+                    field: field.clone(),
+                });
+            }
+        }
+    }
+
+    Ok(())
 }
 
 fn canonically_equivalent_in_helper(
@@ -655,31 +772,39 @@ fn canonically_equivalent_in_helper(
     against: &[SerializableData],
 ) -> Result<(), CheckError> {
     for definition in data {
-        if let Some(other) = against.iter().find(|data| data.ident == definition.ident) {
-            if !canonically_equivalent_data(&definition, other, against) {
-                println!("{:?} != {:?}", definition, other);
-                return Err(CheckError::Mismatch(AbsolutePath(vec![definition.ident])));
-            }
-        } else {
-            return Err(CheckError::Missing(AbsolutePath(vec![definition.ident])));
-        }
+        check_canonical(&definition, against)?;
     }
 
     Ok(())
 }
 
+/// Checks a single binding's data against the canonical definitions it should correspond to.
+#[doc(hidden)]
+pub fn check_canonical(
+    definition: &SerializableData,
+    against: &[SerializableData],
+) -> Result<(), CheckError> {
+    if let Some(other) = against.iter().find(|data| data.ident == definition.ident) {
+        canonically_equivalent_data(definition, other, against)
+    } else {
+        Err(CheckError::Missing(AbsolutePath(vec![definition
+            .ident
+            .clone()])))
+    }
+}
+
 pub fn canonically_equivalent_in<A: Adt>(against: &[u8]) -> Result<(), CanonicalEquivalenceError> {
     let bump = Bump::new();
 
     Ok(canonically_equivalent_in_helper(
         vec![A::DEFINITION.generate_data(&bump).try_into().unwrap()],
-        &bincode::deserialize::<Vec<_>>(against)?,
+        &container::decode::<Vec<_>>(against)?,
     )?)
 }
 
 #[doc(hidden)]
 pub fn deserialize_defs(buffer: &[u8]) -> Result<Vec<SerializableData>, CanonicalEquivalenceError> {
-    bincode::deserialize(buffer).map_err(CanonicalEquivalenceError::Bincode)
+    container::decode(buffer).map_err(CanonicalEquivalenceError::Container)
 }
 
 pub fn canonically_equivalent_all_in<A: Adt>(
@@ -692,7 +817,7 @@ pub fn canonically_equivalent_all_in<A: Adt>(
             .into_iter()
             .map(|a| a.try_into().unwrap())
             .collect(),
-        &bincode::deserialize::<Vec<_>>(against)?,
+        &container::decode::<Vec<_>>(against)?,
     )?)
 }
 
@@ -0,0 +1,174 @@
+use std::collections::HashSet;
+
+use serde::de::DeserializeSeed;
+use serde_json::{json, Value};
+use welkin::{parser::AbsolutePath, source::load, SerializableData};
+use welkin_binding_lib::{Schema, ToWelkin, MAX_SIZE};
+use welkin_core::term::Term;
+
+fn definitions() -> Vec<SerializableData> {
+    load(
+        concat!(env!("CARGO_MANIFEST_DIR"), "/../../.welkin/core"),
+        &HashSet::new(),
+    )
+    .unwrap()
+    .data_declarations
+}
+
+/// The type `name` applied to `arguments`.
+fn ty(name: &str, arguments: Vec<Term<AbsolutePath>>) -> Term<AbsolutePath> {
+    arguments.into_iter().fold(
+        Term::Reference(AbsolutePath(vec![name.into()])),
+        |function, argument| Term::Apply {
+            erased: true,
+            function: Box::new(function),
+            argument: Box::new(argument),
+        },
+    )
+}
+
+fn scalar(name: &str) -> Term<AbsolutePath> {
+    ty(name, vec![])
+}
+
+fn read(schema: &Schema, ty: Term<AbsolutePath>, value: &Value) -> Result<Term<String>, String> {
+    schema
+        .seed(ty)
+        .deserialize(value)
+        .map_err(|e| format!("{}", e))
+}
+
+fn write(schema: &Schema, ty: Term<AbsolutePath>, term: &Term<String>) -> Value {
+    serde_json::to_value(schema.serialize(ty, term)).unwrap()
+}
+
+/// Asserts that `value` reads as a term of type `ty` that is written back as `value`, returning
+/// the term.
+fn assert_round_trips(schema: &Schema, ty: Term<AbsolutePath>, value: Value) -> Term<String> {
+    let term = read(schema, ty.clone(), &value).unwrap();
+    assert_eq!(write(schema, ty, &term), value);
+    term
+}
+
+fn show<E: std::fmt::Debug>(term: Result<Term<String>, E>) -> String {
+    format!("{:?}", term.unwrap())
+}
+
+#[test]
+fn round_trips_scalars() {
+    let definitions = definitions();
+    let schema = Schema::new(&definitions);
+
+    let cases = vec![
+        (
+            scalar("String"),
+            json!("hé 🦀"),
+            show("hé 🦀".to_owned().to_welkin()),
+        ),
+        (scalar("Size"), json!(3), show(3u64.to_welkin())),
+        (scalar("Char"), json!("x"), show('x'.to_welkin())),
+        (scalar("Bool"), json!(false), show(false.to_welkin())),
+    ];
+
+    for (ty, value, expected) in cases {
+        assert_eq!(
+            format!("{:?}", assert_round_trips(&schema, ty, value)),
+            expected
+        );
+    }
+}
+
+#[test]
+fn round_trips_vectors() {
+    let definitions = definitions();
+    let schema = Schema::new(&definitions);
+
+    let term = assert_round_trips(
+        &schema,
+        ty("Vector", vec![scalar("Size"), scalar("Size")]),
+        json!([1, 2, 3]),
+    );
+    assert_eq!(format!("{:?}", term), show(vec![1u64, 2, 3].to_welkin()));
+
+    assert_round_trips(
+        &schema,
+        ty("Vector", vec![scalar("Bool"), scalar("Size")]),
+        json!([]),
+    );
+}
+
+#[test]
+fn round_trips_data_with_a_single_variant() {
+    let definitions = definitions();
+    let schema = Schema::new(&definitions);
+
+    let term = assert_round_trips(
+        &schema,
+        ty("Pair", vec![scalar("Size"), scalar("Bool")]),
+        json!({ "left": 1, "right": true }),
+    );
+    assert_eq!(format!("{:?}", term), show((1u64, true).to_welkin()));
+
+    assert_round_trips(&schema, scalar("Unit"), json!(null));
+}
+
+#[test]
+fn round_trips_externally_tagged_data() {
+    let definitions = definitions();
+    let schema = Schema::new(&definitions);
+
+    let maybe = ty("Maybe", vec![scalar("Size")]);
+
+    let term = assert_round_trips(&schema, maybe.clone(), json!("nothing"));
+    assert_eq!(format!("{:?}", term), show(None::<u64>.to_welkin()));
+
+    let term = assert_round_trips(&schema, maybe, json!({ "just": { "value": 2 } }));
+    assert_eq!(format!("{:?}", term), show(Some(2u64).to_welkin()));
+
+    let term = assert_round_trips(
+        &schema,
+        ty("Either", vec![scalar("Char"), scalar("String")]),
+        json!({ "right": { "value": "text" } }),
+    );
+    assert_eq!(
+        format!("{:?}", term),
+        show(Err::<char, _>("text".to_owned()).to_welkin())
+    );
+
+    assert_round_trips(
+        &schema,
+        ty(
+            "Maybe",
+            vec![ty(
+                "Vector",
+                vec![
+                    ty("Pair", vec![scalar("Char"), scalar("Bool")]),
+                    scalar("Size"),
+                ],
+            )],
+        ),
+        json!({ "just": { "value": [{ "left": "a", "right": true }] } }),
+    );
+}
+
+#[test]
+fn rejects_values_that_do_not_match_their_type() {
+    let definitions = definitions();
+    let schema = Schema::new(&definitions);
+
+    let maybe = ty("Maybe", vec![scalar("Size")]);
+    let pair = ty("Pair", vec![scalar("Size"), scalar("Bool")]);
+
+    assert!(read(&schema, maybe.clone(), &json!("some")).is_err());
+    assert!(read(&schema, maybe.clone(), &json!("just")).is_err());
+    assert!(read(&schema, maybe, &json!({ "nothing": {}, "just": {} })).is_err());
+    assert!(read(&schema, pair.clone(), &json!({ "left": 1 })).is_err());
+    assert!(read(
+        &schema,
+        pair,
+        &json!({ "left": 1, "right": true, "other": 2 })
+    )
+    .is_err());
+    assert!(read(&schema, scalar("Size"), &json!(MAX_SIZE + 1)).is_err());
+    assert!(read(&schema, scalar("Missing"), &json!(null)).is_err());
+}
@@ -0,0 +1,141 @@
+use std::{collections::HashSet, fmt::Debug};
+
+use combine::{stream::position, EasyParser};
+use welkin::{
+    compiler::{term::Compile, LocalResolver},
+    parser::{term::term, Bump},
+    runtime::Runtime,
+    source::load,
+    Terms,
+};
+use welkin_binding_lib::{ConversionError, FromWelkin, ToWelkin, Word, MAX_SIZE};
+use welkin_core::term::Term;
+
+/// The core library, which defines the data the conversions read and write.
+fn core() -> Runtime {
+    let library = load(
+        concat!(env!("CARGO_MANIFEST_DIR"), "/../../.welkin/core"),
+        &HashSet::new(),
+    )
+    .unwrap();
+
+    Runtime::new(Terms {
+        data: library.declarations,
+    })
+}
+
+/// Compiles a term such as a `~literal` block and evaluates it against the core library.
+fn compiled(runtime: &Runtime, source: &str) -> Term<String> {
+    let bump = Bump::new();
+    let (term, _) = term(Default::default(), &bump)
+        .easy_parse(position::Stream::new(source))
+        .unwrap();
+
+    runtime
+        .evaluate(
+            term.compile(LocalResolver::new())
+                .map_reference(|path| Term::Reference(format!("{:?}", path))),
+        )
+        .unwrap()
+}
+
+/// Asserts that `source` compiles to the same term `value` is written as, and that both read back
+/// as `value`.
+fn assert_matches_compiled<T>(runtime: &Runtime, source: &str, value: T)
+where
+    T: FromWelkin + ToWelkin + Clone + PartialEq + Debug,
+    <T as FromWelkin>::Error: Debug,
+    <T as ToWelkin>::Error: Debug,
+{
+    let compiled = compiled(runtime, source);
+    let written = value.clone().to_welkin().unwrap();
+
+    assert_eq!(
+        format!("{:?}", written),
+        format!("{:?}", compiled),
+        "{}",
+        source
+    );
+    assert_eq!(T::from_welkin(compiled).unwrap(), value, "{}", source);
+}
+
+fn round_trip<T>(value: T) -> T
+where
+    T: FromWelkin + ToWelkin,
+    <T as FromWelkin>::Error: Debug,
+    <T as ToWelkin>::Error: Debug,
+{
+    T::from_welkin(value.to_welkin().unwrap()).unwrap()
+}
+
+#[test]
+fn reads_words_least_significant_bit_outermost() {
+    let runtime = core();
+
+    assert_matches_compiled(&runtime, "~literal Word 00000110", Word(6u8));
+    assert_matches_compiled(&runtime, "~literal Word 10000000", Word(128u8));
+    assert_matches_compiled(&runtime, "~literal Word 0000000100000010", Word(258u16));
+
+    assert!(matches!(
+        Word::<u16>::from_welkin(compiled(&runtime, "~literal Word 00000110")),
+        Err(ConversionError::WordWidth {
+            expected: 16,
+            got: 8
+        })
+    ));
+}
+
+#[test]
+fn reads_chars_as_their_compiled_bytes() {
+    let runtime = core();
+
+    for character in &['a', 'é', '€', '🦀'] {
+        assert_matches_compiled(
+            &runtime,
+            &format!("~literal Char '{}'", character),
+            *character,
+        );
+    }
+}
+
+#[test]
+fn reads_compiled_sizes_strings_and_vectors() {
+    let runtime = core();
+
+    assert_matches_compiled(&runtime, "~literal Size 0", 0u64);
+    assert_matches_compiled(&runtime, "~literal Size 3", 3u8);
+    assert_matches_compiled(&runtime, "~literal String \"hé 🦀\"", "hé 🦀".to_owned());
+    assert_eq!(
+        Vec::<bool>::from_welkin(compiled(
+            &runtime,
+            "~literal Vector [Bool] [Bool::true, Bool::false]"
+        ))
+        .unwrap(),
+        vec![true, false]
+    );
+}
+
+#[test]
+fn refuses_sizes_too_large_to_write() {
+    assert_eq!(round_trip(MAX_SIZE), MAX_SIZE);
+    assert!(matches!(
+        (MAX_SIZE + 1).to_welkin(),
+        Err(ConversionError::SizeLimit(size)) if size == MAX_SIZE + 1
+    ));
+    assert_eq!(round_trip(Word(u64::MAX)), Word(u64::MAX));
+}
+
+#[test]
+fn round_trips_std_types() {
+    assert_eq!(round_trip(Some(3u8)), Some(3u8));
+    assert_eq!(round_trip(None::<u8>), None);
+    assert_eq!(round_trip(Ok::<_, char>(true)), Ok(true));
+    assert_eq!(round_trip(Err::<bool, _>('x')), Err('x'));
+    assert_eq!(
+        round_trip((1u16, "pair".to_owned())),
+        (1u16, "pair".to_owned())
+    );
+    assert_eq!(round_trip(vec![Some(()), None]), vec![Some(()), None]);
+    assert_eq!(round_trip(String::new()), String::new());
+    assert_eq!(round_trip(vec![Word(7u32)]), vec![Word(7u32)]);
+}
@@ -0,0 +1,90 @@
+use welkin_binding_lib::{
+    snippet, ConversionError, DecodeError, Diagnose, FromWelkin, Segment, ToWelkin, Word,
+};
+use welkin_core::term::{Index, Term};
+
+/// The term of data with `variants` variants that applies the variable `index` to nothing.
+fn variable(variants: usize, index: usize) -> Term<String> {
+    (0..variants).fold(Term::Variable(Index(index)), |body, _| Term::Lambda {
+        erased: false,
+        body: Box::new(body),
+    })
+}
+
+#[test]
+fn displays_errors_with_their_paths() {
+    let error = DecodeError::new("invalid")
+        .within(Segment::Index(2), "u8")
+        .within(
+            Segment::Field {
+                variant: "new",
+                field: "items",
+            },
+            "Vec<u8>",
+        );
+
+    assert_eq!(error.expected.as_deref(), Some("u8"));
+    assert_eq!(
+        format!("{}", error),
+        "error reading u8 at new.items[2]: invalid"
+    );
+
+    let error = DecodeError::new("expected lambda").with_term(&Term::Variable(Index(0)));
+    assert_eq!(
+        format!("{}", error),
+        format!(
+            "error reading value: expected lambda, got `{:?}`",
+            Term::<String>::Variable(Index(0))
+        )
+    );
+}
+
+#[test]
+fn locates_errors_in_nested_values() {
+    let term = vec![None, Some((true, Word(7u8)))].to_welkin().unwrap();
+    let error = Vec::<Option<(bool, Word<u16>)>>::from_welkin(term)
+        .unwrap_err()
+        .diagnose();
+
+    assert_eq!(
+        error.path,
+        vec![
+            Segment::Index(1),
+            Segment::Field {
+                variant: "just",
+                field: "value",
+            },
+            Segment::Field {
+                variant: "new",
+                field: "right",
+            },
+        ]
+    );
+    assert_eq!(
+        format!("{}", error),
+        "error reading Word<u16> at [1].just.value.new.right: expected a word of 16 bits, got 8"
+    );
+}
+
+#[test]
+fn reports_the_terms_of_unreadable_variants() {
+    let term = variable(2, 5);
+    let error = bool::from_welkin(term.clone()).unwrap_err();
+
+    assert!(matches!(
+        error,
+        ConversionError::InvalidVariant {
+            got: 5,
+            expected_at_most: 2,
+            ..
+        }
+    ));
+    assert_eq!(error.diagnose().term, Some(snippet(&term)));
+
+    // `just` without its value
+    let term = variable(2, 0);
+    let error = Option::<bool>::from_welkin(term.clone()).unwrap_err();
+
+    assert!(matches!(error, ConversionError::InsufficientFields(_)));
+    assert_eq!(error.diagnose().term, Some(snippet(&term)));
+}
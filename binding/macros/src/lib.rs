@@ -1,15 +1,14 @@
 use quote::quote;
-mod bind;
 use synstructure::decl_derive;
-mod adt;
+use welkin_binding_codegen::derive_adt;
 
 decl_derive!(
-    [Adt, attributes(inductive)] =>
+    [Adt, attributes(inductive, variants, welkin)] =>
     adt_derive
 );
 
 fn adt_derive(item: synstructure::Structure) -> proc_macro2::TokenStream {
-    adt::derive(item).unwrap_or_else(|e| {
+    derive_adt(item).unwrap_or_else(|e| {
         let e = format!("{}", e);
 
         quote! {
@@ -21,7 +20,23 @@ fn adt_derive(item: synstructure::Structure) -> proc_macro2::TokenStream {
     })
 }
 
+/// Generates bindings for the data types read from the `#[path]` of an inline module, either a
+/// directory of welkin source or exported definitions. Call `welkin_binding::rerun_if_changed`
+/// on source directories from a build script to pick up files added to them.
 #[proc_macro]
 pub fn bind(mod_declaration: proc_macro::TokenStream) -> proc_macro::TokenStream {
-    bind::bind(mod_declaration.into()).into()
+    welkin_binding_codegen::bind(mod_declaration.into()).into()
+}
+
+/// Declares Rust functions that apply a welkin definition to their arguments.
+///
+/// The declarations start with `#![path = "..."]`, naming the welkin source directory relative to
+/// the crate root; the types of its definitions say which arguments are erased. Each item is a
+/// bodyless `fn` declaration whose arguments implement `ToAnalogue` and whose return type
+/// implements `FromAnalogue`. The definition defaults to the name of the function and can be given
+/// with `#[definition = "Bool::not"]`. Erased type parameters of the definition are applied
+/// implicitly. The generated function takes an `Evaluator` as its first argument.
+#[proc_macro]
+pub fn welkin_fn(declarations: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    welkin_binding_codegen::welkin_fn(declarations.into()).into()
 }
@@ -66,10 +66,13 @@ where
                         many1::<String, _, _>(none_of(['\\', '"'])),
                         '\\',
                         one_of(['\\', '"'])
-                    )))).map(|string: String| {
+                    )).skip(bare_token('"')))).map(|string: String| {
+                        let mut unescaped = String::new();
                         let mut string = string.chars();
-                        string.next_back();
-                        Literal::String(string.as_str().to_owned())
+                        while let Some(character) = string.next() {
+                            unescaped.extend(if character == '\\' { string.next() } else { Some(character) });
+                        }
+                        Literal::String(unescaped)
                     })
             },
             _ => unexpected_any("unknown literal format")
@@ -0,0 +1,28 @@
+use combine::Parser;
+use parser::{
+    term::{term, Block, Context, Literal},
+    Bump, Term,
+};
+
+fn parse_string(source: &str) -> (String, &str) {
+    let bump = Bump::new();
+    let (parsed, remainder) = term(Context {}, &bump).parse(source).unwrap();
+
+    match parsed {
+        Term::Block(Block::Literal(Literal::String(string), _)) => (string, remainder),
+        parsed => panic!("expected a string literal, got {:?}", parsed),
+    }
+}
+
+#[test]
+fn consumes_closing_quote() {
+    assert_eq!(parse_string(r#"~literal String "abc""#), ("abc".into(), ""));
+}
+
+#[test]
+fn unescapes_quotes_and_backslashes() {
+    assert_eq!(
+        parse_string(r#"~literal String "a\"b\\c""#),
+        (r#"a"b\c"#.into(), "")
+    );
+}
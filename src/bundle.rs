@@ -0,0 +1,395 @@
+use std::collections::{HashMap, HashSet};
+
+use parser::AbsolutePath;
+use serde::{Deserialize, Serialize};
+use welkin_core::term::{Index, Term};
+
+use crate::{
+    container::{Container, Kind},
+    graph::Graph,
+    hash::Hash,
+    TermExt,
+};
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Bundle {
+    pub entries: Vec<(AbsolutePath, Hash)>,
+    pub definitions: Vec<(AbsolutePath, Hash, Term<AbsolutePath>)>,
+}
+
+impl Container for Bundle {
+    const KIND: Kind = Kind::Bundle;
+    const VERSION: u32 = 1;
+}
+
+#[derive(Debug, Clone)]
+pub struct BundleOptions {
+    pub entries: Vec<AbsolutePath>,
+    /// Inline acyclic definitions of at most this many nodes into their use sites.
+    pub inline: Option<usize>,
+    /// Lift closed subterms of at least this many nodes that occur more than once into shared
+    /// definitions.
+    pub share: Option<usize>,
+}
+
+impl Default for BundleOptions {
+    fn default() -> Self {
+        BundleOptions {
+            entries: vec![AbsolutePath(vec!["main".into()])],
+            inline: None,
+            share: None,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum BundleError {
+    MissingEntry(AbsolutePath),
+}
+
+fn shift_down(term: &mut Term<AbsolutePath>, cutoff: usize) {
+    use Term::*;
+
+    match term {
+        Variable(Index(index)) => {
+            if *index > cutoff {
+                *index -= 1;
+            }
+        }
+        Lambda { body, .. } => shift_down(body, cutoff + 1),
+        Apply {
+            function, argument, ..
+        } => {
+            shift_down(function, cutoff);
+            shift_down(argument, cutoff);
+        }
+        Put(term) => shift_down(term, cutoff),
+        Duplicate { expression, body } => {
+            shift_down(expression, cutoff);
+            shift_down(body, cutoff + 1);
+        }
+        _ => {}
+    }
+}
+
+/// Removes everything without computational content: annotations, types, and erased lambdas and
+/// applications.
+pub fn erase(term: &Term<AbsolutePath>) -> Term<AbsolutePath> {
+    use Term::*;
+
+    match term {
+        Lambda { body, erased: true } => {
+            let mut body = erase(body);
+            shift_down(&mut body, 0);
+            body
+        }
+        Lambda {
+            body,
+            erased: false,
+        } => Lambda {
+            body: Box::new(erase(body)),
+            erased: false,
+        },
+        Apply {
+            function,
+            erased: true,
+            ..
+        } => erase(function),
+        Apply {
+            function,
+            argument,
+            erased: false,
+        } => Apply {
+            function: Box::new(erase(function)),
+            argument: Box::new(erase(argument)),
+            erased: false,
+        },
+        Annotation { expression, .. } => erase(expression),
+        Put(term) => Put(Box::new(erase(term))),
+        Duplicate { expression, body } => Duplicate {
+            expression: Box::new(erase(expression)),
+            body: Box::new(erase(body)),
+        },
+        Variable(index) => Variable(index.clone()),
+        Reference(reference) => Reference(reference.clone()),
+        Primitive(prim) => Primitive(prim.clone()),
+        Universe | Function { .. } | Wrap(_) => Universe,
+    }
+}
+
+fn size(term: &Term<AbsolutePath>) -> usize {
+    use Term::*;
+
+    1 + match term {
+        Lambda { body, .. } => size(body),
+        Apply {
+            function, argument, ..
+        } => size(function) + size(argument),
+        Put(term) | Wrap(term) => size(term),
+        Duplicate { expression, body } => size(expression) + size(body),
+        Function {
+            argument_type,
+            return_type,
+            ..
+        } => size(argument_type) + size(return_type),
+        Annotation { expression, ty, .. } => size(expression) + size(ty),
+        Variable(_) | Reference(_) | Primitive(_) | Universe => 0,
+    }
+}
+
+fn inline(
+    term: &Term<AbsolutePath>,
+    inlinable: &HashMap<AbsolutePath, Term<AbsolutePath>>,
+) -> Term<AbsolutePath> {
+    use Term::*;
+
+    match term {
+        Reference(reference) => match inlinable.get(reference) {
+            Some(term) => inline(term, inlinable),
+            None => Reference(reference.clone()),
+        },
+        Lambda { body, erased } => Lambda {
+            body: Box::new(inline(body, inlinable)),
+            erased: *erased,
+        },
+        Apply {
+            function,
+            argument,
+            erased,
+        } => Apply {
+            function: Box::new(inline(function, inlinable)),
+            argument: Box::new(inline(argument, inlinable)),
+            erased: *erased,
+        },
+        Put(term) => Put(Box::new(inline(term, inlinable))),
+        Duplicate { expression, body } => Duplicate {
+            expression: Box::new(inline(expression, inlinable)),
+            body: Box::new(inline(body, inlinable)),
+        },
+        term => term.clone(),
+    }
+}
+
+struct Sharing {
+    min_size: usize,
+    counts: HashMap<Hash, isize>,
+    shared: HashMap<Hash, AbsolutePath>,
+    definitions: Vec<(AbsolutePath, Term<AbsolutePath>)>,
+}
+
+impl Sharing {
+    // a lone reference or universe is no larger than a reference to a shared copy of it
+    fn qualifies(&self, size: usize, free: usize) -> bool {
+        free == 0 && size > 1 && size >= self.min_size
+    }
+
+    /// Adds `times` to the count of every qualifying subterm of `term`, returning the size of
+    /// `term` and the number of enclosing binders it refers to.
+    fn count(&mut self, term: &Term<AbsolutePath>, times: isize) -> (usize, usize) {
+        use Term::*;
+
+        let (size, free) = match term {
+            Variable(Index(index)) => (1, index + 1),
+            Lambda { body, .. } => {
+                let (size, free) = self.count(body, times);
+                (size + 1, free.saturating_sub(1))
+            }
+            Apply {
+                function, argument, ..
+            } => {
+                let (f_size, f_free) = self.count(function, times);
+                let (a_size, a_free) = self.count(argument, times);
+                (f_size + a_size + 1, f_free.max(a_free))
+            }
+            Put(term) => {
+                let (size, free) = self.count(term, times);
+                (size + 1, free)
+            }
+            Duplicate { expression, body } => {
+                let (e_size, e_free) = self.count(expression, times);
+                let (b_size, b_free) = self.count(body, times);
+                (e_size + b_size + 1, e_free.max(b_free.saturating_sub(1)))
+            }
+            _ => (1, 0),
+        };
+
+        if self.qualifies(size, free) {
+            *self.counts.entry(term.hash()).or_insert(0) += times;
+        }
+
+        (size, free)
+    }
+
+    /// Returns the shared definition standing in for `term`, defining it if `term` still occurs
+    /// more than once.
+    fn shared(&mut self, term: &Term<AbsolutePath>) -> Option<AbsolutePath> {
+        let hash = term.hash();
+
+        if let Some(name) = self.shared.get(&hash) {
+            return Some(name.clone());
+        }
+
+        let count = self.counts.get(&hash).copied().unwrap_or(0);
+        if count <= 1 {
+            return None;
+        }
+
+        let name = AbsolutePath(vec!["__shared".into(), format!("{}", self.shared.len())]);
+        self.shared.insert(hash, name.clone());
+
+        // every other occurrence becomes a reference, so the subterms within them no longer occur
+        // there, and only those that still repeat are shared in turn
+        self.count(term, 1 - count);
+        let definition = self.share(term, true);
+        self.definitions.push((name.clone(), definition));
+
+        Some(name)
+    }
+
+    /// Replaces repeated subterms of `term` with references to shared definitions, outermost
+    /// first.
+    fn share(&mut self, term: &Term<AbsolutePath>, root: bool) -> Term<AbsolutePath> {
+        use Term::*;
+
+        match term {
+            Lambda { .. } | Apply { .. } | Put(_) | Duplicate { .. } if !root => {
+                if let Some(name) = self.shared(term) {
+                    return Reference(name);
+                }
+            }
+            _ => {}
+        }
+
+        match term {
+            Lambda { body, erased } => Lambda {
+                body: Box::new(self.share(body, false)),
+                erased: *erased,
+            },
+            Apply {
+                function,
+                argument,
+                erased,
+            } => Apply {
+                function: Box::new(self.share(function, false)),
+                argument: Box::new(self.share(argument, false)),
+                erased: *erased,
+            },
+            Put(term) => Put(Box::new(self.share(term, false))),
+            Duplicate { expression, body } => Duplicate {
+                expression: Box::new(self.share(expression, false)),
+                body: Box::new(self.share(body, false)),
+            },
+            term => term.clone(),
+        }
+    }
+}
+
+impl Bundle {
+    pub fn new<'a>(
+        declarations: impl IntoIterator<
+            Item = &'a (AbsolutePath, Term<AbsolutePath>, Term<AbsolutePath>),
+        >,
+        options: &BundleOptions,
+    ) -> Result<Self, BundleError> {
+        let mut erased: HashMap<AbsolutePath, Term<AbsolutePath>> = declarations
+            .into_iter()
+            .map(|(path, _, term)| (path.clone(), erase(term)))
+            .collect();
+
+        for entry in &options.entries {
+            if !erased.contains_key(entry) {
+                return Err(BundleError::MissingEntry(entry.clone()));
+            }
+        }
+
+        let shake = |erased: &mut HashMap<AbsolutePath, Term<AbsolutePath>>| {
+            let graph = Graph::from_terms(erased.iter());
+            let roots: Vec<_> = options
+                .entries
+                .iter()
+                .map(|entry| format!("{:?}", entry))
+                .collect();
+            let reachable: HashSet<String> = graph
+                .reachable(roots.iter().map(String::as_str))
+                .into_iter()
+                .map(String::from)
+                .collect();
+            erased.retain(|path, _| reachable.contains(&format!("{:?}", path)));
+        };
+
+        shake(&mut erased);
+
+        if let Some(threshold) = options.inline {
+            let graph = Graph::from_terms(erased.iter());
+            let inlinable: HashMap<_, _> = erased
+                .iter()
+                .filter(|(path, term)| {
+                    let name = format!("{:?}", path);
+                    let recursive = graph
+                        .dependencies(&name)
+                        .map(|dependencies| {
+                            graph
+                                .reachable(dependencies.iter().map(String::as_str))
+                                .contains(name.as_str())
+                        })
+                        .unwrap_or(false);
+                    !recursive && size(term) <= threshold && !options.entries.contains(*path)
+                })
+                .map(|(path, term)| (path.clone(), term.clone()))
+                .collect();
+
+            for term in erased.values_mut() {
+                *term = inline(term, &inlinable);
+            }
+
+            shake(&mut erased);
+        }
+
+        let mut definitions: Vec<_> = erased.into_iter().collect();
+        definitions.sort_by_key(|(path, _)| format!("{:?}", path));
+
+        if let Some(min_size) = options.share {
+            let mut sharing = Sharing {
+                min_size,
+                counts: HashMap::new(),
+                shared: HashMap::new(),
+                definitions: vec![],
+            };
+
+            for (_, term) in &definitions {
+                sharing.count(term, 1);
+            }
+
+            for (_, term) in &mut definitions {
+                *term = sharing.share(term, true);
+            }
+
+            definitions.extend(sharing.definitions);
+        }
+
+        let definitions: Vec<_> = definitions
+            .into_iter()
+            .map(|(path, term)| {
+                let hash = term.hash();
+                (path, hash, term)
+            })
+            .collect();
+
+        let entries = options
+            .entries
+            .iter()
+            .map(|entry| {
+                let (_, hash, _) = definitions
+                    .iter()
+                    .find(|(path, _, _)| path == entry)
+                    .unwrap();
+                (entry.clone(), hash.clone())
+            })
+            .collect();
+
+        Ok(Bundle {
+            entries,
+            definitions,
+        })
+    }
+}
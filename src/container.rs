@@ -0,0 +1,308 @@
+use std::{
+    error::Error,
+    fmt::{self, Display},
+};
+
+use parser::AbsolutePath;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::{
+    hash::{hash_bytes, Hash},
+    Parameter, SerializableData, SerializableVariant, Terms,
+};
+
+pub const MAGIC: [u8; 4] = *b"WLKN";
+
+/// Version assigned to files written before the container format existed, which hold a bare
+/// bincode payload without magic bytes or a header.
+pub const LEGACY_VERSION: u32 = 0;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    Terms,
+    Definitions,
+    Stored,
+    Bundle,
+}
+
+impl Display for Kind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Kind::Terms => write!(f, "terms"),
+            Kind::Definitions => write!(f, "definitions"),
+            Kind::Stored => write!(f, "stored definition"),
+            Kind::Bundle => write!(f, "bundle"),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct Header {
+    kind: Kind,
+    version: u32,
+    checksum: Hash,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Document<T> {
+    format: String,
+    kind: Kind,
+    version: u32,
+    data: T,
+}
+
+#[derive(Debug)]
+pub enum ContainerError {
+    Kind { expected: Kind, found: Kind },
+    UnsupportedVersion { kind: Kind, version: u32 },
+    Checksum,
+    NotAContainer,
+    Bincode(bincode::Error),
+    Json(serde_json::Error),
+}
+
+impl Display for ContainerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ContainerError::Kind { expected, found } => {
+                write!(f, "expected a container of {}, found {}", expected, found)
+            }
+            ContainerError::UnsupportedVersion { kind, version } => {
+                write!(f, "no migration from version {} of {}", version, kind)
+            }
+            ContainerError::Checksum => write!(f, "checksum mismatch"),
+            ContainerError::NotAContainer => write!(f, "missing container header"),
+            ContainerError::Bincode(e) => write!(f, "bincode error: {}", e),
+            ContainerError::Json(e) => write!(f, "json error: {}", e),
+        }
+    }
+}
+
+impl Error for ContainerError {}
+
+/// A value that can be stored in a versioned container.
+///
+/// Bumping `VERSION` must be accompanied by a `migrate` arm that reads every older version.
+pub trait Container: Serialize + DeserializeOwned {
+    const KIND: Kind;
+    const VERSION: u32;
+
+    fn migrate(version: u32, payload: &[u8]) -> Result<Self, ContainerError> {
+        let _ = payload;
+        Err(ContainerError::UnsupportedVersion {
+            kind: Self::KIND,
+            version,
+        })
+    }
+}
+
+impl Container for Terms {
+    const KIND: Kind = Kind::Terms;
+    const VERSION: u32 = 1;
+
+    fn migrate(version: u32, payload: &[u8]) -> Result<Self, ContainerError> {
+        match version {
+            LEGACY_VERSION => bincode::deserialize(payload).map_err(ContainerError::Bincode),
+            version => Err(ContainerError::UnsupportedVersion {
+                kind: Self::KIND,
+                version,
+            }),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct SerializableVariantV1 {
+    inhabitants: Vec<(String, welkin_core::term::Term<AbsolutePath>)>,
+}
+
+impl From<SerializableVariantV1> for SerializableVariant {
+    fn from(variant: SerializableVariantV1) -> Self {
+        SerializableVariant {
+            inhabitants: variant.inhabitants,
+            indices: vec![],
+        }
+    }
+}
+
+fn migrate_variants(
+    variants: Vec<(String, SerializableVariantV1)>,
+) -> Vec<(String, SerializableVariant)> {
+    variants
+        .into_iter()
+        .map(|(ident, variant)| (ident, variant.into()))
+        .collect()
+}
+
+#[derive(Deserialize)]
+struct SerializableDataV1 {
+    variants: Vec<(String, SerializableVariantV1)>,
+    ident: String,
+    type_arguments: usize,
+    indices: usize,
+    skipped_type_arguments: Vec<usize>,
+}
+
+impl From<SerializableDataV1> for SerializableData {
+    fn from(data: SerializableDataV1) -> Self {
+        SerializableData {
+            parameters: (0..data.type_arguments + data.skipped_type_arguments.len())
+                .map(|index| {
+                    if data.skipped_type_arguments.contains(&index) {
+                        Parameter::Erased
+                    } else {
+                        Parameter::Type
+                    }
+                })
+                .collect(),
+            variants: migrate_variants(data.variants),
+            ident: data.ident,
+            type_arguments: data.type_arguments,
+            indices: data.indices,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct SerializableDataV2 {
+    variants: Vec<(String, SerializableVariantV1)>,
+    ident: String,
+    type_arguments: usize,
+    indices: usize,
+    parameters: Vec<Parameter>,
+}
+
+impl From<SerializableDataV2> for SerializableData {
+    fn from(data: SerializableDataV2) -> Self {
+        SerializableData {
+            variants: migrate_variants(data.variants),
+            ident: data.ident,
+            type_arguments: data.type_arguments,
+            indices: data.indices,
+            parameters: data.parameters,
+        }
+    }
+}
+
+impl Container for Vec<SerializableData> {
+    const KIND: Kind = Kind::Definitions;
+    const VERSION: u32 = 3;
+
+    fn migrate(version: u32, payload: &[u8]) -> Result<Self, ContainerError> {
+        match version {
+            // version 1 recorded skipped type arguments instead of how each parameter is carried
+            LEGACY_VERSION | 1 => bincode::deserialize::<Vec<SerializableDataV1>>(payload)
+                .map(|data| data.into_iter().map(From::from).collect())
+                .map_err(ContainerError::Bincode),
+            // version 2 didn't record the indices variants are constructed with
+            2 => bincode::deserialize::<Vec<SerializableDataV2>>(payload)
+                .map(|data| data.into_iter().map(From::from).collect())
+                .map_err(ContainerError::Bincode),
+            version => Err(ContainerError::UnsupportedVersion {
+                kind: Self::KIND,
+                version,
+            }),
+        }
+    }
+}
+
+pub fn encode<T: Container>(value: &T) -> Vec<u8> {
+    let payload = bincode::serialize(value).unwrap();
+
+    let header = Header {
+        kind: T::KIND,
+        version: T::VERSION,
+        checksum: hash_bytes(&payload),
+    };
+
+    let mut data = MAGIC.to_vec();
+    data.extend(bincode::serialize(&header).unwrap());
+    data.extend(payload);
+    data
+}
+
+fn header(data: &[u8]) -> Result<(Header, &[u8]), ContainerError> {
+    if !data.starts_with(&MAGIC) {
+        return Err(ContainerError::NotAContainer);
+    }
+
+    let data = &data[MAGIC.len()..];
+    let header: Header = bincode::deserialize(data).map_err(ContainerError::Bincode)?;
+    let len = bincode::serialized_size(&header).map_err(ContainerError::Bincode)? as usize;
+    let payload = &data[len..];
+
+    if hash_bytes(payload) != header.checksum {
+        return Err(ContainerError::Checksum);
+    }
+
+    Ok((header, payload))
+}
+
+/// Returns the kind of data held by a container.
+pub fn kind(data: &[u8]) -> Result<Kind, ContainerError> {
+    header(data).map(|(header, _)| header.kind)
+}
+
+/// Reads a container of `T`, migrating older versions. Data without magic bytes is treated as a
+/// legacy bare bincode payload.
+pub fn decode<T: Container>(data: &[u8]) -> Result<T, ContainerError> {
+    if !data.starts_with(&MAGIC) {
+        return T::migrate(LEGACY_VERSION, data);
+    }
+
+    let (header, payload) = header(data)?;
+
+    if header.kind != T::KIND {
+        return Err(ContainerError::Kind {
+            expected: T::KIND,
+            found: header.kind,
+        });
+    }
+
+    if header.version == T::VERSION {
+        bincode::deserialize(payload).map_err(ContainerError::Bincode)
+    } else {
+        T::migrate(header.version, payload)
+    }
+}
+
+/// Writes `T` as pretty-printed JSON with the same kind and version information as the binary
+/// container, suitable for diffing.
+pub fn to_json<T: Container>(value: &T) -> String {
+    serde_json::to_string_pretty(&Document {
+        format: "welkin".into(),
+        kind: T::KIND,
+        version: T::VERSION,
+        data: value,
+    })
+    .unwrap()
+}
+
+/// Returns the kind of data held by a JSON export.
+pub fn json_kind(data: &str) -> Result<Kind, ContainerError> {
+    let document: Document<serde::de::IgnoredAny> =
+        serde_json::from_str(data).map_err(ContainerError::Json)?;
+
+    Ok(document.kind)
+}
+
+pub fn from_json<T: Container>(data: &str) -> Result<T, ContainerError> {
+    let document: Document<serde_json::Value> =
+        serde_json::from_str(data).map_err(ContainerError::Json)?;
+
+    if document.kind != T::KIND {
+        return Err(ContainerError::Kind {
+            expected: T::KIND,
+            found: document.kind,
+        });
+    }
+
+    if document.version != T::VERSION {
+        return Err(ContainerError::UnsupportedVersion {
+            kind: T::KIND,
+            version: document.version,
+        });
+    }
+
+    serde_json::from_value(document.data).map_err(ContainerError::Json)
+}
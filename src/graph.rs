@@ -0,0 +1,235 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+
+use parser::AbsolutePath;
+use serde::{Deserialize, Serialize};
+use welkin_core::term::{Primitives, Term};
+
+pub fn references<'a, T, V: Primitives<T>>(term: &'a Term<T, V>, f: &mut impl FnMut(&'a T)) {
+    use Term::*;
+
+    match term {
+        Reference(reference) => f(reference),
+        Lambda { body, .. } => references(body, f),
+        Apply {
+            function, argument, ..
+        } => {
+            references(function, f);
+            references(argument, f);
+        }
+        Put(term) | Wrap(term) => references(term, f),
+        Duplicate { expression, body } => {
+            references(expression, f);
+            references(body, f);
+        }
+        Function {
+            argument_type,
+            return_type,
+            ..
+        } => {
+            references(argument_type, f);
+            references(return_type, f);
+        }
+        Annotation { expression, ty, .. } => {
+            references(expression, f);
+            references(ty, f);
+        }
+        Variable(_) | Universe | Primitive(_) => {}
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(transparent)]
+pub struct Graph {
+    edges: BTreeMap<String, BTreeSet<String>>,
+}
+
+impl Graph {
+    pub fn new<'a>(
+        declarations: impl IntoIterator<
+            Item = &'a (AbsolutePath, Term<AbsolutePath>, Term<AbsolutePath>),
+        >,
+    ) -> Self {
+        let mut graph = Graph::default();
+
+        for (path, ty, term) in declarations {
+            graph.insert(path, &[ty, term]);
+        }
+
+        graph
+    }
+
+    /// Builds the graph of untyped definitions, such as those of an erased bundle.
+    pub fn from_terms<'a>(
+        definitions: impl IntoIterator<Item = (&'a AbsolutePath, &'a Term<AbsolutePath>)>,
+    ) -> Self {
+        let mut graph = Graph::default();
+
+        for (path, term) in definitions {
+            graph.insert(path, &[term]);
+        }
+
+        graph
+    }
+
+    fn insert(&mut self, path: &AbsolutePath, terms: &[&Term<AbsolutePath>]) {
+        let mut dependencies = BTreeSet::new();
+        let mut insert = |reference: &AbsolutePath| {
+            dependencies.insert(format!("{:?}", reference));
+        };
+        for term in terms {
+            references(*term, &mut insert);
+        }
+        self.edges.insert(format!("{:?}", path), dependencies);
+    }
+
+    pub fn definitions(&self) -> impl Iterator<Item = &str> {
+        self.edges.keys().map(String::as_str)
+    }
+
+    pub fn dependencies(&self, name: &str) -> Option<&BTreeSet<String>> {
+        self.edges.get(name)
+    }
+
+    /// Every definition transitively referenced by the given roots, including the roots themselves.
+    pub fn reachable<'a>(&'a self, roots: impl IntoIterator<Item = &'a str>) -> BTreeSet<&'a str> {
+        let mut seen = BTreeSet::new();
+        let mut stack: Vec<&str> = roots.into_iter().collect();
+
+        while let Some(name) = stack.pop() {
+            if let Some((name, dependencies)) = self.edges.get_key_value(name) {
+                if seen.insert(name.as_str()) {
+                    stack.extend(dependencies.iter().map(String::as_str));
+                }
+            }
+        }
+
+        seen
+    }
+
+    /// The strongly connected components of the graph, each sorted by name. Every component comes
+    /// after the components it depends on.
+    pub fn components(&self) -> Vec<Vec<&str>> {
+        struct Tarjan<'a> {
+            graph: &'a Graph,
+            index: HashMap<&'a str, usize>,
+            low: HashMap<&'a str, usize>,
+            stack: Vec<&'a str>,
+            on_stack: HashSet<&'a str>,
+            components: Vec<Vec<&'a str>>,
+        }
+
+        impl<'a> Tarjan<'a> {
+            fn visit(&mut self, name: &'a str) {
+                let graph = self.graph;
+                let index = self.index.len();
+
+                self.index.insert(name, index);
+                self.low.insert(name, index);
+                self.stack.push(name);
+                self.on_stack.insert(name);
+
+                for dependency in &graph.edges[name] {
+                    let dependency = dependency.as_str();
+
+                    if !graph.edges.contains_key(dependency) {
+                        continue;
+                    }
+
+                    let low = if !self.index.contains_key(dependency) {
+                        self.visit(dependency);
+                        self.low[dependency]
+                    } else if self.on_stack.contains(dependency) {
+                        self.index[dependency]
+                    } else {
+                        continue;
+                    };
+
+                    if low < self.low[name] {
+                        self.low.insert(name, low);
+                    }
+                }
+
+                if self.low[name] == index {
+                    let mut component = vec![];
+
+                    while let Some(member) = self.stack.pop() {
+                        self.on_stack.remove(member);
+                        component.push(member);
+
+                        if member == name {
+                            break;
+                        }
+                    }
+
+                    component.sort();
+                    self.components.push(component);
+                }
+            }
+        }
+
+        let mut tarjan = Tarjan {
+            graph: self,
+            index: HashMap::new(),
+            low: HashMap::new(),
+            stack: vec![],
+            on_stack: HashSet::new(),
+            components: vec![],
+        };
+
+        for name in self.definitions() {
+            if !tarjan.index.contains_key(name) {
+                tarjan.visit(name);
+            }
+        }
+
+        tarjan.components
+    }
+
+    pub fn unreachable<'a>(&'a self, roots: impl IntoIterator<Item = &'a str>) -> Vec<&'a str> {
+        let reachable = self.reachable(roots);
+
+        self.definitions()
+            .filter(|name| !reachable.contains(name))
+            .collect()
+    }
+
+    /// Number of distinct definitions transitively referenced by `name`, not counting `name`
+    /// itself unless it is recursive.
+    pub fn closure_size(&self, name: &str) -> usize {
+        let dependencies = match self.edges.get(name) {
+            Some(dependencies) => dependencies,
+            None => return 0,
+        };
+
+        self.reachable(dependencies.iter().map(String::as_str))
+            .len()
+    }
+
+    /// Closure sizes of all definitions, largest first.
+    pub fn closure_sizes(&self) -> Vec<(&str, usize)> {
+        let mut sizes: Vec<_> = self
+            .definitions()
+            .map(|name| (name, self.closure_size(name)))
+            .collect();
+        sizes.sort_by(|(a_name, a), (b_name, b)| b.cmp(a).then(a_name.cmp(b_name)));
+        sizes
+    }
+
+    pub fn to_dot(&self) -> String {
+        fn quote(name: &str) -> String {
+            format!("\"{}\"", name.replace('\\', "\\\\").replace('"', "\\\""))
+        }
+
+        let mut dot = String::from("digraph welkin {\n");
+
+        for (name, dependencies) in &self.edges {
+            dot.push_str(&format!("    {};\n", quote(name)));
+            for dependency in dependencies {
+                dot.push_str(&format!("    {} -> {};\n", quote(name), quote(dependency)));
+            }
+        }
+
+        dot.push_str("}\n");
+        dot
+    }
+}
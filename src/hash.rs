@@ -1,14 +1,16 @@
 use std::{
     borrow::Cow,
     convert::TryInto,
-    fmt::{self, Debug},
+    fmt::{self, Debug, Display},
+    str::FromStr,
 };
 
+use parser::AbsolutePath;
 use ring::digest::{Context, SHA256};
 use serde::{Deserialize, Serialize};
 use welkin_core::term::{None, Primitives, Term};
 
-#[derive(Serialize, Deserialize, Clone, Hash, Eq, PartialEq)]
+#[derive(Serialize, Deserialize, Clone, Hash, Eq, PartialEq, Ord, PartialOrd)]
 pub struct Hash([u8; 32]);
 
 impl Debug for Hash {
@@ -21,6 +23,36 @@ impl Debug for Hash {
     }
 }
 
+impl Display for Hash {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for byte in &self.0 {
+            write!(f, "{:02x}", byte)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub struct InvalidHash;
+
+impl FromStr for Hash {
+    type Err = InvalidHash;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.len() != 64 || !s.is_ascii() {
+            return Err(InvalidHash);
+        }
+
+        let mut hash = [0u8; 32];
+
+        for (i, byte) in hash.iter_mut().enumerate() {
+            *byte = u8::from_str_radix(&s[i * 2..i * 2 + 2], 16).map_err(|_| InvalidHash)?;
+        }
+
+        Ok(Hash(hash))
+    }
+}
+
 pub trait ReferenceHash {
     fn hash(&self) -> Cow<'_, [u8]>;
 }
@@ -64,6 +96,12 @@ impl ReferenceHash for Hash {
     }
 }
 
+impl ReferenceHash for AbsolutePath {
+    fn hash(&self) -> Cow<'_, [u8]> {
+        Cow::Owned(format!("{:?}", self).into_bytes())
+    }
+}
+
 impl ReferenceHash for None {
     fn hash(&self) -> Cow<'_, [u8]> {
         panic!()
@@ -130,3 +168,11 @@ pub(crate) fn hash<T: ReferenceHash, V: Primitives<T> + ReferenceHash>(t: &Term<
 
     Hash(ctx.finish().as_ref().try_into().unwrap())
 }
+
+pub(crate) fn hash_bytes(data: &[u8]) -> Hash {
+    let mut ctx = Context::new(&SHA256);
+
+    ctx.update(data);
+
+    Hash(ctx.finish().as_ref().try_into().unwrap())
+}
@@ -1,5 +1,5 @@
 use std::{
-    convert::TryFrom,
+    collections::HashSet,
     ops::{Deref, DerefMut},
 };
 
@@ -21,6 +21,20 @@ pub mod definitions;
 
 pub mod compiler;
 
+pub mod graph;
+
+pub mod bundle;
+
+pub mod container;
+
+pub mod store;
+
+pub mod source;
+
+pub mod runtime;
+
+pub mod typescript;
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Terms {
     pub data: Vec<(AbsolutePath, Term<AbsolutePath>, Term<AbsolutePath>)>,
@@ -536,98 +550,247 @@ impl<'a, 'b, T: 'a + 'b, U: Primitives<T> + 'a + 'b> Reallocate<T, U, Bumpalo<'b
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SerializableVariant {
     pub inhabitants: Vec<(String, welkin_core::term::Term<AbsolutePath>)>,
+    /// The reference heading each index the variant is constructed with, such as `Size::succ` for
+    /// `~with { Size::succ(size) }`, or `None` where an index isn't headed by a reference.
+    pub indices: Vec<Option<AbsolutePath>>,
+}
+
+/// How a type argument of a data type is represented in bindings.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Parameter {
+    /// A type, or a type family such as `A: Size |-> *` whose applications are all represented by
+    /// the same Rust type, carried as a type parameter.
+    Type,
+    /// A value taken by constructors at runtime but not stored in the data they build, left out
+    /// of the binding like an index.
+    Value,
+    /// An erased value, or a type that no field is built from, left out of the binding.
+    Erased,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SerializableData {
     pub variants: Vec<(String, SerializableVariant)>,
     pub ident: String,
+    /// The number of `Parameter::Type` entries in `parameters`.
     pub type_arguments: usize,
     pub indices: usize,
-    pub skipped_type_arguments: Vec<usize>,
+    pub parameters: Vec<Parameter>,
 }
 
-#[derive(Debug)]
-pub struct NotCompatible;
+impl SerializableData {
+    /// Keeps only the arguments of an application of this type that are carried in bindings,
+    /// dropping other parameters and indices. Every parameter must be applied, followed by at
+    /// most as many arguments as there are indices, or `None` is returned.
+    pub fn carried_arguments<T>(&self, arguments: impl IntoIterator<Item = T>) -> Option<Vec<T>> {
+        let arguments: Vec<_> = arguments.into_iter().collect();
+
+        // leaving off indices applies the type as a family, which is still bound by its parameters
+        if arguments.len() < self.parameters.len()
+            || arguments.len() > self.parameters.len() + self.indices
+        {
+            return None;
+        }
 
-impl<'a> TryFrom<Data<'a>> for SerializableData {
-    type Error = NotCompatible;
+        Some(
+            arguments
+                .into_iter()
+                .enumerate()
+                .filter(|(index, _)| self.parameters.get(*index) == Some(&Parameter::Type))
+                .map(|(_, argument)| argument)
+                .collect(),
+        )
+    }
+}
 
-    fn try_from(data: Data<'a>) -> Result<Self, Self::Error> {
-        let type_arguments = data.type_arguments;
+fn is_kind(term: &parser::Term<'_>) -> bool {
+    match term {
+        parser::Term::Universe => true,
+        parser::Term::Function { return_type, .. } => is_kind(&return_type.data),
+        _ => false,
+    }
+}
 
-        let mut skipped_type_arguments = vec![];
+/// Collects the types a field's type is built from by application, which are the types a binding
+/// of that field can represent.
+pub fn mentioned_types(term: &Term<AbsolutePath>, used: &mut HashSet<String>) {
+    match term {
+        Term::Apply {
+            function, argument, ..
+        } => {
+            mentioned_types(function, used);
+            mentioned_types(argument, used);
+        }
+        Term::Reference(reference) => {
+            if let [segment] = reference.0.as_slice() {
+                used.insert(segment.clone());
+            }
+        }
+        _ => {}
+    }
+}
 
-        let type_arguments = type_arguments
+/// Replaces references to carried type arguments, and applications of carried type families,
+/// with the positional `T{n}` names used by bindings.
+fn carry_type_arguments(
+    term: Term<AbsolutePath>,
+    carried: &[(String, usize)],
+) -> Term<AbsolutePath> {
+    fn carried_name(
+        term: &Term<AbsolutePath>,
+        carried: &[(String, usize)],
+    ) -> Option<AbsolutePath> {
+        let mut head = term;
+
+        while let Term::Apply { function, .. } = head {
+            head = &**function;
+        }
+
+        if let Term::Reference(reference) = head {
+            if let [segment] = reference.0.as_slice() {
+                return carried
+                    .iter()
+                    .find(|(name, _)| name == segment)
+                    .map(|(_, position)| AbsolutePath(vec![format!("T{}", position)]));
+            }
+        }
+
+        None
+    }
+
+    if let Some(name) = carried_name(&term, carried) {
+        return Term::Reference(name);
+    }
+
+    match term {
+        Term::Lambda { body, erased } => Term::Lambda {
+            body: Box::new(carry_type_arguments(*body, carried)),
+            erased,
+        },
+        Term::Apply {
+            function,
+            argument,
+            erased,
+        } => Term::Apply {
+            function: Box::new(carry_type_arguments(*function, carried)),
+            argument: Box::new(carry_type_arguments(*argument, carried)),
+            erased,
+        },
+        Term::Put(term) => Term::Put(Box::new(carry_type_arguments(*term, carried))),
+        Term::Wrap(term) => Term::Wrap(Box::new(carry_type_arguments(*term, carried))),
+        Term::Duplicate { expression, body } => Term::Duplicate {
+            expression: Box::new(carry_type_arguments(*expression, carried)),
+            body: Box::new(carry_type_arguments(*body, carried)),
+        },
+        Term::Function {
+            argument_type,
+            return_type,
+            erased,
+        } => Term::Function {
+            argument_type: Box::new(carry_type_arguments(*argument_type, carried)),
+            return_type: Box::new(carry_type_arguments(*return_type, carried)),
+            erased,
+        },
+        Term::Annotation {
+            checked,
+            expression,
+            ty,
+        } => Term::Annotation {
+            checked,
+            expression: Box::new(carry_type_arguments(*expression, carried)),
+            ty: Box::new(carry_type_arguments(*ty, carried)),
+        },
+        term => term,
+    }
+}
+
+/// The reference an index expression is headed by, if any.
+fn index_head(term: &Term<AbsolutePath>) -> Option<AbsolutePath> {
+    let mut head = term;
+
+    while let Term::Apply { function, .. } = head {
+        head = &**function;
+    }
+
+    match head {
+        Term::Reference(reference) => Some(reference.clone()),
+        _ => None,
+    }
+}
+
+impl<'a> From<Data<'a>> for SerializableData {
+    fn from(data: Data<'a>) -> Self {
+        let variants: Vec<_> = data
+            .variants
+            .into_iter()
+            .map(|variant| {
+                (
+                    variant.ident.0.data.as_str().to_owned(),
+                    variant
+                        .inhabitants
+                        .into_iter()
+                        .filter(|(_, _, erased)| !erased)
+                        .map(|(ident, ty, _)| {
+                            (ident.0.data.to_string(), ty.compile(LocalResolver::new()))
+                        })
+                        .collect::<Vec<_>>(),
+                    variant
+                        .indices
+                        .into_iter()
+                        .map(|index| index_head(&index.compile(LocalResolver::new())))
+                        .collect::<Vec<_>>(),
+                )
+            })
+            .collect();
+
+        let mut used = HashSet::new();
+
+        for (_, inhabitants, _) in &variants {
+            for (_, ty) in inhabitants {
+                mentioned_types(ty, &mut used);
+            }
+        }
+
+        let mut carried = vec![];
+
+        // constructors take non-erased parameters at runtime, but the data they build doesn't store
+        // them, so only the types fields are built from are carried
+        let parameters = data
+            .type_arguments
             .into_iter()
-            .enumerate()
-            .filter_map(
-                |(index, (ident, ty, erased))| -> Option<Result<_, NotCompatible>> {
-                    if !erased {
-                        Some(Err(NotCompatible))
-                    } else {
-                        if let Some(parser::Term::Universe) = ty {
-                            Some(Ok(ident.0.data.as_str().to_owned()))
-                        } else if ty.is_none() {
-                            Some(Ok(ident.0.data.as_str().to_owned()))
-                        } else {
-                            skipped_type_arguments.push(index);
-                            None
-                        }
-                    }
-                },
-            )
-            .collect::<Result<Vec<_>, _>>()?;
-
-        Ok(SerializableData {
-            skipped_type_arguments,
+            .map(|(ident, ty, erased)| {
+                let name = ident.0.data.as_str().to_owned();
+                if ty.as_ref().map(is_kind).unwrap_or(true) && used.contains(&name) {
+                    carried.push((name, carried.len()));
+                    Parameter::Type
+                } else if erased {
+                    Parameter::Erased
+                } else {
+                    Parameter::Value
+                }
+            })
+            .collect();
+
+        SerializableData {
+            type_arguments: carried.len(),
             indices: data.indices.len(),
-            variants: data
-                .variants
+            parameters,
+            variants: variants
                 .into_iter()
-                .map(|variant| {
+                .map(|(ident, inhabitants, indices)| {
                     (
-                        variant.ident.0.data.as_str().to_owned(),
+                        ident,
                         SerializableVariant {
-                            inhabitants: variant
-                                .inhabitants
+                            inhabitants: inhabitants
                                 .into_iter()
-                                .filter_map(|(ident, ty, erased)| {
-                                    if erased {
-                                        None
-                                    } else {
-                                        Some((
-                                            ident.0.data.to_string(),
-                                            ty.compile(LocalResolver::new()).map_reference(
-                                                |reference| {
-                                                    if let Some(segment) = reference.0.first() {
-                                                        if reference.0.len() == 1 {
-                                                            if let Some(position) = type_arguments
-                                                                .iter()
-                                                                .position(|ident| ident == segment)
-                                                            {
-                                                                return Term::Reference(
-                                                                    AbsolutePath(vec![format!(
-                                                                        "T{}",
-                                                                        position
-                                                                    )]),
-                                                                );
-                                                            }
-                                                        }
-                                                    };
-                                                    Term::Reference(reference)
-                                                },
-                                            ),
-                                        ))
-                                    }
-                                })
+                                .map(|(ident, ty)| (ident, carry_type_arguments(ty, &carried)))
                                 .collect(),
+                            indices,
                         },
                     )
                 })
                 .collect(),
             ident: data.ident.0.data.as_str().to_owned(),
-            type_arguments: type_arguments.len(),
-        })
+        }
     }
 }
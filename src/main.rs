@@ -1,24 +1,23 @@
 use std::{
     collections::{HashMap, HashSet},
-    convert::TryInto,
     fs::read_to_string,
-    path::Component,
     process::exit,
     time::SystemTime,
 };
 
-use combine::{stream::position, EasyParser};
+use combine::EasyParser;
 use welkin::{
-    compiler::{item::Compile as _, term::Compile as _, BumpPath, LocalResolver, Resolve},
-    Bumpalo, SerializableData, Terms,
+    bundle::{Bundle, BundleOptions},
+    compiler::{term::Compile as _, BumpPath, LocalResolver},
+    container::{self, Kind},
+    graph::Graph,
+    store::{Store, StoredDefinition},
+    Bumpalo, SerializableData, TermExt, Terms,
 };
 
-use parser::{
-    items, term::term as parse_term, AbsolutePath, BlockItem, BumpString, BumpVec, Ident, Item,
-    Path,
-};
+use parser::{term::term as parse_term, AbsolutePath};
 
-use walkdir::WalkDir;
+mod repl;
 use welkin_core::{
     net::{Net, VisitNetExt},
     term::{
@@ -27,7 +26,10 @@ use welkin_core::{
     },
 };
 
-fn read_size<T, V: Primitives<T>, A: Allocator<T, V>>(term: Term<T, V, A>) -> String {
+// each reader gives up on a term that isn't of the shape its type is compiled to, as can happen in
+// a library that defines the type differently, and the term is then shown as it is
+
+fn read_size<T, V: Primitives<T>, A: Allocator<T, V>>(term: Term<T, V, A>) -> Option<String> {
     if let Term::Lambda { body, .. } = term {
         if let Term::Lambda { body, .. } = body.into_inner() {
             let mut term = body;
@@ -36,30 +38,24 @@ fn read_size<T, V: Primitives<T>, A: Allocator<T, V>>(term: Term<T, V, A>) -> St
                 ctr += 1;
                 term = argument;
             }
-            return format!("SIZE = {}", ctr);
+            return Some(format!("SIZE = {}", ctr));
         }
-        panic!()
     }
-    panic!()
+    None
 }
 
-fn read_bool<T, V: Primitives<T>, A: Allocator<T, V>>(term: Term<T, V, A>) -> bool {
+fn read_bool<T, V: Primitives<T>, A: Allocator<T, V>>(term: Term<T, V, A>) -> Option<bool> {
     if let Term::Lambda { body, .. } = term {
         if let Term::Lambda { body, .. } = body.into_inner() {
             if let Term::Variable(var) = body.into_inner() {
-                var.0 == 1
-            } else {
-                panic!()
+                return Some(var.0 == 1);
             }
-        } else {
-            panic!()
         }
-    } else {
-        panic!()
     }
+    None
 }
 
-fn read_word<T, V: Primitives<T>, A: Allocator<T, V>>(term: Term<T, V, A>) -> Vec<bool> {
+fn read_word<T, V: Primitives<T>, A: Allocator<T, V>>(term: Term<T, V, A>) -> Option<Vec<bool>> {
     let mut data = vec![];
     let mut term = term;
     loop {
@@ -67,26 +63,26 @@ fn read_word<T, V: Primitives<T>, A: Allocator<T, V>>(term: Term<T, V, A>) -> Ve
             term = body.into_inner();
         }
         match term {
-            Term::Variable(_) => break data,
+            Term::Variable(_) => break Some(data),
             Term::Apply {
                 argument, function, ..
             } => {
                 match function.into_inner() {
                     Term::Variable(Index(0)) => data.push(true),
                     Term::Variable(Index(1)) => data.push(false),
-                    _ => panic!("invalid word"),
+                    _ => return None,
                 };
                 term = argument.into_inner();
             }
-            _ => panic!("invalid word"),
+            _ => return None,
         }
     }
 }
 
-fn read_char<T, V: Primitives<T>, A: Allocator<T, V>>(term: Term<T, V, A>) -> char {
+fn read_char<T, V: Primitives<T>, A: Allocator<T, V>>(term: Term<T, V, A>) -> Option<char> {
     if let Term::Lambda { body, .. } = term {
         if let Term::Apply { argument, .. } = body.into_inner() {
-            let bits = read_word(argument.into_inner());
+            let bits = read_word(argument.into_inner())?;
             let mut bytes = [0u8; 4];
 
             for (bit, bits) in bits.as_slice().chunks(8).rev().enumerate() {
@@ -101,22 +97,19 @@ fn read_char<T, V: Primitives<T>, A: Allocator<T, V>>(term: Term<T, V, A>) -> ch
                     byte |= 1 << (7 - idx);
                 }
 
-                bytes[bit] = byte;
+                *bytes.get_mut(bit)? = byte;
             }
 
-            char::from_u32(u32::from_be_bytes(bytes)).unwrap()
-        } else {
-            panic!()
+            return char::from_u32(u32::from_be_bytes(bytes));
         }
-    } else {
-        panic!()
     }
+    None
 }
 
 fn read_vector<T, V: Primitives<T>, A: Allocator<T, V>, U>(
     term: Term<T, V, A>,
-    read_element: impl Fn(Term<T, V, A>) -> U,
-) -> Vec<U> {
+    read_element: impl Fn(Term<T, V, A>) -> Option<U>,
+) -> Option<Vec<U>> {
     let mut data = vec![];
     let mut term = term;
     loop {
@@ -124,49 +117,45 @@ fn read_vector<T, V: Primitives<T>, A: Allocator<T, V>, U>(
             term = body.into_inner();
         }
         match term {
-            Term::Variable(_) => break data,
+            Term::Variable(_) => break Some(data),
             Term::Apply {
                 argument, function, ..
             } => {
                 if let Term::Apply { argument, .. } = function.into_inner() {
-                    data.push(read_element(argument.into_inner()));
+                    data.push(read_element(argument.into_inner())?);
                 } else {
-                    panic!()
+                    return None;
                 }
                 term = argument.into_inner();
             }
-            _ => panic!("invalid vector"),
+            _ => return None,
         }
     }
 }
 
-fn read_string<T, V: Primitives<T>, A: Allocator<T, V>>(term: Term<T, V, A>) -> String {
+fn read_string<T, V: Primitives<T>, A: Allocator<T, V>>(term: Term<T, V, A>) -> Option<String> {
     if let Term::Lambda { body, .. } = term {
         if let Term::Apply { argument, .. } = body.into_inner() {
-            read_vector(argument.into_inner(), read_char)
-                .into_iter()
-                .collect()
-        } else {
-            panic!()
+            return Some(
+                read_vector(argument.into_inner(), read_char)?
+                    .into_iter()
+                    .collect(),
+            );
         }
-    } else {
-        panic!()
     }
+    None
 }
 
 fn read_sized<T, V: Primitives<T>, A: Allocator<T, V>, U>(
     term: Term<T, V, A>,
-    read_element: impl Fn(Term<T, V, A>) -> U,
-) -> U {
+    read_element: impl Fn(Term<T, V, A>) -> Option<U>,
+) -> Option<U> {
     if let Term::Lambda { body, .. } = term {
         if let Term::Apply { argument, .. } = body.into_inner() {
-            read_element(argument.into_inner())
-        } else {
-            panic!()
+            return read_element(argument.into_inner());
         }
-    } else {
-        panic!()
     }
+    None
 }
 
 fn bump_to_system<'a, T: 'a, U: Primitives<T> + 'a>(
@@ -217,148 +206,62 @@ fn bump_to_system<'a, T: 'a, U: Primitives<T> + 'a>(
     }
 }
 
-fn main() {
-    let mut cache = MapCache::new();
+pub(crate) type Defs<'a> = HashMap<
+    BumpPath<'a>,
+    (
+        Term<BumpPath<'a>, welkin_core::term::None, Bumpalo<'a>>,
+        Term<BumpPath<'a>, welkin_core::term::None, Bumpalo<'a>>,
+    ),
+>;
 
-    let mut declarations = vec![];
-    let bump = bumpalo::Bump::new();
-
-    let mut parsing_time = 0;
-    let mut tc_times = vec![];
-    let mut codegen_time = 0;
+pub(crate) use welkin::source::{load, Library};
 
-    let names = if let Ok(dump) = std::env::var("WELKIN_DUMP_NAMES") {
-        dump.split(",").into_iter().map(String::from).collect()
-    } else {
-        HashSet::new()
-    };
+pub(crate) fn build_defs<'a>(
+    declarations: impl IntoIterator<Item = (AbsolutePath, Term<AbsolutePath>, Term<AbsolutePath>)>,
+    defs_bump: &'a bumpalo::Bump,
+) -> Defs<'a> {
+    let defs_bm = Bumpalo(defs_bump);
 
-    let mut data_declarations = vec![];
-
-    for entry in WalkDir::new(std::env::args().skip(1).next().unwrap_or_else(|| {
-        eprintln!("USAGE:\nwelkin <SOURCE_DIR>");
-        exit(1)
-    }))
-    .into_iter()
-    .skip(1)
-    {
-        let entry = entry.unwrap();
-        if entry.file_type().is_dir() {
-            continue;
-        }
-        let entry = entry.into_path();
-        let mut name = entry
-            .components()
-            .skip(1)
-            .map(|a| match a {
-                Component::Normal(a) => a.to_string_lossy().split(".").next().unwrap().to_owned(),
-                _ => panic!(),
-            })
-            .collect::<Vec<String>>();
-        name = name
-            .into_iter()
-            .skip_while(|a| !a.chars().next().unwrap().is_uppercase())
-            .collect();
-        if name.len() > 1 && name.last().unwrap() == &name[name.len() - 2] {
-            name.pop();
-        }
-        let hr_name = name.clone().join("::");
-        let data = read_to_string(entry).unwrap();
-        let data = data.trim();
-        let data = data
-            .split('\n')
-            .filter(|a| !a.trim().starts_with("//"))
-            .collect::<Vec<_>>()
-            .join("\n");
-        let data = position::Stream::new(data.trim());
-        let now = SystemTime::now();
-
-        let (items, remainder) = items(&bump).easy_parse(data).unwrap_or_else(|e| {
-            println!("{}in {}", e, hr_name);
-            exit(1)
-        });
-
-        parsing_time += now.elapsed().unwrap().as_millis();
-        if !remainder.input.is_empty() {
-            eprintln!(
-                "PARSING ENDED BEFORE EOF IN {} WITH \n{}\nREMAINING",
-                hr_name, remainder.input
-            );
-            exit(1);
-        } else {
-            let now = SystemTime::now();
-            for item in items {
-                if let Item::Declaration(t) = item {
-                    let ty = t.ty.compile(LocalResolver::new());
-                    let term = t.term.compile(LocalResolver::new());
-                    if names.contains(&hr_name) {
-                        println!("NAME: {}\n{:?}\n{:?}\n", hr_name, ty, term);
-                    }
-                    let name = if name.len() == 0 {
-                        vec![t.ident.0]
-                    } else {
-                        name.clone()
-                            .into_iter()
-                            .map(|a| BumpString::from_str(&a, &bump))
-                            .collect()
-                    };
-
-                    declarations.push((
-                        LocalResolver::new().canonicalize(Path(BumpVec::from_iterator(
-                            name.clone().into_iter().map(Ident),
-                            &bump,
-                        ))),
-                        ty,
-                        term,
-                    ));
-                } else if let Item::Block(block) = item {
-                    #[allow(irrefutable_let_patterns)]
-                    if let BlockItem::Data(data) = block {
-                        if let Ok(data) = TryInto::<SerializableData>::try_into(data.clone()) {
-                            data_declarations.push(data);
-                        }
-                        let compiled = data.compile(LocalResolver::new());
-                        if names.contains(&hr_name) {
-                            for (_, ty, term) in &compiled {
-                                println!("NAME: {}\n{:?}\n{:?}\n", hr_name, ty, term);
-                            }
-                        }
-                        declarations.extend(compiled);
-                    }
-                }
-            }
-            codegen_time += now.elapsed().unwrap().as_millis();
-        }
-    }
-
-    let defs_bump = bumpalo::Bump::new();
-    let defs_bm = Bumpalo(&defs_bump);
-
-    let defs: HashMap<_, _> = declarations
-        .clone()
+    declarations
         .into_iter()
-        .map(|(a, b, c): (AbsolutePath, Term<_, _>, Term<_, _>)| {
+        .map(|(a, b, c)| {
             (
-                BumpPath::new_in(a, &defs_bump),
+                BumpPath::new_in(a, defs_bump),
                 (
                     defs_bm.reallocate(
-                        b.map_reference(|a| Term::Reference(BumpPath::new_in(a, &defs_bump))),
+                        b.map_reference(|a| Term::Reference(BumpPath::new_in(a, defs_bump))),
                     ),
                     defs_bm.reallocate(
-                        c.map_reference(|a| Term::Reference(BumpPath::new_in(a, &defs_bump))),
+                        c.map_reference(|a| Term::Reference(BumpPath::new_in(a, defs_bump))),
                     ),
                 ),
             )
         })
-        .collect();
+        .collect()
+}
 
-    let mut ok = 0;
-    let mut err = 0;
-    let mut errs = String::new();
+pub(crate) struct Report {
+    pub(crate) ok: usize,
+    pub(crate) err: usize,
+    pub(crate) errs: String,
+    pub(crate) tc_time: u128,
+    pub(crate) tc_times: Vec<(String, u128)>,
+}
 
-    let mut tc_time = 0;
+pub(crate) fn check_defs<'a>(
+    defs: &Defs<'a>,
+    defs_bm: &Bumpalo<'a>,
+    cache: &mut MapCache,
+) -> Report {
+    let mut report = Report {
+        ok: 0,
+        err: 0,
+        errs: String::new(),
+        tc_time: 0,
+        tc_times: vec![],
+    };
 
-    for (path, (ty, term)) in &defs {
+    for (path, (ty, term)) in defs {
         let now = SystemTime::now();
 
         let mut er = 0;
@@ -369,12 +272,14 @@ fn main() {
         let ty = bm.reallocating_copy(ty);
         let term = bm.reallocating_copy(term);
 
-        if term.is_recursive_in(&defs, &bm, &defs_bm) {
+        let errs = &mut report.errs;
+
+        if term.is_recursive_in(defs, &bm, defs_bm) {
             errs.push_str(&format!("{:?} IS DEFINED RECURSIVELY\n", path));
             er = 1;
         }
 
-        if ty.is_recursive_in(&defs, &bm, &defs_bm) {
+        if ty.is_recursive_in(defs, &bm, defs_bm) {
             errs.push_str(&format!("{:?} (TYPE) IS DEFINED RECURSIVELY\n", path));
             er = 1;
         }
@@ -388,45 +293,86 @@ fn main() {
             er = 1;
         }
 
-        if let Err(e) = term.check_in(&ty, &defs, &bm, &mut cache) {
+        if let Err(e) = term.check_in(&ty, defs, &bm, &mut *cache) {
             errs.push_str(&format!("\n{:?} ERR\n{:?}\n", path, e));
             er = 1;
         } else {
-            if let Err(e) = ty.check_in(&Term::Universe, &defs, &bm, &mut cache) {
+            if let Err(e) = ty.check_in(&Term::Universe, defs, &bm, &mut *cache) {
                 errs.push_str(&format!(
                     "\n{:?} ERR\n{:?}\nwhen checking {:?} in universe\n",
                     path, e, ty
                 ));
                 er = 1;
             } else {
-                ok += if er != 0 { 0 } else { 1 };
+                report.ok += if er != 0 { 0 } else { 1 };
             }
         }
-        err += er;
+        report.err += er;
         let elapsed = now.elapsed().unwrap().as_millis();
-        tc_times.push((path, elapsed));
-        tc_times.sort_by_key(|(_, a)| *a);
-        tc_times.reverse();
-        tc_times.truncate(3);
-        tc_time += elapsed;
+        report.tc_times.push((format!("{:?}", path), elapsed));
+        report.tc_times.sort_by_key(|(_, a)| *a);
+        report.tc_times.reverse();
+        report.tc_times.truncate(3);
+        report.tc_time += elapsed;
+    }
+
+    report
+}
+
+/// Builds a bundle of the definitions reachable from the entries, type-checking just those unless
+/// the whole library is already known to check.
+fn shaken_bundle(
+    library: &Library,
+    options: &BundleOptions,
+    checked: bool,
+    cache: &mut MapCache,
+) -> Result<Bundle, String> {
+    if !checked {
+        let graph = Graph::new(&library.declarations);
+        let entries: Vec<String> = options
+            .entries
+            .iter()
+            .map(|entry| format!("{:?}", entry))
+            .collect();
+        let reachable = graph.reachable(entries.iter().map(String::as_str));
+
+        let bump = bumpalo::Bump::new();
+        let defs = build_defs(
+            library
+                .declarations
+                .iter()
+                .filter(|(path, _, _)| reachable.contains(format!("{:?}", path).as_str()))
+                .cloned(),
+            &bump,
+        );
+
+        let report = check_defs(&defs, &Bumpalo(&bump), cache);
+        if report.err != 0 {
+            return Err(report.errs);
+        }
     }
 
-    println!("{}", errs);
-    println!("CHECKED {}", ok + err);
-    println!("{} OK", ok);
-    println!("{} ERR", err);
+    Bundle::new(&library.declarations, options)
+        .map_err(|e| format!("could not build shaken bundle: {:?}", e))
+}
+
+pub(crate) fn print_report(report: &Report, library: &Library) {
+    println!("{}", report.errs);
+    println!("CHECKED {}", report.ok + report.err);
+    println!("{} OK", report.ok);
+    println!("{} ERR", report.err);
     println!(
         "PARSING {}ms | CODEGEN {}ms | TC {}ms",
-        parsing_time, codegen_time, tc_time
+        library.parsing_time, library.codegen_time, report.tc_time
     );
 
-    if tc_time > 200 {
+    if report.tc_time > 200 {
         println!("\nTOP 3 TC:");
-        for (name, time) in tc_times {
+        for (name, time) in &report.tc_times {
             println!(
                 "{:width$} TOOK {}ms",
                 {
-                    let mut data = format!("{:?}", name);
+                    let mut data = name.clone();
                     let l = data.len();
                     data.truncate(22);
                     if l > 22 {
@@ -439,18 +385,425 @@ fn main() {
             );
         }
     }
+}
+
+pub(crate) fn normalize<'a>(
+    term: Term<BumpPath<'a>, welkin_core::term::None, Bumpalo<'a>>,
+    defs: &Defs<'a>,
+    defs_bm: &Bumpalo<'a>,
+) -> Result<Term<String>, String> {
+    let term = term
+        .stratified_in(defs, defs_bm)
+        .map_err(|e| format!("{:?}", e))?;
+    let mut net = term
+        .into_net::<Net<u32>>()
+        .map_err(|e| format!("{:?}", e))?;
+    net.reduce_all();
+    Ok(net.read_term(welkin_core::net::Index(0)))
+}
+
+pub(crate) fn show<'a>(
+    value: Term<String>,
+    mut ty: Term<BumpPath<'a>, welkin_core::term::None, Bumpalo<'a>>,
+    defs: &Defs<'a>,
+    defs_bm: &Bumpalo<'a>,
+    cache: &mut MapCache,
+) -> String {
+    let bump = bumpalo::Bump::new();
+
+    while let Term::Wrap(t) = ty {
+        ty = t.into_inner();
+    }
+
+    // a library without the type, or one it can't be compared with, doesn't match
+    let mut is_ty = |ty: &Term<_, _, _>, name: &str| {
+        let expected = match parse_term(Default::default(), &bump).easy_parse(name) {
+            Ok((expected, _)) => expected,
+            Err(_) => return false,
+        };
+        let expected = defs_bm.reallocating_copy(
+            &expected
+                .compile(LocalResolver::new())
+                .map_reference(|a| Term::Reference(BumpPath::new_in(a, defs_bm.0))),
+        );
+        ty.equivalent_in(&expected, defs, defs_bm, &mut *cache)
+            .unwrap_or(false)
+    };
 
-    if err == 0 {
+    let shown = if is_ty(&ty, "Size") {
+        read_size(value.clone())
+    } else if is_ty(&ty, "Bool") {
+        read_bool(value.clone()).map(|value| format!("BOOL = {:?}", value))
+    } else if is_ty(&ty, "Char") {
+        read_char(value.clone()).map(|value| format!("CHAR = {:?}", value))
+    } else if is_ty(&ty, "Sized[String]") {
+        read_sized(value.clone(), read_string).map(|value| format!("SIZED STRING = {:?}", value))
+    } else {
+        None
+    };
+
+    shown.unwrap_or_else(|| format!("{:?}", value))
+}
+
+fn usage() -> ! {
+    eprintln!(
+        "USAGE:\nwelkin <SOURCE_DIR> [--export-defs <PATH>] [--export-terms <PATH>] [--entry <NAME>]... [--inline <SIZE>] [--share <SIZE>] [--shaken-bundle <PATH>]\nwelkin repl <SOURCE_DIR>\nwelkin convert <INPUT> <OUTPUT>\nwelkin store add <STORE_DIR> <SOURCE_DIR>\nwelkin store lookup <STORE_DIR> <NAME|HASH>\nwelkin store diff <STORE_DIR> <NAME|HASH> <NAME|HASH>\nwelkin graph <SOURCE_DIR> [--root <NAME>]... [--dot <PATH>] [--json <PATH>]\nwelkin bindgen <INPUT> [--root <DIR>] [--output <PATH>]\nwelkin typescript <SOURCE_DIR|DEFS> <OUTPUT>"
+    );
+    exit(1)
+}
+
+/// Converts between binary containers and their JSON form; a `.json` input is written as a binary
+/// container and anything else is written as JSON.
+fn convert(input: &str, output: &str) -> Result<(), String> {
+    fn json_to_binary<T: container::Container>(
+        data: &str,
+    ) -> Result<Vec<u8>, container::ContainerError> {
+        Ok(container::encode(&container::from_json::<T>(data)?))
+    }
+
+    fn binary_to_json<T: container::Container>(
+        data: &[u8],
+    ) -> Result<Vec<u8>, container::ContainerError> {
+        Ok(container::to_json(&container::decode::<T>(data)?).into_bytes())
+    }
+
+    let converted = if input.ends_with(".json") {
+        let data = read_to_string(input).map_err(|e| format!("could not read {}: {}", input, e))?;
+        match container::json_kind(&data).map_err(|e| e.to_string())? {
+            Kind::Terms => json_to_binary::<Terms>(&data),
+            Kind::Definitions => json_to_binary::<Vec<SerializableData>>(&data),
+            Kind::Stored => json_to_binary::<StoredDefinition>(&data),
+            Kind::Bundle => json_to_binary::<Bundle>(&data),
+        }
+    } else {
+        let data = std::fs::read(input).map_err(|e| format!("could not read {}: {}", input, e))?;
+        match container::kind(&data).map_err(|e| e.to_string())? {
+            Kind::Terms => binary_to_json::<Terms>(&data),
+            Kind::Definitions => binary_to_json::<Vec<SerializableData>>(&data),
+            Kind::Stored => binary_to_json::<StoredDefinition>(&data),
+            Kind::Bundle => binary_to_json::<Bundle>(&data),
+        }
+    }
+    .map_err(|e| e.to_string())?;
+
+    std::fs::write(output, converted).map_err(|e| format!("could not write {}: {}", output, e))
+}
+
+fn store(
+    names: &HashSet<String>,
+    mut args: impl Iterator<Item = String>,
+) -> Result<(), welkin::store::StoreError> {
+    let command = args.next().unwrap_or_else(|| usage());
+    let mut store = Store::open(args.next().unwrap_or_else(|| usage()))?;
+
+    let resolve = |args: &mut dyn Iterator<Item = String>| {
+        let name = args.next().unwrap_or_else(|| usage());
+        store.resolve(&name).unwrap_or_else(|| {
+            eprintln!("{} IS NOT A NAME OR HASH IN THE STORE", name);
+            exit(1)
+        })
+    };
+
+    match command.as_str() {
+        "add" => {
+            let library =
+                load(&args.next().unwrap_or_else(|| usage()), names).unwrap_or_else(|e| {
+                    eprintln!("{}", e);
+                    exit(1)
+                });
+
+            let added = store.add(&library.declarations)?;
+
+            for added in added.iter().filter(|added| added.new) {
+                println!("ADDED {} {}", added.hash, added.name);
+            }
+            println!(
+                "{} NEW, {} UNCHANGED",
+                added.iter().filter(|added| added.new).count(),
+                added.iter().filter(|added| !added.new).count()
+            );
+
+            let mut seen = HashSet::new();
+            for added in &added {
+                let aliases = store.names(&added.hash);
+                if aliases.len() > 1 && seen.insert(added.hash.clone()) {
+                    println!("IDENTICAL {}", aliases.join(", "));
+                }
+            }
+        }
+        "lookup" => {
+            let hash = resolve(&mut args);
+            let definition = store.get(&hash)?;
+            println!("HASH: {}", hash);
+            println!("NAMES: {}", store.names(&hash).join(", "));
+            println!("{:?}\n{:?}", definition.ty, definition.term);
+        }
+        "diff" => {
+            let a = resolve(&mut args);
+            let b = resolve(&mut args);
+
+            if a == b {
+                println!("IDENTICAL {}", a);
+                return Ok(());
+            }
+
+            let (a_definition, b_definition) = (store.get(&a)?, store.get(&b)?);
+
+            println!("DIFFERENT {} {}", a, b);
+            if a_definition.ty.hash() == b_definition.ty.hash() {
+                println!("TYPES IDENTICAL");
+            } else {
+                println!(
+                    "TYPES DIFFER\n- {:?}\n+ {:?}",
+                    a_definition.ty, b_definition.ty
+                );
+            }
+            if a_definition.term.hash() != b_definition.term.hash() {
+                println!("TERMS DIFFER");
+            }
+
+            let (a_dependencies, b_dependencies) =
+                (a_definition.dependencies(), b_definition.dependencies());
+            for hash in a_dependencies.difference(&b_dependencies) {
+                println!("- {} {}", hash, store.names(hash).join(", "));
+            }
+            for hash in b_dependencies.difference(&a_dependencies) {
+                println!("+ {} {}", hash, store.names(hash).join(", "));
+            }
+        }
+        _ => usage(),
+    }
+
+    Ok(())
+}
+
+fn graph(source: &str, names: &HashSet<String>, mut args: impl Iterator<Item = String>) {
+    let library = load(source, names).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        exit(1)
+    });
+
+    let graph = Graph::new(&library.declarations);
+
+    let mut roots = vec![];
+
+    while let Some(a) = args.next() {
+        match a.as_str() {
+            "--root" => roots.push(args.next().unwrap_or_else(|| usage())),
+            "--dot" => {
+                std::fs::write(args.next().unwrap_or_else(|| usage()), graph.to_dot()).unwrap();
+                eprintln!("EXPORTED dot");
+            }
+            "--json" => {
+                std::fs::write(
+                    args.next().unwrap_or_else(|| usage()),
+                    serde_json::to_string_pretty(&graph).unwrap(),
+                )
+                .unwrap();
+                eprintln!("EXPORTED json");
+            }
+            _ => usage(),
+        }
+    }
+
+    if roots.is_empty() {
+        roots.push("main".into());
+    }
+
+    let unreachable = graph.unreachable(roots.iter().map(String::as_str));
+
+    println!(
+        "{} UNREACHABLE FROM {}",
+        unreachable.len(),
+        roots.join(", ")
+    );
+    for name in unreachable {
+        println!("\t{}", name);
+    }
+
+    println!("\nCLOSURE SIZES");
+    for (name, size) in graph.closure_sizes() {
+        println!("\t{}\t{}", size, name);
+    }
+}
+
+/// Writes TypeScript declarations for the data declared in a source directory or exported with
+/// `--export-defs`.
+fn typescript(input: &str, output: &str, names: &HashSet<String>) -> Result<(), String> {
+    let defs = if std::path::Path::new(input).is_dir() {
+        load(input, names)?.data_declarations
+    } else {
+        let data = std::fs::read(input).map_err(|e| e.to_string())?;
+        container::decode::<Vec<SerializableData>>(&data).map_err(|e| e.to_string())?
+    };
+
+    std::fs::write(output, welkin::typescript::generate(&defs)).map_err(|e| e.to_string())
+}
+
+/// Runs `welkin-bindgen`, preferring the one installed alongside this binary. Binding generation
+/// depends on this crate, so it lives in a binary of its own.
+fn bindgen(args: impl Iterator<Item = String>) -> ! {
+    let name = format!("welkin-bindgen{}", std::env::consts::EXE_SUFFIX);
+
+    let program = std::env::current_exe()
+        .ok()
+        .map(|exe| exe.with_file_name(&name))
+        .filter(|path| path.exists())
+        .unwrap_or_else(|| name.into());
+
+    match std::process::Command::new(&program).args(args).status() {
+        Ok(status) => exit(status.code().unwrap_or(1)),
+        Err(e) => {
+            eprintln!("could not run {}: {}", program.display(), e);
+            exit(1)
+        }
+    }
+}
+
+fn main() {
+    let mut cache = MapCache::new();
+
+    let names = if let Ok(dump) = std::env::var("WELKIN_DUMP_NAMES") {
+        dump.split(",").into_iter().map(String::from).collect()
+    } else {
+        HashSet::new()
+    };
+
+    let mut args = std::env::args().skip(1);
+
+    let source = args.next().unwrap_or_else(|| usage());
+
+    if source == "repl" {
+        repl::repl(&args.next().unwrap_or_else(|| usage()), &names);
+        return;
+    }
+
+    if source == "convert" {
+        let input = args.next().unwrap_or_else(|| usage());
+        let output = args.next().unwrap_or_else(|| usage());
+        if let Err(e) = convert(&input, &output) {
+            eprintln!("{}", e);
+            exit(1);
+        }
+        return;
+    }
+
+    if source == "store" {
+        if let Err(e) = store(&names, args) {
+            eprintln!("{}", e);
+            exit(1);
+        }
+        return;
+    }
+
+    if source == "graph" {
+        graph(&args.next().unwrap_or_else(|| usage()), &names, args);
+        return;
+    }
+
+    if source == "bindgen" {
+        bindgen(args);
+    }
+
+    if source == "typescript" {
+        let input = args.next().unwrap_or_else(|| usage());
+        let output = args.next().unwrap_or_else(|| usage());
+        if let Err(e) = typescript(&input, &output, &names) {
+            eprintln!("{}", e);
+            exit(1);
+        }
+        return;
+    }
+
+    let library = load(&source, &names).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        exit(1)
+    });
+
+    let defs_bump = bumpalo::Bump::new();
+    let defs_bm = Bumpalo(&defs_bump);
+
+    let defs = build_defs(library.declarations.clone(), &defs_bump);
+
+    let report = check_defs(&defs, &defs_bm, &mut cache);
+
+    print_report(&report, &library);
+
+    let mut bundle_options = BundleOptions::default();
+    let mut entries = vec![];
+    let mut shaken = None;
+
+    let mut args = std::env::args().skip(2);
+    while let Some(a) = args.next() {
+        match a.as_str() {
+            "--entry" => {
+                entries.push(AbsolutePath(
+                    args.next()
+                        .expect("expected name for bundle entry")
+                        .split("::")
+                        .map(String::from)
+                        .collect(),
+                ));
+            }
+            "--inline" => {
+                bundle_options.inline = Some(
+                    args.next()
+                        .and_then(|a| a.parse().ok())
+                        .expect("expected size for inlining threshold"),
+                );
+            }
+            "--share" => {
+                bundle_options.share = Some(
+                    args.next()
+                        .and_then(|a| a.parse().ok())
+                        .expect("expected size for sharing threshold"),
+                );
+            }
+            "--shaken-bundle" => {
+                shaken = Some(args.next().expect("expected path for shaken bundle export"));
+            }
+            "--bundle" => {
+                eprintln!("--bundle has been replaced by --shaken-bundle");
+                exit(1);
+            }
+            _ => {}
+        }
+    }
+
+    if !entries.is_empty() {
+        bundle_options.entries = entries;
+    }
+
+    if let Some(path) = shaken {
+        match shaken_bundle(&library, &bundle_options, report.err == 0, &mut cache) {
+            Ok(bundle) => {
+                std::fs::write(path, container::encode(&bundle)).unwrap();
+                eprintln!(
+                    "EXPORTED shaken bundle ({} definitions)",
+                    bundle.definitions.len()
+                );
+            }
+            Err(e) => {
+                eprintln!("{}", e);
+                exit(1);
+            }
+        }
+    }
+
+    let name = BumpPath::new_in(AbsolutePath(vec!["main".into()]), &defs_bump);
+
+    // a library without main is still checked, and can still be bundled from its entries
+    if report.err == 0 && defs.get_typed(&name).is_none() {
+        return;
+    }
+
+    if report.err == 0 {
         println!("\nmain normalizes to:\n{}", {
-            let name = BumpPath::new_in(AbsolutePath(vec!["main".into()]), &defs_bump);
             let data = defs.get_typed(&name).unwrap();
             let (ty, term) = data.as_ref();
-            let mut ty = defs_bm.copy(ty);
+            let ty = defs_bm.copy(ty);
             let term = defs_bm.copy(term);
-            let main = term.stratified_in(&defs, &defs_bm).unwrap();
-            let mut net = main.clone().into_net::<Net<u32>>().unwrap();
-            net.reduce_all();
-            let main: Term<String> = net.read_term(welkin_core::net::Index(0));
+            let main = normalize(term, &defs, &defs_bm).unwrap();
 
             let mut args = std::env::args().skip(2).peekable();
 
@@ -460,18 +813,13 @@ fn main() {
 
             while let Some(a) = args.next() {
                 match a.as_str() {
-                    "--bundle" => {
-                        std::fs::write(
-                            args.next().expect("expected path for bundle export"),
-                            bincode::serialize(&main).unwrap(),
-                        )
-                        .unwrap();
-                        eprintln!("EXPORTED bundle");
+                    "--entry" | "--inline" | "--share" | "--shaken-bundle" => {
+                        args.next();
                     }
                     "--export-defs" => {
                         std::fs::write(
                             args.next().expect("expected path for defs export"),
-                            bincode::serialize(&data_declarations).unwrap(),
+                            container::encode(&library.data_declarations),
                         )
                         .unwrap();
                         eprintln!("EXPORTED definitions");
@@ -501,7 +849,7 @@ fn main() {
                         }
                         std::fs::write(
                             args.next().expect("expected path for terms export"),
-                            bincode::serialize(&terms).unwrap(),
+                            container::encode(&terms),
                         )
                         .unwrap();
                         eprintln!("EXPORTED terms");
@@ -510,38 +858,7 @@ fn main() {
                 }
             }
 
-            while let Term::Wrap(t) = ty {
-                ty = t.into_inner();
-            }
-
-            let mut is_ty = |ty: &Term<_, _, _>, name: &str| {
-                ty.equivalent_in(
-                    &defs_bm.reallocating_copy(
-                        &parse_term(Default::default(), &bump)
-                            .easy_parse(name)
-                            .unwrap()
-                            .0
-                            .compile(LocalResolver::new())
-                            .map_reference(|a| Term::Reference(BumpPath::new_in(a, &defs_bump))),
-                    ),
-                    &defs,
-                    &defs_bm,
-                    &mut cache,
-                )
-                .unwrap()
-            };
-
-            if is_ty(&ty, "Size") {
-                read_size(main)
-            } else if is_ty(&ty, "Bool") {
-                format!("BOOL = {:?}", read_bool(main))
-            } else if is_ty(&ty, "Char") {
-                format!("CHAR = {:?}", read_char(main))
-            } else if is_ty(&ty, "Sized[String]") {
-                format!("SIZED STRING = {:?}", read_sized(main, read_string))
-            } else {
-                format!("{:?}", main)
-            }
+            show(main, ty, &defs, &defs_bm, &mut cache)
         });
     } else {
         exit(1);
@@ -0,0 +1,290 @@
+use std::{
+    collections::HashSet,
+    io::{self, BufRead, Write},
+    process::exit,
+};
+
+use combine::{stream::position, EasyParser, Parser};
+use parser::{
+    term::term as parse_term,
+    util::{ident, token},
+    AbsolutePath,
+};
+use welkin::{
+    compiler::{term::Compile as _, BumpPath, LocalResolver},
+    Bumpalo,
+};
+use welkin_core::term::{alloc::Reallocate, MapCache, Term};
+
+use crate::{build_defs, check_defs, load, normalize, print_report, show, Defs, Library};
+
+const HELP: &str = "\
+<EXPR>                  infer the type of EXPR, then normalize and print it
+:type <EXPR>            print the inferred type of EXPR
+:def <NAME> : <T> = <E> check E against T and add it to the session as NAME
+:dump <NAME>            print the type and term of a definition
+:reload                 reload the library from disk, keeping session definitions
+:help                   print this message
+:quit                   exit the repl";
+
+enum Exit {
+    Reload,
+    Quit,
+}
+
+type Declaration = (AbsolutePath, Term<AbsolutePath>, Term<AbsolutePath>);
+
+fn parse_expression(input: &str) -> Result<Term<AbsolutePath>, String> {
+    let bump = bumpalo::Bump::new();
+
+    let (term, remainder) = parse_term(Default::default(), &bump)
+        .easy_parse(position::Stream::new(input))
+        .map_err(|e| format!("{}", e))?;
+
+    if !remainder.input.trim().is_empty() {
+        return Err(format!(
+            "PARSING ENDED BEFORE EOF WITH \n{}\nREMAINING",
+            remainder.input
+        ));
+    }
+
+    Ok(term.compile(LocalResolver::new()))
+}
+
+fn parse_definition(input: &str) -> Result<Declaration, String> {
+    let bump = bumpalo::Bump::new();
+
+    let ((name, _, ty, _, term), remainder) = (
+        ident(&bump),
+        token(':'),
+        parse_term(Default::default(), &bump),
+        token('='),
+        parse_term(Default::default(), &bump),
+    )
+        .easy_parse(position::Stream::new(input))
+        .map_err(|e| format!("{}", e))?;
+
+    if !remainder.input.trim().is_empty() {
+        return Err(format!(
+            "PARSING ENDED BEFORE EOF WITH \n{}\nREMAINING",
+            remainder.input
+        ));
+    }
+
+    Ok((
+        AbsolutePath(vec![name.0.to_string()]),
+        ty.compile(LocalResolver::new()),
+        term.compile(LocalResolver::new()),
+    ))
+}
+
+fn parse_path(input: &str) -> AbsolutePath {
+    AbsolutePath(input.trim().split("::").map(String::from).collect())
+}
+
+fn evaluate<'a>(
+    input: &str,
+    print_value: bool,
+    defs: &Defs<'a>,
+    defs_bm: &Bumpalo<'a>,
+    cache: &mut MapCache,
+) -> Result<String, String> {
+    let term = defs_bm.reallocate(
+        parse_expression(input)?.map_reference(|a| Term::Reference(BumpPath::new_in(a, defs_bm.0))),
+    );
+
+    let ty = term
+        .infer_in(defs, defs_bm, &mut *cache)
+        .map_err(|e| format!("ERR\n{:?}", e))?;
+
+    if !print_value {
+        return Ok(format!("{:?}", ty));
+    }
+
+    if let Err(e) = term.is_stratified() {
+        return Err(format!("NOT STRATIFIED\n\t{:?}", e));
+    }
+
+    let value = normalize(term, defs, defs_bm)?;
+
+    Ok(show(value, ty, defs, defs_bm, cache))
+}
+
+fn define<'a>(
+    input: &str,
+    session: &mut Vec<Declaration>,
+    defs: &mut Defs<'a>,
+    defs_bm: &Bumpalo<'a>,
+    cache: &mut MapCache,
+) -> Result<String, String> {
+    let (path, ty, term) = parse_definition(input)?;
+
+    let b_ty = defs_bm.reallocate(
+        ty.clone()
+            .map_reference(|a| Term::Reference(BumpPath::new_in(a, defs_bm.0))),
+    );
+    let b_term = defs_bm.reallocate(
+        term.clone()
+            .map_reference(|a| Term::Reference(BumpPath::new_in(a, defs_bm.0))),
+    );
+
+    if b_term.is_stratified().is_err() {
+        return Err(format!("{:?} IS NOT STRATIFIED", path));
+    }
+    if b_ty.is_stratified().is_err() {
+        return Err(format!("{:?} (TYPE) IS NOT STRATIFIED", path));
+    }
+
+    b_ty.check_in(&Term::Universe, &*defs, defs_bm, &mut *cache)
+        .map_err(|e| {
+            format!(
+                "{:?} ERR\n{:?}\nwhen checking {:?} in universe",
+                path, e, b_ty
+            )
+        })?;
+    b_term
+        .check_in(&b_ty, &*defs, defs_bm, &mut *cache)
+        .map_err(|e| format!("{:?} ERR\n{:?}", path, e))?;
+
+    // a redefinition can close a cycle through definitions that use the old one
+    let name = BumpPath::new_in(path.clone(), defs_bm.0);
+    let previous = defs.insert(name.clone(), (b_ty, b_term));
+
+    let recursive = {
+        let bp = bumpalo::Bump::new();
+        let bm = Bumpalo(&bp);
+        let (b_ty, b_term) = &defs[&name];
+
+        if bm
+            .reallocating_copy(b_term)
+            .is_recursive_in(&*defs, &bm, defs_bm)
+        {
+            Some(format!("{:?} IS DEFINED RECURSIVELY", path))
+        } else if bm
+            .reallocating_copy(b_ty)
+            .is_recursive_in(&*defs, &bm, defs_bm)
+        {
+            Some(format!("{:?} (TYPE) IS DEFINED RECURSIVELY", path))
+        } else {
+            None
+        }
+    };
+
+    if let Some(e) = recursive {
+        match previous {
+            Some(previous) => defs.insert(name, previous),
+            None => defs.remove(&name),
+        };
+        return Err(e);
+    }
+
+    session.retain(|(name, _, _)| name != &path);
+    session.push((path.clone(), ty, term));
+
+    Ok(format!("DEFINED {:?}", path))
+}
+
+fn session<'a>(
+    lines: &mut impl Iterator<Item = io::Result<String>>,
+    session: &mut Vec<Declaration>,
+    defs: &mut Defs<'a>,
+    defs_bm: &Bumpalo<'a>,
+    cache: &mut MapCache,
+) -> Exit {
+    loop {
+        print!("> ");
+        if let Err(e) = io::stdout().flush() {
+            eprintln!("COULD NOT WRITE PROMPT\n{}", e);
+            return Exit::Quit;
+        }
+
+        // an error reading stdin is not one the next read recovers from
+        let line = match lines.next() {
+            Some(Ok(line)) => line,
+            Some(Err(e)) => {
+                eprintln!("COULD NOT READ LINE\n{}", e);
+                return Exit::Quit;
+            }
+            None => {
+                println!();
+                return Exit::Quit;
+            }
+        };
+        let line = line.trim();
+
+        let (command, argument) = if line.starts_with(':') {
+            match line.find(char::is_whitespace) {
+                Some(split) => (&line[..split], line[split..].trim()),
+                None => (line, ""),
+            }
+        } else {
+            ("", line)
+        };
+
+        let result = match command {
+            "" if argument.is_empty() => continue,
+            "" => evaluate(argument, true, defs, defs_bm, cache),
+            ":t" | ":type" => evaluate(argument, false, defs, defs_bm, cache),
+            ":d" | ":def" => define(argument, session, defs, defs_bm, cache),
+            ":dump" => {
+                let path = parse_path(argument);
+                match defs.get(&BumpPath::new_in(path.clone(), defs_bm.0)) {
+                    Some((ty, term)) => Ok(format!("NAME: {:?}\n{:?}\n{:?}", path, ty, term)),
+                    None => Err(format!("{:?} IS NOT DEFINED", path)),
+                }
+            }
+            ":r" | ":reload" => return Exit::Reload,
+            ":q" | ":quit" => return Exit::Quit,
+            ":h" | ":help" => Ok(HELP.to_owned()),
+            command => Err(format!("unknown command {}, try :help", command)),
+        };
+
+        match result {
+            Ok(output) => println!("{}", output),
+            Err(e) => println!("{}", e),
+        }
+    }
+}
+
+pub fn repl(source: &str, names: &HashSet<String>) {
+    let mut cache = MapCache::new();
+
+    let stdin = io::stdin();
+    let mut lines = stdin.lock().lines();
+
+    let mut library: Library = load(source, names).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        exit(1)
+    });
+    let mut declarations: Vec<Declaration> = vec![];
+
+    loop {
+        let defs_bump = bumpalo::Bump::new();
+        let defs_bm = Bumpalo(&defs_bump);
+
+        let mut defs = build_defs(
+            library
+                .declarations
+                .iter()
+                .chain(declarations.iter())
+                .cloned(),
+            &defs_bump,
+        );
+
+        print_report(&check_defs(&defs, &defs_bm, &mut cache), &library);
+
+        match session(
+            &mut lines,
+            &mut declarations,
+            &mut defs,
+            &defs_bm,
+            &mut cache,
+        ) {
+            Exit::Quit => break,
+            Exit::Reload => match load(source, names) {
+                Ok(reloaded) => library = reloaded,
+                Err(e) => eprintln!("{}\nKEEPING PREVIOUS LIBRARY", e),
+            },
+        }
+    }
+}
@@ -0,0 +1,434 @@
+use std::{
+    collections::{HashMap, HashSet},
+    error::Error,
+    fmt::{self, Display},
+    iter,
+};
+
+use welkin_core::{
+    net::{Index, Net, VisitNetExt},
+    term::{alloc::System, MapCache, Term},
+};
+
+use crate::{
+    bundle::Bundle,
+    container::{self, ContainerError},
+    graph::Graph,
+    Terms,
+};
+
+/// How terms are normalized.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Evaluation {
+    /// Reduce an interaction net built from the stratified term.
+    Net,
+    /// Normalize the term directly by substitution.
+    Substitution,
+}
+
+impl Default for Evaluation {
+    fn default() -> Self {
+        Evaluation::Net
+    }
+}
+
+#[derive(Debug)]
+pub enum RuntimeError {
+    Container(ContainerError),
+    Missing(String),
+    /// The definition was loaded from a bundle, which carries no types.
+    Untyped(String),
+    Recursive(String),
+    /// The definition was applied to more arguments than its type takes.
+    Arity(String),
+    Stratification {
+        path: String,
+        error: String,
+    },
+    Check {
+        path: String,
+        error: String,
+    },
+    Evaluation(String),
+}
+
+impl Display for RuntimeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RuntimeError::Container(e) => write!(f, "container error: {}", e),
+            RuntimeError::Missing(path) => write!(f, "no definition named {}", path),
+            RuntimeError::Untyped(path) => write!(f, "{} has no type", path),
+            RuntimeError::Recursive(path) => write!(f, "{} is defined recursively", path),
+            RuntimeError::Arity(path) => {
+                write!(f, "{} is applied to more arguments than it takes", path)
+            }
+            RuntimeError::Stratification { path, error } => {
+                write!(f, "{} is not stratified: {}", path, error)
+            }
+            RuntimeError::Check { path, error } => write!(f, "{} does not check: {}", path, error),
+            RuntimeError::Evaluation(error) => write!(f, "evaluation error: {}", error),
+        }
+    }
+}
+
+impl Error for RuntimeError {}
+
+impl From<ContainerError> for RuntimeError {
+    fn from(e: ContainerError) -> Self {
+        RuntimeError::Container(e)
+    }
+}
+
+/// Replaces the self and argument binders of a function type's return type, found `depth` binders
+/// in, with closed terms.
+fn instantiate(
+    term: &mut Term<String>,
+    depth: usize,
+    function: &Term<String>,
+    argument: &Term<String>,
+) {
+    match term {
+        Term::Variable(welkin_core::term::Index(index)) => {
+            let index = *index;
+
+            if index == depth {
+                *term = argument.clone();
+            } else if index == depth + 1 {
+                *term = function.clone();
+            } else if index > depth + 1 {
+                *term = Term::Variable(welkin_core::term::Index(index - 2));
+            }
+        }
+        Term::Lambda { body, .. } => instantiate(body, depth + 1, function, argument),
+        Term::Apply {
+            function: f,
+            argument: a,
+            ..
+        } => {
+            instantiate(f, depth, function, argument);
+            instantiate(a, depth, function, argument);
+        }
+        Term::Put(term) | Term::Wrap(term) => instantiate(term, depth, function, argument),
+        Term::Duplicate { expression, body } => {
+            instantiate(expression, depth, function, argument);
+            instantiate(body, depth + 1, function, argument);
+        }
+        Term::Function {
+            argument_type,
+            return_type,
+            ..
+        } => {
+            instantiate(argument_type, depth, function, argument);
+            instantiate(return_type, depth + 2, function, argument);
+        }
+        Term::Annotation { expression, ty, .. } => {
+            instantiate(expression, depth, function, argument);
+            instantiate(ty, depth, function, argument);
+        }
+        Term::Reference(_) | Term::Universe | Term::Primitive(_) => {}
+    }
+}
+
+/// Hosts a set of compiled definitions for checking and evaluation.
+///
+/// Definitions are named by their path joined with `::`, the same form references take in
+/// `Term<String>`. The type-checking cache, the set of checked definitions and the normal forms
+/// of definitions are kept across calls.
+pub struct Runtime {
+    definitions: HashMap<String, (Term<String>, Term<String>)>,
+    graph: Graph,
+    typed: bool,
+    evaluation: Evaluation,
+    cache: MapCache,
+    checked: HashSet<String>,
+    normalized: HashMap<String, Term<String>>,
+}
+
+impl Runtime {
+    pub fn new(terms: Terms) -> Self {
+        let graph = Graph::new(&terms.data);
+
+        Runtime::from_definitions(
+            graph,
+            terms.data.into_iter().map(|(path, ty, term)| {
+                (
+                    format!("{:?}", path),
+                    ty.map_reference(|a| Term::Reference(format!("{:?}", a))),
+                    term.map_reference(|a| Term::Reference(format!("{:?}", a))),
+                )
+            }),
+            true,
+        )
+    }
+
+    /// Loads a bundle. Bundled definitions are erased, so they can be evaluated but not checked.
+    pub fn from_bundle(bundle: Bundle) -> Self {
+        let graph = Graph::from_terms(
+            bundle
+                .definitions
+                .iter()
+                .map(|(path, _, term)| (path, term)),
+        );
+
+        Runtime::from_definitions(
+            graph,
+            bundle.definitions.into_iter().map(|(path, _, term)| {
+                (
+                    format!("{:?}", path),
+                    Term::Universe,
+                    term.map_reference(|a| Term::Reference(format!("{:?}", a))),
+                )
+            }),
+            false,
+        )
+    }
+
+    fn from_definitions(
+        graph: Graph,
+        definitions: impl IntoIterator<Item = (String, Term<String>, Term<String>)>,
+        typed: bool,
+    ) -> Self {
+        Runtime {
+            definitions: definitions
+                .into_iter()
+                .map(|(path, ty, term)| (path, (ty, term)))
+                .collect(),
+            graph,
+            typed,
+            evaluation: Evaluation::default(),
+            cache: MapCache::new(),
+            checked: HashSet::new(),
+            normalized: HashMap::new(),
+        }
+    }
+
+    /// Reads a `Terms` container as written by `--export-terms`.
+    pub fn from_terms_bytes(data: &[u8]) -> Result<Self, RuntimeError> {
+        Ok(Runtime::new(container::decode(data)?))
+    }
+
+    /// Reads a bundle as written by `--shaken-bundle`.
+    pub fn from_bundle_bytes(data: &[u8]) -> Result<Self, RuntimeError> {
+        Ok(Runtime::from_bundle(container::decode(data)?))
+    }
+
+    pub fn with_evaluation(mut self, evaluation: Evaluation) -> Self {
+        self.set_evaluation(evaluation);
+        self
+    }
+
+    pub fn set_evaluation(&mut self, evaluation: Evaluation) {
+        if evaluation != self.evaluation {
+            self.normalized.clear();
+        }
+        self.evaluation = evaluation;
+    }
+
+    pub fn definitions(&self) -> impl Iterator<Item = &str> {
+        self.definitions.keys().map(String::as_str)
+    }
+
+    fn get(&self, path: &str) -> Result<&(Term<String>, Term<String>), RuntimeError> {
+        self.definitions
+            .get(path)
+            .ok_or_else(|| RuntimeError::Missing(path.to_owned()))
+    }
+
+    pub fn type_of(&self, path: &str) -> Result<&Term<String>, RuntimeError> {
+        let (ty, _) = self.get(path)?;
+
+        if self.typed {
+            Ok(ty)
+        } else {
+            Err(RuntimeError::Untyped(path.to_owned()))
+        }
+    }
+
+    /// Checks a definition against its type, along with every definition it depends on.
+    pub fn check_definition(&mut self, path: &str) -> Result<(), RuntimeError> {
+        if !self.typed {
+            return Err(RuntimeError::Untyped(path.to_owned()));
+        }
+
+        self.get(path)?;
+
+        // the definition itself is checked first so that its own failure is the one reported
+        let dependencies: Vec<_> = self
+            .graph
+            .reachable(iter::once(path))
+            .into_iter()
+            .filter(|dependency| *dependency != path)
+            .map(str::to_owned)
+            .collect();
+
+        self.check_single(path)?;
+
+        for dependency in dependencies {
+            self.check_single(&dependency)?;
+        }
+
+        Ok(())
+    }
+
+    /// Checks a single definition against its type, assuming the types of those it references.
+    fn check_single(&mut self, path: &str) -> Result<(), RuntimeError> {
+        if self.checked.contains(path) {
+            return Ok(());
+        }
+
+        // borrowed by field so the cache can be borrowed mutably alongside
+        let (ty, term) = self
+            .definitions
+            .get(path)
+            .ok_or_else(|| RuntimeError::Missing(path.to_owned()))?;
+
+        if term.is_recursive_in(&self.definitions, &System, &System)
+            || ty.is_recursive_in(&self.definitions, &System, &System)
+        {
+            return Err(RuntimeError::Recursive(path.to_owned()));
+        }
+
+        for term in [term, ty].iter() {
+            term.is_stratified()
+                .map_err(|e| RuntimeError::Stratification {
+                    path: path.to_owned(),
+                    error: format!("{:?}", e),
+                })?;
+        }
+
+        ty.check_in(&Term::Universe, &self.definitions, &System, &mut self.cache)
+            .map_err(|e| RuntimeError::Check {
+                path: path.to_owned(),
+                error: format!("{:?}", e),
+            })?;
+        term.check_in(ty, &self.definitions, &System, &mut self.cache)
+            .map_err(|e| RuntimeError::Check {
+                path: path.to_owned(),
+                error: format!("{:?}", e),
+            })?;
+
+        self.checked.insert(path.to_owned());
+
+        Ok(())
+    }
+
+    /// Checks a definition before it is evaluated. Bundles carry no types, but only checked
+    /// definitions are bundled.
+    fn require_checked(&mut self, path: &str) -> Result<(), RuntimeError> {
+        if self.typed {
+            self.check_definition(path)
+        } else {
+            self.get(path).map(|_| ())
+        }
+    }
+
+    /// Checks every definition, returning the first failure.
+    pub fn check(&mut self) -> Result<(), RuntimeError> {
+        let mut paths: Vec<_> = self.definitions.keys().cloned().collect();
+        paths.sort();
+
+        if !self.typed {
+            return paths
+                .first()
+                .map_or(Ok(()), |path| Err(RuntimeError::Untyped(path.clone())));
+        }
+
+        for path in paths {
+            self.check_single(&path)?;
+        }
+
+        Ok(())
+    }
+
+    /// Normalizes an arbitrary term with references into the loaded definitions.
+    pub fn evaluate(&self, term: Term<String>) -> Result<Term<String>, RuntimeError> {
+        match self.evaluation {
+            Evaluation::Net => {
+                let term = term
+                    .stratified_in(&self.definitions, &System)
+                    .map_err(|e| RuntimeError::Evaluation(format!("{:?}", e)))?;
+                let mut net = term
+                    .into_net::<Net<u32>>()
+                    .map_err(|e| RuntimeError::Evaluation(format!("{:?}", e)))?;
+                net.reduce_all();
+                Ok(net.read_term(Index(0)))
+            }
+            Evaluation::Substitution => {
+                let mut term = term;
+                term.normalize(&self.definitions)
+                    .map_err(|e| RuntimeError::Evaluation(format!("{:?}", e)))?;
+                Ok(term)
+            }
+        }
+    }
+
+    /// Returns the normal form of a definition, reusing it on later calls. The definition is
+    /// checked first.
+    pub fn normalize(&mut self, path: &str) -> Result<Term<String>, RuntimeError> {
+        if let Some(term) = self.normalized.get(path) {
+            return Ok(term.clone());
+        }
+
+        self.require_checked(path)?;
+
+        let term = self.evaluate(Term::Reference(path.to_owned()))?;
+        self.normalized.insert(path.to_owned(), term.clone());
+
+        Ok(term)
+    }
+
+    /// Applies a definition to closed arguments in order and normalizes the result. The definition
+    /// is checked first, and each argument is erased if its parameter is. Bundled definitions have
+    /// had their erased parameters removed, so only the remaining arguments are given to them.
+    pub fn apply(
+        &mut self,
+        path: &str,
+        arguments: impl IntoIterator<Item = Term<String>>,
+    ) -> Result<Term<String>, RuntimeError> {
+        self.require_checked(path)?;
+
+        let mut ty = if self.typed {
+            Some(self.get(path)?.0.clone())
+        } else {
+            None
+        };
+        let mut term = Term::Reference(path.to_owned());
+
+        for argument in arguments {
+            let erased = match ty.take() {
+                Some(mut parameters) => {
+                    // the type may only be a function once its references are unfolded
+                    if !matches!(parameters, Term::Function { .. }) {
+                        parameters
+                            .normalize(&self.definitions)
+                            .map_err(|e| RuntimeError::Evaluation(format!("{:?}", e)))?;
+                    }
+
+                    match parameters {
+                        Term::Function {
+                            erased,
+                            mut return_type,
+                            ..
+                        } => {
+                            // binding the function and its argument closes the return type, so
+                            // that it can be normalized in turn
+                            instantiate(&mut return_type, 0, &term, &argument);
+                            ty = Some(*return_type);
+                            erased
+                        }
+                        _ => return Err(RuntimeError::Arity(path.to_owned())),
+                    }
+                }
+                None => false,
+            };
+
+            term = Term::Apply {
+                erased,
+                function: Box::new(term),
+                argument: Box::new(argument),
+            };
+        }
+
+        self.evaluate(term)
+    }
+}
@@ -0,0 +1,133 @@
+use std::{
+    collections::HashSet,
+    fs::read_to_string,
+    path::{Component, Path, PathBuf},
+    time::SystemTime,
+};
+
+use combine::{stream::position, EasyParser};
+use parser::{items, AbsolutePath, BlockItem, BumpString, BumpVec, Ident, Item};
+use walkdir::WalkDir;
+use welkin_core::term::Term;
+
+use crate::{
+    compiler::{item::Compile as _, term::Compile as _, LocalResolver, Resolve},
+    SerializableData,
+};
+
+pub struct Library {
+    pub declarations: Vec<(AbsolutePath, Term<AbsolutePath>, Term<AbsolutePath>)>,
+    pub data_declarations: Vec<SerializableData>,
+    /// Every source file read, for change tracking.
+    pub files: Vec<PathBuf>,
+    pub parsing_time: u128,
+    pub codegen_time: u128,
+}
+
+/// Parses and compiles every `.w` file under `source`. Definitions whose names appear in `names`
+/// are printed as they are compiled.
+pub fn load(source: impl AsRef<Path>, names: &HashSet<String>) -> Result<Library, String> {
+    let bump = bumpalo::Bump::new();
+
+    let mut library = Library {
+        declarations: vec![],
+        data_declarations: vec![],
+        files: vec![],
+        parsing_time: 0,
+        codegen_time: 0,
+    };
+
+    for entry in WalkDir::new(source.as_ref()).into_iter().skip(1) {
+        let entry = entry.map_err(|e| format!("{}", e))?;
+        if entry.file_type().is_dir() {
+            continue;
+        }
+        let entry = entry.into_path();
+        let mut name = entry
+            .strip_prefix(source.as_ref())
+            .unwrap()
+            .components()
+            .map(|a| match a {
+                Component::Normal(a) => {
+                    Ok(a.to_string_lossy().split(".").next().unwrap().to_owned())
+                }
+                _ => Err(format!("unexpected component in path {}", entry.display())),
+            })
+            .collect::<Result<Vec<String>, _>>()?;
+        name = name
+            .into_iter()
+            .skip_while(|a| !a.chars().next().unwrap().is_uppercase())
+            .collect();
+        if name.len() > 1 && name.last().unwrap() == &name[name.len() - 2] {
+            name.pop();
+        }
+        let hr_name = name.clone().join("::");
+        let data = read_to_string(&entry).map_err(|e| format!("{}", e))?;
+        library.files.push(entry);
+        let data = data.trim();
+        let data = data
+            .split('\n')
+            .filter(|a| !a.trim().starts_with("//"))
+            .collect::<Vec<_>>()
+            .join("\n");
+        let data = position::Stream::new(data.trim());
+        let now = SystemTime::now();
+
+        let (items, remainder) = items(&bump)
+            .easy_parse(data)
+            .map_err(|e| format!("{}in {}", e, hr_name))?;
+
+        library.parsing_time += now.elapsed().unwrap().as_millis();
+        if !remainder.input.is_empty() {
+            return Err(format!(
+                "PARSING ENDED BEFORE EOF IN {} WITH \n{}\nREMAINING",
+                hr_name, remainder.input
+            ));
+        } else {
+            let now = SystemTime::now();
+            for item in items {
+                if let Item::Declaration(t) = item {
+                    let ty = t.ty.compile(LocalResolver::new());
+                    let term = t.term.compile(LocalResolver::new());
+                    if names.contains(&hr_name) {
+                        println!("NAME: {}\n{:?}\n{:?}\n", hr_name, ty, term);
+                    }
+                    let name = if name.len() == 0 {
+                        vec![t.ident.0]
+                    } else {
+                        name.clone()
+                            .into_iter()
+                            .map(|a| BumpString::from_str(&a, &bump))
+                            .collect()
+                    };
+
+                    library.declarations.push((
+                        LocalResolver::new().canonicalize(parser::Path(BumpVec::from_iterator(
+                            name.clone().into_iter().map(Ident),
+                            &bump,
+                        ))),
+                        ty,
+                        term,
+                    ));
+                } else if let Item::Block(block) = item {
+                    #[allow(irrefutable_let_patterns)]
+                    if let BlockItem::Data(data) = block {
+                        library
+                            .data_declarations
+                            .push(SerializableData::from(data.clone()));
+                        let compiled = data.compile(LocalResolver::new());
+                        if names.contains(&hr_name) {
+                            for (_, ty, term) in &compiled {
+                                println!("NAME: {}\n{:?}\n{:?}\n", hr_name, ty, term);
+                            }
+                        }
+                        library.declarations.extend(compiled);
+                    }
+                }
+            }
+            library.codegen_time += now.elapsed().unwrap().as_millis();
+        }
+    }
+
+    Ok(library)
+}
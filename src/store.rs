@@ -0,0 +1,306 @@
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    error::Error,
+    fmt::{self, Display},
+    fs, io,
+    path::PathBuf,
+};
+
+use parser::AbsolutePath;
+use serde::{Deserialize, Serialize};
+use welkin_core::term::Term;
+
+use crate::{
+    container::{self, Container, ContainerError, Kind},
+    graph::{references, Graph},
+    hash::{hash_bytes, Hash, ReferenceHash},
+    TermExt,
+};
+
+/// A definition with every reference replaced by the hash of the definition it names.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct StoredDefinition {
+    pub ty: Term<Hash>,
+    pub term: Term<Hash>,
+}
+
+impl Container for StoredDefinition {
+    const KIND: Kind = Kind::Stored;
+    const VERSION: u32 = 1;
+}
+
+impl StoredDefinition {
+    pub fn hash(&self) -> Hash {
+        let mut data = self.ty.hash().hash().into_owned();
+        data.extend(self.term.hash().hash().iter());
+        hash_bytes(&data)
+    }
+
+    pub fn dependencies(&self) -> BTreeSet<Hash> {
+        let mut dependencies = BTreeSet::new();
+        let mut insert = |hash: &Hash| {
+            dependencies.insert(hash.clone());
+        };
+        references(&self.ty, &mut insert);
+        references(&self.term, &mut insert);
+        dependencies
+    }
+}
+
+fn cycle_hash(depth: usize) -> Hash {
+    let mut data = b"welkin::cycle".to_vec();
+    data.extend(&(depth as u32).to_be_bytes());
+    hash_bytes(&data)
+}
+
+fn unresolved_hash(path: &AbsolutePath) -> Hash {
+    let mut data = b"welkin::unresolved".to_vec();
+    data.extend(path.hash().iter());
+    hash_bytes(&data)
+}
+
+struct Hasher<'a> {
+    declarations: HashMap<&'a AbsolutePath, (&'a Term<AbsolutePath>, &'a Term<AbsolutePath>)>,
+    stack: Vec<&'a AbsolutePath>,
+    done: HashMap<AbsolutePath, (Hash, StoredDefinition)>,
+}
+
+impl<'a> Hasher<'a> {
+    fn visit(&mut self, path: &'a AbsolutePath) -> Hash {
+        if let Some((hash, _)) = self.done.get(path) {
+            return hash.clone();
+        }
+
+        if let Some(position) = self.stack.iter().position(|item| *item == path) {
+            return cycle_hash(self.stack.len() - position);
+        }
+
+        let (ty, term) = match self.declarations.get(path) {
+            Some(declaration) => *declaration,
+            None => return unresolved_hash(path),
+        };
+
+        self.stack.push(path);
+
+        let mut names = vec![];
+        references(ty, &mut |reference| names.push(reference));
+        references(term, &mut |reference| names.push(reference));
+
+        let hashes: HashMap<_, _> = names
+            .into_iter()
+            .map(|reference| (reference.clone(), self.visit(reference)))
+            .collect();
+
+        self.stack.pop();
+
+        let definition = StoredDefinition {
+            ty: ty
+                .clone()
+                .map_reference(|reference| Term::Reference(hashes[&reference].clone())),
+            term: term
+                .clone()
+                .map_reference(|reference| Term::Reference(hashes[&reference].clone())),
+        };
+        let hash = definition.hash();
+
+        self.done.insert(path.clone(), (hash.clone(), definition));
+
+        hash
+    }
+}
+
+/// Computes the Merkle hash of every declaration.
+///
+/// A reference back into a cycle of definitions currently being hashed is replaced by a marker
+/// recording how many definitions up the cycle it points, so self-recursive definitions hash
+/// independently of their name. Each cycle is hashed once everything it references outside itself
+/// is, starting from its lexicographically smallest name, so its hashes depend only on the cycle
+/// and its dependencies.
+pub fn merkle_hashes<'a>(
+    declarations: impl IntoIterator<Item = &'a (AbsolutePath, Term<AbsolutePath>, Term<AbsolutePath>)>,
+) -> BTreeMap<String, (Hash, StoredDefinition)> {
+    let declarations: Vec<_> = declarations.into_iter().collect();
+    let graph = Graph::new(declarations.iter().copied());
+
+    let paths: HashMap<_, _> = declarations
+        .iter()
+        .map(|(path, _, _)| (format!("{:?}", path), path))
+        .collect();
+
+    let mut hasher = Hasher {
+        declarations: declarations
+            .iter()
+            .map(|(path, ty, term)| (path, (ty, term)))
+            .collect(),
+        stack: vec![],
+        done: HashMap::new(),
+    };
+
+    for component in graph.components() {
+        hasher.visit(paths[component[0]]);
+    }
+
+    hasher
+        .done
+        .into_iter()
+        .map(|(path, entry)| (format!("{:?}", path), entry))
+        .collect()
+}
+
+#[derive(Debug)]
+pub enum StoreError {
+    Io(io::Error),
+    Container(ContainerError),
+    Json(serde_json::Error),
+    Missing(Hash),
+    InvalidHash { name: String, hash: String },
+}
+
+impl Display for StoreError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StoreError::Io(e) => write!(f, "io error: {}", e),
+            StoreError::Container(e) => write!(f, "container error: {}", e),
+            StoreError::Json(e) => write!(f, "json error: {}", e),
+            StoreError::Missing(hash) => write!(f, "no definition with hash {}", hash),
+            StoreError::InvalidHash { name, hash } => {
+                write!(f, "index maps {} to invalid hash {}", name, hash)
+            }
+        }
+    }
+}
+
+impl Error for StoreError {}
+
+impl From<io::Error> for StoreError {
+    fn from(e: io::Error) -> Self {
+        StoreError::Io(e)
+    }
+}
+
+impl From<ContainerError> for StoreError {
+    fn from(e: ContainerError) -> Self {
+        StoreError::Container(e)
+    }
+}
+
+impl From<serde_json::Error> for StoreError {
+    fn from(e: serde_json::Error) -> Self {
+        StoreError::Json(e)
+    }
+}
+
+pub struct Added {
+    pub name: String,
+    pub hash: Hash,
+    pub new: bool,
+}
+
+/// A directory of definitions stored under `defs/<hash>`, with a name→hash index in
+/// `names.json`.
+pub struct Store {
+    root: PathBuf,
+    index: BTreeMap<String, Hash>,
+}
+
+impl Store {
+    pub fn open(root: impl Into<PathBuf>) -> Result<Self, StoreError> {
+        let root = root.into();
+
+        fs::create_dir_all(root.join("defs"))?;
+
+        let index_path = root.join("names.json");
+
+        let index = if index_path.exists() {
+            let index: BTreeMap<String, String> =
+                serde_json::from_str(&fs::read_to_string(index_path)?)?;
+            index
+                .into_iter()
+                .map(|(name, hash)| match hash.parse() {
+                    Ok(parsed) => Ok((name, parsed)),
+                    Err(_) => Err(StoreError::InvalidHash { name, hash }),
+                })
+                .collect::<Result<_, _>>()?
+        } else {
+            BTreeMap::new()
+        };
+
+        Ok(Store { root, index })
+    }
+
+    fn path(&self, hash: &Hash) -> PathBuf {
+        self.root.join("defs").join(hash.to_string())
+    }
+
+    fn save_index(&self) -> Result<(), StoreError> {
+        let index: BTreeMap<_, _> = self
+            .index
+            .iter()
+            .map(|(name, hash)| (name, hash.to_string()))
+            .collect();
+
+        fs::write(
+            self.root.join("names.json"),
+            serde_json::to_string_pretty(&index)?,
+        )?;
+
+        Ok(())
+    }
+
+    /// Stores every declaration and points its name at the resulting hash.
+    pub fn add<'a>(
+        &mut self,
+        declarations: impl IntoIterator<
+            Item = &'a (AbsolutePath, Term<AbsolutePath>, Term<AbsolutePath>),
+        >,
+    ) -> Result<Vec<Added>, StoreError> {
+        let mut added = vec![];
+
+        for (name, (hash, definition)) in merkle_hashes(declarations) {
+            let path = self.path(&hash);
+            let new = !path.exists();
+
+            if new {
+                fs::write(path, container::encode(&definition))?;
+            }
+
+            self.index.insert(name.clone(), hash.clone());
+            added.push(Added { name, hash, new });
+        }
+
+        self.save_index()?;
+
+        Ok(added)
+    }
+
+    pub fn get(&self, hash: &Hash) -> Result<StoredDefinition, StoreError> {
+        let path = self.path(hash);
+
+        if !path.exists() {
+            return Err(StoreError::Missing(hash.clone()));
+        }
+
+        Ok(container::decode(&fs::read(path)?)?)
+    }
+
+    /// Resolves either an indexed name or a hash.
+    pub fn resolve(&self, name_or_hash: &str) -> Option<Hash> {
+        self.index
+            .get(name_or_hash)
+            .cloned()
+            .or_else(|| name_or_hash.parse().ok())
+    }
+
+    /// All indexed names pointing at `hash`.
+    pub fn names(&self, hash: &Hash) -> Vec<&str> {
+        self.index
+            .iter()
+            .filter(|(_, other)| *other == hash)
+            .map(|(name, _)| name.as_str())
+            .collect()
+    }
+
+    pub fn index(&self) -> &BTreeMap<String, Hash> {
+        &self.index
+    }
+}
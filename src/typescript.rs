@@ -0,0 +1,434 @@
+//! Generates TypeScript declarations, with codecs to and from the JSON form of `Term<String>`, for
+//! data declarations.
+//!
+//! Each data type becomes a union tagged by `$variant` along with a `$Codec` of the same name, or a
+//! function from codecs of its type parameters to one when it has any. `String`, `Size`, `Char`,
+//! `Bool`, `Word` and `Vector` map to native values as they do in Rust bindings, and types that
+//! aren't declared are left as terms. Names in the prelude and type parameters start with `$`,
+//! which welkin identifiers can't, so they never collide with declared types.
+//!
+//! The codecs only build and read terms in JavaScript. Whelk's wasm interface still exchanges
+//! binary containers, so handing it JSON terms is left to the caller.
+
+use std::fmt::Write;
+
+use parser::AbsolutePath;
+use welkin_core::term::Term;
+
+use crate::SerializableData;
+
+const PRELUDE: &str = r#"export type $Term =
+  | { Variable: number }
+  | { Lambda: { body: $Term; erased: boolean } }
+  | { Apply: { function: $Term; argument: $Term; erased: boolean } }
+  | { Put: $Term }
+  | { Duplicate: { expression: $Term; body: $Term } }
+  | { Reference: string }
+  | "Universe"
+  | { Function: { argument_type: $Term; return_type: $Term; erased: boolean } }
+  | { Annotation: { checked: boolean; expression: $Term; ty: $Term } }
+  | { Wrap: $Term };
+
+export interface $Codec<T> {
+  encode(value: T): $Term;
+  decode(term: $Term): T;
+}
+
+export class $DecodeError extends Error {}
+
+export function $construct(variants: number, variant: number, fields: $Term[]): $Term {
+  let term: $Term = { Variable: variants - 1 - variant };
+  for (const field of fields) {
+    term = { Apply: { function: term, argument: field, erased: false } };
+  }
+  for (let i = 0; i < variants; i++) {
+    term = { Lambda: { body: term, erased: false } };
+  }
+  return term;
+}
+
+export function $readVariant(term: $Term, variants: number, data: string): [number, $Term[]] {
+  for (let i = 0; i < variants; i++) {
+    if (typeof term === "object" && "Lambda" in term) {
+      term = term.Lambda.body;
+    } else {
+      throw new $DecodeError(`expected lambda in ${data}`);
+    }
+  }
+  const fields: $Term[] = [];
+  while (typeof term === "object" && "Apply" in term) {
+    fields.push(term.Apply.argument);
+    term = term.Apply.function;
+  }
+  if (typeof term !== "object" || !("Variable" in term)) {
+    throw new $DecodeError(`expected application or variable in ${data}`);
+  }
+  if (term.Variable >= variants) {
+    throw new $DecodeError(
+      `invalid variant ${term.Variable} of ${data}, expected at most ${variants}`
+    );
+  }
+  fields.reverse();
+  return [variants - 1 - term.Variable, fields];
+}
+
+export function $expectFields(fields: $Term[], count: number, data: string, variant: string) {
+  if (fields.length < count) {
+    throw new $DecodeError(`insufficient fields present in ${data}::${variant}`);
+  }
+}
+
+export const $termCodec: $Codec<$Term> = {
+  encode(value) {
+    return value;
+  },
+  decode(term) {
+    return term;
+  },
+};
+
+export const $sizeCodec: $Codec<number> = {
+  encode(value) {
+    let term: $Term = { Variable: 0 };
+    for (let i = 0; i < value; i++) {
+      term = { Apply: { function: { Variable: 1 }, argument: term, erased: false } };
+    }
+    return { Lambda: { body: { Lambda: { body: term, erased: false } }, erased: false } };
+  },
+  decode(term) {
+    for (let i = 0; i < 2; i++) {
+      if (typeof term === "object" && "Lambda" in term) {
+        term = term.Lambda.body;
+      } else {
+        throw new $DecodeError("expected lambda in Size");
+      }
+    }
+    let size = 0;
+    while (typeof term === "object" && "Apply" in term) {
+      size += 1;
+      term = term.Apply.argument;
+    }
+    if (typeof term !== "object" || !("Variable" in term)) {
+      throw new $DecodeError("expected application or variable in Size");
+    }
+    return size;
+  },
+};
+
+export const $boolCodec: $Codec<boolean> = {
+  encode(value) {
+    return $construct(2, value ? 0 : 1, []);
+  },
+  decode(term) {
+    return $readVariant(term, 2, "Bool")[0] === 0;
+  },
+};
+
+// bits are listed outermost first
+export const $wordCodec: $Codec<boolean[]> = {
+  encode(bits) {
+    let term = $construct(3, 0, []);
+    for (let i = bits.length - 1; i >= 0; i--) {
+      term = $construct(3, bits[i] ? 2 : 1, [term]);
+    }
+    return term;
+  },
+  decode(term) {
+    const bits: boolean[] = [];
+    for (;;) {
+      const [variant, fields] = $readVariant(term, 3, "Word");
+      if (variant === 0) {
+        return bits;
+      }
+      $expectFields(fields, 1, "Word", variant === 1 ? "low" : "high");
+      bits.push(variant === 2);
+      term = fields[0];
+    }
+  },
+};
+
+// a character is a 32-bit word of its little-endian bytes, each most significant bit first
+export const $charCodec: $Codec<string> = {
+  encode(value) {
+    const code = value.codePointAt(0) ?? 0;
+    const bits: boolean[] = [];
+    for (let byte = 0; byte < 4; byte++) {
+      for (let idx = 0; idx < 8; idx++) {
+        bits.push(((code >>> (byte * 8 + 7 - idx)) & 1) === 1);
+      }
+    }
+    return $construct(1, 0, [$wordCodec.encode(bits)]);
+  },
+  decode(term) {
+    const [, fields] = $readVariant(term, 1, "Char");
+    $expectFields(fields, 1, "Char", "new");
+    const bits = $wordCodec.decode(fields[0]);
+    if (bits.length !== 32) {
+      throw new $DecodeError(`expected word of width 32 in Char, got ${bits.length}`);
+    }
+    let code = 0;
+    bits.forEach((bit, idx) => {
+      if (bit) {
+        code += 2 ** (Math.floor(idx / 8) * 8 + 7 - (idx % 8));
+      }
+    });
+    if (code > 0x10ffff || (code >= 0xd800 && code <= 0xdfff)) {
+      throw new $DecodeError(`invalid character ${code}`);
+    }
+    return String.fromCodePoint(code);
+  },
+};
+
+export function $vectorCodec<A>(A: $Codec<A>): $Codec<A[]> {
+  return {
+    encode(elements) {
+      let term = $construct(2, 0, []);
+      for (let i = elements.length - 1; i >= 0; i--) {
+        term = $construct(2, 1, [A.encode(elements[i]), term]);
+      }
+      return term;
+    },
+    decode(term) {
+      const elements: A[] = [];
+      for (;;) {
+        const [variant, fields] = $readVariant(term, 2, "Vector");
+        if (variant === 0) {
+          return elements;
+        }
+        $expectFields(fields, 2, "Vector", "cons");
+        elements.push(A.decode(fields[0]));
+        term = fields[1];
+      }
+    },
+  };
+}
+
+export const $stringCodec: $Codec<string> = {
+  encode(value) {
+    return $construct(1, 0, [$vectorCodec($charCodec).encode(Array.from(value))]);
+  },
+  decode(term) {
+    const [, fields] = $readVariant(term, 1, "String");
+    $expectFields(fields, 1, "String", "new");
+    return $vectorCodec($charCodec).decode(fields[0]).join("");
+  },
+};
+"#;
+
+/// Types represented natively rather than by their declarations.
+const NATIVE: &[&str] = &["String", "Size", "Char", "Bool", "Word", "Vector"];
+
+/// Type parameters are named by position, prefixed like the prelude so that they can't shadow
+/// declared types.
+fn parameter(idx: usize) -> String {
+    format!("${}", idx)
+}
+
+/// The TypeScript type of a field along with an expression for its codec.
+fn field(ty: &Term<AbsolutePath>, defs: &[SerializableData]) -> (String, String) {
+    let mut head = ty;
+    let mut arguments = vec![];
+
+    while let Term::Apply {
+        function, argument, ..
+    } = head
+    {
+        arguments.push(&**argument);
+        head = &**function;
+    }
+
+    arguments.reverse();
+
+    let name = match head {
+        Term::Reference(path) if path.0.len() == 1 => path.0[0].as_str(),
+        _ => return ("$Term".into(), "$termCodec".into()),
+    };
+
+    if let Some(idx) = name
+        .strip_prefix('T')
+        .and_then(|idx| idx.parse::<usize>().ok())
+    {
+        return (parameter(idx), parameter(idx));
+    }
+
+    match name {
+        "String" => ("string".into(), "$stringCodec".into()),
+        "Size" => ("number".into(), "$sizeCodec".into()),
+        "Char" => ("string".into(), "$charCodec".into()),
+        "Bool" => ("boolean".into(), "$boolCodec".into()),
+        "Word" => ("boolean[]".into(), "$wordCodec".into()),
+        "Vector" if !arguments.is_empty() => {
+            let (ty, codec) = field(arguments[0], defs);
+            (format!("Array<{}>", ty), format!("$vectorCodec({})", codec))
+        }
+        name => match defs
+            .iter()
+            .find(|data| data.ident == name)
+            .and_then(|data| data.carried_arguments(arguments))
+        {
+            Some(arguments) => {
+                let (types, codecs): (Vec<_>, Vec<_>) = arguments
+                    .into_iter()
+                    .map(|argument| field(argument, defs))
+                    .unzip();
+
+                if types.is_empty() {
+                    (name.to_owned(), name.to_owned())
+                } else {
+                    (
+                        format!("{}<{}>", name, types.join(", ")),
+                        format!("{}({})", name, codecs.join(", ")),
+                    )
+                }
+            }
+            None => ("$Term".into(), "$termCodec".into()),
+        },
+    }
+}
+
+fn data(out: &mut String, data: &SerializableData, defs: &[SerializableData]) {
+    let ident = &data.ident;
+    let variants = data.variants.len();
+
+    let parameters: Vec<_> = (0..data.type_arguments).map(parameter).collect();
+    let generics = if parameters.is_empty() {
+        String::new()
+    } else {
+        format!("<{}>", parameters.join(", "))
+    };
+
+    write!(out, "\nexport type {}{} =", ident, generics).unwrap();
+
+    if data.variants.is_empty() {
+        out.push_str(" never");
+    }
+
+    for (name, variant) in &data.variants {
+        write!(out, "\n  | {{ $variant: {:?}", name).unwrap();
+        for (field_name, ty) in &variant.inhabitants {
+            write!(out, "; {:?}: {}", field_name, field(ty, defs).0).unwrap();
+        }
+        out.push_str(" }");
+    }
+
+    out.push_str(";\n\n");
+
+    let codec = format!("$Codec<{}{}>", ident, generics);
+
+    if parameters.is_empty() {
+        writeln!(out, "export const {}: {} = {{", ident, codec).unwrap();
+    } else {
+        let arguments: Vec<_> = parameters
+            .iter()
+            .map(|parameter| format!("{}: $Codec<{}>", parameter, parameter))
+            .collect();
+        writeln!(
+            out,
+            "export function {}{}({}): {} {{\n  return {{",
+            ident,
+            generics,
+            arguments.join(", "),
+            codec
+        )
+        .unwrap();
+    }
+
+    let indent = if parameters.is_empty() { "" } else { "  " };
+
+    writeln!(out, "{}  encode(value) {{", indent).unwrap();
+    writeln!(out, "{}    switch (value.$variant) {{", indent).unwrap();
+    for (idx, (name, variant)) in data.variants.iter().enumerate() {
+        let fields: Vec<_> = variant
+            .inhabitants
+            .iter()
+            .map(|(field_name, ty)| {
+                format!("{}.encode(value[{:?}])", field(ty, defs).1, field_name)
+            })
+            .collect();
+        writeln!(out, "{}      case {:?}:", indent, name).unwrap();
+        writeln!(
+            out,
+            "{}        return $construct({}, {}, [{}]);",
+            indent,
+            variants,
+            idx,
+            fields.join(", ")
+        )
+        .unwrap();
+    }
+    writeln!(out, "{}    }}", indent).unwrap();
+    writeln!(
+        out,
+        "{}    throw new Error(`unknown variant of {}`);",
+        indent, ident
+    )
+    .unwrap();
+    writeln!(out, "{}  }},", indent).unwrap();
+
+    writeln!(out, "{}  decode(term) {{", indent).unwrap();
+    writeln!(
+        out,
+        "{}    const [variant, fields] = $readVariant(term, {}, {:?});",
+        indent, variants, ident
+    )
+    .unwrap();
+    writeln!(out, "{}    switch (variant) {{", indent).unwrap();
+    for (idx, (name, variant)) in data.variants.iter().enumerate() {
+        writeln!(out, "{}      case {}:", indent, idx).unwrap();
+        if !variant.inhabitants.is_empty() {
+            writeln!(
+                out,
+                "{}        $expectFields(fields, {}, {:?}, {:?});",
+                indent,
+                variant.inhabitants.len(),
+                ident,
+                name
+            )
+            .unwrap();
+        }
+        let mut value = format!("{{ $variant: {:?}", name);
+        for (field_idx, (field_name, ty)) in variant.inhabitants.iter().enumerate() {
+            write!(
+                value,
+                ", {:?}: {}.decode(fields[{}])",
+                field_name,
+                field(ty, defs).1,
+                field_idx
+            )
+            .unwrap();
+        }
+        value.push_str(" }");
+        writeln!(out, "{}        return {};", indent, value).unwrap();
+    }
+    writeln!(out, "{}    }}", indent).unwrap();
+    writeln!(
+        out,
+        "{}    throw new $DecodeError(`invalid variant ${{variant}} of {}`);",
+        indent, ident
+    )
+    .unwrap();
+    writeln!(out, "{}  }},", indent).unwrap();
+
+    if parameters.is_empty() {
+        out.push_str("};\n");
+    } else {
+        out.push_str("  };\n}\n");
+    }
+}
+
+/// Generates a TypeScript module for the data declarations.
+pub fn generate(defs: &[SerializableData]) -> String {
+    let mut out = String::from("// generated by `welkin typescript`, do not edit\n\n");
+
+    out.push_str(PRELUDE);
+
+    for def in defs {
+        if NATIVE.contains(&def.ident.as_str()) {
+            continue;
+        }
+
+        data(&mut out, def, defs);
+    }
+
+    out
+}
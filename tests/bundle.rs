@@ -0,0 +1,247 @@
+use parser::AbsolutePath;
+use welkin::bundle::{erase, Bundle, BundleError, BundleOptions};
+use welkin_core::term::{Index, Term};
+
+fn path(segments: &[&str]) -> AbsolutePath {
+    AbsolutePath(segments.iter().map(|segment| segment.to_string()).collect())
+}
+
+fn reference(segments: &[&str]) -> Term<AbsolutePath> {
+    Term::Reference(path(segments))
+}
+
+fn variable(index: usize) -> Term<AbsolutePath> {
+    Term::Variable(Index(index))
+}
+
+fn lambda(erased: bool, body: Term<AbsolutePath>) -> Term<AbsolutePath> {
+    Term::Lambda {
+        erased,
+        body: Box::new(body),
+    }
+}
+
+fn apply(
+    erased: bool,
+    function: Term<AbsolutePath>,
+    argument: Term<AbsolutePath>,
+) -> Term<AbsolutePath> {
+    Term::Apply {
+        erased,
+        function: Box::new(function),
+        argument: Box::new(argument),
+    }
+}
+
+fn declarations(
+    terms: Vec<(&str, Term<AbsolutePath>)>,
+) -> Vec<(AbsolutePath, Term<AbsolutePath>, Term<AbsolutePath>)> {
+    terms
+        .into_iter()
+        .map(|(name, term)| (path(&[name]), Term::Universe, term))
+        .collect()
+}
+
+fn bundle(terms: Vec<(&str, Term<AbsolutePath>)>, options: BundleOptions) -> Bundle {
+    Bundle::new(&declarations(terms), &options).unwrap()
+}
+
+/// The names of the bundled definitions, in order.
+fn names(bundle: &Bundle) -> Vec<String> {
+    bundle
+        .definitions
+        .iter()
+        .map(|(path, _, _)| format!("{:?}", path))
+        .collect()
+}
+
+fn definition(bundle: &Bundle, segments: &[&str]) -> String {
+    let (_, _, term) = bundle
+        .definitions
+        .iter()
+        .find(|(path, _, _)| *path == self::path(segments))
+        .unwrap();
+    format!("{:?}", term)
+}
+
+fn show(term: Term<AbsolutePath>) -> String {
+    format!("{:?}", term)
+}
+
+/// `x |> x`, the smallest closed subterm that can be shared.
+fn id() -> Term<AbsolutePath> {
+    lambda(false, variable(0))
+}
+
+/// `y |> y(x |> x)`, which contains `id` once.
+fn pass_id() -> Term<AbsolutePath> {
+    lambda(false, apply(false, variable(0), id()))
+}
+
+#[test]
+fn erases_erased_binders_and_types() {
+    // a |> B ||> a[B](B ~as b -> B)
+    let term = lambda(
+        false,
+        lambda(
+            true,
+            apply(
+                false,
+                apply(true, variable(1), variable(0)),
+                Term::Function {
+                    erased: false,
+                    argument_type: Box::new(variable(0)),
+                    return_type: Box::new(variable(2)),
+                },
+            ),
+        ),
+    );
+
+    assert_eq!(
+        show(erase(&term)),
+        show(lambda(false, apply(false, variable(0), Term::Universe)))
+    );
+}
+
+#[test]
+fn shifts_variables_bound_outside_erased_binders() {
+    // a |> B ||> c |> a(c)
+    let term = lambda(
+        false,
+        lambda(true, lambda(false, apply(false, variable(2), variable(0)))),
+    );
+
+    assert_eq!(
+        show(erase(&term)),
+        show(lambda(
+            false,
+            lambda(false, apply(false, variable(1), variable(0)))
+        ))
+    );
+}
+
+#[test]
+fn shakes_definitions_unreachable_from_entries() {
+    let terms = vec![
+        ("main", apply(false, reference(&["used"]), id())),
+        ("used", id()),
+        ("unused", reference(&["used"])),
+    ];
+
+    let bundle = bundle(terms.clone(), BundleOptions::default());
+    assert_eq!(names(&bundle), vec!["main", "used"]);
+    assert_eq!(bundle.entries.len(), 1);
+
+    let options = BundleOptions {
+        entries: vec![path(&["missing"])],
+        ..BundleOptions::default()
+    };
+    assert!(matches!(
+        Bundle::new(&declarations(terms), &options),
+        Err(BundleError::MissingEntry(entry)) if entry == path(&["missing"])
+    ));
+}
+
+#[test]
+fn inlines_small_acyclic_definitions() {
+    let bundle = bundle(
+        vec![
+            (
+                "main",
+                apply(
+                    false,
+                    apply(false, reference(&["id"]), reference(&["loop"])),
+                    reference(&["id"]),
+                ),
+            ),
+            ("id", id()),
+            (
+                "loop",
+                lambda(false, apply(false, reference(&["loop"]), variable(0))),
+            ),
+        ],
+        BundleOptions {
+            inline: Some(10),
+            ..BundleOptions::default()
+        },
+    );
+
+    // `loop` refers to itself, so it stays a definition of its own
+    assert_eq!(names(&bundle), vec!["loop", "main"]);
+    assert_eq!(
+        definition(&bundle, &["main"]),
+        show(apply(false, apply(false, id(), reference(&["loop"])), id()))
+    );
+}
+
+#[test]
+fn shares_repeated_subterms_once() {
+    let bundle = bundle(
+        vec![("main", apply(false, pass_id(), pass_id()))],
+        BundleOptions {
+            share: Some(2),
+            ..BundleOptions::default()
+        },
+    );
+
+    // `id` repeats only within the shared `pass_id`, so it isn't shared on its own
+    assert_eq!(names(&bundle), vec!["main", "__shared::0"]);
+    assert_eq!(
+        definition(&bundle, &["main"]),
+        show(apply(
+            false,
+            reference(&["__shared", "0"]),
+            reference(&["__shared", "0"])
+        ))
+    );
+    assert_eq!(definition(&bundle, &["__shared", "0"]), show(pass_id()));
+}
+
+#[test]
+fn shares_subterms_repeated_outside_shared_ones() {
+    let bundle = bundle(
+        vec![(
+            "main",
+            apply(false, apply(false, pass_id(), pass_id()), id()),
+        )],
+        BundleOptions {
+            share: Some(2),
+            ..BundleOptions::default()
+        },
+    );
+
+    assert_eq!(bundle.definitions.len(), 3);
+    assert_eq!(
+        definition(&bundle, &["main"]),
+        show(apply(
+            false,
+            apply(
+                false,
+                reference(&["__shared", "0"]),
+                reference(&["__shared", "0"])
+            ),
+            reference(&["__shared", "1"])
+        ))
+    );
+    assert_eq!(
+        definition(&bundle, &["__shared", "0"]),
+        show(lambda(
+            false,
+            apply(false, variable(0), reference(&["__shared", "1"]))
+        ))
+    );
+    assert_eq!(definition(&bundle, &["__shared", "1"]), show(id()));
+}
+
+#[test]
+fn does_not_share_below_the_threshold() {
+    let bundle = bundle(
+        vec![("main", apply(false, pass_id(), pass_id()))],
+        BundleOptions {
+            share: Some(6),
+            ..BundleOptions::default()
+        },
+    );
+
+    assert_eq!(names(&bundle), vec!["main"]);
+}
@@ -0,0 +1,110 @@
+use parser::AbsolutePath;
+use serde::Serialize;
+use welkin::{
+    container::{self, ContainerError, Kind},
+    Parameter, SerializableData, Terms,
+};
+use welkin_core::term::Term;
+
+fn terms() -> Terms {
+    Terms {
+        data: vec![(
+            AbsolutePath(vec!["x".into()]),
+            Term::Universe,
+            Term::Universe,
+        )],
+    }
+}
+
+#[test]
+fn round_trips() {
+    let data = container::encode(&terms());
+
+    assert!(matches!(container::kind(&data), Ok(Kind::Terms)));
+    assert_eq!(
+        format!("{:?}", container::decode::<Terms>(&data).unwrap()),
+        format!("{:?}", terms())
+    );
+}
+
+#[test]
+fn rejects_corrupted_payloads() {
+    let mut data = container::encode(&terms());
+    *data.last_mut().unwrap() ^= 1;
+
+    assert!(matches!(
+        container::decode::<Terms>(&data),
+        Err(ContainerError::Checksum)
+    ));
+    assert!(matches!(
+        container::kind(&data),
+        Err(ContainerError::Checksum)
+    ));
+}
+
+#[test]
+fn rejects_other_kinds() {
+    let data = container::encode(&terms());
+
+    assert!(matches!(
+        container::decode::<Vec<SerializableData>>(&data),
+        Err(ContainerError::Kind {
+            expected: Kind::Definitions,
+            found: Kind::Terms,
+        })
+    ));
+}
+
+#[test]
+fn migrates_legacy_terms() {
+    let data = bincode::serialize(&terms()).unwrap();
+
+    assert_eq!(
+        format!("{:?}", container::decode::<Terms>(&data).unwrap()),
+        format!("{:?}", terms())
+    );
+}
+
+#[test]
+fn migrates_legacy_definitions() {
+    #[derive(Serialize)]
+    struct LegacyVariant {
+        inhabitants: Vec<(String, Term<AbsolutePath>)>,
+    }
+
+    #[derive(Serialize)]
+    struct Legacy {
+        variants: Vec<(String, LegacyVariant)>,
+        ident: String,
+        type_arguments: usize,
+        indices: usize,
+        skipped_type_arguments: Vec<usize>,
+    }
+
+    let data = bincode::serialize(&vec![Legacy {
+        variants: vec![(
+            "new".into(),
+            LegacyVariant {
+                inhabitants: vec![(
+                    "value".into(),
+                    Term::Reference(AbsolutePath(vec!["T0".into()])),
+                )],
+            },
+        )],
+        ident: "Pair".into(),
+        type_arguments: 1,
+        indices: 0,
+        skipped_type_arguments: vec![0],
+    }])
+    .unwrap();
+
+    let definitions = container::decode::<Vec<SerializableData>>(&data).unwrap();
+    assert_eq!(definitions.len(), 1);
+    assert_eq!(definitions[0].ident, "Pair");
+    assert_eq!(
+        definitions[0].parameters,
+        vec![Parameter::Erased, Parameter::Type]
+    );
+    assert_eq!(definitions[0].variants[0].1.inhabitants.len(), 1);
+    assert!(definitions[0].variants[0].1.indices.is_empty());
+}
@@ -0,0 +1,101 @@
+use parser::AbsolutePath;
+use welkin::graph::Graph;
+use welkin_core::term::Term;
+
+/// A definition of `name` whose term references each of `references`.
+fn declaration(
+    name: &str,
+    references: &[&str],
+) -> (AbsolutePath, Term<AbsolutePath>, Term<AbsolutePath>) {
+    let term = references
+        .iter()
+        .map(|reference| Term::Reference(AbsolutePath(vec![reference.to_string()])))
+        .fold(Term::Universe, |function, argument| Term::Apply {
+            erased: false,
+            function: Box::new(function),
+            argument: Box::new(argument),
+        });
+
+    (AbsolutePath(vec![name.into()]), Term::Universe, term)
+}
+
+/// `a` and `b` depend on `c`, `d` on itself, `e` and `f` on each other and `g` on an undefined `x`.
+fn graph() -> Graph {
+    Graph::new(&[
+        declaration("a", &["b", "c"]),
+        declaration("b", &["c"]),
+        declaration("c", &[]),
+        declaration("d", &["d"]),
+        declaration("e", &["c", "f"]),
+        declaration("f", &["e"]),
+        declaration("g", &["x"]),
+    ])
+}
+
+#[test]
+fn orders_components_after_their_dependencies() {
+    let graph = graph();
+
+    assert_eq!(
+        graph.components(),
+        vec![
+            vec!["c"],
+            vec!["b"],
+            vec!["a"],
+            vec!["d"],
+            vec!["e", "f"],
+            vec!["g"],
+        ]
+    );
+}
+
+#[test]
+fn reaches_definitions_through_their_dependencies() {
+    let graph = graph();
+
+    assert_eq!(
+        graph.reachable(vec!["a"]).into_iter().collect::<Vec<_>>(),
+        vec!["a", "b", "c"]
+    );
+    assert_eq!(
+        graph.reachable(vec!["f"]).into_iter().collect::<Vec<_>>(),
+        vec!["c", "e", "f"]
+    );
+    // undefined names are neither reached nor roots
+    assert_eq!(
+        graph.reachable(vec!["g"]).into_iter().collect::<Vec<_>>(),
+        vec!["g"]
+    );
+    assert!(graph.reachable(vec!["x"]).is_empty());
+
+    assert_eq!(graph.unreachable(vec!["a"]), vec!["d", "e", "f", "g"]);
+    assert_eq!(
+        graph.unreachable(vec!["a", "d", "e", "g"]),
+        Vec::<&str>::new()
+    );
+}
+
+#[test]
+fn counts_definitions_themselves_in_their_closure_only_when_recursive() {
+    let graph = graph();
+
+    assert_eq!(graph.closure_size("a"), 2);
+    assert_eq!(graph.closure_size("c"), 0);
+    assert_eq!(graph.closure_size("d"), 1);
+    assert_eq!(graph.closure_size("e"), 3);
+    assert_eq!(graph.closure_size("g"), 0);
+    assert_eq!(graph.closure_size("x"), 0);
+
+    assert_eq!(
+        graph.closure_sizes(),
+        vec![
+            ("e", 3),
+            ("f", 3),
+            ("a", 2),
+            ("b", 1),
+            ("d", 1),
+            ("c", 0),
+            ("g", 0),
+        ]
+    );
+}
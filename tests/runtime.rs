@@ -0,0 +1,244 @@
+use parser::AbsolutePath;
+use welkin::{
+    bundle::{Bundle, BundleOptions},
+    container,
+    runtime::{Evaluation, Runtime, RuntimeError},
+    Terms,
+};
+use welkin_core::term::{Index, Term};
+
+const EVALUATIONS: [Evaluation; 2] = [Evaluation::Net, Evaluation::Substitution];
+
+fn path(name: &str) -> AbsolutePath {
+    AbsolutePath(vec![name.into()])
+}
+
+fn reference(name: &str) -> Term<AbsolutePath> {
+    Term::Reference(path(name))
+}
+
+fn variable(index: usize) -> Term<AbsolutePath> {
+    Term::Variable(Index(index))
+}
+
+fn lambda(erased: bool, body: Term<AbsolutePath>) -> Term<AbsolutePath> {
+    Term::Lambda {
+        erased,
+        body: Box::new(body),
+    }
+}
+
+fn apply(
+    erased: bool,
+    function: Term<AbsolutePath>,
+    argument: Term<AbsolutePath>,
+) -> Term<AbsolutePath> {
+    Term::Apply {
+        erased,
+        function: Box::new(function),
+        argument: Box::new(argument),
+    }
+}
+
+/// A function type, whose return type is under binders for the function and then its argument.
+fn function(
+    erased: bool,
+    argument_type: Term<AbsolutePath>,
+    return_type: Term<AbsolutePath>,
+) -> Term<AbsolutePath> {
+    Term::Function {
+        erased,
+        argument_type: Box::new(argument_type),
+        return_type: Box::new(return_type),
+    }
+}
+
+/// Church booleans, the identity function, and definitions built from them. `broken` doesn't
+/// check against its type, and `uses_broken` checks only by assuming it does.
+fn terms() -> Terms {
+    // A |-> A -> A -> A
+    let bool_type = function(
+        true,
+        Term::Universe,
+        function(
+            false,
+            variable(0),
+            function(false, variable(2), variable(4)),
+        ),
+    );
+
+    let declarations = vec![
+        (path("Bool"), Term::Universe, bool_type),
+        (
+            path("true"),
+            reference("Bool"),
+            lambda(true, lambda(false, lambda(false, variable(1)))),
+        ),
+        (
+            path("false"),
+            reference("Bool"),
+            lambda(true, lambda(false, lambda(false, variable(0)))),
+        ),
+        (
+            path("id"),
+            // A |-> A -> A
+            function(
+                true,
+                Term::Universe,
+                function(false, variable(0), variable(2)),
+            ),
+            lambda(true, lambda(false, variable(0))),
+        ),
+        (
+            path("main"),
+            reference("Bool"),
+            apply(
+                false,
+                apply(true, reference("id"), reference("Bool")),
+                reference("true"),
+            ),
+        ),
+        (path("broken"), reference("Bool"), Term::Universe),
+        (path("uses_broken"), reference("Bool"), reference("broken")),
+    ];
+
+    Terms { data: declarations }
+}
+
+fn runtime(evaluation: Evaluation) -> Runtime {
+    Runtime::new(terms()).with_evaluation(evaluation)
+}
+
+fn bundled(evaluation: Evaluation) -> Runtime {
+    let options = BundleOptions {
+        entries: vec![path("main"), path("id"), path("false")],
+        ..BundleOptions::default()
+    };
+    let bundle = Bundle::new(&terms().data, &options).unwrap();
+
+    Runtime::from_bundle_bytes(&container::encode(&bundle))
+        .unwrap()
+        .with_evaluation(evaluation)
+}
+
+fn show(term: Result<Term<String>, RuntimeError>) -> String {
+    format!("{:?}", term.unwrap())
+}
+
+#[test]
+fn reports_types() {
+    let runtime = runtime(Evaluation::default());
+
+    assert_eq!(
+        format!("{:?}", runtime.type_of("main").unwrap()),
+        format!("{:?}", Term::<String>::Reference("Bool".into()))
+    );
+    assert!(matches!(
+        runtime.type_of("missing"),
+        Err(RuntimeError::Missing(_))
+    ));
+    assert!(matches!(
+        bundled(Evaluation::default()).type_of("main"),
+        Err(RuntimeError::Untyped(_))
+    ));
+}
+
+#[test]
+fn checks_definitions_with_their_dependencies() {
+    let mut runtime = runtime(Evaluation::default());
+
+    runtime.check_definition("main").unwrap();
+    assert!(matches!(
+        runtime.check_definition("uses_broken"),
+        Err(RuntimeError::Check { path, .. }) if path == "broken"
+    ));
+    assert!(matches!(
+        runtime.check_definition("missing"),
+        Err(RuntimeError::Missing(_))
+    ));
+    assert!(matches!(runtime.check(), Err(RuntimeError::Check { .. })));
+}
+
+#[test]
+fn normalizes_checked_definitions() {
+    for &evaluation in &EVALUATIONS {
+        let mut runtime = runtime(evaluation);
+
+        assert_eq!(
+            show(runtime.normalize("main")),
+            show(runtime.normalize("true"))
+        );
+        assert!(matches!(
+            runtime.normalize("uses_broken"),
+            Err(RuntimeError::Check { .. })
+        ));
+    }
+}
+
+#[test]
+fn applies_definitions_erasing_erased_arguments() {
+    for &evaluation in &EVALUATIONS {
+        let mut runtime = runtime(evaluation);
+
+        assert_eq!(
+            show(runtime.apply(
+                "id",
+                vec![
+                    Term::Reference("Bool".into()),
+                    Term::Reference("false".into())
+                ],
+            )),
+            show(runtime.normalize("false"))
+        );
+        assert!(matches!(
+            runtime.apply("Bool", vec![Term::Reference("true".into())]),
+            Err(RuntimeError::Arity(_))
+        ));
+    }
+}
+
+#[test]
+fn applies_definitions_through_instantiated_return_types() {
+    for &evaluation in &EVALUATIONS {
+        let mut runtime = runtime(evaluation);
+
+        // `id[Bool](true)` returns an `A` that only takes further arguments once it is
+        // instantiated to `Bool` and unfolded
+        assert_eq!(
+            show(runtime.apply(
+                "id",
+                vec![
+                    Term::Reference("Bool".into()),
+                    Term::Reference("true".into()),
+                    Term::Reference("Bool".into()),
+                    Term::Reference("false".into()),
+                    Term::Reference("true".into()),
+                ],
+            )),
+            show(runtime.normalize("false"))
+        );
+    }
+}
+
+#[test]
+fn loads_bundles() {
+    for &evaluation in &EVALUATIONS {
+        let mut runtime = bundled(evaluation);
+
+        assert!(runtime.definitions().all(|name| name != "uses_broken"));
+        assert!(matches!(
+            runtime.check_definition("main"),
+            Err(RuntimeError::Untyped(_))
+        ));
+
+        // bundled definitions have no erased parameters left to apply
+        assert_eq!(
+            show(runtime.apply("id", vec![Term::Reference("false".into())])),
+            show(runtime.normalize("false"))
+        );
+        assert_eq!(
+            show(runtime.normalize("main")),
+            show(runtime.normalize("true"))
+        );
+    }
+}
@@ -0,0 +1,35 @@
+use parser::AbsolutePath;
+use welkin::store::merkle_hashes;
+use welkin_core::term::Term;
+
+fn declaration(
+    name: &str,
+    reference: &str,
+) -> (AbsolutePath, Term<AbsolutePath>, Term<AbsolutePath>) {
+    (
+        AbsolutePath(vec![name.into()]),
+        Term::Universe,
+        Term::Reference(AbsolutePath(vec![reference.into()])),
+    )
+}
+
+#[test]
+fn cycles_hash_independently_of_unrelated_definitions() {
+    let mut declarations = vec![declaration("a", "b"), declaration("b", "a")];
+    let before = merkle_hashes(&declarations);
+
+    // sorts before the cycle and enters it through its other member
+    declarations.push(declaration("0", "b"));
+    let after = merkle_hashes(&declarations);
+
+    assert_eq!(before["a"].0, after["a"].0);
+    assert_eq!(before["b"].0, after["b"].0);
+    assert_ne!(after["a"].0, after["b"].0);
+}
+
+#[test]
+fn self_recursion_hashes_independently_of_names() {
+    let hashes = merkle_hashes(&[declaration("f", "f"), declaration("g", "g")]);
+
+    assert_eq!(hashes["f"].0, hashes["g"].0);
+}
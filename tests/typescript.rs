@@ -0,0 +1,184 @@
+use parser::AbsolutePath;
+use welkin::{typescript::generate, Parameter, SerializableData, SerializableVariant};
+use welkin_core::term::Term;
+
+fn reference(name: &str) -> Term<AbsolutePath> {
+    Term::Reference(AbsolutePath(vec![name.into()]))
+}
+
+fn apply(function: Term<AbsolutePath>, argument: Term<AbsolutePath>) -> Term<AbsolutePath> {
+    Term::Apply {
+        erased: true,
+        function: Box::new(function),
+        argument: Box::new(argument),
+    }
+}
+
+/// Data without indices, each variant given with its fields.
+fn data(
+    ident: &str,
+    type_arguments: usize,
+    variants: Vec<(&str, Vec<(&str, Term<AbsolutePath>)>)>,
+) -> SerializableData {
+    SerializableData {
+        variants: variants
+            .into_iter()
+            .map(|(name, fields)| {
+                (
+                    name.to_owned(),
+                    SerializableVariant {
+                        inhabitants: fields
+                            .into_iter()
+                            .map(|(name, ty)| (name.to_owned(), ty))
+                            .collect(),
+                        indices: vec![],
+                    },
+                )
+            })
+            .collect(),
+        ident: ident.to_owned(),
+        type_arguments,
+        indices: 0,
+        parameters: vec![Parameter::Type; type_arguments],
+    }
+}
+
+/// The declarations generated for `defs`, without the prelude shared by every module.
+fn declarations(defs: &[SerializableData]) -> String {
+    generate(defs)
+        .strip_prefix(&generate(&[]))
+        .unwrap()
+        .to_owned()
+}
+
+#[test]
+fn generates_data_without_parameters() {
+    let flag = data("Flag", 0, vec![("on", vec![]), ("off", vec![])]);
+
+    assert_eq!(
+        declarations(&[flag]),
+        r#"
+export type Flag =
+  | { $variant: "on" }
+  | { $variant: "off" };
+
+export const Flag: $Codec<Flag> = {
+  encode(value) {
+    switch (value.$variant) {
+      case "on":
+        return $construct(2, 0, []);
+      case "off":
+        return $construct(2, 1, []);
+    }
+    throw new Error(`unknown variant of Flag`);
+  },
+  decode(term) {
+    const [variant, fields] = $readVariant(term, 2, "Flag");
+    switch (variant) {
+      case 0:
+        return { $variant: "on" };
+      case 1:
+        return { $variant: "off" };
+    }
+    throw new $DecodeError(`invalid variant ${variant} of Flag`);
+  },
+};
+"#
+    );
+}
+
+#[test]
+fn generates_codecs_of_parameters_as_functions() {
+    let maybe = data(
+        "Maybe",
+        1,
+        vec![
+            ("nothing", vec![]),
+            ("just", vec![("value", reference("T0"))]),
+        ],
+    );
+
+    assert_eq!(
+        declarations(&[maybe]),
+        r#"
+export type Maybe<$0> =
+  | { $variant: "nothing" }
+  | { $variant: "just"; "value": $0 };
+
+export function Maybe<$0>($0: $Codec<$0>): $Codec<Maybe<$0>> {
+  return {
+    encode(value) {
+      switch (value.$variant) {
+        case "nothing":
+          return $construct(2, 0, []);
+        case "just":
+          return $construct(2, 1, [$0.encode(value["value"])]);
+      }
+      throw new Error(`unknown variant of Maybe`);
+    },
+    decode(term) {
+      const [variant, fields] = $readVariant(term, 2, "Maybe");
+      switch (variant) {
+        case 0:
+          return { $variant: "nothing" };
+        case 1:
+          $expectFields(fields, 1, "Maybe", "just");
+          return { $variant: "just", "value": $0.decode(fields[0]) };
+      }
+      throw new $DecodeError(`invalid variant ${variant} of Maybe`);
+    },
+  };
+}
+"#
+    );
+}
+
+#[test]
+fn maps_fields_to_native_declared_or_term_types() {
+    let size = data("Size", 0, vec![("zero", vec![]), ("succ", vec![])]);
+    let maybe = data(
+        "Maybe",
+        1,
+        vec![
+            ("nothing", vec![]),
+            ("just", vec![("value", reference("T0"))]),
+        ],
+    );
+    let record = data(
+        "Record",
+        0,
+        vec![(
+            "new",
+            vec![
+                ("size", reference("Size")),
+                (
+                    "names",
+                    apply(
+                        apply(reference("Vector"), reference("String")),
+                        reference("Size"),
+                    ),
+                ),
+                ("count", apply(reference("Maybe"), reference("Size"))),
+                ("other", reference("Missing")),
+            ],
+        )],
+    );
+
+    let generated = declarations(&[size, maybe, record]);
+
+    // native types are left out of the declarations
+    assert!(!generated.contains("export const Size"));
+    assert!(generated.contains(
+        r#"| { $variant: "new"; "size": number; "names": Array<string>; "count": Maybe<number>; "other": $Term };"#
+    ));
+    assert!(generated.contains(
+        r#"return $construct(1, 0, [$sizeCodec.encode(value["size"]), $vectorCodec($stringCodec).encode(value["names"]), Maybe($sizeCodec).encode(value["count"]), $termCodec.encode(value["other"])]);"#
+    ));
+}
+
+#[test]
+fn declares_data_without_variants_as_never() {
+    let void = data("Void", 0, vec![]);
+
+    assert!(declarations(&[void]).starts_with("\nexport type Void = never;\n"));
+}
@@ -0,0 +1,75 @@
+use std::process::exit;
+
+use welkin::{container, Terms};
+use whelk_core::differential::{Differential, Generator, Report};
+
+fn usage() -> ! {
+    eprintln!(
+        "USAGE:\ndifferential <TERMS> [--generate <COUNT>] [--seed <SEED>] [--depth <DEPTH>]"
+    );
+    exit(1)
+}
+
+fn main() {
+    let mut args = std::env::args().skip(1);
+
+    let input = args.next().unwrap_or_else(|| usage());
+
+    let mut generate = 0;
+    let mut seed = 0;
+    let mut depth = 4;
+
+    while let Some(a) = args.next() {
+        let mut value = || {
+            args.next()
+                .and_then(|a| a.parse::<u64>().ok())
+                .unwrap_or_else(|| usage())
+        };
+        match a.as_str() {
+            "--generate" => generate = value() as usize,
+            "--seed" => seed = value(),
+            "--depth" => depth = value() as usize,
+            _ => usage(),
+        }
+    }
+
+    let terms: Terms = std::fs::read(&input)
+        .map_err(|e| e.to_string())
+        .and_then(|data| container::decode(&data).map_err(|e| e.to_string()))
+        .unwrap_or_else(|e| {
+            eprintln!("could not read {}: {}", input, e);
+            exit(1)
+        });
+
+    let count = terms.data.len();
+    let mut differential = Differential::new(terms);
+
+    let mut reports = differential.compare_all();
+
+    let mut generator = Generator::new(seed, depth);
+    for idx in 0..generate {
+        let (ty, term) = generator.generate();
+        let discrepancies = differential.compare(&ty, &term);
+        if !discrepancies.is_empty() {
+            reports.push(Report {
+                name: format!("generated #{} (seed {}) {:?} : {:?}", idx, seed, term, ty),
+                discrepancies,
+            });
+        }
+    }
+
+    for report in &reports {
+        print!("{}", report);
+    }
+
+    eprintln!(
+        "compared {} definitions and {} generated terms, {} disagree",
+        count,
+        generate,
+        reports.len()
+    );
+
+    if !reports.is_empty() {
+        exit(1);
+    }
+}
@@ -0,0 +1,122 @@
+//! Suggestions for what to fill a hole with, ranked by how well their types fit its goal.
+
+use serde::{Deserialize, Serialize};
+use welkin_core::term::EqualityCache;
+
+use crate::{
+    worker::DefWrapper,
+    zipper::{
+        analysis::{arguments_to_fit, AnalysisTerm, TypedDefinitions},
+        Term,
+    },
+};
+
+/// How a candidate's type compares with the goal, best first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum Fit {
+    /// The candidate fits once applied to this many holes.
+    Fits(usize),
+    /// There is no goal or the candidate's type is not known.
+    Unknown,
+    Mismatch,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Completion {
+    pub name: String,
+    pub fit: Fit,
+    /// Whether the candidate is bound in the scratchpad rather than defined.
+    pub local: bool,
+    /// The erasure of each argument applied for the fit.
+    erasures: Vec<bool>,
+}
+
+impl Completion {
+    /// The candidate applied to a hole for each of the arguments it needs to fit.
+    pub fn term(&self) -> Term<()> {
+        self.erasures
+            .iter()
+            .fold(Term::Reference(self.name.clone(), ()), |term, erased| {
+                Term::Application {
+                    erased: *erased,
+                    function: Box::new(term),
+                    argument: Box::new(Term::Hole(())),
+                    annotation: (),
+                }
+            })
+    }
+}
+
+/// The binders in scope at the hole annotated with `hole` in `term` and the definitions in
+/// `defs` starting with `prefix`, ranked by how their types fit the hole's goal when `term` is
+/// checked against `ty`.
+///
+/// Local binders shadow definitions of the same name.
+pub fn complete<T: Clone + PartialEq>(
+    term: &AnalysisTerm<Option<T>>,
+    ty: &AnalysisTerm<Option<T>>,
+    hole: &T,
+    prefix: &str,
+    defs: &DefWrapper,
+    cache: &mut impl EqualityCache,
+) -> Vec<Completion> {
+    let scope = match term.scope_in(ty, hole, defs, cache) {
+        Some(scope) => scope,
+        None => return vec![],
+    };
+
+    let mut completions: Vec<Completion> = vec![];
+
+    for (name, ty) in scope.locals {
+        let name = match name {
+            Some(name) => name,
+            None => continue,
+        };
+        if !name.starts_with(prefix) || completions.iter().any(|c| c.name == name) {
+            continue;
+        }
+
+        let (fit, erasures) = fit(ty, &scope.goal, defs, cache);
+        completions.push(Completion {
+            name,
+            fit,
+            local: true,
+            erasures,
+        });
+    }
+
+    for name in defs.names() {
+        if !name.starts_with(prefix) || completions.iter().any(|c| c.name == name) {
+            continue;
+        }
+
+        let ty = TypedDefinitions::<Option<usize>>::get_typed(defs, &name)
+            .map(|definition| definition.as_ref().0.clone());
+        let (fit, erasures) = fit(ty, &scope.goal, defs, cache);
+        completions.push(Completion {
+            name,
+            fit,
+            local: false,
+            erasures,
+        });
+    }
+
+    completions.sort_by(|a, b| (a.fit, !a.local, &a.name).cmp(&(b.fit, !b.local, &b.name)));
+    completions
+}
+
+/// How a candidate of type `ty` fits `goal`, with the erasure of each argument it needs.
+fn fit(
+    ty: Option<AnalysisTerm<Option<usize>>>,
+    goal: &Option<AnalysisTerm<Option<usize>>>,
+    defs: &DefWrapper,
+    cache: &mut impl EqualityCache,
+) -> (Fit, Vec<bool>) {
+    match (ty, goal) {
+        (Some(ty), Some(goal)) => match arguments_to_fit(ty, goal, defs, cache) {
+            Some(erasures) => (Fit::Fits(erasures.len()), erasures),
+            None => (Fit::Mismatch, vec![]),
+        },
+        _ => (Fit::Unknown, vec![]),
+    }
+}
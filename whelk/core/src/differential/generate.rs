@@ -0,0 +1,183 @@
+use welkin_core::term::{Index, Term};
+
+/// A small deterministic generator, so that a failing case can be reproduced from its seed.
+pub struct Rng(u64);
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Rng(seed)
+    }
+
+    // splitmix64
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e3779b97f4a7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^ (z >> 31)
+    }
+
+    pub fn below(&mut self, bound: usize) -> usize {
+        (self.next_u64() % bound as u64) as usize
+    }
+
+    pub fn chance(&mut self, numerator: usize, denominator: usize) -> bool {
+        self.below(denominator) < numerator
+    }
+}
+
+/// Closed types built from the universe, so that generated terms never need to shift a type
+/// under a binder.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Type {
+    Universe,
+    Function {
+        erased: bool,
+        argument: Box<Type>,
+        ret: Box<Type>,
+    },
+}
+
+impl Type {
+    fn to_term(&self) -> Term<String> {
+        match self {
+            Type::Universe => Term::Universe,
+            Type::Function {
+                erased,
+                argument,
+                ret,
+            } => Term::Function {
+                erased: *erased,
+                argument_type: Box::new(argument.to_term()),
+                return_type: Box::new(ret.to_term()),
+            },
+        }
+    }
+
+    /// The argument types to apply a term of this type to in order to reach `target`.
+    fn spine_to(&self, target: &Type) -> Option<Vec<(bool, Type)>> {
+        if self == target {
+            return Some(vec![]);
+        }
+
+        match self {
+            Type::Function {
+                erased,
+                argument,
+                ret,
+            } => ret.spine_to(target).map(|mut spine| {
+                spine.insert(0, (*erased, (**argument).clone()));
+                spine
+            }),
+            Type::Universe => None,
+        }
+    }
+}
+
+struct Binding {
+    ty: Type,
+    /// Whether the binding can still appear in a computational position. Bindings of erased
+    /// lambdas never can, and others can appear once.
+    usable: bool,
+}
+
+/// Generates random well-typed, stratified terms paired with their types.
+///
+/// Terms are built from lambdas, variables and applications of variables, over closed types
+/// made of the universe and functions.
+pub struct Generator {
+    rng: Rng,
+    depth: usize,
+}
+
+impl Generator {
+    pub fn new(seed: u64, depth: usize) -> Self {
+        Generator {
+            rng: Rng::new(seed),
+            depth,
+        }
+    }
+
+    /// Returns a type and a term of that type.
+    pub fn generate(&mut self) -> (Term<String>, Term<String>) {
+        let ty = self.ty(self.depth);
+        let term = self.term(&ty, &mut vec![], self.depth);
+        (ty.to_term(), term)
+    }
+
+    fn ty(&mut self, depth: usize) -> Type {
+        if depth == 0 || self.rng.chance(1, 3) {
+            Type::Universe
+        } else {
+            Type::Function {
+                erased: self.rng.chance(1, 4),
+                argument: Box::new(self.ty(depth - 1)),
+                ret: Box::new(self.ty(depth - 1)),
+            }
+        }
+    }
+
+    fn term(&mut self, ty: &Type, context: &mut Vec<Binding>, depth: usize) -> Term<String> {
+        let candidates: Vec<_> = context
+            .iter()
+            .enumerate()
+            .filter(|(_, binding)| binding.usable)
+            .filter_map(|(idx, binding)| binding.ty.spine_to(ty).map(|spine| (idx, spine)))
+            .collect();
+
+        if !candidates.is_empty() && (depth == 0 || self.rng.chance(1, 2)) {
+            let (idx, spine) = candidates[self.rng.below(candidates.len())].clone();
+            context[idx].usable = false;
+
+            let mut term = Term::Variable(Index(context.len() - 1 - idx));
+
+            for (erased, argument) in spine {
+                let argument = if erased {
+                    // erased arguments are ignored by stratification, so they use nothing
+                    self.closed(&argument)
+                } else {
+                    self.term(&argument, context, depth.saturating_sub(1))
+                };
+                term = Term::Apply {
+                    erased,
+                    function: Box::new(term),
+                    argument: Box::new(argument),
+                };
+            }
+
+            return term;
+        }
+
+        match ty {
+            Type::Universe => {
+                if depth == 0 || self.rng.chance(1, 2) {
+                    Term::Universe
+                } else {
+                    self.ty(depth - 1).to_term()
+                }
+            }
+            Type::Function {
+                erased,
+                argument,
+                ret,
+            } => {
+                context.push(Binding {
+                    ty: (**argument).clone(),
+                    usable: !*erased,
+                });
+                let body = self.term(ret, context, depth.saturating_sub(1));
+                context.pop();
+
+                Term::Lambda {
+                    erased: *erased,
+                    body: Box::new(body),
+                }
+            }
+        }
+    }
+
+    /// A term of `ty` that refers to no bindings.
+    fn closed(&mut self, ty: &Type) -> Term<String> {
+        self.term(ty, &mut vec![], 0)
+    }
+}
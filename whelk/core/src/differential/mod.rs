@@ -0,0 +1,307 @@
+//! Runs whelk's checker and normalizer over `AnalysisTerm` alongside those of `welkin_core`, and
+//! reports any definition on which the two disagree.
+
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    fmt::{self, Display},
+    rc::Rc,
+};
+
+use welkin::Terms;
+use welkin_core::term::{alloc::System, MapCache, Term};
+
+use crate::{worker::DefWrapper, zipper::analysis::AnalysisTerm};
+
+mod generate;
+pub use generate::{Generator, Rng};
+
+/// How far a definition got through checking.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Verdict {
+    Accepted,
+    Recursive,
+    Unstratified,
+    Rejected,
+}
+
+/// The verdict of one checker, with the error that led to it.
+#[derive(Debug, Clone)]
+pub struct Outcome {
+    pub verdict: Verdict,
+    pub error: Option<String>,
+}
+
+impl Outcome {
+    fn accepted() -> Self {
+        Outcome {
+            verdict: Verdict::Accepted,
+            error: None,
+        }
+    }
+
+    fn failed(verdict: Verdict, error: impl fmt::Debug) -> Self {
+        Outcome {
+            verdict,
+            error: Some(format!("{:?}", error)),
+        }
+    }
+}
+
+impl Display for Outcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self.verdict)?;
+        if let Some(error) = &self.error {
+            write!(f, " ({})", error)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum Discrepancy {
+    /// Converting to `AnalysisTerm` and back did not give the original term.
+    RoundTrip {
+        original: String,
+        converted: String,
+    },
+    Verdict {
+        core: Outcome,
+        whelk: Outcome,
+    },
+    NormalForm {
+        core: String,
+        whelk: String,
+    },
+    /// Only one side could normalize a definition both sides accepted.
+    Normalization {
+        core: Outcome,
+        whelk: Outcome,
+    },
+}
+
+impl Display for Discrepancy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Discrepancy::RoundTrip {
+                original,
+                converted,
+            } => write!(
+                f,
+                "conversion does not round trip\n\toriginal: {}\n\tconverted: {}",
+                original, converted
+            ),
+            Discrepancy::Verdict { core, whelk } => write!(
+                f,
+                "verdicts differ\n\twelkin-core: {}\n\twhelk: {}",
+                core, whelk
+            ),
+            Discrepancy::NormalForm { core, whelk } => write!(
+                f,
+                "normal forms differ\n\twelkin-core: {}\n\twhelk: {}",
+                core, whelk
+            ),
+            Discrepancy::Normalization { core, whelk } => write!(
+                f,
+                "normalization differs\n\twelkin-core: {}\n\twhelk: {}",
+                core, whelk
+            ),
+        }
+    }
+}
+
+/// The discrepancies found in one definition.
+#[derive(Debug, Clone)]
+pub struct Report {
+    pub name: String,
+    pub discrepancies: Vec<Discrepancy>,
+}
+
+impl Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for discrepancy in &self.discrepancies {
+            writeln!(f, "{}: {}", self.name, discrepancy)?;
+        }
+        Ok(())
+    }
+}
+
+pub struct Differential {
+    defs: DefWrapper,
+    core_cache: MapCache,
+    whelk_cache: MapCache,
+}
+
+impl Differential {
+    pub fn new(terms: Terms) -> Self {
+        Differential {
+            defs: DefWrapper(Rc::new(RefCell::new(HashMap::new())), Rc::new(terms.into())),
+            core_cache: MapCache::new(),
+            whelk_cache: MapCache::new(),
+        }
+    }
+
+    /// Every definition, named and referring to others as the checkers expect.
+    pub fn definitions(&self) -> Vec<(String, Term<String>, Term<String>)> {
+        self.defs
+            .1
+            .data
+            .iter()
+            .map(|(path, ty, term)| {
+                (
+                    format!("{:?}", path),
+                    ty.clone()
+                        .map_reference(|a| Term::Reference(format!("{:?}", a))),
+                    term.clone()
+                        .map_reference(|a| Term::Reference(format!("{:?}", a))),
+                )
+            })
+            .collect()
+    }
+
+    /// Compares every definition, returning a report for each on which the checkers disagree.
+    pub fn compare_all(&mut self) -> Vec<Report> {
+        let mut definitions = self.definitions();
+        definitions.sort_by(|a, b| a.0.cmp(&b.0));
+
+        definitions
+            .into_iter()
+            .filter_map(|(name, ty, term)| {
+                let discrepancies = self.compare(&ty, &term);
+                if discrepancies.is_empty() {
+                    None
+                } else {
+                    Some(Report {
+                        name,
+                        discrepancies,
+                    })
+                }
+            })
+            .collect()
+    }
+
+    /// Checks `term` against `ty` with both checkers and, where both accept it, normalizes it
+    /// with both.
+    pub fn compare(&mut self, ty: &Term<String>, term: &Term<String>) -> Vec<Discrepancy> {
+        let mut discrepancies = vec![];
+
+        let analysis_ty = round_trip(ty, &mut discrepancies);
+        let analysis_term = round_trip(term, &mut discrepancies);
+
+        let core = self.check_core(ty, term);
+        let whelk = self.check_whelk(&analysis_ty, &analysis_term);
+
+        if core.verdict != whelk.verdict {
+            discrepancies.push(Discrepancy::Verdict { core, whelk });
+        } else if core.verdict == Verdict::Accepted {
+            let mut core_normal = term.clone();
+            let core = core_normal
+                .normalize(&self.defs)
+                .map(|_| core_normal)
+                .map_err(|e| Outcome::failed(Verdict::Rejected, e));
+
+            let mut whelk_normal = analysis_term;
+            let whelk = whelk_normal
+                .normalize_in(&self.defs)
+                .map(|_| Term::<String>::from(whelk_normal))
+                .map_err(|e| Outcome::failed(Verdict::Rejected, e));
+
+            match (core, whelk) {
+                (Ok(core), Ok(whelk)) => {
+                    let (core, whelk) = (format!("{:?}", core), format!("{:?}", whelk));
+                    if core != whelk {
+                        discrepancies.push(Discrepancy::NormalForm { core, whelk });
+                    }
+                }
+                (Err(_), Err(_)) => {}
+                (core, whelk) => discrepancies.push(Discrepancy::Normalization {
+                    core: core.err().unwrap_or_else(Outcome::accepted),
+                    whelk: whelk.err().unwrap_or_else(Outcome::accepted),
+                }),
+            }
+        }
+
+        discrepancies
+    }
+
+    fn check_core(&mut self, ty: &Term<String>, term: &Term<String>) -> Outcome {
+        if term.is_recursive_in(&self.defs, &System, &System)
+            || ty.is_recursive_in(&self.defs, &System, &System)
+        {
+            return Outcome {
+                verdict: Verdict::Recursive,
+                error: None,
+            };
+        }
+
+        for term in [term, ty].iter() {
+            if let Err(e) = term.is_stratified() {
+                return Outcome::failed(Verdict::Unstratified, e);
+            }
+        }
+
+        if let Err(e) = ty.check_in(&Term::Universe, &self.defs, &System, &mut self.core_cache) {
+            return Outcome::failed(Verdict::Rejected, e);
+        }
+        if let Err(e) = term.check_in(ty, &self.defs, &System, &mut self.core_cache) {
+            return Outcome::failed(Verdict::Rejected, e);
+        }
+
+        Outcome::accepted()
+    }
+
+    fn check_whelk(
+        &mut self,
+        ty: &AnalysisTerm<Option<()>>,
+        term: &AnalysisTerm<Option<()>>,
+    ) -> Outcome {
+        if term.is_recursive_in(&self.defs) || ty.is_recursive_in(&self.defs) {
+            return Outcome {
+                verdict: Verdict::Recursive,
+                error: None,
+            };
+        }
+
+        for term in [term, ty].iter() {
+            if let Err(e) = term.is_stratified() {
+                return Outcome::failed(Verdict::Unstratified, e);
+            }
+        }
+
+        let universe = AnalysisTerm::Universe(None);
+
+        for (term, ty) in [(ty, &universe), (term, ty)].iter() {
+            if let Err(e) = term.check_in(
+                ty,
+                &self.defs,
+                &mut |_, _| {},
+                &mut |_, _| {},
+                &mut self.whelk_cache,
+            ) {
+                return Outcome::failed(Verdict::Rejected, e);
+            }
+        }
+
+        Outcome::accepted()
+    }
+}
+
+fn round_trip(
+    term: &Term<String>,
+    discrepancies: &mut Vec<Discrepancy>,
+) -> AnalysisTerm<Option<()>> {
+    let analysis: AnalysisTerm<Option<()>> = term.clone().into();
+
+    let (original, converted) = (
+        format!("{:?}", term),
+        format!("{:?}", Term::<String>::from(analysis.clone())),
+    );
+    if original != converted {
+        discrepancies.push(Discrepancy::RoundTrip {
+            original,
+            converted,
+        });
+    }
+
+    analysis
+}
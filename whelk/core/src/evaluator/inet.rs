@@ -4,7 +4,7 @@ use welkin_core::{
     term::{DefinitionResult, Definitions, Term},
 };
 
-use crate::edit::zipper::analysis::NormalizationError;
+use crate::zipper::analysis::NormalizationError;
 
 use std::fmt::Debug;
 
@@ -1,8 +1,8 @@
 use futures::future::{ready, Ready};
 use welkin_core::term::{DefinitionResult, Definitions, Term};
 
-use crate::edit::{
-    dynamic::abst::controls::Zero,
+use crate::{
+    literal::Zero,
     zipper::analysis::{AnalysisTerm, NormalizationError, TypedDefinitions},
 };
 
@@ -0,0 +1,170 @@
+//! Undo and redo of scratchpad edits, kept as encoded snapshots of the whole term.
+
+use std::{
+    collections::VecDeque,
+    fmt::{self, Debug},
+};
+
+use crate::zipper::{decode, encode, Cursor, Path, Term, TermData};
+
+struct Snapshot<K> {
+    data: Vec<u8>,
+    /// Records with the same group replace one another rather than each being undone in turn.
+    group: Option<K>,
+}
+
+/// The states a scratchpad has been in, holding encoded snapshots up to `budget` bytes in total
+/// and forgetting the oldest past that.
+pub struct History<K = Vec<u8>> {
+    /// Oldest first, ending with the current state.
+    past: VecDeque<Snapshot<K>>,
+    /// Most recently undone last.
+    future: Vec<Snapshot<K>>,
+    budget: usize,
+    used: usize,
+}
+
+impl<K: PartialEq> History<K> {
+    pub fn new(term: &Term<()>, budget: usize) -> Self {
+        let data = snapshot(term).unwrap_or_default();
+        let used = data.len();
+
+        History {
+            past: vec![Snapshot { data, group: None }].into(),
+            future: vec![],
+            budget,
+            used,
+        }
+    }
+
+    /// Records `term` as the current state, unless it already is.
+    ///
+    /// Where `group` is that of the current state, the current state is replaced instead, so that
+    /// a run of edits to one field is undone at once.
+    pub fn record(&mut self, term: &Term<()>, group: Option<K>) {
+        let data = match snapshot(term) {
+            Some(data) => data,
+            None => return,
+        };
+
+        let current = self.past.back_mut().unwrap();
+        if current.data == data {
+            return;
+        }
+
+        for snapshot in self.future.drain(..) {
+            self.used -= snapshot.data.len();
+        }
+
+        if group.is_some() && current.group == group {
+            self.used -= current.data.len();
+            self.used += data.len();
+            current.data = data;
+        } else {
+            self.used += data.len();
+            self.past.push_back(Snapshot { data, group });
+        }
+
+        while self.used > self.budget && self.past.len() > 1 {
+            let snapshot = self.past.pop_front().unwrap();
+            self.used -= snapshot.data.len();
+        }
+    }
+
+    /// Steps back to the previous state, returning it.
+    pub fn undo(&mut self) -> Option<Term<()>> {
+        if self.past.len() < 2 {
+            return None;
+        }
+
+        let mut undone = self.past.pop_back().unwrap();
+        undone.group = None;
+        self.future.push(undone);
+
+        self.current()
+    }
+
+    /// Steps forward to the most recently undone state, returning it.
+    pub fn redo(&mut self) -> Option<Term<()>> {
+        let redone = self.future.pop()?;
+        self.past.push_back(redone);
+
+        self.current()
+    }
+
+    pub fn can_undo(&self) -> bool {
+        self.past.len() > 1
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.future.is_empty()
+    }
+
+    /// The bytes held by all snapshots.
+    pub fn used(&self) -> usize {
+        self.used
+    }
+
+    fn current(&mut self) -> Option<Term<()>> {
+        let current = self.past.back_mut().unwrap();
+        // an edit after moving through history starts a new record rather than replacing this one
+        current.group = None;
+        decode::<TermData>(&current.data).ok().map(Term::from)
+    }
+}
+
+impl<K> Debug for History<K> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("History")
+            .field("past", &self.past.len())
+            .field("future", &self.future.len())
+            .field("used", &self.used)
+            .finish()
+    }
+}
+
+fn snapshot(term: &Term<()>) -> Option<Vec<u8>> {
+    encode(TermData::from(term.clone())).ok()
+}
+
+/// Where `cursor` is in its term and what kind of term it is focused on, for grouping edits made
+/// there with `History::record`.
+pub fn location<T: 'static>(cursor: &Cursor<T>) -> Vec<u8> {
+    let mut location = vec![match cursor {
+        Cursor::Lambda(_) => 0,
+        Cursor::Application(_) => 1,
+        Cursor::Put(_) => 2,
+        Cursor::Reference(_) => 3,
+        Cursor::Duplication(_) => 4,
+        Cursor::Universe(_) => 5,
+        Cursor::Function(_) => 6,
+        Cursor::Wrap(_) => 7,
+        Cursor::Hole(_) => 8,
+        Cursor::Dynamic(_) => 9,
+    }];
+
+    let mut path = cursor.path();
+    loop {
+        let (step, up) = match path {
+            Path::Top => break,
+            Path::Lambda { up, .. } => (0, up),
+            Path::ApplicationFunction { up, .. } => (1, up),
+            Path::ApplicationArgument { up, .. } => (2, up),
+            Path::Put { up, .. } => (3, up),
+            Path::Reference { up, .. } => (4, up),
+            Path::DuplicationExpression { up, .. } => (5, up),
+            Path::DuplicationBody { up, .. } => (6, up),
+            Path::Universe { up, .. } => (7, up),
+            Path::FunctionArgumentType { up, .. } => (8, up),
+            Path::FunctionReturnType { up, .. } => (9, up),
+            Path::Wrap { up, .. } => (10, up),
+            Path::Hole { up, .. } => (11, up),
+            Path::Dynamic { up, .. } => (12, up),
+        };
+        location.push(step);
+        path = up;
+    }
+
+    location.reverse();
+    location
+}
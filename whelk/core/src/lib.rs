@@ -0,0 +1,13 @@
+//! The parts of the whelk editor that do not depend on a browser: the scratchpad term zipper and
+//! its analyses, evaluators, compressed literals, edit history, completion of holes, the protocol
+//! spoken to the analysis worker and the bookkeeping of the pool of them, along with a harness
+//! comparing the analyses against `welkin_core`.
+
+pub mod completion;
+pub mod differential;
+pub mod evaluator;
+pub mod history;
+pub mod literal;
+pub mod pool;
+pub mod worker;
+pub mod zipper;
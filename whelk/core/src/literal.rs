@@ -6,7 +6,7 @@ use std::{
 
 use serde::{Deserialize, Serialize};
 
-use crate::edit::zipper::{CompressedTerm, Term};
+use crate::zipper::{CompressedTerm, Term};
 
 #[derive(Serialize, Deserialize, Clone, Hash, PartialEq, Eq)]
 pub struct CompressedWord {
@@ -14,9 +14,13 @@ pub struct CompressedWord {
 }
 
 impl CompressedWord {
-    fn new(data: Vec<bool>) -> Self {
+    pub fn new(data: Vec<bool>) -> Self {
         CompressedWord { data }
     }
+
+    pub fn bits(&self) -> &[bool] {
+        &self.data
+    }
 }
 
 impl<T: Zero + Clone> CompressedTerm<T> for CompressedWord {
@@ -198,9 +202,13 @@ pub struct CompressedChar {
 }
 
 impl CompressedChar {
-    fn new(char: char) -> CompressedChar {
+    pub fn new(char: char) -> CompressedChar {
         CompressedChar { char }
     }
+
+    pub fn char(&self) -> char {
+        self.char
+    }
 }
 
 impl<T: Zero + Clone> CompressedTerm<T> for CompressedChar {
@@ -318,6 +326,10 @@ impl CompressedString {
     pub fn new(data: String) -> CompressedString {
         CompressedString { data }
     }
+
+    pub fn data(&self) -> &str {
+        &self.data
+    }
 }
 
 impl<T: Zero + Clone> CompressedTerm<T> for CompressedString {
@@ -0,0 +1,171 @@
+//! The bookkeeping of a pool of workers that requests are shared between: which worker each
+//! request goes to, which requests each worker has yet to answer and which requests bring a fresh
+//! worker to the state of the one it replaces. Posting requests and starting workers is left to
+//! the caller.
+
+use std::collections::{HashMap, VecDeque};
+
+use uuid::Uuid;
+
+use crate::worker::{CheckError, WorkerRequest, WorkerRequestVariant};
+
+/// A request kept to be replayed to fresh workers.
+struct Setup {
+    idx: Uuid,
+    /// The worker the request is pinned to, where it is not sent to all of them.
+    target: Option<usize>,
+    /// The name of the definition the request registers, if it registers one.
+    registers: Option<String>,
+    data: Vec<u8>,
+}
+
+pub struct Pool {
+    /// For each worker, each request sent to it and not yet answered, the one it is handling
+    /// first.
+    pending: Vec<VecDeque<(Uuid, Vec<u8>)>>,
+    /// The requests that bring a fresh worker to the state of the one it replaces, in order.
+    setup: Vec<Setup>,
+    /// The worker holding each set of temporary definitions.
+    pins: HashMap<Uuid, usize>,
+}
+
+impl Pool {
+    /// The bookkeeping of a pool of `workers` workers, of which there is at least one.
+    pub fn new(workers: usize) -> Self {
+        Pool {
+            pending: (0..workers.max(1)).map(|_| VecDeque::new()).collect(),
+            setup: vec![],
+            pins: HashMap::new(),
+        }
+    }
+
+    /// The worker to send `variant` to, or none where it changes the definitions every worker
+    /// holds.
+    ///
+    /// Requests using temporary definitions go to the worker holding them, failing if no worker
+    /// does, and others to the worker with the fewest requests waiting on it.
+    pub fn route(
+        &self,
+        variant: &WorkerRequestVariant,
+    ) -> Result<Option<usize>, CheckError<Option<u64>>> {
+        let pinned = match variant {
+            WorkerRequestVariant::Initialize(_) | WorkerRequestVariant::Register(..) => {
+                return Ok(None)
+            }
+            WorkerRequestVariant::Check(_, _, Some(temp)) => Some(
+                self.pins
+                    .get(temp)
+                    .copied()
+                    .ok_or(CheckError::UnknownTempDefs(*temp))?,
+            ),
+            WorkerRequestVariant::ClearTempDefs(temp) => self.pins.get(temp).copied(),
+            _ => None,
+        };
+
+        Ok(Some(pinned.unwrap_or_else(|| {
+            (0..self.pending.len())
+                .min_by_key(|slot| self.pending[*slot].len())
+                .unwrap()
+        })))
+    }
+
+    /// Notes `request`, encoded as `data`, as sent to `target`, or to every worker where that is
+    /// none, returning the workers to post it to.
+    ///
+    /// Requests that change the definitions a worker holds are kept to replay to fresh workers,
+    /// each replacing any kept before that it undoes.
+    pub fn send(
+        &mut self,
+        request: &WorkerRequest,
+        target: Option<usize>,
+        data: Vec<u8>,
+    ) -> Vec<usize> {
+        let idx = request.idx;
+
+        let (setup, registers) = match &request.variant {
+            WorkerRequestVariant::Initialize(_) => {
+                self.setup.retain(|setup| setup.target.is_some());
+                (true, None)
+            }
+            WorkerRequestVariant::Register(name, _, _) => {
+                self.setup
+                    .retain(|setup| setup.registers.as_ref() != Some(name));
+                (true, Some(name.clone()))
+            }
+            WorkerRequestVariant::TempDefs(_) => {
+                if let Some(target) = target {
+                    self.pins.insert(idx, target);
+                }
+                (true, None)
+            }
+            WorkerRequestVariant::ClearTempDefs(temp) => {
+                self.setup.retain(|setup| setup.idx != *temp);
+                self.pins.remove(temp);
+                (false, None)
+            }
+            _ => (false, None),
+        };
+
+        if setup {
+            self.setup.push(Setup {
+                idx,
+                target,
+                registers,
+                data: data.clone(),
+            });
+        }
+
+        let slots = match target {
+            Some(slot) => vec![slot],
+            None => (0..self.pending.len()).collect(),
+        };
+        for slot in &slots {
+            self.pending[*slot].push_back((idx, data.clone()));
+        }
+        slots
+    }
+
+    /// The request the worker `slot` is handling, if it has any to handle.
+    pub fn running(&self, slot: usize) -> Option<Uuid> {
+        self.pending[slot].front().map(|(idx, _)| *idx)
+    }
+
+    /// Notes that the worker `slot` has answered the request `idx`.
+    pub fn answered(&mut self, slot: usize, idx: Uuid) {
+        self.pending[slot].retain(|(pending, _)| *pending != idx);
+    }
+
+    /// Stops waiting on the request `idx`, returning each worker it was sent to along with
+    /// whether that worker is handling it, and so has to be replaced.
+    pub fn interrupt(&mut self, idx: Uuid) -> Vec<(usize, bool)> {
+        let mut sent = vec![];
+
+        for (slot, pending) in self.pending.iter_mut().enumerate() {
+            if !pending.iter().any(|(pending, _)| *pending == idx) {
+                continue;
+            }
+
+            let running = pending.front().map(|(idx, _)| *idx) == Some(idx);
+            pending.retain(|(pending, _)| *pending != idx);
+            sent.push((slot, running));
+        }
+
+        sent
+    }
+
+    /// The requests to post to a fresh worker replacing the worker `slot`: those that bring it to
+    /// the state of the one it replaces, then those still waiting on an answer.
+    pub fn replay(&self, slot: usize) -> Vec<Vec<u8>> {
+        let pending = &self.pending[slot];
+
+        self.setup
+            .iter()
+            .filter(|setup| {
+                setup.target.map_or(true, |target| target == slot)
+                    && !pending.iter().any(|(pending, _)| *pending == setup.idx)
+            })
+            .map(|setup| setup.data.clone())
+            .chain(pending.iter().map(|(_, data)| data.clone()))
+            .collect()
+    }
+}
@@ -0,0 +1,446 @@
+use std::{
+    cell::RefCell,
+    collections::{HashMap, VecDeque},
+    rc::Rc,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use uuid::Uuid;
+use welkin::{parser::AbsolutePath, Terms};
+use welkin_core::term::{DefinitionResult, MapCache, Term, TypedDefinitions};
+
+use crate::{
+    completion::{complete, Completion},
+    evaluator::{CoreEvaluator, Inet},
+    literal::Zero,
+    zipper::analysis::{self, AnalysisError, AnalysisTerm, StratificationError},
+};
+
+#[derive(Debug, Serialize, Deserialize)]
+pub enum CheckError<T> {
+    Analysis(AnalysisError<T>),
+    Stratification(StratificationError),
+    Recursive,
+    Interrupted(Interruption),
+    /// The temporary definitions with the given id are not held by the worker.
+    UnknownTempDefs(Uuid),
+}
+
+/// Why a request went unanswered by the worker.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Error)]
+pub enum Interruption {
+    #[error("cancelled")]
+    Cancelled,
+    #[error("timed out")]
+    TimedOut,
+}
+
+impl<T> CheckError<T> {
+    pub fn map_annotation<U, F: FnMut(T) -> U>(self, call: &mut F) -> CheckError<U> {
+        match self {
+            CheckError::Analysis(e) => CheckError::Analysis(e.map_annotation(call)),
+            CheckError::Stratification(e) => CheckError::Stratification(e),
+            CheckError::Recursive => CheckError::Recursive,
+            CheckError::Interrupted(e) => CheckError::Interrupted(e),
+            CheckError::UnknownTempDefs(id) => CheckError::UnknownTempDefs(id),
+        }
+    }
+}
+
+impl<T> From<AnalysisError<T>> for CheckError<T> {
+    fn from(e: AnalysisError<T>) -> Self {
+        CheckError::Analysis(e)
+    }
+}
+
+impl<T> From<StratificationError> for CheckError<T> {
+    fn from(e: StratificationError) -> Self {
+        CheckError::Stratification(e)
+    }
+}
+
+#[derive(Clone)]
+pub struct DefWrapper(
+    pub Rc<RefCell<HashMap<String, (Term<String>, Term<String>)>>>,
+    pub Rc<LoadedTerms>,
+);
+
+impl DefWrapper {
+    /// The name of every definition, registered or loaded.
+    pub fn names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.0.borrow().keys().cloned().collect();
+        names.extend(self.1.index.keys().cloned());
+        names.sort();
+        names.dedup();
+        names
+    }
+}
+
+/// The definitions of a library, indexed by the names they are referred to by, so that looking
+/// each one up in turn doesn't search the whole library.
+pub struct LoadedTerms {
+    terms: Terms,
+    index: HashMap<String, usize>,
+}
+
+impl LoadedTerms {
+    fn get(&self, name: &str) -> Option<&(AbsolutePath, Term<AbsolutePath>, Term<AbsolutePath>)> {
+        self.index.get(name).map(|&index| &self.terms.data[index])
+    }
+}
+
+impl From<Terms> for LoadedTerms {
+    fn from(terms: Terms) -> Self {
+        // in reverse, so that the first of any definitions with the same name is the one kept
+        let index = terms
+            .data
+            .iter()
+            .enumerate()
+            .rev()
+            .map(|(index, (path, _, _))| (format!("{:?}", path), index))
+            .collect();
+
+        LoadedTerms { terms, index }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct DefWrapperData(pub HashMap<String, (Term<String>, Term<String>)>, pub Terms);
+
+impl From<DefWrapper> for DefWrapperData {
+    fn from(data: DefWrapper) -> Self {
+        DefWrapperData(data.0.borrow().clone(), data.1.terms.clone())
+    }
+}
+
+impl From<DefWrapperData> for DefWrapper {
+    fn from(data: DefWrapperData) -> Self {
+        DefWrapper(Rc::new(RefCell::new(data.0)), Rc::new(data.1.into()))
+    }
+}
+
+impl<T: Zero> analysis::TypedDefinitions<T> for DefWrapper {
+    fn get_typed(
+        &self,
+        name: &str,
+    ) -> Option<analysis::DefinitionResult<(AnalysisTerm<T>, AnalysisTerm<T>)>> {
+        TypedDefinitions::get_typed(self, &name.to_owned()).map(|defs| match defs {
+            DefinitionResult::Borrowed((ty, term)) => {
+                analysis::DefinitionResult::Owned((ty.clone().into(), term.clone().into()))
+            }
+            DefinitionResult::Owned((ty, term)) => {
+                analysis::DefinitionResult::Owned((ty.into(), term.into()))
+            }
+        })
+    }
+}
+
+impl TypedDefinitions<String> for DefWrapper {
+    fn get_typed(&self, n: &String) -> Option<DefinitionResult<(Term<String>, Term<String>)>> {
+        self.0
+            .borrow()
+            .get(n)
+            .map(|(ty, term)| DefinitionResult::Owned((ty.clone(), term.clone())))
+            .or_else(|| {
+                let (_, ty, term) = self.1.get(n)?;
+                Some(DefinitionResult::Owned((
+                    ty.clone()
+                        .map_reference(|a| Term::Reference(format!("{:?}", a))),
+                    term.clone()
+                        .map_reference(|a| Term::Reference(format!("{:?}", a))),
+                )))
+            })
+    }
+}
+
+pub struct MergeDefs<'a>(
+    pub &'a DefWrapper,
+    pub &'a Option<Vec<(String, Term<String>, Term<String>)>>,
+);
+
+impl<'a, T: Zero> analysis::TypedDefinitions<T> for MergeDefs<'a> {
+    fn get_typed(
+        &self,
+        name: &str,
+    ) -> Option<analysis::DefinitionResult<(AnalysisTerm<T>, AnalysisTerm<T>)>> {
+        self.1
+            .as_ref()
+            .and_then(|defs| defs.iter().find(|(n, _, _)| n.as_str() == name).cloned())
+            .map(|(_, ty, term)| analysis::DefinitionResult::Owned((ty.into(), term.into())))
+            .or_else(|| self.0.get_typed(name))
+    }
+}
+
+/// The most solutions a search returns.
+const SEARCH_SOLUTIONS: usize = 8;
+
+#[derive(Serialize, Deserialize)]
+pub enum WorkerRequestVariant {
+    Check(
+        AnalysisTerm<Option<u64>>,
+        AnalysisTerm<Option<u64>>,
+        Option<Uuid>,
+    ),
+    Register(String, Term<String>, Term<String>),
+    Initialize(DefWrapperData),
+    Evaluate(Term<String>),
+    ExpandEvaluate(AnalysisTerm<()>),
+    /// Registers definitions for checks to use until cleared, under the id of the request.
+    TempDefs(Vec<(String, Term<String>, Term<String>)>),
+    ClearTempDefs(Uuid),
+    /// Drops the request with the given id, if it has been received and not yet handled.
+    Cancel(Uuid),
+    /// Searches for terms to fill the hole with the given annotation for up to the given number
+    /// of milliseconds.
+    Search(
+        AnalysisTerm<Option<u64>>,
+        AnalysisTerm<Option<u64>>,
+        u64,
+        u64,
+    ),
+    /// Suggests what to fill the hole with the given annotation with, starting with the given
+    /// prefix.
+    Complete(
+        AnalysisTerm<Option<u64>>,
+        AnalysisTerm<Option<u64>>,
+        u64,
+        String,
+    ),
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct WorkerRequest {
+    pub variant: WorkerRequestVariant,
+    pub idx: Uuid,
+    /// The milliseconds the request may take before the worker is presumed stuck and replaced.
+    pub limit: Option<u64>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct WorkerResponse {
+    pub idx: Uuid,
+    pub inferred: Vec<(u64, AnalysisTerm<Option<u64>>)>,
+    pub filled: Vec<(u64, AnalysisTerm<Option<u64>>)>,
+    pub evaluated: Option<Term<String>>,
+    pub solutions: Vec<AnalysisTerm<()>>,
+    pub completions: Vec<Completion>,
+    pub data: Result<(), CheckError<Option<u64>>>,
+    pub id: Option<Uuid>,
+}
+
+impl WorkerResponse {
+    /// The answer to the request with id `idx` when it is not handled.
+    pub fn interrupted(idx: Uuid, interruption: Interruption) -> Self {
+        WorkerResponse::failed(idx, CheckError::Interrupted(interruption))
+    }
+
+    /// The answer to the request with id `idx` when it fails with `error` before it is handled.
+    pub fn failed(idx: Uuid, error: CheckError<Option<u64>>) -> Self {
+        WorkerResponse {
+            idx,
+            inferred: vec![],
+            filled: vec![],
+            evaluated: None,
+            solutions: vec![],
+            completions: vec![],
+            data: Err(error),
+            id: None,
+        }
+    }
+}
+
+/// The definitions and caches a worker answers requests against, independent of how requests
+/// reach it.
+#[derive(Clone)]
+pub struct WorkerState {
+    defs: Rc<RefCell<Option<DefWrapper>>>,
+    cache: Rc<RefCell<MapCache>>,
+    temp_defs: Rc<RefCell<HashMap<Uuid, Vec<(String, Term<String>, Term<String>)>>>>,
+    /// Requests received and not yet handled, in order.
+    queue: Rc<RefCell<VecDeque<WorkerRequest>>>,
+    /// The time in milliseconds, against which searches are budgeted.
+    clock: Rc<dyn Fn() -> f64>,
+}
+
+impl WorkerState {
+    pub fn new() -> Self {
+        WorkerState {
+            defs: Rc::new(RefCell::new(None)),
+            cache: Rc::new(RefCell::new(MapCache::new())),
+            temp_defs: Rc::new(RefCell::new(HashMap::new())),
+            queue: Rc::new(RefCell::new(VecDeque::new())),
+            clock: Rc::new(|| {
+                SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .unwrap()
+                    .as_secs_f64()
+                    * 1000.
+            }),
+        }
+    }
+
+    /// Budgets searches against `clock` rather than the system time, which is not available
+    /// everywhere a worker runs.
+    pub fn with_clock(mut self, clock: impl Fn() -> f64 + 'static) -> Self {
+        self.clock = Rc::new(clock);
+        self
+    }
+
+    /// Queues `request` to be handled by [`WorkerState::next`], or applies it at once if it is
+    /// a cancellation, so that it can overtake the request it cancels.
+    pub fn receive(&self, request: WorkerRequest) {
+        match request.variant {
+            WorkerRequestVariant::Cancel(idx) => self.cancel(idx),
+            _ => self.queue.borrow_mut().push_back(request),
+        }
+    }
+
+    /// Handles the earliest request received and neither handled nor cancelled yet, if any.
+    pub async fn next(&self) -> Option<WorkerResponse> {
+        let request = self.queue.borrow_mut().pop_front()?;
+        Some(self.handle(request).await)
+    }
+
+    fn cancel(&self, idx: Uuid) {
+        self.queue.borrow_mut().retain(|request| request.idx != idx);
+    }
+
+    pub async fn handle(&self, request: WorkerRequest) -> WorkerResponse {
+        let mut inferred = vec![];
+        let mut filled = vec![];
+        let mut evaluated = None;
+        let mut solutions = vec![];
+        let mut completions = vec![];
+        let mut id = None;
+
+        let response = match request.variant {
+            WorkerRequestVariant::Check(term, ty, temp_id) => {
+                let temp_defs = temp_id.and_then(|id| self.temp_defs.borrow_mut().remove(&id));
+
+                let res = {
+                    let defs = self.defs.borrow();
+                    let defs = defs.as_ref().unwrap();
+                    let cache = &mut *self.cache.borrow_mut();
+                    let defs = MergeDefs(defs, &temp_defs);
+                    (|| -> Result<(), CheckError<Option<u64>>> {
+                        if let (Some(id), None) = (temp_id, &temp_defs) {
+                            return Err(CheckError::UnknownTempDefs(id));
+                        }
+                        term.check_in(
+                            &ty,
+                            &defs,
+                            &mut |annotation, ty| {
+                                if let Some(annotation) = annotation {
+                                    inferred.push((*annotation, ty.clone()))
+                                }
+                            },
+                            &mut |annotation, ty| {
+                                if let Some(annotation) = annotation {
+                                    filled.push((*annotation, ty.clone()))
+                                }
+                            },
+                            cache,
+                        )?;
+                        term.is_stratified()?;
+                        if term.is_recursive_in(&defs) {
+                            Err(CheckError::Recursive)
+                        } else {
+                            Ok(())
+                        }
+                    })()
+                };
+
+                if let (Some(temp_id), Some(temp_defs)) = (temp_id, temp_defs) {
+                    self.temp_defs.borrow_mut().insert(temp_id, temp_defs);
+                }
+
+                res
+            }
+            WorkerRequestVariant::Initialize(data) => {
+                *self.defs.borrow_mut() = Some(data.into());
+                Ok(())
+            }
+            WorkerRequestVariant::Register(name, ty, term) => {
+                self.defs
+                    .borrow()
+                    .as_ref()
+                    .unwrap()
+                    .0
+                    .borrow_mut()
+                    .insert(name, (ty, term));
+                Ok(())
+            }
+            WorkerRequestVariant::Evaluate(term) => {
+                evaluated = Some(self.evaluate(term).await);
+                Ok(())
+            }
+            WorkerRequestVariant::ExpandEvaluate(term) => {
+                evaluated = Some(self.evaluate(term.into()).await);
+                Ok(())
+            }
+            WorkerRequestVariant::TempDefs(temp_defs) => {
+                // named for the request, so that it names them again if sent to a fresh worker
+                id = Some(request.idx);
+                self.temp_defs.borrow_mut().insert(request.idx, temp_defs);
+                Ok(())
+            }
+            WorkerRequestVariant::ClearTempDefs(uuid) => {
+                self.temp_defs.borrow_mut().remove(&uuid);
+                Ok(())
+            }
+            WorkerRequestVariant::Cancel(uuid) => {
+                self.cancel(uuid);
+                Ok(())
+            }
+            WorkerRequestVariant::Search(term, ty, hole, budget) => {
+                let defs = self.defs.borrow();
+                let defs = defs.as_ref().unwrap();
+                let deadline = (self.clock)() + budget as f64;
+                solutions = term.search_in(
+                    &ty,
+                    &hole,
+                    &defs.names(),
+                    defs,
+                    SEARCH_SOLUTIONS,
+                    &mut || (self.clock)() >= deadline,
+                    &mut *self.cache.borrow_mut(),
+                );
+                Ok(())
+            }
+            WorkerRequestVariant::Complete(term, ty, hole, prefix) => {
+                let defs = self.defs.borrow();
+                completions = complete(
+                    &term,
+                    &ty,
+                    &hole,
+                    &prefix,
+                    defs.as_ref().unwrap(),
+                    &mut *self.cache.borrow_mut(),
+                );
+                Ok(())
+            }
+        };
+
+        WorkerResponse {
+            data: response,
+            idx: request.idx,
+            evaluated,
+            solutions,
+            completions,
+            inferred,
+            filled,
+            id,
+        }
+    }
+
+    async fn evaluate(&self, term: Term<String>) -> Term<String> {
+        let e = {
+            let defs = self.defs.borrow();
+            let defs = defs.as_ref().unwrap();
+            Inet(defs.clone()).evaluate(term)
+        };
+        e.await.unwrap()
+    }
+}
@@ -8,7 +8,7 @@ use std::{
 use welkin_core::term::EqualityCache;
 type BumpBox<'a, T> = bumpalo::boxed::Box<'a, T>;
 
-use crate::edit::zipper::analysis::BasicContext;
+use crate::zipper::analysis::BasicContext;
 
 use super::{normalize::NormalizationError, AnalysisTerm, Definitions, TypedDefinitions};
 
@@ -2,7 +2,7 @@ use serde::{Deserialize, Serialize};
 use std::fmt::Debug;
 use welkin_core::term::EqualityCache;
 
-use crate::edit::zipper::analysis::BasicContext;
+use crate::zipper::analysis::BasicContext;
 
 use super::{normalize::NormalizationError, AnalysisTerm, TypedDefinitions};
 
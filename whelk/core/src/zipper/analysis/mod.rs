@@ -2,10 +2,11 @@ mod check;
 mod infer;
 pub use infer::AnalysisError;
 use serde::{Deserialize, Serialize};
-mod cursor;
 mod equivalent;
 mod is_complete;
 mod normalize;
+mod search;
+pub(crate) use search::arguments_to_fit;
 mod shift;
 mod stratified;
 pub use stratified::StratificationError;
@@ -21,13 +22,10 @@ use std::{
 use derivative::Derivative;
 use welkin_core::term::{self, Index};
 
-use crate::edit::dynamic::abst::{
-    controls::{conv_compressed, Variable, Zero},
-    implementation::Root,
-};
+use crate::literal::{conv_compressed, Zero};
 
 use super::{
-    dynamic::{Dynamic, DynamicTerm},
+    dynamic::{Annotated, Dynamic, DynamicTerm, VariableTerm},
     CompressedTerm, Cursor, Term,
 };
 
@@ -88,46 +86,58 @@ impl<'a, U: Clone, T: TypedDefinitions<U>> term::TypedDefinitions<String> for De
 }
 
 #[derive(Derivative, Clone, Serialize, Deserialize)]
-#[derivative(Hash(bound = ""))]
+#[derivative(Hash(bound = ""), PartialEq(bound = ""), Eq(bound = ""))]
 pub enum AnalysisTerm<T> {
     Lambda {
         erased: bool,
         name: Option<String>,
         body: Box<AnalysisTerm<T>>,
-        #[derivative(Hash = "ignore")]
+        #[derivative(Hash = "ignore", PartialEq = "ignore")]
         annotation: T,
     },
-    Variable(usize, #[derivative(Hash = "ignore")] T),
+    Variable(
+        usize,
+        #[derivative(Hash = "ignore", PartialEq = "ignore")] T,
+    ),
     Application {
         erased: bool,
         function: Box<AnalysisTerm<T>>,
         argument: Box<AnalysisTerm<T>>,
-        #[derivative(Hash = "ignore")]
+        #[derivative(Hash = "ignore", PartialEq = "ignore")]
         annotation: T,
     },
-    Put(Box<AnalysisTerm<T>>, #[derivative(Hash = "ignore")] T),
+    Put(
+        Box<AnalysisTerm<T>>,
+        #[derivative(Hash = "ignore", PartialEq = "ignore")] T,
+    ),
     Duplication {
         binder: Option<String>,
         expression: Box<AnalysisTerm<T>>,
         body: Box<AnalysisTerm<T>>,
-        #[derivative(Hash = "ignore")]
+        #[derivative(Hash = "ignore", PartialEq = "ignore")]
         annotation: T,
     },
-    Reference(String, #[derivative(Hash = "ignore")] T),
+    Reference(
+        String,
+        #[derivative(Hash = "ignore", PartialEq = "ignore")] T,
+    ),
 
-    Universe(#[derivative(Hash = "ignore")] T),
+    Universe(#[derivative(Hash = "ignore", PartialEq = "ignore")] T),
     Function {
         erased: bool,
         name: Option<String>,
         self_name: Option<String>,
         argument_type: Box<AnalysisTerm<T>>,
         return_type: Box<AnalysisTerm<T>>,
-        #[derivative(Hash = "ignore")]
+        #[derivative(Hash = "ignore", PartialEq = "ignore")]
         annotation: T,
     },
-    Wrap(Box<AnalysisTerm<T>>, #[derivative(Hash = "ignore")] T),
+    Wrap(
+        Box<AnalysisTerm<T>>,
+        #[derivative(Hash = "ignore", PartialEq = "ignore")] T,
+    ),
 
-    Hole(#[derivative(Hash = "ignore")] T),
+    Hole(#[derivative(Hash = "ignore", PartialEq = "ignore")] T),
 
     Annotation {
         checked: bool,
@@ -208,7 +218,7 @@ impl<T: Debug> Debug for AnalysisTerm<T> {
 
 impl<T: Zero + Clone> From<AnalysisTerm<T>> for Term<T>
 where
-    Root: DynamicTerm<T>,
+    VariableTerm: DynamicTerm<T>,
 {
     fn from(term: AnalysisTerm<T>) -> Self {
         match term {
@@ -224,7 +234,7 @@ where
                 annotation,
             },
             AnalysisTerm::Variable(idx, annotation) => {
-                Term::Dynamic(Dynamic::new(annotation, Root::new(Variable::new(idx))))
+                Term::Dynamic(Dynamic::new(annotation, VariableTerm::new(idx)))
             }
             AnalysisTerm::Application {
                 erased,
@@ -504,11 +514,29 @@ impl<T: Clone + 'static> From<Cursor<T>> for AnalysisTerm<Option<T>> {
             Cursor::Hole(cursor) => AnalysisTerm::Hole(Some(cursor.annotation)),
 
             Cursor::Dynamic(cursor) => {
+                let annotated = cursor
+                    .term()
+                    .as_any()
+                    .downcast_ref::<Annotated<T>>()
+                    .cloned();
                 let term = cursor.clone().expand();
-                AnalysisTerm::from_unit_term_and_context(
-                    term,
-                    &mut DescentContext::new(&Cursor::Dynamic(cursor)),
-                )
+                let cursor = Cursor::Dynamic(cursor);
+                let mut context = DescentContext::new(&cursor);
+                if let Some(Annotated { term, ty }) = annotated {
+                    AnalysisTerm::Annotation {
+                        checked: false,
+                        term: Box::new(AnalysisTerm::from_unit_term_and_context(
+                            term.clear_annotation(),
+                            &mut context,
+                        )),
+                        ty: Box::new(AnalysisTerm::from_unit_term_and_context(
+                            ty.clear_annotation(),
+                            &mut context,
+                        )),
+                    }
+                } else {
+                    AnalysisTerm::from_unit_term_and_context(term, &mut context)
+                }
             }
         }
     }
@@ -687,10 +715,26 @@ impl<T: Clone + Zero> AnalysisTerm<T> {
                 T::zero(),
             ),
             Term::Hole(_) => AnalysisTerm::Hole(T::zero()),
-            Term::Dynamic(term) => AnalysisTerm::from_unit_term_and_context(
-                term.term().box_clone().expand(),
-                &mut *ctx,
-            ),
+            Term::Dynamic(term) => {
+                if let Some(annotated) = term.term().as_any().downcast_ref::<Annotated<()>>() {
+                    AnalysisTerm::Annotation {
+                        checked: false,
+                        term: Box::new(AnalysisTerm::from_unit_term_and_context(
+                            annotated.term.clone(),
+                            &mut *ctx,
+                        )),
+                        ty: Box::new(AnalysisTerm::from_unit_term_and_context(
+                            annotated.ty.clone(),
+                            &mut *ctx,
+                        )),
+                    }
+                } else {
+                    AnalysisTerm::from_unit_term_and_context(
+                        term.term().box_clone().expand(),
+                        &mut *ctx,
+                    )
+                }
+            }
             Term::Compressed(data) => AnalysisTerm::Compressed(data),
         }
     }
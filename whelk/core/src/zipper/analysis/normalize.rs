@@ -2,8 +2,8 @@ use std::mem::replace;
 
 use serde::{Deserialize, Serialize};
 
-use crate::edit::{
-    dynamic::abst::controls::Zero,
+use crate::{
+    literal::Zero,
     zipper::{Cursor, Path},
 };
 
@@ -0,0 +1,499 @@
+use std::{
+    cmp::Ordering,
+    collections::{BinaryHeap, HashMap, HashSet},
+    fmt::Debug,
+};
+
+use welkin_core::term::EqualityCache;
+
+use super::{AnalysisTerm, Definitions, TypedDefinitions};
+
+/// The most arguments a candidate is applied to in looking for a fit.
+const MAX_ARGUMENTS: usize = 16;
+
+/// The largest solution looked for, counting each node and each hole left to fill.
+const MAX_SIZE: usize = 24;
+
+/// The annotation of the hole whose filling gives the type of a local variable.
+const PROBE: usize = usize::MAX;
+
+/// A solution in progress, with the holes it has opened filled in turn.
+struct Partial {
+    size: usize,
+    /// Breaks ties in size in favour of the partial solution found first.
+    order: usize,
+    fillings: HashMap<usize, AnalysisTerm<Option<usize>>>,
+    /// Each hole left to fill with the number of binders around it, the next to fill first.
+    open: Vec<(usize, usize)>,
+}
+
+impl Partial {
+    fn fill(&self, term: &AnalysisTerm<Option<usize>>) -> AnalysisTerm<Option<usize>> {
+        let mut term = term.clone();
+        fill(&mut term, &self.fillings);
+        term
+    }
+}
+
+impl PartialEq for Partial {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Partial {}
+
+impl PartialOrd for Partial {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Partial {
+    // reversed, so that the queue gives the smallest first
+    fn cmp(&self, other: &Self) -> Ordering {
+        (other.size, other.order).cmp(&(self.size, self.order))
+    }
+}
+
+/// A way to fill a hole, with the holes it opens.
+struct Extension {
+    filling: AnalysisTerm<Option<usize>>,
+    nodes: usize,
+    opened: Vec<(usize, usize)>,
+}
+
+impl<T: Clone + PartialEq> AnalysisTerm<Option<T>> {
+    /// Looks for complete terms to fill the hole annotated with `hole` such that `self` checks
+    /// against `ty`, built from lambdas, local variables and the definitions named in `names`,
+    /// applied to further such terms.
+    ///
+    /// Returns up to `count` solutions, smallest first, stopping early once `expired` returns
+    /// true.
+    pub fn search_in<U: TypedDefinitions<Option<usize>>>(
+        &self,
+        ty: &Self,
+        hole: &T,
+        names: &[String],
+        definitions: &U,
+        count: usize,
+        expired: &mut impl FnMut() -> bool,
+        cache: &mut impl EqualityCache,
+    ) -> Vec<AnalysisTerm<()>> {
+        let term = self
+            .clone()
+            .map_annotation(&mut |annotation| match annotation {
+                Some(annotation) if &annotation == hole => Some(0),
+                _ => None,
+            });
+        let ty = ty.clone().map_annotation(&mut |_| None);
+
+        let binders = match term.binders_around(0) {
+            Some(binders) => binders.len(),
+            None => return vec![],
+        };
+
+        let mut queue = BinaryHeap::new();
+        queue.push(Partial {
+            size: 1,
+            order: 0,
+            fillings: HashMap::new(),
+            open: vec![(0, binders)],
+        });
+
+        let mut next_hole = 1;
+        let mut order = 1;
+
+        let mut solutions = vec![];
+        let mut found = HashSet::new();
+
+        while let Some(partial) = queue.pop() {
+            if solutions.len() >= count || expired() {
+                break;
+            }
+
+            let (hole, binders) = match partial.open.first() {
+                Some(open) => *open,
+                None => {
+                    let solution = partial
+                        .fill(&AnalysisTerm::Hole(Some(0)))
+                        .clear_annotation();
+                    if found.insert(solution.clone()) {
+                        solutions.push(solution);
+                    }
+                    continue;
+                }
+            };
+
+            let whole = partial.fill(&term);
+
+            let mut goal = None;
+            let _ = whole.check_in(
+                &ty,
+                definitions,
+                &mut |annotation, ty| {
+                    if annotation == Some(&hole) {
+                        goal = Some(ty.clone());
+                    }
+                },
+                &mut |_, _| {},
+                cache,
+            );
+            let goal = match goal {
+                Some(goal) => goal,
+                None => continue,
+            };
+
+            let mut extensions = vec![];
+
+            let mut reduced = goal.clone();
+            if reduced.weak_normalize_in(definitions).is_ok() {
+                if let AnalysisTerm::Function { erased, name, .. } = reduced {
+                    extensions.push(Extension {
+                        filling: AnalysisTerm::Lambda {
+                            erased,
+                            name,
+                            body: Box::new(AnalysisTerm::Hole(Some(next_hole))),
+                            annotation: None,
+                        },
+                        nodes: 1,
+                        opened: vec![(next_hole, binders + 1)],
+                    });
+                    next_hole += 1;
+                }
+            }
+
+            for index in 0..binders {
+                if let Some(local_ty) = local_type(&whole, &ty, hole, index, definitions, cache) {
+                    if let Some(erasures) = arguments_to_fit(local_ty, &goal, definitions, cache) {
+                        extensions.push(applied(
+                            AnalysisTerm::Variable(index, None),
+                            &erasures,
+                            binders,
+                            &mut next_hole,
+                        ));
+                    }
+                }
+            }
+
+            // constructors of the goal's type first
+            let constructors = head(&goal).map(|name| format!("{}::", name));
+            let mut names = names.iter().collect::<Vec<_>>();
+            names.sort_by_key(|name| match &constructors {
+                Some(prefix) => !name.starts_with(prefix),
+                None => true,
+            });
+
+            for name in names {
+                // libraries are large, so the budget is checked between each definition tried
+                if expired() {
+                    break;
+                }
+
+                let ty = match definitions.get_typed(name) {
+                    Some(definition) => definition.as_ref().0.clone(),
+                    None => continue,
+                };
+                if let Some(erasures) = arguments_to_fit(ty, &goal, definitions, cache) {
+                    extensions.push(applied(
+                        AnalysisTerm::Reference(name.clone(), None),
+                        &erasures,
+                        binders,
+                        &mut next_hole,
+                    ));
+                }
+            }
+
+            for extension in extensions {
+                if expired() {
+                    break;
+                }
+
+                let size = partial.size + extension.nodes + extension.opened.len() - 1;
+                if size > MAX_SIZE {
+                    continue;
+                }
+
+                let mut fillings = partial.fillings.clone();
+                fillings.insert(hole, extension.filling);
+                let mut open = extension.opened;
+                open.extend(partial.open[1..].iter().cloned());
+
+                let extended = Partial {
+                    size,
+                    order,
+                    fillings,
+                    open,
+                };
+                order += 1;
+
+                if extended
+                    .fill(&term)
+                    .check_in(&ty, definitions, &mut |_, _| {}, &mut |_, _| {}, cache)
+                    .is_ok()
+                {
+                    queue.push(extended);
+                }
+            }
+        }
+
+        solutions
+    }
+
+    /// The goal of the hole annotated with `hole` when `self` is checked against `ty`, and the
+    /// name and type of each binder in scope there, innermost first, each where it is known.
+    ///
+    /// Returns `None` if there is no such hole.
+    pub(crate) fn scope_in<U: TypedDefinitions<Option<usize>>>(
+        &self,
+        ty: &Self,
+        hole: &T,
+        definitions: &U,
+        cache: &mut impl EqualityCache,
+    ) -> Option<Scope> {
+        let term = self
+            .clone()
+            .map_annotation(&mut |annotation| match annotation {
+                Some(annotation) if &annotation == hole => Some(0),
+                _ => None,
+            });
+        let ty = ty.clone().map_annotation(&mut |_| None);
+
+        let names = term.binders_around(0)?;
+
+        let mut goal = None;
+        let _ = term.check_in(
+            &ty,
+            definitions,
+            &mut |annotation, ty| {
+                if annotation == Some(&0) {
+                    goal = Some(ty.clone());
+                }
+            },
+            &mut |_, _| {},
+            cache,
+        );
+
+        let locals = names
+            .into_iter()
+            .enumerate()
+            .map(|(index, name)| (name, local_type(&term, &ty, 0, index, definitions, cache)))
+            .collect();
+
+        Some(Scope { goal, locals })
+    }
+}
+
+/// The types around a hole, as found by [`AnalysisTerm::scope_in`].
+pub(crate) struct Scope {
+    pub goal: Option<AnalysisTerm<Option<usize>>>,
+    pub locals: Vec<(Option<String>, Option<AnalysisTerm<Option<usize>>>)>,
+}
+
+impl AnalysisTerm<Option<usize>> {
+    /// The name of each binder around the hole annotated with `label`, innermost first.
+    fn binders_around(&self, label: usize) -> Option<Vec<Option<String>>> {
+        use AnalysisTerm::*;
+
+        match self {
+            Hole(Some(annotation)) if *annotation == label => Some(vec![]),
+            Lambda { body, name, .. } => body.binders_around(label).map(|mut binders| {
+                binders.push(name.clone());
+                binders
+            }),
+            Application {
+                function, argument, ..
+            } => function
+                .binders_around(label)
+                .or_else(|| argument.binders_around(label)),
+            Put(term, _) | Wrap(term, _) => term.binders_around(label),
+            Duplication {
+                expression,
+                body,
+                binder,
+                ..
+            } => expression.binders_around(label).or_else(|| {
+                body.binders_around(label).map(|mut binders| {
+                    binders.push(binder.clone());
+                    binders
+                })
+            }),
+            Function {
+                argument_type,
+                return_type,
+                name,
+                self_name,
+                ..
+            } => argument_type.binders_around(label).or_else(|| {
+                return_type.binders_around(label).map(|mut binders| {
+                    binders.push(name.clone());
+                    binders.push(self_name.clone());
+                    binders
+                })
+            }),
+            Annotation { term, ty, .. } => term
+                .binders_around(label)
+                .or_else(|| ty.binders_around(label)),
+            Variable(_, _) | Reference(_, _) | Universe(_) | Hole(_) | Compressed(_) => None,
+        }
+    }
+}
+
+/// The type of the local variable `index` at the hole annotated with `hole` in `term`, found by
+/// checking `term` against `ty` with the variable in the hole.
+fn local_type<U: TypedDefinitions<Option<usize>>>(
+    term: &AnalysisTerm<Option<usize>>,
+    ty: &AnalysisTerm<Option<usize>>,
+    hole: usize,
+    index: usize,
+    definitions: &U,
+    cache: &mut impl EqualityCache,
+) -> Option<AnalysisTerm<Option<usize>>> {
+    let mut probed = HashMap::new();
+    probed.insert(
+        hole,
+        AnalysisTerm::Annotation {
+            checked: false,
+            term: Box::new(AnalysisTerm::Variable(index, None)),
+            ty: Box::new(AnalysisTerm::Hole(Some(PROBE))),
+        },
+    );
+    let mut probe = term.clone();
+    fill(&mut probe, &probed);
+
+    let mut local_ty = None;
+    let _ = probe.check_in(
+        ty,
+        definitions,
+        &mut |_, _| {},
+        &mut |annotation, ty| {
+            // the hole is filled again with the goal once the probe is checked
+            if annotation == Some(&PROBE) && local_ty.is_none() {
+                local_ty = Some(ty.clone());
+            }
+        },
+        cache,
+    );
+    local_ty
+}
+
+/// Replaces each hole in `term` that has a filling with it, and likewise the holes in that.
+fn fill(
+    term: &mut AnalysisTerm<Option<usize>>,
+    fillings: &HashMap<usize, AnalysisTerm<Option<usize>>>,
+) {
+    use AnalysisTerm::*;
+
+    if let Hole(Some(label)) = term {
+        match fillings.get(label) {
+            Some(filling) => *term = filling.clone(),
+            None => return,
+        }
+    }
+
+    match term {
+        Lambda { body, .. } => fill(body, fillings),
+        Application {
+            function, argument, ..
+        } => {
+            fill(function, fillings);
+            fill(argument, fillings);
+        }
+        Put(term, _) | Wrap(term, _) => fill(term, fillings),
+        Duplication {
+            expression, body, ..
+        } => {
+            fill(expression, fillings);
+            fill(body, fillings);
+        }
+        Function {
+            argument_type,
+            return_type,
+            ..
+        } => {
+            fill(argument_type, fillings);
+            fill(return_type, fillings);
+        }
+        Annotation { term, ty, .. } => {
+            fill(term, fillings);
+            fill(ty, fillings);
+        }
+        Variable(_, _) | Reference(_, _) | Universe(_) | Hole(_) | Compressed(_) => {}
+    }
+}
+
+/// `function` applied to a new hole for each of `erasures`.
+fn applied(
+    function: AnalysisTerm<Option<usize>>,
+    erasures: &[bool],
+    binders: usize,
+    next_hole: &mut usize,
+) -> Extension {
+    let mut opened = vec![];
+
+    let filling = erasures.iter().fold(function, |function, erased| {
+        opened.push((*next_hole, binders));
+        let argument = AnalysisTerm::Hole(Some(*next_hole));
+        *next_hole += 1;
+
+        AnalysisTerm::Application {
+            erased: *erased,
+            function: Box::new(function),
+            argument: Box::new(argument),
+            annotation: None,
+        }
+    });
+
+    Extension {
+        filling,
+        nodes: erasures.len() + 1,
+        opened,
+    }
+}
+
+/// The name of the type `ty` is an application of, if any.
+fn head<T>(ty: &AnalysisTerm<T>) -> Option<&str> {
+    match ty {
+        AnalysisTerm::Reference(name, _) => Some(name),
+        AnalysisTerm::Application { function, .. } => head(function),
+        AnalysisTerm::Annotation { term, .. } => head(term),
+        _ => None,
+    }
+}
+
+/// The erasure of each argument `ty` must be applied to for the result to be equivalent to
+/// `goal`, trying fewer arguments first.
+pub(crate) fn arguments_to_fit<T: Clone + Debug, U: Definitions<Option<T>>>(
+    mut ty: AnalysisTerm<Option<T>>,
+    goal: &AnalysisTerm<Option<T>>,
+    definitions: &U,
+    cache: &mut impl EqualityCache,
+) -> Option<Vec<bool>> {
+    let mut erasures = vec![];
+
+    while erasures.len() <= MAX_ARGUMENTS {
+        if ty
+            .equivalent_in(goal, definitions, &mut |_, _| {}, cache)
+            .ok()?
+        {
+            return Some(erasures);
+        }
+
+        ty.weak_normalize_in(definitions).ok()?;
+
+        if let AnalysisTerm::Function {
+            erased,
+            mut return_type,
+            ..
+        } = ty
+        {
+            return_type.substitute_function_in(AnalysisTerm::Hole(None), &AnalysisTerm::Hole(None));
+            erasures.push(erased);
+            ty = *return_type;
+        } else {
+            return None;
+        }
+    }
+
+    None
+}
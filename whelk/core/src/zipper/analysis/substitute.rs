@@ -1,5 +1,5 @@
-use crate::edit::{
-    dynamic::abst::controls::Zero,
+use crate::{
+    literal::Zero,
     zipper::{Cursor, Path},
 };
 
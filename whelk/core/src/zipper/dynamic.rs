@@ -1,20 +1,9 @@
-use futures::channel::mpsc::Sender;
-use mincodec::{MapDeserialize, MapSerialize, MinCodecRead, MinCodecWrite};
+use mincodec::{MapDeserialize, MapSerialize, MinCodec, MinCodecRead, MinCodecWrite};
 use serde::{de, Deserialize, Serialize};
-use std::fmt::Debug;
+use std::{any::Any, cell::RefCell, collections::HashMap, convert::TryInto, fmt::Debug};
 use thiserror::Error;
-use wasm_bindgen::JsValue;
-use web_sys::Node;
 
-use crate::edit::{
-    dynamic::{
-        abst::{controls::ControlData, implementation::Root},
-        Def, DefData,
-    },
-    DynamicVariance, UiSection,
-};
-
-use super::{decode, Cursor, DynamicCursor, Path, Term};
+use super::{decode, encode, Cursor, DynamicCursor, Term, TermData};
 
 #[derive(Debug, Error)]
 pub enum DynamicReadError {
@@ -26,6 +15,26 @@ pub enum DynamicReadError {
     Invalid,
 }
 
+/// Reads a dynamic term from its encoding, without the leading index byte.
+pub type Decoder = fn(&[u8]) -> Result<Dynamic<()>, DynamicReadError>;
+
+thread_local! {
+    static DECODERS: RefCell<HashMap<u8, Decoder>> = RefCell::new({
+        let mut decoders = HashMap::new();
+        decoders.insert(b'v', VariableTerm::decode as Decoder);
+        decoders.insert(b'a', Annotated::<()>::decode as Decoder);
+        decoders
+    });
+}
+
+/// Makes dynamic terms with the given index readable by `Dynamic::from_buffer`, replacing any
+/// decoder previously registered for it.
+pub fn register_dynamic(index: u8, decode: Decoder) {
+    DECODERS.with(|decoders| {
+        decoders.borrow_mut().insert(index, decode);
+    });
+}
+
 impl Dynamic<()> {
     pub fn to_buffer(self) -> Vec<u8> {
         let mut buffer = vec![self.term.index()];
@@ -34,40 +43,152 @@ impl Dynamic<()> {
     }
 
     pub fn from_buffer(data: Vec<u8>) -> Result<Dynamic<()>, DynamicReadError> {
-        if let Some(first) = data.first() {
-            Ok(match *first as char {
-                'D' => {
-                    let DefData {
-                        binder,
-                        expression,
-                        body,
-                    } = if let Ok(data) = decode(&data[1..]) {
-                        data
-                    } else {
-                        return Err(DynamicReadError::Invalid);
-                    };
-                    Dynamic {
-                        annotation: ().into(),
-                        term: Box::new(Def::new(body.into(), expression.into(), binder)),
-                    }
-                }
-                'i' => {
-                    let data: ControlData = if let Ok(data) = decode(&data[1..]) {
-                        data
-                    } else {
-                        return Err(DynamicReadError::Invalid);
-                    };
-                    Dynamic {
-                        annotation: ().into(),
-                        term: Box::new(Root::new(data.to_control())),
-                    }
-                }
-                first => Err(DynamicReadError::Unknown(first as u8))?,
-            })
+        let first = *data.first().ok_or(DynamicReadError::TooShort)?;
+        let decode = DECODERS
+            .with(|decoders| decoders.borrow().get(&first).copied())
+            .ok_or(DynamicReadError::Unknown(first))?;
+        decode(&data[1..])
+    }
+}
+
+/// A variable left in place of a hole by `fill_hole`, which expands back to a hole wherever
+/// the interface that gives it a meaning is absent.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VariableTerm(usize);
+
+impl VariableTerm {
+    pub fn new(idx: usize) -> Self {
+        VariableTerm(idx)
+    }
+
+    pub fn idx(&self) -> usize {
+        self.0
+    }
+
+    fn decode(data: &[u8]) -> Result<Dynamic<()>, DynamicReadError> {
+        let bytes: [u8; 8] = data.try_into().map_err(|_| DynamicReadError::Invalid)?;
+        Ok(Dynamic::new(
+            (),
+            VariableTerm(u64::from_be_bytes(bytes) as usize),
+        ))
+    }
+}
+
+impl<T> DynamicTerm<T> for VariableTerm {
+    fn debug(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result
+    where
+        T: Debug,
+    {
+        Debug::fmt(self, f)
+    }
+
+    fn index(&self) -> u8 {
+        b'v'
+    }
+
+    fn encode(self: Box<Self>) -> Vec<u8> {
+        (self.0 as u64).to_be_bytes().to_vec()
+    }
+
+    fn expand(self: Box<Self>) -> Term<()> {
+        Term::Hole(())
+    }
+
+    fn box_clone(&self) -> Box<dyn DynamicTerm<T>>
+    where
+        T: Clone,
+    {
+        Box::new(*self)
+    }
+
+    fn clear_annotation(self: Box<Self>) -> Box<dyn DynamicTerm<()>> {
+        self
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn into_any(self: Box<Self>) -> Box<dyn Any> {
+        self
+    }
+}
+
+/// A term together with the type it is expected to have, which the zipper has no node for.
+/// Analysis checks the term against the type and it otherwise expands to the term alone.
+#[derive(Debug, Clone)]
+pub struct Annotated<T> {
+    pub term: Term<T>,
+    pub ty: Term<T>,
+}
+
+#[derive(MinCodec)]
+struct AnnotatedData {
+    term: TermData,
+    ty: TermData,
+}
+
+impl<T> Annotated<T> {
+    pub fn new(term: Term<T>, ty: Term<T>) -> Self {
+        Annotated { term, ty }
+    }
+}
+
+impl Annotated<()> {
+    fn decode(data: &[u8]) -> Result<Dynamic<()>, DynamicReadError> {
+        let AnnotatedData { term, ty } = decode(data).map_err(|_| DynamicReadError::Invalid)?;
+        Ok(Dynamic::new((), Annotated::new(term.into(), ty.into())))
+    }
+}
+
+impl<T: Clone + 'static> DynamicTerm<T> for Annotated<T> {
+    fn debug(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result
+    where
+        T: Debug,
+    {
+        Debug::fmt(self, f)
+    }
+
+    fn index(&self) -> u8 {
+        b'a'
+    }
+
+    fn encode(self: Box<Self>) -> Vec<u8> {
+        if let Ok(data) = encode(AnnotatedData {
+            term: self.term.clear_annotation().into(),
+            ty: self.ty.clear_annotation().into(),
+        }) {
+            data
         } else {
-            Err(DynamicReadError::TooShort)
+            panic!()
         }
     }
+
+    fn expand(self: Box<Self>) -> Term<()> {
+        self.term.clear_annotation()
+    }
+
+    fn box_clone(&self) -> Box<dyn DynamicTerm<T>>
+    where
+        T: Clone,
+    {
+        Box::new(self.clone())
+    }
+
+    fn clear_annotation(self: Box<Self>) -> Box<dyn DynamicTerm<()>> {
+        Box::new(Annotated {
+            term: self.term.clear_annotation(),
+            ty: self.ty.clear_annotation(),
+        })
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn into_any(self: Box<Self>) -> Box<dyn Any> {
+        self
+    }
 }
 
 impl Serialize for Dynamic<()> {
@@ -189,30 +310,11 @@ pub trait DynamicTerm<T> {
     where
         T: Clone;
 
-    fn add_ui(
-        self: Box<Self>,
-        sender: &Sender<()>,
-        editable: bool,
-    ) -> (UiSection, Box<dyn DynamicTerm<UiSection>>);
-
-    fn apply_mutations(
-        self: Box<Self>,
-        up: Path<UiSection>,
-        annotation: Box<dyn DynamicVariance>,
-        focused: &mut Option<Cursor<UiSection>>,
-        sender: &Sender<()>,
-    ) -> Result<Cursor<UiSection>, JsValue>
-    where
-        Term<T>: Into<Term<UiSection>>;
-
-    fn render_to(
-        &self,
-        up: &Path<UiSection>,
-        annotation: &dyn DynamicVariance,
-        node: &Node,
-    ) -> Result<(), JsValue>;
-
     fn clear_annotation(self: Box<Self>) -> Box<dyn DynamicTerm<()>>;
+
+    /// The concrete term, for interfaces that extend dynamic terms with behaviour of their own.
+    fn as_any(&self) -> &dyn Any;
+    fn into_any(self: Box<Self>) -> Box<dyn Any>;
 }
 
 impl<T, U: DynamicTerm<T> + ?Sized> DynamicTerm<T> for Box<U> {
@@ -230,36 +332,6 @@ impl<T, U: DynamicTerm<T> + ?Sized> DynamicTerm<T> for Box<U> {
         U::box_clone(self.as_ref())
     }
 
-    fn add_ui(
-        self: Box<Self>,
-        trigger_update: &Sender<()>,
-        editable: bool,
-    ) -> (UiSection, Box<dyn DynamicTerm<UiSection>>) {
-        U::add_ui(*self, trigger_update, editable)
-    }
-
-    fn apply_mutations(
-        self: Box<Self>,
-        up: Path<UiSection>,
-        annotation: Box<dyn DynamicVariance>,
-        focused: &mut Option<Cursor<UiSection>>,
-        sender: &Sender<()>,
-    ) -> Result<Cursor<UiSection>, JsValue>
-    where
-        Term<T>: Into<Term<UiSection>>,
-    {
-        U::apply_mutations(*self, up, annotation, focused, sender)
-    }
-
-    fn render_to(
-        &self,
-        up: &Path<UiSection>,
-        annotation: &dyn DynamicVariance,
-        node: &Node,
-    ) -> Result<(), JsValue> {
-        U::render_to(self, up, annotation, node)
-    }
-
     fn clear_annotation(self: Box<Self>) -> Box<dyn DynamicTerm<()>> {
         U::clear_annotation(*self)
     }
@@ -275,6 +347,14 @@ impl<T, U: DynamicTerm<T> + ?Sized> DynamicTerm<T> for Box<U> {
     fn expand(self: Box<Self>) -> Term<()> {
         U::expand(*self)
     }
+
+    fn as_any(&self) -> &dyn Any {
+        U::as_any(self)
+    }
+
+    fn into_any(self: Box<Self>) -> Box<dyn Any> {
+        U::into_any(*self)
+    }
 }
 
 impl<T> DynamicCursor<T> {
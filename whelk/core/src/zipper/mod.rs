@@ -16,14 +16,15 @@ use std::{
 };
 pub mod analysis;
 pub mod dynamic;
+mod refactor;
+pub mod source;
+pub use refactor::{at_location, RefactorError};
 use serde::{Deserialize, Serialize};
 use welkin_core::term::{self, Index};
 
 use self::dynamic::{Dynamic, DynamicTerm};
 
-use super::dynamic::abst::controls::{
-    CompressedChar, CompressedSize, CompressedString, CompressedWord, Zero,
-};
+use crate::literal::{CompressedChar, CompressedSize, CompressedString, CompressedWord, Zero};
 
 #[derive(Debug, Clone, MinCodec, Serialize, Deserialize)]
 #[bounds()]
@@ -518,6 +519,14 @@ impl<T> Hash for Box<dyn CompressedTerm<T>> {
     }
 }
 
+impl<T> PartialEq for Box<dyn CompressedTerm<T>> {
+    fn eq(&self, other: &Self) -> bool {
+        self.partial_eq(&**other).unwrap_or(false)
+    }
+}
+
+impl<T> Eq for Box<dyn CompressedTerm<T>> {}
+
 fn ty_hash<T: 'static>() -> u64 {
     let mut hasher = DefaultHasher::new();
     TypeId::of::<T>().hash(&mut hasher);
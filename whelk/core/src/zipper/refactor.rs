@@ -0,0 +1,708 @@
+//! Refactorings of the focused term of a cursor, each of which leaves the cursor on the rewritten
+//! term with every variable still bound as it was.
+
+use std::collections::HashSet;
+
+use thiserror::Error;
+
+use crate::{history, literal::Zero};
+
+use super::{
+    analysis::{AnalysisTerm, TypedDefinitions},
+    dynamic::{Annotated, Dynamic, VariableTerm},
+    source::Fresh,
+    Cursor, Path, Term,
+};
+
+#[derive(Debug, Error)]
+pub enum RefactorError {
+    #[error("the focused term is not a lambda applied to an argument")]
+    NotARedex,
+    #[error("the focused term does not have a function type")]
+    NotAFunction,
+    #[error("the focused term is not a reference to a definition")]
+    NotAReference,
+    #[error("`{0}` is not defined")]
+    Undefined(String),
+    #[error("`{0}` would be captured by a binder of the same name")]
+    Capture(String),
+}
+
+impl<T: Zero + Clone + 'static> Cursor<T> {
+    /// Wraps the focused term in a lambda binding `name`, which must not already be used in it.
+    pub fn wrap_lambda(self, erased: bool, name: String) -> Result<Cursor<T>, RefactorError> {
+        let term = Term::from(self.clone());
+
+        let mut names = HashSet::new();
+        free_names(&term, &mut vec![], &mut names);
+        if names.contains(&name) {
+            return Err(RefactorError::Capture(name));
+        }
+
+        Ok(self.map_focus(|body| Term::Lambda {
+            erased,
+            name: Some(name),
+            body: Box::new(body),
+            annotation: T::zero(),
+        }))
+    }
+
+    /// Applies the focused term to a hole.
+    pub fn wrap_application(self, erased: bool) -> Cursor<T> {
+        self.map_focus(|function| Term::Application {
+            erased,
+            function: Box::new(function),
+            argument: Box::new(Term::Hole(T::zero())),
+            annotation: T::zero(),
+        })
+    }
+
+    /// Annotates the focused term with the type it is expected to have.
+    pub fn wrap_annotation(self, ty: Term<T>) -> Cursor<T> {
+        self.map_focus(|term| Term::Dynamic(Dynamic::new(T::zero(), Annotated::new(term, ty))))
+    }
+
+    /// Substitutes the argument of an applied lambda into its body.
+    pub fn beta_reduce(self) -> Result<Cursor<T>, RefactorError> {
+        let binders = binders(self.path());
+        let up = self.path().clone();
+
+        if let AnalysisTerm::Application {
+            erased,
+            function,
+            argument,
+            ..
+        } = self.into_analysis()
+        {
+            if let AnalysisTerm::Lambda {
+                erased: lambda_erased,
+                mut body,
+                ..
+            } = *function
+            {
+                if erased == lambda_erased {
+                    body.substitute_top_in(&argument);
+                    return Ok(Cursor::from_term_and_path(named(*body, &binders)?, up));
+                }
+            }
+        }
+
+        Err(RefactorError::NotARedex)
+    }
+
+    /// Wraps the focused term in a lambda that applies it to its argument, where `ty` is the
+    /// function type inferred for the focused term.
+    pub fn eta_expand<U>(self, ty: &AnalysisTerm<U>) -> Result<Cursor<T>, RefactorError> {
+        let (erased, name) = match ty {
+            AnalysisTerm::Function { erased, name, .. } => (*erased, name.clone()),
+            _ => return Err(RefactorError::NotAFunction),
+        };
+
+        let binders = binders(self.path());
+        let up = self.path().clone();
+
+        let mut function = self.into_analysis();
+        function.shift_top();
+
+        let term = AnalysisTerm::Lambda {
+            erased,
+            name,
+            body: Box::new(AnalysisTerm::Application {
+                erased,
+                function: Box::new(function),
+                argument: Box::new(AnalysisTerm::Variable(0, T::zero())),
+                annotation: T::zero(),
+            }),
+            annotation: T::zero(),
+        };
+
+        Ok(Cursor::from_term_and_path(named(term, &binders)?, up))
+    }
+
+    /// Replaces the focused term with a reference to a new definition of it, applied to the
+    /// variables it uses. Returns the cursor alongside the `define` scratchpad term for the new
+    /// definition, whose type is left as holes for each variable and the result.
+    pub fn extract(self, name: String) -> Result<(Cursor<T>, Term<T>), RefactorError> {
+        let binders = binders(self.path());
+        if binders
+            .iter()
+            .any(|(binder, _)| binder.as_ref() == Some(&name))
+        {
+            return Err(RefactorError::Capture(name));
+        }
+
+        let term = Term::from(self.clone());
+
+        let mut names = HashSet::new();
+        free_names(&term, &mut vec![], &mut names);
+
+        // innermost first, as the binders are
+        let abstracted: Vec<_> = binders
+            .iter()
+            .enumerate()
+            .filter_map(|(idx, (binder, erased))| {
+                let binder = binder.as_ref()?;
+                let innermost = binders
+                    .iter()
+                    .position(|(other, _)| other.as_ref() == Some(binder));
+                if names.contains(binder) && innermost == Some(idx) {
+                    Some((binder.clone(), *erased))
+                } else {
+                    None
+                }
+            })
+            .collect();
+
+        let (body, ty) = abstracted.iter().fold(
+            (term, Term::Hole(T::zero())),
+            |(body, ty), (binder, erased)| {
+                (
+                    Term::Lambda {
+                        erased: *erased,
+                        name: Some(binder.clone()),
+                        body: Box::new(body),
+                        annotation: T::zero(),
+                    },
+                    Term::Function {
+                        erased: *erased,
+                        name: Some(binder.clone()),
+                        self_name: None,
+                        argument_type: Box::new(Term::Hole(T::zero())),
+                        return_type: Box::new(ty),
+                        annotation: T::zero(),
+                    },
+                )
+            },
+        );
+
+        let definition = vec![Term::Reference(name.clone(), T::zero()), ty, body]
+            .into_iter()
+            .fold(
+                Term::Reference("define".into(), T::zero()),
+                |function, argument| apply(false, function, argument),
+            );
+
+        let cursor = self.map_focus(|_| {
+            abstracted.iter().rev().fold(
+                Term::Reference(name, T::zero()),
+                |function, (binder, erased)| {
+                    apply(
+                        *erased,
+                        function,
+                        Term::Reference(binder.clone(), T::zero()),
+                    )
+                },
+            )
+        });
+
+        Ok((cursor, definition))
+    }
+
+    /// Replaces a reference to a definition with the term it is defined as.
+    pub fn inline<D: TypedDefinitions<Option<T>>>(
+        self,
+        definitions: &D,
+    ) -> Result<Cursor<T>, RefactorError> {
+        let name = match &self {
+            Cursor::Reference(cursor) => cursor.name().to_owned(),
+            _ => return Err(RefactorError::NotAReference),
+        };
+
+        let binders = binders(self.path());
+        if binders
+            .iter()
+            .any(|(binder, _)| binder.as_ref() == Some(&name))
+        {
+            return Err(RefactorError::NotAReference);
+        }
+
+        let definition = definitions
+            .get_typed(&name)
+            .ok_or_else(|| RefactorError::Undefined(name.clone()))?;
+        let mut term = definition
+            .as_ref()
+            .1
+            .clone()
+            .map_annotation(&mut |annotation| annotation.unwrap_or_else(T::zero));
+        term.shift_top_by(binders.len() as isize);
+
+        let term = named(term, &binders)?;
+        Ok(self.map_focus(|_| term))
+    }
+
+    fn map_focus(self, call: impl FnOnce(Term<T>) -> Term<T>) -> Cursor<T> {
+        let up = self.path().clone();
+        Cursor::from_term_and_path(call(self.into()), up)
+    }
+
+    fn into_analysis(self) -> AnalysisTerm<T> {
+        AnalysisTerm::from(self)
+            .map_annotation(&mut |annotation| annotation.unwrap_or_else(T::zero))
+    }
+}
+
+/// The cursor at the location `location` gives in another copy of the same term, as when it is
+/// rebuilt without annotations. Locations within dynamic terms can't be followed.
+pub fn at_location<T: 'static>(term: Term<T>, location: &[u8]) -> Option<Cursor<T>> {
+    let (_, steps) = location.split_last()?;
+    let mut cursor = Cursor::from(term);
+
+    for &step in steps {
+        cursor = match (step, cursor) {
+            (0, Cursor::Lambda(cursor)) => cursor.body(),
+            (1, Cursor::Application(cursor)) => cursor.function(),
+            (2, Cursor::Application(cursor)) => cursor.argument(),
+            (3, Cursor::Put(cursor)) => cursor.term(),
+            (5, Cursor::Duplication(cursor)) => cursor.expression(),
+            (6, Cursor::Duplication(cursor)) => cursor.body(),
+            (8, Cursor::Function(cursor)) => cursor.argument_type(),
+            (9, Cursor::Function(cursor)) => cursor.return_type(),
+            (10, Cursor::Wrap(cursor)) => cursor.term(),
+            _ => return None,
+        };
+    }
+
+    if history::location(&cursor) == location {
+        Some(cursor)
+    } else {
+        None
+    }
+}
+
+fn apply<T: Zero>(erased: bool, function: Term<T>, argument: Term<T>) -> Term<T> {
+    Term::Application {
+        erased,
+        function: Box::new(function),
+        argument: Box::new(argument),
+        annotation: T::zero(),
+    }
+}
+
+/// The binders in scope at `path` with whether each is erased, innermost first as in
+/// `Cursor::context`.
+fn binders<T>(mut path: &Path<T>) -> Vec<(Option<String>, bool)> {
+    let mut binders = vec![];
+
+    loop {
+        path = match path {
+            Path::Top => break,
+            Path::Lambda {
+                erased, name, up, ..
+            } => {
+                binders.push((name.clone(), *erased));
+                up
+            }
+            Path::DuplicationBody { binder, up, .. } => {
+                binders.push((binder.clone(), false));
+                up
+            }
+            Path::FunctionReturnType {
+                erased,
+                name,
+                self_name,
+                up,
+                ..
+            } => {
+                binders.push((name.clone(), *erased));
+                binders.push((self_name.clone(), false));
+                up
+            }
+            Path::ApplicationFunction { up, .. }
+            | Path::ApplicationArgument { up, .. }
+            | Path::Put { up, .. }
+            | Path::Reference { up, .. }
+            | Path::DuplicationExpression { up, .. }
+            | Path::Universe { up, .. }
+            | Path::FunctionArgumentType { up, .. }
+            | Path::Wrap { up, .. }
+            | Path::Hole { up, .. }
+            | Path::Dynamic { up, .. } => up,
+        };
+    }
+
+    binders
+}
+
+/// The names `term` refers to that it does not bind itself.
+pub(super) fn free_names<T: Clone + 'static>(
+    term: &Term<T>,
+    bound: &mut Vec<String>,
+    names: &mut HashSet<String>,
+) {
+    match term {
+        Term::Lambda { name, body, .. } => scoped_free_names(&[name], body, bound, names),
+        Term::Application {
+            function, argument, ..
+        } => {
+            free_names(function, bound, names);
+            free_names(argument, bound, names);
+        }
+        Term::Put(term, _) | Term::Wrap(term, _) => free_names(term, bound, names),
+        Term::Duplication {
+            binder,
+            expression,
+            body,
+            ..
+        } => {
+            free_names(expression, bound, names);
+            scoped_free_names(&[binder], body, bound, names);
+        }
+        Term::Reference(name, _) => {
+            if !bound.contains(name) {
+                names.insert(name.clone());
+            }
+        }
+        Term::Function {
+            name,
+            self_name,
+            argument_type,
+            return_type,
+            ..
+        } => {
+            free_names(argument_type, bound, names);
+            scoped_free_names(&[self_name, name], return_type, bound, names);
+        }
+        Term::Dynamic(term) => {
+            if let Some(annotated) = term.term().as_any().downcast_ref::<Annotated<T>>() {
+                free_names(&annotated.term, bound, names);
+                free_names(&annotated.ty, bound, names);
+            } else {
+                free_names(&term.term().box_clone().expand(), bound, names);
+            }
+        }
+        Term::Universe(_) | Term::Hole(_) | Term::Compressed(_) => {}
+    }
+}
+
+fn scoped_free_names<T: Clone + 'static>(
+    binders: &[&Option<String>],
+    term: &Term<T>,
+    bound: &mut Vec<String>,
+    names: &mut HashSet<String>,
+) {
+    let len = bound.len();
+    bound.extend(binders.iter().filter_map(|binder| (*binder).clone()));
+    free_names(term, bound, names);
+    bound.truncate(len);
+}
+
+/// Converts `term` back to a term with named variables in the scope of `binders`, renaming any
+/// binder of it that would capture a name used beneath it.
+fn named<T: Zero + Clone + 'static>(
+    term: AnalysisTerm<T>,
+    binders: &[(Option<String>, bool)],
+) -> Result<Term<T>, RefactorError> {
+    let mut taken: HashSet<_> = binders
+        .iter()
+        .filter_map(|(binder, _)| binder.clone())
+        .collect();
+    analysis_names(&term, &mut taken);
+
+    Namer {
+        fresh: Fresh::new(taken),
+        scope: binders
+            .iter()
+            .rev()
+            .map(|(binder, _)| binder.clone())
+            .collect(),
+    }
+    .name(term)
+}
+
+struct Namer {
+    fresh: Fresh,
+    /// The name of each binder in scope, innermost last.
+    scope: Vec<Option<String>>,
+}
+
+impl Namer {
+    fn name<T: Zero + Clone + 'static>(
+        &mut self,
+        term: AnalysisTerm<T>,
+    ) -> Result<Term<T>, RefactorError> {
+        Ok(match term {
+            AnalysisTerm::Lambda {
+                erased,
+                name,
+                body,
+                annotation,
+            } => {
+                let name = self.bind(name, &body, 1);
+                let body = self.scoped(&[&name], *body)?;
+                Term::Lambda {
+                    erased,
+                    name,
+                    body: Box::new(body),
+                    annotation,
+                }
+            }
+            AnalysisTerm::Variable(idx, annotation) => match self.variable(idx) {
+                Some(name) => Term::Reference(name, annotation),
+                None => Term::Dynamic(Dynamic::new(annotation, VariableTerm::new(idx))),
+            },
+            AnalysisTerm::Application {
+                erased,
+                function,
+                argument,
+                annotation,
+            } => Term::Application {
+                erased,
+                function: Box::new(self.name(*function)?),
+                argument: Box::new(self.name(*argument)?),
+                annotation,
+            },
+            AnalysisTerm::Put(term, annotation) => {
+                Term::Put(Box::new(self.name(*term)?), annotation)
+            }
+            AnalysisTerm::Duplication {
+                binder,
+                expression,
+                body,
+                annotation,
+            } => {
+                let expression = self.name(*expression)?;
+                let binder = self.bind(binder, &body, 1);
+                let body = self.scoped(&[&binder], *body)?;
+                Term::Duplication {
+                    binder,
+                    expression: Box::new(expression),
+                    body: Box::new(body),
+                    annotation,
+                }
+            }
+            AnalysisTerm::Reference(name, annotation) => {
+                if self.scope.contains(&Some(name.clone())) {
+                    return Err(RefactorError::Capture(name));
+                }
+                Term::Reference(name, annotation)
+            }
+            AnalysisTerm::Universe(annotation) => Term::Universe(annotation),
+            AnalysisTerm::Function {
+                erased,
+                name,
+                self_name,
+                argument_type,
+                return_type,
+                annotation,
+            } => {
+                let argument_type = self.name(*argument_type)?;
+                let self_name = self.bind(self_name, &return_type, 2);
+                self.scope.push(self_name.clone());
+                let name = self.bind(name, &return_type, 1);
+                let return_type = self.scoped(&[&name], *return_type);
+                self.scope.pop();
+                Term::Function {
+                    erased,
+                    name,
+                    self_name,
+                    argument_type: Box::new(argument_type),
+                    return_type: Box::new(return_type?),
+                    annotation,
+                }
+            }
+            AnalysisTerm::Wrap(term, annotation) => {
+                Term::Wrap(Box::new(self.name(*term)?), annotation)
+            }
+            AnalysisTerm::Hole(annotation) => Term::Hole(annotation),
+            AnalysisTerm::Annotation { term, ty, .. } => Term::Dynamic(Dynamic::new(
+                T::zero(),
+                Annotated::new(self.name(*term)?, self.name(*ty)?),
+            )),
+            term @ AnalysisTerm::Compressed(_) => term.into(),
+        })
+    }
+
+    /// The name that refers to the variable `idx`, unless it has none or a binder within it
+    /// shadows that name.
+    fn variable(&self, idx: usize) -> Option<String> {
+        let position = self.scope.len().checked_sub(idx + 1)?;
+        let name = self.scope[position].as_ref()?;
+        if self
+            .scope
+            .iter()
+            .rposition(|binder| binder.as_ref() == Some(name))
+            == Some(position)
+        {
+            Some(name.clone())
+        } else {
+            None
+        }
+    }
+
+    fn scoped<T: Zero + Clone + 'static>(
+        &mut self,
+        binders: &[&Option<String>],
+        term: AnalysisTerm<T>,
+    ) -> Result<Term<T>, RefactorError> {
+        let len = self.scope.len();
+        self.scope
+            .extend(binders.iter().map(|binder| (*binder).clone()));
+        let term = self.name(term);
+        self.scope.truncate(len);
+        term
+    }
+
+    /// The name to give a binder whose body is `body`, in which the binder is the variable
+    /// `skip - 1` and those from `skip` on are in scope here.
+    fn bind<T>(
+        &mut self,
+        name: Option<String>,
+        body: &AnalysisTerm<T>,
+        skip: usize,
+    ) -> Option<String> {
+        let mut free = HashSet::new();
+        free_variables(body, 0, &mut free);
+
+        let mut used: HashSet<_> = free
+            .iter()
+            .filter(|idx| **idx >= skip)
+            .filter_map(|idx| {
+                self.scope
+                    .len()
+                    .checked_sub(idx - skip + 1)
+                    .and_then(|position| self.scope[position].clone())
+            })
+            .collect();
+        references(body, &mut used);
+
+        match name {
+            Some(name) if !used.contains(&name) => Some(name),
+            None if !free.contains(&(skip - 1)) => None,
+            _ => Some(self.fresh.name()),
+        }
+    }
+}
+
+/// The variables of `term` bound outside of it, relative to `term`.
+fn free_variables<T>(term: &AnalysisTerm<T>, depth: usize, free: &mut HashSet<usize>) {
+    match term {
+        AnalysisTerm::Variable(idx, _) => {
+            if *idx >= depth {
+                free.insert(idx - depth);
+            }
+        }
+        AnalysisTerm::Lambda { body, .. } => free_variables(body, depth + 1, free),
+        AnalysisTerm::Application {
+            function, argument, ..
+        } => {
+            free_variables(function, depth, free);
+            free_variables(argument, depth, free);
+        }
+        AnalysisTerm::Put(term, _) | AnalysisTerm::Wrap(term, _) => {
+            free_variables(term, depth, free)
+        }
+        AnalysisTerm::Duplication {
+            expression, body, ..
+        } => {
+            free_variables(expression, depth, free);
+            free_variables(body, depth + 1, free);
+        }
+        AnalysisTerm::Function {
+            argument_type,
+            return_type,
+            ..
+        } => {
+            free_variables(argument_type, depth, free);
+            free_variables(return_type, depth + 2, free);
+        }
+        AnalysisTerm::Annotation { term, ty, .. } => {
+            free_variables(term, depth, free);
+            free_variables(ty, depth, free);
+        }
+        AnalysisTerm::Reference(..)
+        | AnalysisTerm::Universe(_)
+        | AnalysisTerm::Hole(_)
+        | AnalysisTerm::Compressed(_) => {}
+    }
+}
+
+fn references<T>(term: &AnalysisTerm<T>, names: &mut HashSet<String>) {
+    match term {
+        AnalysisTerm::Reference(name, _) => {
+            names.insert(name.clone());
+        }
+        AnalysisTerm::Lambda { body, .. } => references(body, names),
+        AnalysisTerm::Application {
+            function, argument, ..
+        } => {
+            references(function, names);
+            references(argument, names);
+        }
+        AnalysisTerm::Put(term, _) | AnalysisTerm::Wrap(term, _) => references(term, names),
+        AnalysisTerm::Duplication {
+            expression, body, ..
+        } => {
+            references(expression, names);
+            references(body, names);
+        }
+        AnalysisTerm::Function {
+            argument_type,
+            return_type,
+            ..
+        } => {
+            references(argument_type, names);
+            references(return_type, names);
+        }
+        AnalysisTerm::Annotation { term, ty, .. } => {
+            references(term, names);
+            references(ty, names);
+        }
+        AnalysisTerm::Variable(..)
+        | AnalysisTerm::Universe(_)
+        | AnalysisTerm::Hole(_)
+        | AnalysisTerm::Compressed(_) => {}
+    }
+}
+
+/// Every name in `term`, bound or not, so that fresh names can avoid them all.
+fn analysis_names<T>(term: &AnalysisTerm<T>, taken: &mut HashSet<String>) {
+    references(term, taken);
+    binder_names(term, taken);
+}
+
+fn binder_names<T>(term: &AnalysisTerm<T>, taken: &mut HashSet<String>) {
+    match term {
+        AnalysisTerm::Lambda { name, body, .. } => {
+            taken.extend(name.clone());
+            binder_names(body, taken);
+        }
+        AnalysisTerm::Application {
+            function, argument, ..
+        } => {
+            binder_names(function, taken);
+            binder_names(argument, taken);
+        }
+        AnalysisTerm::Put(term, _) | AnalysisTerm::Wrap(term, _) => binder_names(term, taken),
+        AnalysisTerm::Duplication {
+            binder,
+            expression,
+            body,
+            ..
+        } => {
+            taken.extend(binder.clone());
+            binder_names(expression, taken);
+            binder_names(body, taken);
+        }
+        AnalysisTerm::Function {
+            name,
+            self_name,
+            argument_type,
+            return_type,
+            ..
+        } => {
+            taken.extend(name.clone());
+            taken.extend(self_name.clone());
+            binder_names(argument_type, taken);
+            binder_names(return_type, taken);
+        }
+        AnalysisTerm::Annotation { term, ty, .. } => {
+            binder_names(term, taken);
+            binder_names(ty, taken);
+        }
+        AnalysisTerm::Variable(..)
+        | AnalysisTerm::Reference(..)
+        | AnalysisTerm::Universe(_)
+        | AnalysisTerm::Hole(_)
+        | AnalysisTerm::Compressed(_) => {}
+    }
+}
@@ -0,0 +1,632 @@
+use std::collections::HashSet;
+
+use combine::{stream::position, EasyParser};
+use thiserror::Error;
+use welkin::{
+    compiler::{term::Compile, LocalResolver, Resolve},
+    parser::{
+        term::{term as parse_term, Block, Literal, Term as SourceTerm},
+        AbsolutePath, Bump, Ident,
+    },
+};
+use welkin_core::term::{Index, Term as CoreTerm};
+
+use crate::literal::{CompressedChar, CompressedSize, CompressedString, CompressedWord};
+
+use super::{refactor::free_names, CompressedTerm, Term};
+
+/// The name that holes are written as, which is read back as a hole wherever it is not bound.
+const HOLE: &str = "_";
+
+#[derive(Debug, Error)]
+pub enum SourceError {
+    #[error("{0}")]
+    Parse(String),
+    #[error("parsing ended before end of input with `{0}` remaining")]
+    Remaining(String),
+    #[error("the self binder `{0}` of a non-erased function has no surface syntax")]
+    SelfBinder(String),
+}
+
+/// Prints a term as welkin source.
+///
+/// Binders keep their names where those are valid identifiers and are otherwise given fresh names
+/// that capture nothing. The self binder of a non-erased function has no surface syntax, so it is
+/// dropped where nothing refers to it and the term is refused otherwise.
+pub fn to_source(term: &Term<()>) -> Result<String, SourceError> {
+    if let Some(name) = unwritable_self(term) {
+        return Err(SourceError::SelfBinder(name));
+    }
+
+    let mut taken = HashSet::new();
+    names(term, &mut taken);
+
+    Ok(Printer {
+        fresh: Fresh::new(taken),
+        scope: vec![],
+    }
+    .print(term, Position::Term))
+}
+
+/// Reads welkin source as a term, with `~literal` blocks read as compressed literals where the
+/// scratchpad has one and `_` read as a hole.
+pub fn from_source(source: &str) -> Result<Term<()>, SourceError> {
+    let bump = Bump::new();
+
+    let (term, remainder) = parse_term(Default::default(), &bump)
+        .easy_parse(position::Stream::new(source))
+        .map_err(|e| SourceError::Parse(format!("{}", e)))?;
+
+    if !remainder.input.trim().is_empty() {
+        return Err(SourceError::Remaining(remainder.input.to_owned()));
+    }
+
+    Ok(Reader {
+        bump: &bump,
+        scope: vec![],
+    }
+    .read(term))
+}
+
+/// The first self binder of a non-erased function that its return type refers to.
+fn unwritable_self(term: &Term<()>) -> Option<String> {
+    match term {
+        Term::Function {
+            erased,
+            name,
+            self_name,
+            argument_type,
+            return_type,
+            ..
+        } => {
+            if let (false, Some(self_name)) = (erased, self_name) {
+                let mut free = HashSet::new();
+                free_names(return_type, &mut name.iter().cloned().collect(), &mut free);
+                if free.contains(self_name) {
+                    return Some(self_name.clone());
+                }
+            }
+
+            unwritable_self(argument_type).or_else(|| unwritable_self(return_type))
+        }
+        Term::Lambda { body, .. } => unwritable_self(body),
+        Term::Application {
+            function, argument, ..
+        } => unwritable_self(function).or_else(|| unwritable_self(argument)),
+        Term::Put(term, _) | Term::Wrap(term, _) => unwritable_self(term),
+        Term::Duplication {
+            expression, body, ..
+        } => unwritable_self(expression).or_else(|| unwritable_self(body)),
+        Term::Dynamic(term) => unwritable_self(&term.term().box_clone().expand()),
+        Term::Compressed(data) if literal(&**data).is_none() => unwritable_self(&data.expand()),
+        Term::Reference(..) | Term::Universe(_) | Term::Hole(_) | Term::Compressed(_) => None,
+    }
+}
+
+fn is_ident(name: &str) -> bool {
+    !name.is_empty() && name != HOLE && name.chars().all(|c| c.is_alphabetic() || c == '_')
+}
+
+fn names(term: &Term<()>, taken: &mut HashSet<String>) {
+    match term {
+        Term::Lambda { name, body, .. } => {
+            taken.extend(name.clone());
+            names(body, taken);
+        }
+        Term::Application {
+            function, argument, ..
+        } => {
+            names(function, taken);
+            names(argument, taken);
+        }
+        Term::Put(term, _) | Term::Wrap(term, _) => names(term, taken),
+        Term::Duplication {
+            binder,
+            expression,
+            body,
+            ..
+        } => {
+            taken.extend(binder.clone());
+            names(expression, taken);
+            names(body, taken);
+        }
+        Term::Reference(name, _) => {
+            taken.insert(name.clone());
+        }
+        Term::Function {
+            name,
+            self_name,
+            argument_type,
+            return_type,
+            ..
+        } => {
+            taken.extend(name.clone());
+            taken.extend(self_name.clone());
+            names(argument_type, taken);
+            names(return_type, taken);
+        }
+        Term::Dynamic(term) => names(&term.term().box_clone().expand(), taken),
+        Term::Universe(_) | Term::Hole(_) | Term::Compressed(_) => {}
+    }
+}
+
+fn core_names(term: &CoreTerm<AbsolutePath>, taken: &mut HashSet<String>) {
+    match term {
+        CoreTerm::Reference(path) => {
+            taken.insert(path.0.join("::"));
+        }
+        CoreTerm::Lambda { body, .. } => core_names(body, taken),
+        CoreTerm::Apply {
+            function, argument, ..
+        } => {
+            core_names(function, taken);
+            core_names(argument, taken);
+        }
+        CoreTerm::Put(term) | CoreTerm::Wrap(term) => core_names(term, taken),
+        CoreTerm::Duplicate { expression, body } => {
+            core_names(expression, taken);
+            core_names(body, taken);
+        }
+        CoreTerm::Function {
+            argument_type,
+            return_type,
+            ..
+        } => {
+            core_names(argument_type, taken);
+            core_names(return_type, taken);
+        }
+        CoreTerm::Annotation { expression, ty, .. } => {
+            core_names(expression, taken);
+            core_names(ty, taken);
+        }
+        CoreTerm::Variable(_) | CoreTerm::Primitive(_) | CoreTerm::Universe => {}
+    }
+}
+
+/// Binder names that are not otherwise in use, so that introducing one captures nothing.
+pub(super) struct Fresh {
+    taken: HashSet<String>,
+    next: usize,
+}
+
+impl Fresh {
+    pub(super) fn new(taken: HashSet<String>) -> Self {
+        Fresh { taken, next: 0 }
+    }
+
+    pub(super) fn name(&mut self) -> String {
+        loop {
+            let mut idx = self.next;
+            self.next += 1;
+
+            let mut name = String::new();
+            loop {
+                name.insert(0, (b'a' + (idx % 26) as u8) as char);
+                if idx < 26 {
+                    break;
+                }
+                idx = idx / 26 - 1;
+            }
+
+            if self.taken.insert(name.clone()) {
+                return name;
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Position {
+    /// Anywhere a full term is read, such as an argument or a body.
+    Term,
+    /// The type either side of an arrow, the expression of a duplication or a wrapped term.
+    Fragment,
+    /// The function of an application.
+    Head,
+}
+
+struct Printer {
+    fresh: Fresh,
+    /// The original name of each binder in scope, alongside the name it is printed with.
+    scope: Vec<(Option<String>, String)>,
+}
+
+impl Printer {
+    fn bind(&mut self, name: &Option<String>) -> String {
+        let printed = match name {
+            Some(name) if is_ident(name) => name.clone(),
+            _ => self.fresh.name(),
+        };
+        self.scope.push((name.clone(), printed.clone()));
+        printed
+    }
+
+    fn reference(&self, name: &str) -> String {
+        self.scope
+            .iter()
+            .rev()
+            .find(|(binder, _)| binder.as_deref().unwrap_or("") == name)
+            .map(|(_, printed)| printed.clone())
+            .unwrap_or_else(|| name.to_owned())
+    }
+
+    fn print(&mut self, term: &Term<()>, position: Position) -> String {
+        let grouped = match term {
+            Term::Reference(..) | Term::Hole(_) => false,
+            Term::Universe(_) | Term::Wrap(..) | Term::Application { .. } => {
+                position == Position::Head
+            }
+            Term::Compressed(data) if literal(&**data).is_some() => position == Position::Head,
+            Term::Dynamic(data) => return self.print(&data.term().box_clone().expand(), position),
+            Term::Compressed(data) => return self.print(&data.expand(), position),
+            Term::Lambda { .. }
+            | Term::Duplication { .. }
+            | Term::Put(..)
+            | Term::Function { .. } => position != Position::Term,
+        };
+
+        let printed = self.print_inner(term);
+
+        if grouped {
+            format!("({})", printed)
+        } else {
+            printed
+        }
+    }
+
+    fn print_inner(&mut self, term: &Term<()>) -> String {
+        match term {
+            Term::Lambda {
+                erased, name, body, ..
+            } => {
+                let binder = self.bind(name);
+                let body = self.print(body, Position::Term);
+                self.scope.pop();
+
+                format!("{} {} {}", binder, if *erased { "||>" } else { "|>" }, body)
+            }
+            Term::Application { .. } => self.print_application(term),
+            Term::Put(term, _) => format!(">{}", self.print(term, Position::Term)),
+            Term::Duplication {
+                binder,
+                expression,
+                body,
+                ..
+            } => {
+                let expression = self.print(expression, Position::Fragment);
+                let binder = self.bind(binder);
+                let body = self.print(body, Position::Term);
+                self.scope.pop();
+
+                format!("{} < {} {}", binder, expression, body)
+            }
+            Term::Reference(name, _) => self.reference(name),
+            Term::Universe(_) => "*".into(),
+            Term::Function {
+                erased,
+                name,
+                self_name,
+                argument_type,
+                return_type,
+                ..
+            } => {
+                let argument_type = self.print(argument_type, Position::Fragment);
+                let self_binder = self.bind(self_name);
+                let binder = self.bind(name);
+                let return_type = if let Term::Function { .. } = **return_type {
+                    self.print(return_type, Position::Term)
+                } else {
+                    self.print(return_type, Position::Fragment)
+                };
+                self.scope.pop();
+                self.scope.pop();
+
+                let binder = if name.is_some() {
+                    format!(" ~as {}", binder)
+                } else {
+                    String::new()
+                };
+                let arrow = match (erased, self_name) {
+                    (false, _) => "->".into(),
+                    (true, None) => "|->".into(),
+                    (true, Some(_)) => format!("|-{}->", self_binder),
+                };
+
+                format!("{}{} {} {}", argument_type, binder, arrow, return_type)
+            }
+            Term::Wrap(term, _) => format!("'{}", self.print(term, Position::Fragment)),
+            Term::Hole(_) => HOLE.into(),
+            Term::Compressed(data) => literal(&**data).unwrap(),
+            Term::Dynamic(_) => unreachable!(),
+        }
+    }
+
+    /// Prints an application spine as few calls as the surface syntax allows, each taking a run of
+    /// erased arguments and then a run of concrete ones.
+    fn print_application(&mut self, mut term: &Term<()>) -> String {
+        let mut arguments = vec![];
+
+        while let Term::Application {
+            erased,
+            function,
+            argument,
+            ..
+        } = term
+        {
+            arguments.push((*erased, &**argument));
+            term = &**function;
+        }
+
+        let mut printed = self.print(term, Position::Head);
+        let mut arguments = arguments.into_iter().rev().peekable();
+        let mut first = true;
+
+        while arguments.peek().is_some() {
+            if !first {
+                printed = format!("({})", printed);
+            }
+            first = false;
+
+            for erased in [true, false] {
+                let mut run = vec![];
+                while let Some((_, argument)) = arguments.next_if(|(e, _)| *e == erased) {
+                    run.push(self.print(argument, Position::Term));
+                }
+                if !run.is_empty() {
+                    let (open, close) = if erased { ('[', ']') } else { ('(', ')') };
+                    printed.push_str(&format!("{}{}{}", open, run.join(", "), close));
+                }
+            }
+        }
+
+        printed
+    }
+}
+
+/// Prints a compressed term as a `~literal` block, if it is one the surface syntax has.
+fn literal(data: &dyn CompressedTerm<()>) -> Option<String> {
+    Some(if let Some(size) = data.downcast_ref::<CompressedSize>() {
+        format!("~literal Size {}", size.size())
+    } else if let Some(word) = data.downcast_ref::<CompressedWord>() {
+        format!(
+            "~literal Word {}",
+            word.bits()
+                .iter()
+                .map(|bit| if *bit { '1' } else { '0' })
+                .collect::<String>()
+        )
+    } else if let Some(character) = data.downcast_ref::<CompressedChar>() {
+        format!("~literal Char '{}'", character.char())
+    } else if let Some(string) = data.downcast_ref::<CompressedString>() {
+        format!(
+            "~literal String \"{}\"",
+            string.data().replace('\\', "\\\\").replace('"', "\\\"")
+        )
+    } else {
+        return None;
+    })
+}
+
+fn reference(name: &str) -> Term<()> {
+    Term::Reference(name.into(), ())
+}
+
+fn apply(erased: bool, function: Term<()>, argument: Term<()>) -> Term<()> {
+    Term::Application {
+        erased,
+        function: Box::new(function),
+        argument: Box::new(argument),
+        annotation: (),
+    }
+}
+
+struct Reader<'a> {
+    bump: &'a Bump,
+    scope: Vec<Option<String>>,
+}
+
+impl<'a> Reader<'a> {
+    fn scoped<T>(&mut self, names: &[Option<String>], call: impl FnOnce(&mut Self) -> T) -> T {
+        self.scope.extend(names.iter().cloned());
+        let data = call(self);
+        self.scope.truncate(self.scope.len() - names.len());
+        data
+    }
+
+    fn read(&mut self, term: SourceTerm<'a>) -> Term<()> {
+        match term {
+            SourceTerm::Universe => Term::Universe(()),
+            SourceTerm::Lambda {
+                argument,
+                body,
+                erased,
+            } => {
+                let name = Some(argument.0.to_string());
+                let body = self.scoped(&[name.clone()], |this| this.read(body.clone_inner()));
+                Term::Lambda {
+                    erased,
+                    name,
+                    body: Box::new(body),
+                    annotation: (),
+                }
+            }
+            SourceTerm::Reference(path) => {
+                let name = path
+                    .0
+                    .iter()
+                    .map(|ident| ident.0.to_string())
+                    .collect::<Vec<_>>()
+                    .join("::");
+
+                if name == HOLE && !self.scope.contains(&Some(name.clone())) {
+                    Term::Hole(())
+                } else {
+                    reference(&name)
+                }
+            }
+            SourceTerm::Application {
+                function,
+                erased,
+                arguments,
+            } => {
+                let mut term = self.read(function.clone_inner());
+                for argument in arguments {
+                    term = apply(erased, term, self.read(argument));
+                }
+                term
+            }
+            SourceTerm::Duplicate {
+                binding,
+                expression,
+                body,
+            } => {
+                let expression = self.read(expression.clone_inner());
+                let binder = Some(binding.0.to_string());
+                let body = self.scoped(&[binder.clone()], |this| this.read(body.clone_inner()));
+                Term::Duplication {
+                    binder,
+                    expression: Box::new(expression),
+                    body: Box::new(body),
+                    annotation: (),
+                }
+            }
+            SourceTerm::Wrap(term) => Term::Wrap(Box::new(self.read(term.clone_inner())), ()),
+            SourceTerm::Put(term) => Term::Put(Box::new(self.read(term.clone_inner())), ()),
+            SourceTerm::Function {
+                self_binding,
+                argument_binding,
+                argument_type,
+                erased,
+                return_type,
+            } => {
+                let argument_type = self.read(argument_type.clone_inner());
+                let self_name = self_binding.map(|ident| ident.0.to_string());
+                let name = argument_binding.map(|ident| ident.0.to_string());
+                let return_type = self.scoped(&[self_name.clone(), name.clone()], |this| {
+                    this.read(return_type.clone_inner())
+                });
+                Term::Function {
+                    erased,
+                    name,
+                    self_name,
+                    argument_type: Box::new(argument_type),
+                    return_type: Box::new(return_type),
+                    annotation: (),
+                }
+            }
+            SourceTerm::Block(Block::Literal(literal, _)) => self.read_literal(literal),
+            SourceTerm::Block(block) => {
+                let mut resolver = LocalResolver::new();
+                for name in &self.scope {
+                    resolver = resolver.descend(
+                        name.as_ref()
+                            .map(|name| Ident::from_str(name.as_str(), self.bump)),
+                    );
+                }
+
+                let term = SourceTerm::Block(block).compile(resolver);
+
+                let mut taken: HashSet<_> = self.scope.iter().flatten().cloned().collect();
+                core_names(&term, &mut taken);
+
+                self.read_core(term, &mut Fresh::new(taken))
+            }
+        }
+    }
+
+    fn read_literal(&mut self, literal: Literal<'a>) -> Term<()> {
+        match literal {
+            Literal::Size(size) => Term::Compressed(Box::new(CompressedSize::new(size))),
+            Literal::Word(bits) => Term::Compressed(Box::new(CompressedWord::new(bits))),
+            Literal::Char(character) => Term::Compressed(Box::new(CompressedChar::new(character))),
+            Literal::String(string) => Term::Compressed(Box::new(CompressedString::new(string))),
+            Literal::Vector { ty, elements } => {
+                let ty = self.read(ty.clone_inner());
+
+                let cons = apply(true, reference("Vector::cons"), ty.clone());
+                let mut term = apply(true, reference("Vector::nil"), ty);
+
+                for (idx, element) in elements.into_iter().rev().enumerate() {
+                    let call = apply(
+                        true,
+                        cons.clone(),
+                        Term::Compressed(Box::new(CompressedSize::new(idx))),
+                    );
+                    term = apply(false, apply(false, call, self.read(element)), term);
+                }
+
+                term
+            }
+        }
+    }
+
+    /// Reads a compiled block back into the scratchpad, naming the binders it introduces.
+    fn read_core(&mut self, term: CoreTerm<AbsolutePath>, fresh: &mut Fresh) -> Term<()> {
+        match term {
+            CoreTerm::Variable(Index(idx)) => self
+                .scope
+                .iter()
+                .rev()
+                .nth(idx)
+                .cloned()
+                .flatten()
+                .map(|name| reference(&name))
+                .unwrap_or(Term::Hole(())),
+            CoreTerm::Lambda { body, erased } => {
+                let name = Some(fresh.name());
+                let body = self.scoped(&[name.clone()], |this| this.read_core(*body, fresh));
+                Term::Lambda {
+                    erased,
+                    name,
+                    body: Box::new(body),
+                    annotation: (),
+                }
+            }
+            CoreTerm::Apply {
+                function,
+                argument,
+                erased,
+            } => {
+                let function = self.read_core(*function, fresh);
+                apply(erased, function, self.read_core(*argument, fresh))
+            }
+            CoreTerm::Put(term) => Term::Put(Box::new(self.read_core(*term, fresh)), ()),
+            CoreTerm::Duplicate { expression, body } => {
+                let expression = self.read_core(*expression, fresh);
+                let binder = Some(fresh.name());
+                let body = self.scoped(&[binder.clone()], |this| this.read_core(*body, fresh));
+                Term::Duplication {
+                    binder,
+                    expression: Box::new(expression),
+                    body: Box::new(body),
+                    annotation: (),
+                }
+            }
+            CoreTerm::Reference(path) => reference(&path.0.join("::")),
+            CoreTerm::Universe => Term::Universe(()),
+            CoreTerm::Function {
+                argument_type,
+                return_type,
+                erased,
+            } => {
+                let argument_type = self.read_core(*argument_type, fresh);
+                let self_name = Some(fresh.name());
+                let name = Some(fresh.name());
+                let return_type = self.scoped(&[self_name.clone(), name.clone()], |this| {
+                    this.read_core(*return_type, fresh)
+                });
+                Term::Function {
+                    erased,
+                    name,
+                    self_name,
+                    argument_type: Box::new(argument_type),
+                    return_type: Box::new(return_type),
+                    annotation: (),
+                }
+            }
+            CoreTerm::Annotation { expression, .. } => self.read_core(*expression, fresh),
+            CoreTerm::Wrap(term) => Term::Wrap(Box::new(self.read_core(*term, fresh)), ()),
+            CoreTerm::Primitive(_) => Term::Hole(()),
+        }
+    }
+}
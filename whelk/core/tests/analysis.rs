@@ -0,0 +1,125 @@
+use welkin_core::term::MapCache;
+use whelk_core::zipper::analysis::{AnalysisError, AnalysisTerm, StratificationError};
+
+mod common;
+use common::{analysis, Defs, ID, ID_TYPE};
+
+fn check(source: &str, ty: &str, defs: &Defs) -> Result<(), AnalysisError<Option<()>>> {
+    analysis(source).check_in(
+        &analysis(ty),
+        defs,
+        &mut |_, _| {},
+        &mut |_, _| {},
+        &mut MapCache::new(),
+    )
+}
+
+#[test]
+fn checks_identity() {
+    check(ID, ID_TYPE, &Defs::default()).unwrap();
+}
+
+#[test]
+fn rejects_erasure_mismatch() {
+    assert!(matches!(
+        check("A |> x |> x", ID_TYPE, &Defs::default()),
+        Err(AnalysisError::ErasureMismatch { .. })
+    ));
+}
+
+#[test]
+fn rejects_wrong_return_type() {
+    assert!(matches!(
+        check("A ||> x |> A", ID_TYPE, &Defs::default()),
+        Err(AnalysisError::TypeError { .. })
+    ));
+}
+
+#[test]
+fn rejects_unbound_reference() {
+    assert!(matches!(
+        check("missing", ID_TYPE, &Defs::default()),
+        Err(AnalysisError::UnboundReference { name, .. }) if name == "missing"
+    ));
+}
+
+#[test]
+fn checks_against_definitions() {
+    let defs = Defs::default().with("id", ID_TYPE, ID);
+
+    check("id", ID_TYPE, &defs).unwrap();
+    check("B ||> id[B]", ID_TYPE, &defs).unwrap();
+}
+
+#[test]
+fn fills_holes() {
+    let mut filled = vec![];
+
+    analysis("A ||> x |> _")
+        .check_in(
+            &analysis(ID_TYPE),
+            &Defs::default(),
+            &mut |_, _| {},
+            &mut |annotation, ty| filled.push((annotation.is_some(), ty.clone())),
+            &mut MapCache::new(),
+        )
+        .unwrap();
+
+    assert_eq!(filled.len(), 1);
+    assert!(matches!(filled[0], (true, AnalysisTerm::Variable(..))));
+}
+
+#[test]
+fn infers_reference_type() {
+    let defs = Defs::default().with("id", ID_TYPE, ID);
+
+    let ty = analysis("id")
+        .infer_in(&defs, &mut |_, _| {}, &mut |_, _| {}, &mut MapCache::new())
+        .unwrap();
+
+    assert_eq!(format!("{:?}", ty), format!("{:?}", analysis(ID_TYPE)));
+}
+
+#[test]
+fn infers_universe() {
+    let ty = analysis("*")
+        .infer_in(
+            &Defs::default(),
+            &mut |_, _| {},
+            &mut |_, _| {},
+            &mut MapCache::new(),
+        )
+        .unwrap();
+
+    assert!(matches!(ty, AnalysisTerm::Universe(_)));
+}
+
+#[test]
+fn normalizes_application() {
+    let defs = Defs::default().with("id", ID_TYPE, ID);
+
+    let mut normalized = analysis("id[*](*)");
+    normalized.normalize_in(&defs).unwrap();
+
+    assert!(matches!(normalized, AnalysisTerm::Universe(_)));
+}
+
+#[test]
+fn stratification() {
+    analysis(ID).is_stratified().unwrap();
+
+    assert!(matches!(
+        analysis("x |> x(x)").is_stratified(),
+        Err(StratificationError::MultiplicityMismatch)
+    ));
+}
+
+#[test]
+fn detects_recursion() {
+    let defs = Defs::default()
+        .with("loop", ID_TYPE, "loop")
+        .with("id", ID_TYPE, ID);
+
+    assert!(analysis("loop").is_recursive_in(&defs));
+    assert!(!analysis("id").is_recursive_in(&defs));
+}
@@ -0,0 +1,65 @@
+//! Terms and definitions shared by the tests of analysis, refactoring, completion and search.
+#![allow(dead_code)]
+
+use std::{cell::RefCell, collections::HashMap, rc::Rc};
+
+use welkin::Terms;
+use welkin_core::term::Term;
+use whelk_core::{
+    worker::DefWrapper,
+    zipper::{
+        analysis::{AnalysisTerm, DefinitionResult, TypedDefinitions},
+        source::from_source,
+        Cursor,
+    },
+};
+
+pub type Analysis = AnalysisTerm<Option<()>>;
+
+pub const ID_TYPE: &str = "* ~as A |-> A ~as x -> A";
+pub const ID: &str = "A ||> x |> x";
+
+pub fn analysis(source: &str) -> Analysis {
+    Cursor::from(from_source(source).unwrap()).into()
+}
+
+/// As `analysis`, for terms whose annotations are of another type.
+pub fn unannotated<T>(source: &str) -> AnalysisTerm<Option<T>> {
+    analysis(source).map_annotation(&mut |_| None)
+}
+
+/// Definitions each given by their type and term in source.
+#[derive(Default)]
+pub struct Defs(HashMap<String, (Analysis, Analysis)>);
+
+impl Defs {
+    pub fn with(mut self, name: &str, ty: &str, term: &str) -> Self {
+        self.0
+            .insert(name.to_owned(), (analysis(ty), analysis(term)));
+        self
+    }
+}
+
+impl TypedDefinitions<Option<()>> for Defs {
+    fn get_typed(&self, name: &str) -> Option<DefinitionResult<(Analysis, Analysis)>> {
+        self.0.get(name).map(DefinitionResult::Borrowed)
+    }
+}
+
+/// The definitions a worker holds, each given by its name, type and term in source.
+pub fn wrapper(definitions: &[(&str, &str, &str)]) -> DefWrapper {
+    let mut defs = HashMap::new();
+    for (name, ty, term) in definitions {
+        defs.insert(
+            name.to_string(),
+            (
+                Term::<String>::from(analysis(ty)),
+                Term::<String>::from(analysis(term)),
+            ),
+        );
+    }
+    DefWrapper(
+        Rc::new(RefCell::new(defs)),
+        Rc::new(Terms { data: vec![] }.into()),
+    )
+}
@@ -0,0 +1,93 @@
+use welkin_core::term::MapCache;
+use whelk_core::{
+    completion::{complete, Fit},
+    worker::DefWrapper,
+    zipper::{analysis::AnalysisTerm, source::to_source},
+};
+
+mod common;
+use common::{unannotated, wrapper, ID, ID_TYPE};
+
+fn defs() -> DefWrapper {
+    wrapper(&[("id", ID_TYPE, ID), ("type", "*", "*"), ("other", "*", "*")])
+}
+
+fn lambda(erased: bool, name: &str, body: AnalysisTerm<Option<u64>>) -> AnalysisTerm<Option<u64>> {
+    AnalysisTerm::Lambda {
+        erased,
+        name: Some(name.into()),
+        body: Box::new(body),
+        annotation: None,
+    }
+}
+
+fn hole() -> AnalysisTerm<Option<u64>> {
+    AnalysisTerm::Hole(Some(0))
+}
+
+fn fits(term: AnalysisTerm<Option<u64>>, ty: &str, prefix: &str) -> Vec<(String, Fit, String)> {
+    complete(
+        &term,
+        &unannotated(ty),
+        &0,
+        prefix,
+        &defs(),
+        &mut MapCache::new(),
+    )
+    .into_iter()
+    .map(|completion| {
+        let term = to_source(&completion.term()).unwrap();
+        (completion.name, completion.fit, term)
+    })
+    .collect()
+}
+
+#[test]
+fn ranks_by_fit_to_goal() {
+    assert_eq!(
+        fits(hole(), "* ~as x -> *", ""),
+        vec![
+            ("id".into(), Fit::Fits(1), "id[_]".into()),
+            ("other".into(), Fit::Mismatch, "other".into()),
+            ("type".into(), Fit::Mismatch, "type".into()),
+        ]
+    );
+}
+
+#[test]
+fn ranks_locals_by_their_types() {
+    assert_eq!(
+        fits(lambda(false, "f", hole()), "(* ~as x -> *) ~as f -> *", ""),
+        vec![
+            ("other".into(), Fit::Fits(0), "other".into()),
+            ("type".into(), Fit::Fits(0), "type".into()),
+            ("f".into(), Fit::Fits(1), "f(_)".into()),
+            ("id".into(), Fit::Fits(2), "id[_](_)".into()),
+        ]
+    );
+
+    assert_eq!(
+        fits(
+            lambda(true, "A", lambda(false, "x", hole())),
+            "* ~as A |-> A ~as x -> A",
+            "",
+        )[0],
+        ("x".into(), Fit::Fits(0), "x".into())
+    );
+}
+
+#[test]
+fn filters_by_prefix_and_shadows_definitions() {
+    let completions = complete(
+        &lambda(false, "id", hole()),
+        &unannotated("* ~as id -> *"),
+        &0,
+        "i",
+        &defs(),
+        &mut MapCache::new(),
+    );
+
+    assert_eq!(completions.len(), 1);
+    assert!(completions[0].local);
+    assert_eq!(completions[0].fit, Fit::Fits(0));
+}
@@ -0,0 +1,21 @@
+use welkin::Terms;
+use whelk_core::differential::{Differential, Generator};
+
+#[test]
+fn generated_terms_agree() {
+    let mut differential = Differential::new(Terms { data: vec![] });
+    let mut generator = Generator::new(0, 4);
+
+    for _ in 0..200 {
+        let (ty, term) = generator.generate();
+        let discrepancies = differential.compare(&ty, &term);
+
+        assert!(
+            discrepancies.is_empty(),
+            "{:?} : {:?}\n{:?}",
+            term,
+            ty,
+            discrepancies
+        );
+    }
+}
@@ -0,0 +1,98 @@
+use whelk_core::{
+    history::{location, History},
+    zipper::{
+        source::{from_source, to_source},
+        Cursor,
+    },
+};
+
+fn term(source: &str) -> whelk_core::zipper::Term {
+    from_source(source).unwrap()
+}
+
+fn source(term: Option<whelk_core::zipper::Term>) -> String {
+    to_source(&term.expect("no state to move to")).unwrap()
+}
+
+#[test]
+fn undoes_and_redoes() {
+    let mut history: History = History::new(&term("_"), 1 << 20);
+
+    history.record(&term("f"), None);
+    history.record(&term("f(x)"), None);
+
+    assert_eq!(source(history.undo()), "f");
+    assert_eq!(source(history.undo()), "_");
+    assert!(history.undo().is_none());
+
+    assert_eq!(source(history.redo()), "f");
+    assert_eq!(source(history.redo()), "f(x)");
+    assert!(history.redo().is_none());
+}
+
+#[test]
+fn recording_discards_undone_states() {
+    let mut history: History = History::new(&term("_"), 1 << 20);
+
+    history.record(&term("f"), None);
+    history.undo();
+    history.record(&term("g"), None);
+
+    assert!(!history.can_redo());
+    assert_eq!(source(history.undo()), "_");
+}
+
+#[test]
+fn ignores_unchanged_terms() {
+    let mut history: History = History::new(&term("f"), 1 << 20);
+
+    history.record(&term("f"), None);
+
+    assert!(!history.can_undo());
+}
+
+#[test]
+fn coalesces_edits_in_one_group() {
+    let mut history = History::new(&term("_"), 1 << 20);
+
+    history.record(&term("a"), Some(0));
+    history.record(&term("ab"), Some(0));
+    history.record(&term("abc"), Some(0));
+    history.record(&term("abc(_)"), Some(1));
+
+    assert_eq!(source(history.undo()), "abc");
+    assert_eq!(source(history.undo()), "_");
+
+    // an edit after undoing does not overwrite the state undone to
+    history.redo();
+    history.record(&term("abcd"), Some(0));
+    assert_eq!(source(history.undo()), "abc");
+}
+
+#[test]
+fn stays_within_budget() {
+    let mut history: History = History::new(&term("_"), 64);
+
+    for idx in 0..100 {
+        history.record(&term(&format!("f{}", "x".repeat(idx % 7 + 1))), None);
+        assert!(history.used() <= 64);
+    }
+
+    let mut undone = 0;
+    while history.undo().is_some() {
+        undone += 1;
+    }
+    assert!(undone > 0 && undone < 100);
+}
+
+#[test]
+fn locations_distinguish_position_and_kind() {
+    let focus = |source: &str| match Cursor::from(term(source)) {
+        Cursor::Lambda(cursor) => location(&cursor.body()),
+        _ => panic!("not a lambda"),
+    };
+
+    assert_eq!(focus("x |> y"), focus("z |> w"));
+    assert_ne!(focus("x |> y"), focus("x |> _"));
+    assert_ne!(focus("x |> y"), location(&Cursor::from(term("y"))));
+}
@@ -0,0 +1,182 @@
+use std::collections::HashMap;
+
+use uuid::Uuid;
+use welkin::Terms;
+use welkin_core::term::Term;
+use whelk_core::{
+    pool::Pool,
+    worker::{CheckError, DefWrapperData, WorkerRequest, WorkerRequestVariant},
+    zipper::analysis::AnalysisTerm,
+};
+
+fn initialize() -> WorkerRequestVariant {
+    WorkerRequestVariant::Initialize(DefWrapperData(HashMap::new(), Terms { data: vec![] }))
+}
+
+fn register(name: &str) -> WorkerRequestVariant {
+    WorkerRequestVariant::Register(name.into(), Term::Universe, Term::Universe)
+}
+
+fn temp_defs() -> WorkerRequestVariant {
+    WorkerRequestVariant::TempDefs(vec![("y".into(), Term::Universe, Term::Universe)])
+}
+
+fn check(temp: Option<Uuid>) -> WorkerRequestVariant {
+    WorkerRequestVariant::Check(
+        AnalysisTerm::Universe(None),
+        AnalysisTerm::Universe(None),
+        temp,
+    )
+}
+
+/// Routes and sends `variant`, encoded as its id, returning the id and the workers it went to.
+fn send(pool: &mut Pool, variant: WorkerRequestVariant) -> (Uuid, Vec<usize>) {
+    let target = pool.route(&variant).unwrap();
+    let request = WorkerRequest {
+        idx: Uuid::new_v4(),
+        variant,
+        limit: None,
+    };
+    let slots = pool.send(&request, target, request.idx.as_bytes().to_vec());
+    (request.idx, slots)
+}
+
+/// Has the worker `slot` answer every request sent to it.
+fn answer_all(pool: &mut Pool, slot: usize) {
+    while let Some(idx) = pool.running(slot) {
+        pool.answered(slot, idx);
+    }
+}
+
+/// The ids of the requests replayed to a fresh worker replacing `slot`.
+fn replayed(pool: &Pool, slot: usize) -> Vec<Uuid> {
+    pool.replay(slot)
+        .iter()
+        .map(|data| Uuid::from_slice(data).unwrap())
+        .collect()
+}
+
+#[test]
+fn routes_to_the_least_busy_worker() {
+    let mut pool = Pool::new(2);
+
+    let (first, slots) = send(&mut pool, check(None));
+    assert_eq!(slots, vec![0]);
+    let (_, slots) = send(&mut pool, check(None));
+    assert_eq!(slots, vec![1]);
+
+    pool.answered(0, first);
+    let (_, slots) = send(&mut pool, check(None));
+    assert_eq!(slots, vec![0]);
+}
+
+#[test]
+fn sends_definitions_to_every_worker() {
+    let mut pool = Pool::new(3);
+
+    assert_eq!(send(&mut pool, initialize()).1, vec![0, 1, 2]);
+    assert_eq!(send(&mut pool, register("x")).1, vec![0, 1, 2]);
+}
+
+#[test]
+fn pins_checks_to_the_worker_holding_their_temporary_definitions() {
+    let mut pool = Pool::new(2);
+
+    send(&mut pool, check(None));
+    let (temp, slots) = send(&mut pool, temp_defs());
+    assert_eq!(slots, vec![1]);
+
+    // the pinned worker is busier, and still chosen
+    assert_eq!(pool.route(&check(Some(temp))).unwrap(), Some(1));
+    assert_eq!(
+        pool.route(&WorkerRequestVariant::ClearTempDefs(temp))
+            .unwrap(),
+        Some(1)
+    );
+
+    send(&mut pool, WorkerRequestVariant::ClearTempDefs(temp));
+    assert!(matches!(
+        pool.route(&check(Some(temp))),
+        Err(CheckError::UnknownTempDefs(id)) if id == temp
+    ));
+}
+
+#[test]
+fn tracks_the_request_each_worker_is_handling() {
+    let mut pool = Pool::new(1);
+    assert_eq!(pool.running(0), None);
+
+    let (first, _) = send(&mut pool, check(None));
+    let (second, _) = send(&mut pool, check(None));
+    assert_eq!(pool.running(0), Some(first));
+
+    pool.answered(0, first);
+    assert_eq!(pool.running(0), Some(second));
+}
+
+#[test]
+fn reports_which_workers_are_handling_interrupted_requests() {
+    let mut pool = Pool::new(2);
+
+    let (running, _) = send(&mut pool, check(None));
+    send(&mut pool, check(None));
+    let (queued, _) = send(&mut pool, check(None));
+    let (everywhere, _) = send(&mut pool, register("x"));
+
+    assert_eq!(pool.interrupt(queued), vec![(0, false)]);
+    assert_eq!(pool.interrupt(running), vec![(0, true)]);
+    assert_eq!(pool.interrupt(everywhere), vec![(0, true), (1, false)]);
+    assert!(pool.interrupt(everywhere).is_empty());
+}
+
+#[test]
+fn replays_the_state_of_a_worker_then_what_it_has_yet_to_answer() {
+    let mut pool = Pool::new(2);
+
+    let (initialized, _) = send(&mut pool, initialize());
+    let (registered, _) = send(&mut pool, register("x"));
+    answer_all(&mut pool, 0);
+    answer_all(&mut pool, 1);
+
+    let (temp, _) = send(&mut pool, temp_defs());
+    // the first worker is still busy with the temporary definitions
+    let (checked, _) = send(&mut pool, check(None));
+
+    assert_eq!(replayed(&pool, 0), vec![initialized, registered, temp]);
+    assert_eq!(replayed(&pool, 1), vec![initialized, registered, checked]);
+
+    // requests still waiting on an answer are replayed once, in the order they were sent
+    let (other, _) = send(&mut pool, register("y"));
+    assert_eq!(
+        replayed(&pool, 0),
+        vec![initialized, registered, temp, other]
+    );
+}
+
+#[test]
+fn replaces_registrations_of_the_same_name() {
+    let mut pool = Pool::new(1);
+
+    let (initialized, _) = send(&mut pool, initialize());
+    send(&mut pool, register("x"));
+    let (y, _) = send(&mut pool, register("y"));
+    let (x, _) = send(&mut pool, register("x"));
+    answer_all(&mut pool, 0);
+
+    assert_eq!(replayed(&pool, 0), vec![initialized, y, x]);
+}
+
+#[test]
+fn reinitializing_forgets_registrations_but_keeps_temporary_definitions() {
+    let mut pool = Pool::new(1);
+
+    send(&mut pool, initialize());
+    send(&mut pool, register("x"));
+    let (temp, _) = send(&mut pool, temp_defs());
+    let (initialized, _) = send(&mut pool, initialize());
+    let (cleared, _) = send(&mut pool, temp_defs());
+    send(&mut pool, WorkerRequestVariant::ClearTempDefs(cleared));
+    answer_all(&mut pool, 0);
+
+    assert_eq!(replayed(&pool, 0), vec![temp, initialized]);
+}
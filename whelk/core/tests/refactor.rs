@@ -0,0 +1,172 @@
+use welkin_core::term::MapCache;
+use whelk_core::{
+    history::location,
+    zipper::{
+        at_location,
+        source::{from_source, to_source},
+        Cursor, RefactorError,
+    },
+};
+
+mod common;
+use common::{analysis, Analysis, Defs, ID, ID_TYPE};
+
+fn cursor(source: &str) -> Cursor {
+    Cursor::from(from_source(source).unwrap())
+}
+
+/// The whole term around `cursor`, printed so that it compares equal to `expected` printed.
+fn assert_source(cursor: Cursor, expected: &str) {
+    let mut cursor = cursor;
+    while !cursor.is_top() {
+        cursor = cursor.ascend();
+    }
+    assert_eq!(
+        to_source(&cursor.into()).unwrap(),
+        to_source(&from_source(expected).unwrap()).unwrap()
+    );
+}
+
+fn body(cursor: Cursor) -> Cursor {
+    match cursor {
+        Cursor::Lambda(cursor) => cursor.body(),
+        _ => panic!("not a lambda"),
+    }
+}
+
+fn function(cursor: Cursor) -> Cursor {
+    match cursor {
+        Cursor::Application(cursor) => cursor.function(),
+        _ => panic!("not an application"),
+    }
+}
+
+fn defs() -> Defs {
+    Defs::default().with("id", ID_TYPE, ID)
+}
+
+#[test]
+fn beta_reduces_under_binders() {
+    let reduced = body(cursor("y |> (x |> z |> x)(y)")).beta_reduce().unwrap();
+    assert_source(reduced, "y |> z |> y");
+}
+
+#[test]
+fn beta_reduction_renames_capturing_binders() {
+    let reduced = body(cursor("y |> (x |> y |> x)(y)")).beta_reduce().unwrap();
+    assert_source(reduced, "y |> a |> y");
+}
+
+#[test]
+fn beta_reduction_needs_a_redex() {
+    assert!(matches!(
+        cursor("f(*)").beta_reduce(),
+        Err(RefactorError::NotARedex)
+    ));
+    assert!(matches!(
+        cursor("(x ||> x)(*)").beta_reduce(),
+        Err(RefactorError::NotARedex)
+    ));
+}
+
+#[test]
+fn eta_expands_to_function_type() {
+    let expanded = cursor("f").eta_expand(&analysis("* ~as A -> *")).unwrap();
+    assert_source(expanded, "A |> f(A)");
+
+    assert!(matches!(
+        cursor("f").eta_expand(&analysis("*")),
+        Err(RefactorError::NotAFunction)
+    ));
+}
+
+#[test]
+fn wraps_in_lambda_without_capture() {
+    assert!(matches!(
+        body(cursor("x |> x")).wrap_lambda(false, "x".into()),
+        Err(RefactorError::Capture(name)) if name == "x"
+    ));
+
+    let wrapped = body(cursor("x |> x"))
+        .wrap_lambda(true, "y".into())
+        .unwrap();
+    assert_source(wrapped, "x |> y ||> x");
+}
+
+#[test]
+fn wraps_in_application() {
+    let wrapped = cursor("f").wrap_application(false);
+    assert_source(wrapped, "f(_)");
+}
+
+#[test]
+fn annotation_is_checked() {
+    let annotate = |ty: &str| {
+        let mut annotated = body(cursor(ID)).wrap_annotation(from_source(ty).unwrap());
+        while !annotated.is_top() {
+            annotated = annotated.ascend();
+        }
+        Analysis::from(annotated).check_in(
+            &analysis(ID_TYPE),
+            &defs(),
+            &mut |_, _| {},
+            &mut |_, _| {},
+            &mut MapCache::new(),
+        )
+    };
+
+    annotate("A ~as y -> A").unwrap();
+    assert!(annotate("* ~as y -> *").is_err());
+}
+
+#[test]
+fn extracts_with_free_variables_abstracted() {
+    let focused = body(body(cursor("A ||> x |> f(x)")));
+    let (replaced, definition) = focused.extract("g".into()).unwrap();
+
+    assert_source(replaced, "A ||> x |> g(x)");
+    assert_eq!(
+        to_source(&definition).unwrap(),
+        to_source(&from_source("define(g, _ ~as x -> _, x |> f(x))").unwrap()).unwrap()
+    );
+
+    assert!(matches!(
+        body(cursor("g |> g")).extract("g".into()),
+        Err(RefactorError::Capture(_))
+    ));
+}
+
+#[test]
+fn inlines_and_reduces_definitions() {
+    let defs = defs();
+
+    let inlined = function(function(cursor("id[*](*)")))
+        .inline(&defs)
+        .unwrap();
+    assert_source(inlined.clone(), "(A ||> x |> x)[*](*)");
+
+    let reduced = inlined.ascend().beta_reduce().unwrap();
+    assert_source(reduced, "(x |> x)(*)");
+
+    assert!(matches!(
+        cursor("missing").inline(&defs),
+        Err(RefactorError::Undefined(name)) if name == "missing"
+    ));
+    assert!(matches!(
+        body(cursor("id |> id")).inline(&defs),
+        Err(RefactorError::NotAReference)
+    ));
+}
+
+#[test]
+fn finds_cursors_at_locations() {
+    let argument = match body(cursor("x |> f(x)")) {
+        Cursor::Application(cursor) => cursor.argument(),
+        _ => panic!("not an application"),
+    };
+
+    let found = at_location(from_source("x |> f(x)").unwrap(), &location(&argument)).unwrap();
+    assert_eq!(location(&found), location(&argument));
+
+    assert!(at_location(from_source("x |> f").unwrap(), &location(&argument)).is_none());
+}
@@ -0,0 +1,82 @@
+use welkin_core::term::MapCache;
+use whelk_core::zipper::analysis::AnalysisTerm;
+
+mod common;
+use common::{analysis, unannotated, wrapper, ID_TYPE};
+
+fn hole() -> AnalysisTerm<Option<u64>> {
+    AnalysisTerm::Hole(Some(0))
+}
+
+fn lambda(erased: bool, body: AnalysisTerm<Option<u64>>) -> AnalysisTerm<Option<u64>> {
+    AnalysisTerm::Lambda {
+        erased,
+        name: None,
+        body: Box::new(body),
+        annotation: None,
+    }
+}
+
+#[test]
+fn finds_local_variables() {
+    let defs = wrapper(&[]);
+
+    let solutions = lambda(true, lambda(false, hole())).search_in(
+        &unannotated(ID_TYPE),
+        &0,
+        &defs.names(),
+        &defs,
+        1,
+        &mut || false,
+        &mut MapCache::new(),
+    );
+
+    assert!(matches!(
+        solutions.as_slice(),
+        [AnalysisTerm::Variable(0, ())]
+    ));
+}
+
+#[test]
+fn finds_smallest_applications_of_definitions() {
+    let defs = wrapper(&[
+        ("Nat", "*", "* ~as P |-> P ~as z -> P"),
+        ("Nat::zero", "Nat", "P ||> z |> z"),
+        ("Nat::succ", "Nat ~as n -> Nat", "n |> n"),
+        ("Unrelated", "*", "*"),
+    ]);
+
+    let solutions: Vec<String> = hole()
+        .search_in(
+            &unannotated("Nat"),
+            &0,
+            &defs.names(),
+            &defs,
+            4,
+            &mut || false,
+            &mut MapCache::new(),
+        )
+        .into_iter()
+        .map(|solution| format!("{:?}", solution))
+        .collect();
+
+    assert_eq!(solutions[0], format!("{:?}", analysis("Nat::zero")));
+    assert!(solutions.contains(&format!("{:?}", analysis("Nat::succ(Nat::zero)"))));
+}
+
+#[test]
+fn stops_when_expired() {
+    let defs = wrapper(&[("Nat", "*", "* ~as P |-> P ~as z -> P")]);
+
+    let solutions = hole().search_in(
+        &unannotated("Nat"),
+        &0,
+        &defs.names(),
+        &defs,
+        4,
+        &mut || true,
+        &mut MapCache::new(),
+    );
+
+    assert!(solutions.is_empty());
+}
@@ -0,0 +1,116 @@
+use whelk_core::zipper::{
+    source::{from_source, to_source, SourceError},
+    Term,
+};
+
+fn reference(name: &str) -> Box<Term<()>> {
+    Box::new(Term::Reference(name.into(), ()))
+}
+
+/// A non-erased function type from `*`, whose return type is `return_type`.
+fn function(self_name: &str, name: &str, return_type: &str) -> Term<()> {
+    Term::Function {
+        erased: false,
+        name: Some(name.into()),
+        self_name: Some(self_name.into()),
+        argument_type: Box::new(Term::Universe(())),
+        return_type: reference(return_type),
+        annotation: (),
+    }
+}
+
+fn assert_round_trips(source: &str) {
+    let parsed = from_source(source).unwrap();
+    let printed = to_source(&parsed).unwrap();
+
+    assert_eq!(
+        format!("{:?}", from_source(&printed).unwrap()),
+        format!("{:?}", parsed),
+        "{} printed as {}",
+        source,
+        printed
+    );
+}
+
+#[test]
+fn round_trips_binders() {
+    for source in &[
+        "x |> y |> x(y)",
+        "A ||> x |> x",
+        "x |> y < x y",
+        "x |> >x",
+        "x |> 'x",
+        "x |> _",
+    ] {
+        assert_round_trips(source);
+    }
+}
+
+#[test]
+fn round_trips_arrows() {
+    for source in &[
+        "* -> *",
+        "* ~as A |-> A ~as x -> A",
+        "* |-s-> s",
+        "* ~as A |-s-> A ~as x -> s",
+    ] {
+        assert_round_trips(source);
+    }
+}
+
+#[test]
+fn round_trips_applications() {
+    for source in &["f[a, b](c)", "(f(a))[b]", "(f(a))(b)", "(x |> x)(y)"] {
+        assert_round_trips(source);
+    }
+}
+
+#[test]
+fn round_trips_literals() {
+    for source in &[
+        "~literal Size 3",
+        "~literal Word 0110",
+        "~literal Char 'a'",
+        "~literal String \"a \\\"quoted\\\" \\\\ string\"",
+        "f(~literal Size 0)",
+        "~literal Vector [Size] [x, y]",
+    ] {
+        assert_round_trips(source);
+    }
+
+    assert_eq!(
+        to_source(&from_source("~literal Word 0110").unwrap()).unwrap(),
+        "~literal Word 0110"
+    );
+}
+
+#[test]
+fn renames_binders_that_are_not_identifiers() {
+    let term = Term::Lambda {
+        erased: false,
+        name: Some("0".into()),
+        body: reference("0"),
+        annotation: (),
+    };
+
+    assert_eq!(to_source(&term).unwrap(), "a |> a");
+}
+
+#[test]
+fn drops_unused_self_binders_of_non_erased_functions() {
+    assert_eq!(
+        to_source(&function("s", "x", "x")).unwrap(),
+        to_source(&from_source("* ~as x -> x").unwrap()).unwrap()
+    );
+}
+
+#[test]
+fn refuses_self_binders_of_non_erased_functions_in_use() {
+    assert!(matches!(
+        to_source(&function("s", "x", "s")),
+        Err(SourceError::SelfBinder(name)) if name == "s"
+    ));
+
+    // the argument binder shadows a self binder of the same name
+    assert!(to_source(&function("x", "x", "x")).is_ok());
+}
@@ -0,0 +1,118 @@
+use std::collections::HashMap;
+
+use futures::executor::block_on;
+use uuid::Uuid;
+use welkin::Terms;
+use welkin_core::term::Term;
+use whelk_core::{
+    worker::{
+        CheckError, DefWrapperData, WorkerRequest, WorkerRequestVariant, WorkerResponse,
+        WorkerState,
+    },
+    zipper::analysis::AnalysisTerm,
+};
+
+fn request(idx: Uuid, variant: WorkerRequestVariant) -> WorkerRequest {
+    WorkerRequest {
+        variant,
+        idx,
+        limit: None,
+    }
+}
+
+fn handle(state: &WorkerState, idx: Uuid, variant: WorkerRequestVariant) -> WorkerResponse {
+    block_on(state.handle(request(idx, variant)))
+}
+
+fn initialized() -> WorkerState {
+    let state = WorkerState::new();
+    handle(
+        &state,
+        Uuid::new_v4(),
+        WorkerRequestVariant::Initialize(DefWrapperData(HashMap::new(), Terms { data: vec![] })),
+    )
+    .data
+    .unwrap();
+    state
+}
+
+fn register(name: &str) -> WorkerRequestVariant {
+    WorkerRequestVariant::Register(name.into(), Term::Universe, Term::Universe)
+}
+
+fn check_reference(state: &WorkerState, name: &str) -> Result<(), CheckError<Option<u64>>> {
+    handle(
+        state,
+        Uuid::new_v4(),
+        WorkerRequestVariant::Check(
+            AnalysisTerm::Reference(name.into(), None),
+            AnalysisTerm::Universe(None),
+            None,
+        ),
+    )
+    .data
+}
+
+#[test]
+fn skips_cancelled_requests() {
+    let state = initialized();
+    let idx = Uuid::new_v4();
+    let other = Uuid::new_v4();
+
+    state.receive(request(idx, register("x")));
+    state.receive(request(other, register("y")));
+    state.receive(request(Uuid::new_v4(), WorkerRequestVariant::Cancel(idx)));
+
+    let response = block_on(state.next()).unwrap();
+    assert_eq!(response.idx, other);
+    assert!(block_on(state.next()).is_none());
+    assert!(check_reference(&state, "x").is_err());
+
+    // cancelling applies only to requests not yet handled
+    state.receive(request(idx, register("x")));
+    block_on(state.next()).unwrap().data.unwrap();
+    state.receive(request(Uuid::new_v4(), WorkerRequestVariant::Cancel(idx)));
+    assert!(block_on(state.next()).is_none());
+    assert!(check_reference(&state, "x").is_ok());
+}
+
+#[test]
+fn names_temporary_definitions_for_their_request() {
+    let state = initialized();
+    let idx = Uuid::new_v4();
+
+    let response = handle(
+        &state,
+        idx,
+        WorkerRequestVariant::TempDefs(vec![("y".into(), Term::Universe, Term::Universe)]),
+    );
+    assert_eq!(response.id, Some(idx));
+
+    let response = handle(
+        &state,
+        Uuid::new_v4(),
+        WorkerRequestVariant::Check(
+            AnalysisTerm::Reference("y".into(), None),
+            AnalysisTerm::Universe(None),
+            Some(idx),
+        ),
+    );
+    assert!(response.data.is_ok());
+}
+
+#[test]
+fn fails_checks_against_unknown_temporary_definitions() {
+    let state = initialized();
+    let idx = Uuid::new_v4();
+
+    let response = handle(
+        &state,
+        Uuid::new_v4(),
+        WorkerRequestVariant::Check(
+            AnalysisTerm::Universe(None),
+            AnalysisTerm::Universe(None),
+            Some(idx),
+        ),
+    );
+    assert!(matches!(response.data, Err(CheckError::UnknownTempDefs(id)) if id == idx));
+}
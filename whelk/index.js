@@ -6,13 +6,11 @@ async function entry() {
     let app = await
     import ('./pkg');
 
-    let worker = new Worker(new URL('./worker.js',
-        import.meta.url));
-
     let terms = await (await (await fetch(new Request('/terms'))).blob()).arrayBuffer();
 
     let term = new Uint8Array(terms);
-    app.entry(term, worker);
+    app.entry(term, () => new Worker(new URL('./worker.js',
+        import.meta.url)));
 }
 
 entry().catch(console.error)
\ No newline at end of file
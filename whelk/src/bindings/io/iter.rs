@@ -3,7 +3,7 @@ use std::pin::Pin;
 use crate::{bindings::w, evaluator::CoreEvaluator};
 use futures::Future;
 use thiserror::Error;
-use welkin_binding::{FromWelkin, ToWelkin};
+use welkin_binding::{DecodeError, Diagnose, FromWelkin, ToWelkin};
 use welkin_core::term::Term;
 
 #[derive(Debug, Clone)]
@@ -14,10 +14,11 @@ pub struct LoopRequest {
 }
 
 #[derive(Debug, Clone, Error)]
-#[error("proceed error")]
 pub enum ProceedError<E> {
+    #[error("proceed error in evaluator: {0}")]
     Evaluator(E),
-    Bool(<w::Bool as FromWelkin>::Error),
+    #[error("proceed error in predicate read: {0}")]
+    Bool(DecodeError),
 }
 
 #[derive(Debug, Clone, Error)]
@@ -26,8 +27,8 @@ pub enum StepError<E, T> {
     Evaluator(E),
     #[error("step error in fulfillment: {0}")]
     Fulfill(T),
-    #[error("step error in io read: {0:?}")]
-    Io(<w::BoxPoly<w::WhelkIO<w::Any>> as FromWelkin>::Error),
+    #[error("step error in io read: {0}")]
+    Io(DecodeError),
 }
 
 impl LoopRequest {
@@ -57,7 +58,7 @@ impl LoopRequest {
                         w::Bool::r#true => true,
                         w::Bool::r#false => false,
                     })
-                    .map_err(ProceedError::Bool)
+                    .map_err(|e| ProceedError::Bool(e.diagnose()))
             })
     }
 
@@ -93,7 +94,7 @@ impl LoopRequest {
                     .await
                     .map_err(StepError::Evaluator)?,
             )
-            .map_err(StepError::Io)?
+            .map_err(|e| StepError::Io(e.diagnose()))?
             {
                 w::BoxPoly::new { data } => data,
             };
@@ -1,10 +1,9 @@
 pub mod iter;
 
 use super::w;
-use derivative::Derivative;
 use std::marker::PhantomData;
 use thiserror::Error;
-use welkin_binding::{FromAnalogue, FromWelkin};
+use welkin_binding::{DecodeError, Diagnose, FromAnalogue, FromWelkin};
 
 use welkin_core::term::Term;
 
@@ -17,14 +16,12 @@ pub struct IoRequest<G, T> {
     phantom: PhantomData<T>,
 }
 
-#[derive(Derivative, Error)]
-#[derivative(Debug(
-    bound = "<<G as FromAnalogue>::Analogue as FromWelkin>::Error: std::fmt::Debug, <<T as FromAnalogue>::Analogue as FromWelkin>::Error: std::fmt::Debug, E::Error: std::fmt::Debug"
-))]
-#[error("fulfillment error")]
-pub enum FulfillmentError<G: FromAnalogue, T: FromAnalogue, E: CoreEvaluator> {
-    Evaluator(E::Error),
-    Io(<w::IO<G, T> as FromWelkin>::Error),
+#[derive(Debug, Error)]
+pub enum FulfillmentError<E> {
+    #[error("fulfillment error in evaluator: {0}")]
+    Evaluator(E),
+    #[error("fulfillment error in io read: {0}")]
+    Io(DecodeError),
 }
 
 impl<G: FromAnalogue, T: FromAnalogue> IoRequest<G, T> {
@@ -32,7 +29,10 @@ impl<G: FromAnalogue, T: FromAnalogue> IoRequest<G, T> {
         self,
         response: Term<String>,
         evaluator: &E,
-    ) -> Result<w::IO<G, T>, FulfillmentError<G, T, E>> {
+    ) -> Result<w::IO<G, T>, FulfillmentError<E::Error>>
+    where
+        <w::IO<G, T> as FromWelkin>::Error: Diagnose,
+    {
         Ok(FromWelkin::from_welkin(
             evaluator
                 .evaluate(Term::Apply {
@@ -43,7 +43,7 @@ impl<G: FromAnalogue, T: FromAnalogue> IoRequest<G, T> {
                 .await
                 .map_err(FulfillmentError::Evaluator)?,
         )
-        .map_err(FulfillmentError::Io)?)
+        .map_err(|e| FulfillmentError::Io(e.diagnose()))?)
     }
 }
 
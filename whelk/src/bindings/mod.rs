@@ -4,11 +4,12 @@ mod conversions;
 pub mod io;
 
 bind! {
-    #[path = "./welkin/defs"]
+    #[path = "../.welkin"]
     #[include(
         Unit,
         Bool,
         Pair,
+        Sized,
         Vector,
         Word,
         Char,
@@ -25,13 +26,6 @@ bind! {
         #[wrapper = "Size"]
         struct Size;
 
-        enum Sized<A> {
-            new {
-                size: Size,
-                data: A
-            }
-        }
-
         #[wrapper = "WhelkRequest::loop.initial"]
         struct WhelkRequestLoopInitialState;
 
@@ -44,10 +38,8 @@ bind! {
         #[wrapper = "Any"]
         struct Any;
 
-        #[indices = 1]
         type WhelkIO<A> = IO<WhelkRequest, A>;
 
-        #[indices = 1]
         enum BoxPoly<A> {
             new {
                 data: A
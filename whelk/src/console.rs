@@ -0,0 +1,162 @@
+use std::{
+    cell::{Cell, RefCell},
+    rc::Rc,
+};
+
+use futures::channel::mpsc::{channel, Receiver, Sender};
+use wasm_bindgen::{prelude::Closure, JsCast, JsValue};
+use web_sys::{Element, HtmlInputElement, KeyboardEvent};
+
+use crate::{push_paragraph, worker::WorkerWrapper, Block};
+
+/// The output of a scratchpad's IO as it runs, through which it is given input and cancelled.
+pub(crate) struct Console {
+    output: Element,
+    session: WorkerWrapper,
+    lines: RefCell<Option<Sender<String>>>,
+    cancelled: Rc<Cell<bool>>,
+    closures: RefCell<Vec<Closure<dyn FnMut(JsValue)>>>,
+}
+
+impl Console {
+    /// A console writing to `output` for IO evaluated through `session`, with the stream of lines
+    /// entered at its prompts.
+    pub(crate) fn new(output: Element, session: WorkerWrapper) -> (Rc<Self>, Receiver<String>) {
+        let (lines, receiver) = channel(1);
+
+        (
+            Rc::new(Console {
+                output,
+                session,
+                lines: RefCell::new(Some(lines)),
+                cancelled: Rc::new(Cell::new(false)),
+                closures: RefCell::new(vec![]),
+            }),
+            receiver,
+        )
+    }
+
+    pub(crate) fn push(&self, block: Block) {
+        if !self.is_closed() {
+            push_paragraph(block, &self.output);
+        }
+    }
+
+    /// Shows an input whose line, once entered, is the next on the stream of lines.
+    pub(crate) fn prompt(&self) {
+        if self.is_closed() {
+            return;
+        }
+
+        let document = web_sys::window().unwrap().document().unwrap();
+
+        let paragraph = document.create_element("p").unwrap();
+        paragraph.class_list().add_2("info", "prompt").unwrap();
+        let header = document.create_element("span").unwrap();
+        header.class_list().add_1("info-header").unwrap();
+        header.set_text_content(Some("IN"));
+        paragraph.append_child(&header).unwrap();
+
+        let input: HtmlInputElement = document
+            .create_element("input")
+            .unwrap()
+            .dyn_into()
+            .unwrap();
+        paragraph.append_child(&input).unwrap();
+        self.output.append_child(&paragraph).unwrap();
+
+        let closure = Closure::wrap(Box::new({
+            let input = input.clone();
+            let mut lines = self.lines.borrow().clone();
+            move |e: JsValue| {
+                let e: KeyboardEvent = e.dyn_into().unwrap();
+                if e.code() != "Enter" || input.disabled() {
+                    return;
+                }
+                e.prevent_default();
+                if let Some(lines) = &mut lines {
+                    if lines.try_send(input.value()).is_ok() {
+                        input.set_disabled(true);
+                    }
+                }
+            }
+        }) as Box<dyn FnMut(JsValue)>);
+        input
+            .add_event_listener_with_callback("keydown", closure.as_ref().unchecked_ref())
+            .unwrap();
+        self.closures.borrow_mut().push(closure);
+
+        input.focus().unwrap();
+    }
+
+    /// Shows the progress of a loop with a button to cancel it, returning a function to update
+    /// the count of iterations that returns whether the loop should go on.
+    pub(crate) fn progress(&self) -> impl Fn(usize) -> bool {
+        let document = web_sys::window().unwrap().document().unwrap();
+
+        let paragraph = document.create_element("p").unwrap();
+        paragraph.class_list().add_2("info", "progress").unwrap();
+        let header = document.create_element("span").unwrap();
+        header.class_list().add_1("info-header").unwrap();
+        header.set_text_content(Some("LOOP"));
+        paragraph.append_child(&header).unwrap();
+        let count = document.create_text_node("0 iterations");
+        paragraph.append_child(&count).unwrap();
+
+        let button = document.create_element("button").unwrap();
+        button.set_text_content(Some("cancel"));
+        paragraph.append_child(&button).unwrap();
+
+        let stopped = Rc::new(Cell::new(false));
+
+        let closure = Closure::wrap(Box::new({
+            let stopped = stopped.clone();
+            let session = self.session.clone();
+            move |_| {
+                stopped.set(true);
+                // the loop only sees `stopped` between iterations, so give up on the one running
+                session.cancel();
+            }
+        }) as Box<dyn FnMut(JsValue)>);
+        button
+            .add_event_listener_with_callback("click", closure.as_ref().unchecked_ref())
+            .unwrap();
+        self.closures.borrow_mut().push(closure);
+
+        if !self.is_closed() {
+            self.output.append_child(&paragraph).unwrap();
+        }
+
+        let cancelled = self.cancelled.clone();
+        let button = RemovedOnDrop(button);
+        move |iterations| {
+            count.set_data(&format!("{} iterations", iterations));
+            let proceed = !stopped.get() && !cancelled.get();
+            if !proceed {
+                button.0.remove();
+            }
+            proceed
+        }
+    }
+
+    /// Stops output, cancels any loop and ends the stream of lines, so that IO waiting at a
+    /// prompt gives up.
+    pub(crate) fn close(&self) {
+        self.cancelled.set(true);
+        self.lines.borrow_mut().take();
+        self.closures.borrow_mut().clear();
+    }
+
+    pub(crate) fn is_closed(&self) -> bool {
+        self.lines.borrow().is_none()
+    }
+}
+
+/// An element taken out of the page once the loop it controls is over.
+struct RemovedOnDrop(Element);
+
+impl Drop for RemovedOnDrop {
+    fn drop(&mut self) {
+        self.0.remove();
+    }
+}
@@ -1,19 +1,44 @@
 use futures::channel::mpsc::Sender;
 use wasm_bindgen::JsValue;
 
-use crate::edit::{mutations::*, ui_section, zipper::Term, UiSectionVariance};
+use crate::edit::{
+    dynamic::HasDynamicUi, mutations::*, ui_section, zipper::Term, UiSectionVariance,
+};
 
 use super::{zipper::Cursor, UiSection};
 
+/// The kind of change a batch of mutations made, which decides whether history groups it with the
+/// edits before it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Edit {
+    /// Nothing changed but the focus.
+    None,
+    /// Text was typed into a name.
+    Text,
+    /// Terms were replaced, removed or toggled.
+    Structure,
+}
+
+impl Edit {
+    pub(crate) fn text(&mut self) {
+        *self = (*self).max(Edit::Text);
+    }
+
+    pub(crate) fn structure(&mut self) {
+        *self = Edit::Structure;
+    }
+}
+
 pub fn apply_mutations(
     data: Cursor<UiSection>,
     focused: &mut Option<Cursor<UiSection>>,
+    edit: &mut Edit,
     sender: &Sender<()>,
 ) -> Result<Cursor<UiSection>, JsValue> {
     Ok(match data {
         Cursor::Lambda(c) => {
             let cursor = c.clone().body();
-            let body: Term<_> = apply_mutations(cursor, focused, sender)?.into();
+            let body: Term<_> = apply_mutations(cursor, focused, edit, sender)?.into();
 
             let mutations: Vec<_> = match &c.annotation().variant {
                 UiSectionVariance::Lambda { mutations, .. } => {
@@ -27,6 +52,7 @@ pub fn apply_mutations(
             for mutation in &mutations {
                 match mutation {
                     LambdaMutation::Update(name) => {
+                        edit.text();
                         c = c.with_name(if name.is_empty() {
                             None
                         } else {
@@ -34,6 +60,7 @@ pub fn apply_mutations(
                         });
                     }
                     LambdaMutation::ToggleErased => {
+                        edit.structure();
                         *c.erased_mut() = !c.erased();
                     }
                     _ => {}
@@ -45,6 +72,7 @@ pub fn apply_mutations(
             for mutation in &mutations {
                 match mutation {
                     LambdaMutation::Remove => {
+                        edit.structure();
                         let annotation = c.annotation().annotation.clone();
                         c = Cursor::Hole(match c {
                             Cursor::Lambda(cursor) => {
@@ -72,9 +100,9 @@ pub fn apply_mutations(
         }
         Cursor::Application(mut cursor) => {
             let function: Term<_> =
-                apply_mutations(cursor.clone().function(), focused, sender)?.into();
+                apply_mutations(cursor.clone().function(), focused, edit, sender)?.into();
             let argument: Term<_> =
-                apply_mutations(cursor.clone().argument(), focused, sender)?.into();
+                apply_mutations(cursor.clone().argument(), focused, edit, sender)?.into();
 
             cursor = cursor.with_function(function);
             cursor = cursor.with_argument(argument);
@@ -89,6 +117,7 @@ pub fn apply_mutations(
             for mutation in &mutations {
                 match mutation {
                     ApplicationMutation::ToggleErased => {
+                        edit.structure();
                         *cursor.erased_mut() = !cursor.erased();
                     }
                     _ => {}
@@ -100,6 +129,7 @@ pub fn apply_mutations(
             for mutation in &mutations {
                 match mutation {
                     ApplicationMutation::Remove => {
+                        edit.structure();
                         let annotation = cursor.annotation().annotation.clone();
                         cursor = Cursor::Hole(match cursor {
                             Cursor::Application(cursor) => {
@@ -126,7 +156,8 @@ pub fn apply_mutations(
             cursor
         }
         Cursor::Put(mut cursor) => {
-            let term: Term<_> = apply_mutations(cursor.clone().term(), focused, sender)?.into();
+            let term: Term<_> =
+                apply_mutations(cursor.clone().term(), focused, edit, sender)?.into();
 
             cursor = cursor.with_term(term);
 
@@ -142,6 +173,7 @@ pub fn apply_mutations(
             for mutation in &mutations {
                 match mutation {
                     PutMutation::Remove => {
+                        edit.structure();
                         let annotation = cursor.annotation().annotation.clone();
                         cursor = Cursor::Hole(match cursor {
                             Cursor::Put(cursor) => {
@@ -177,7 +209,10 @@ pub fn apply_mutations(
 
             for mutation in &mutations {
                 match mutation {
-                    ReferenceMutation::Update(name) => cursor = cursor.with_name(name.clone()),
+                    ReferenceMutation::Update(name) => {
+                        edit.text();
+                        cursor = cursor.with_name(name.clone());
+                    }
                     _ => {}
                 }
             }
@@ -187,6 +222,7 @@ pub fn apply_mutations(
             for mutation in &mutations {
                 match mutation {
                     ReferenceMutation::Remove => {
+                        edit.structure();
                         let annotation = cursor.annotation().annotation.clone();
                         cursor = Cursor::Hole(match cursor {
                             Cursor::Reference(cursor) => {
@@ -214,10 +250,10 @@ pub fn apply_mutations(
         }
         Cursor::Duplication(c) => {
             let cursor = c.clone().body();
-            let body: Term<_> = apply_mutations(cursor, focused, sender)?.into();
+            let body: Term<_> = apply_mutations(cursor, focused, edit, sender)?.into();
 
             let cursor = c.clone().expression();
-            let expression: Term<_> = apply_mutations(cursor, focused, sender)?.into();
+            let expression: Term<_> = apply_mutations(cursor, focused, edit, sender)?.into();
 
             let mutations: Vec<_> = match &c.annotation().variant {
                 UiSectionVariance::Duplication { mutations, .. } => {
@@ -232,6 +268,7 @@ pub fn apply_mutations(
             for mutation in &mutations {
                 match mutation {
                     DuplicationMutation::Update(name) => {
+                        edit.text();
                         c = c.with_binder(if name.is_empty() {
                             None
                         } else {
@@ -248,6 +285,7 @@ pub fn apply_mutations(
             for mutation in &mutations {
                 match mutation {
                     DuplicationMutation::Remove => {
+                        edit.structure();
                         let annotation = c.annotation().annotation.clone();
                         c = Cursor::Hole(match c {
                             Cursor::Duplication(cursor) => {
@@ -286,6 +324,7 @@ pub fn apply_mutations(
             for mutation in &mutations {
                 match mutation {
                     UniverseMutation::Remove => {
+                        edit.structure();
                         let annotation = cursor.annotation().annotation.clone();
                         cursor = Cursor::Hole(match cursor {
                             Cursor::Universe(cursor) => {
@@ -313,10 +352,10 @@ pub fn apply_mutations(
         }
         Cursor::Function(c) => {
             let cursor = c.clone().argument_type();
-            let argument_type: Term<_> = apply_mutations(cursor, focused, sender)?.into();
+            let argument_type: Term<_> = apply_mutations(cursor, focused, edit, sender)?.into();
 
             let cursor = c.clone().return_type();
-            let return_type: Term<_> = apply_mutations(cursor, focused, sender)?.into();
+            let return_type: Term<_> = apply_mutations(cursor, focused, edit, sender)?.into();
 
             let (mutations, self_focused): (Vec<_>, _) = match &c.annotation().variant {
                 UiSectionVariance::Function {
@@ -336,6 +375,7 @@ pub fn apply_mutations(
             for mutation in &mutations {
                 match mutation {
                     FunctionMutation::Update(name) => {
+                        edit.text();
                         c = c.with_name(if name.is_empty() {
                             None
                         } else {
@@ -343,6 +383,7 @@ pub fn apply_mutations(
                         });
                     }
                     FunctionMutation::UpdateSelf(name) => {
+                        edit.text();
                         c = c.with_self_name(if name.is_empty() {
                             None
                         } else {
@@ -350,6 +391,7 @@ pub fn apply_mutations(
                         });
                     }
                     FunctionMutation::ToggleErased => {
+                        edit.structure();
                         *c.erased_mut() = !c.erased();
                     }
                     _ => {}
@@ -361,6 +403,7 @@ pub fn apply_mutations(
             for mutation in &mutations {
                 match mutation {
                     FunctionMutation::Remove => {
+                        edit.structure();
                         let annotation = c.annotation().annotation.clone();
                         c = Cursor::Hole(match c {
                             Cursor::Function(cursor) => {
@@ -392,7 +435,8 @@ pub fn apply_mutations(
             c
         }
         Cursor::Wrap(mut cursor) => {
-            let term: Term<_> = apply_mutations(cursor.clone().term(), focused, sender)?.into();
+            let term: Term<_> =
+                apply_mutations(cursor.clone().term(), focused, edit, sender)?.into();
 
             cursor = cursor.with_term(term);
 
@@ -408,6 +452,7 @@ pub fn apply_mutations(
             for mutation in &mutations {
                 match mutation {
                     WrapMutation::Remove => {
+                        edit.structure();
                         let annotation = cursor.annotation().annotation.clone();
                         cursor = Cursor::Hole(match cursor {
                             Cursor::Wrap(cursor) => {
@@ -447,12 +492,14 @@ pub fn apply_mutations(
             for mutation in &mutations {
                 match mutation {
                     HoleMutation::Replace(term) => {
+                        edit.structure();
                         let term = term.clone();
                         let annotation = cursor.annotation().annotation.clone();
                         cursor = Cursor::from_term_and_path(term, cursor.path().clone());
                         cursor.annotation_mut().annotation = annotation;
                     }
                     HoleMutation::ToParent => {
+                        edit.structure();
                         cursor = cursor.ascend();
                     }
                     _ => {}
@@ -471,18 +518,15 @@ pub fn apply_mutations(
             cursor
         }
 
-        Cursor::Dynamic(cursor) => {
-            let term = cursor.term;
-
-            term.apply_mutations(
-                cursor.up,
-                match &cursor.annotation.variant {
-                    UiSectionVariance::Dynamic(variance) => variance.clone(),
-                    _ => panic!(),
-                },
-                focused,
-                sender,
-            )?
-        }
+        Cursor::Dynamic(cursor) => UiSection::into_ui(cursor.term).apply_mutations(
+            cursor.up,
+            match &cursor.annotation.variant {
+                UiSectionVariance::Dynamic(variance) => variance.clone(),
+                _ => panic!(),
+            },
+            focused,
+            edit,
+            sender,
+        )?,
     })
 }
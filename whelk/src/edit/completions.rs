@@ -0,0 +1,168 @@
+use std::{cell::RefCell, rc::Rc};
+
+use futures::channel::mpsc::Sender;
+use wasm_bindgen::{prelude::Closure, JsCast, JsValue};
+use web_sys::{Element, Event};
+use whelk_core::completion::{Completion, Fit};
+
+use super::{
+    add_ui,
+    mutations::HoleMutation,
+    zipper::{Cursor, Term},
+    UiSection, UiSectionVariance,
+};
+
+thread_local! {
+    static OPEN: RefCell<Option<Dropdown>> = RefCell::new(None);
+}
+
+/// The completions listed beneath a hole.
+struct Dropdown {
+    list: Element,
+    /// The best of the completions, accepted with tab.
+    first: Term<()>,
+    hole: Hole,
+    blur: Closure<dyn FnMut(JsValue)>,
+    _choices: Vec<Closure<dyn FnMut(JsValue)>>,
+}
+
+impl Drop for Dropdown {
+    fn drop(&mut self) {
+        self.list.remove();
+        let _ = self
+            .hole
+            .p
+            .remove_event_listener_with_callback("blur", self.blur.as_ref().unchecked_ref());
+    }
+}
+
+#[derive(Clone)]
+struct Hole {
+    p: Element,
+    mutations: Rc<RefCell<Vec<HoleMutation>>>,
+    sender: Sender<()>,
+}
+
+impl Hole {
+    fn fill(&self, term: Term<()>) {
+        self.p.remove();
+        self.mutations
+            .borrow_mut()
+            .push(HoleMutation::Replace(add_ui(term, &self.sender, true)));
+        let _ = self.sender.clone().try_send(());
+    }
+}
+
+/// The text typed into the hole at `cursor`, which completions should start with.
+pub fn completion_prefix(cursor: &Cursor<UiSection>) -> Option<String> {
+    if let Cursor::Hole(cursor) = cursor {
+        if let UiSectionVariance::Hole { p, .. } = &cursor.annotation().variant {
+            return Some(p.text_content().unwrap_or_default().trim().to_owned());
+        }
+    }
+    None
+}
+
+/// Lists `completions` beneath the hole at `cursor`, filling the hole with whichever is clicked.
+pub fn show_completions(
+    cursor: &Cursor<UiSection>,
+    completions: Vec<Completion>,
+    sender: &Sender<()>,
+) {
+    close_completions();
+
+    let hole = match cursor {
+        Cursor::Hole(cursor) => match &cursor.annotation().variant {
+            UiSectionVariance::Hole { p, mutations, .. } => Hole {
+                p: p.clone(),
+                mutations: mutations.clone(),
+                sender: sender.clone(),
+            },
+            _ => return,
+        },
+        _ => return,
+    };
+
+    if completions.is_empty() {
+        return;
+    }
+
+    let document = web_sys::window().unwrap().document().unwrap();
+
+    let list = document.create_element("ul").unwrap();
+    list.class_list().add_1("completions").unwrap();
+
+    let mut choices = vec![];
+
+    for completion in &completions {
+        let item = document.create_element("li").unwrap();
+        item.class_list()
+            .add_2(
+                "completion",
+                match completion.fit {
+                    Fit::Fits(_) => "fits",
+                    Fit::Unknown => "unknown",
+                    Fit::Mismatch => "mismatch",
+                },
+            )
+            .unwrap();
+        if completion.local {
+            item.class_list().add_1("local").unwrap();
+        }
+        item.set_text_content(Some(&completion.name));
+
+        let closure = Closure::wrap(Box::new({
+            let hole = hole.clone();
+            let list = list.clone();
+            let term = completion.term();
+            move |e: JsValue| {
+                // keeps the hole focused, as its blur would close the list before the click lands
+                e.unchecked_ref::<Event>().prevent_default();
+                list.remove();
+                hole.fill(term.clone());
+            }
+        }) as Box<dyn FnMut(JsValue)>);
+        item.add_event_listener_with_callback("mousedown", closure.as_ref().unchecked_ref())
+            .unwrap();
+        choices.push(closure);
+
+        list.append_child(&item).unwrap();
+    }
+
+    let blur = Closure::wrap(Box::new({
+        let list = list.clone();
+        move |_| {
+            list.remove();
+        }
+    }) as Box<dyn FnMut(JsValue)>);
+    hole.p
+        .add_event_listener_with_callback("blur", blur.as_ref().unchecked_ref())
+        .unwrap();
+
+    hole.p.after_with_node_1(&list).unwrap();
+
+    OPEN.with(|open| {
+        *open.borrow_mut() = Some(Dropdown {
+            list,
+            first: completions[0].term(),
+            hole,
+            blur,
+            _choices: choices,
+        })
+    });
+}
+
+/// Fills the hole completions are listed for with the best of them, returning whether any were.
+pub fn accept_completion() -> bool {
+    let dropdown = match OPEN.with(|open| open.borrow_mut().take()) {
+        Some(dropdown) if dropdown.list.is_connected() => dropdown,
+        _ => return false,
+    };
+
+    dropdown.hole.fill(dropdown.first.clone());
+    true
+}
+
+pub fn close_completions() {
+    OPEN.with(|open| open.borrow_mut().take());
+}
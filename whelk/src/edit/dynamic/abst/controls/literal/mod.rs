@@ -9,10 +9,9 @@ use crate::edit::{
 
 use super::{ControlData, Invoke};
 
-mod codegen;
 mod size;
 mod string;
-pub use codegen::*;
+pub use whelk_core::literal::*;
 pub use size::*;
 pub use string::*;
 
@@ -1,4 +1,4 @@
-use std::{borrow::Cow, cell::RefCell, collections::HashMap, fmt, rc::Rc};
+use std::{any::Any, borrow::Cow, cell::RefCell, collections::HashMap, fmt, rc::Rc};
 
 use futures::channel::mpsc::Sender;
 use ui_section::add_ui;
@@ -12,10 +12,10 @@ use fields::*;
 use crate::edit::{
     focus_contenteditable, focus_element, ui_section,
     zipper::{dynamic::DynamicTerm, encode, Cursor, DynamicCursor, Path, Term},
-    DynamicVariance, UiSection, UiSectionVariance,
+    DynamicVariance, Edit, UiSection, UiSectionVariance,
 };
 
-use super::{AbstractDynamic, Color, DynamicContext, Replace};
+use super::{super::DynamicUi, AbstractDynamic, Color, DynamicContext, Replace};
 
 pub enum HasFocus {
     Element(Element),
@@ -203,6 +203,20 @@ impl DynamicTerm<()> for Root {
         Box::new(self.clone())
     }
 
+    fn clear_annotation(self: Box<Self>) -> Box<dyn DynamicTerm<()>> {
+        Box::new(*self)
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn into_any(self: Box<Self>) -> Box<dyn Any> {
+        self
+    }
+}
+
+impl DynamicUi<()> for Root {
     fn add_ui(
         mut self: Box<Self>,
         sender: &Sender<()>,
@@ -239,6 +253,7 @@ impl DynamicTerm<()> for Root {
         up: Path<UiSection>,
         annotation: Box<dyn DynamicVariance>,
         focused: &mut Option<Cursor<UiSection>>,
+        edit: &mut Edit,
         sender: &Sender<()>,
     ) -> Result<Cursor<UiSection>, JsValue> {
         todo!()
@@ -252,10 +267,6 @@ impl DynamicTerm<()> for Root {
     ) -> Result<(), JsValue> {
         todo!()
     }
-
-    fn clear_annotation(self: Box<Self>) -> Box<dyn DynamicTerm<()>> {
-        Box::new(*self)
-    }
 }
 
 impl DynamicTerm<UiSection> for Root {
@@ -279,6 +290,20 @@ impl DynamicTerm<UiSection> for Root {
         Box::new(self.clone())
     }
 
+    fn clear_annotation(self: Box<Self>) -> Box<dyn DynamicTerm<()>> {
+        Box::new(*self)
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn into_any(self: Box<Self>) -> Box<dyn Any> {
+        self
+    }
+}
+
+impl DynamicUi<UiSection> for Root {
     fn add_ui(
         self: Box<Self>,
         sender: &Sender<()>,
@@ -292,6 +317,7 @@ impl DynamicTerm<UiSection> for Root {
         up: Path<UiSection>,
         annotation: Box<dyn DynamicVariance>,
         focused: &mut Option<Cursor<UiSection>>,
+        edit: &mut Edit,
         sender: &Sender<()>,
     ) -> Result<Cursor<UiSection>, JsValue>
     where
@@ -310,6 +336,7 @@ impl DynamicTerm<UiSection> for Root {
         });
 
         if let Some(replace) = context.borrow().needs_remove.replace(None) {
+            edit.structure();
             cursor = match cursor {
                 Cursor::Dynamic(cursor) => {
                     Cursor::from_term_and_path(add_ui(replace, sender, true), cursor.up)
@@ -345,10 +372,6 @@ impl DynamicTerm<UiSection> for Root {
 
         Ok(())
     }
-
-    fn clear_annotation(self: Box<Self>) -> Box<dyn DynamicTerm<()>> {
-        Box::new(*self)
-    }
 }
 
 impl Root {
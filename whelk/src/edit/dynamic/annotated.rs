@@ -0,0 +1,331 @@
+use std::{
+    borrow::Cow,
+    cell::RefCell,
+    fmt::{self, Debug},
+    rc::Rc,
+};
+
+use futures::channel::mpsc::Sender;
+use ui_section::add_ui;
+use wasm_bindgen::{prelude::Closure, JsCast, JsValue};
+use web_sys::{Element, KeyboardEvent};
+
+use crate::{
+    edit::{
+        apply_mutations, focus_element, render_to, ui_section,
+        zipper::{
+            dynamic::Annotated, BranchWrapper, Cursor, DynamicCursor, HoleCursor, Path, Term,
+        },
+        DynamicVariance, Edit, UiSection, UiSectionVariance,
+    },
+    zipper::dynamic::DynamicTerm,
+};
+
+use super::DynamicUi;
+
+#[derive(Clone, Debug)]
+pub enum AnnotatedMutation {
+    Focus,
+    Remove,
+}
+
+#[derive(Clone, Debug)]
+pub struct AnnotatedVariance {
+    container: Element,
+    span: Element,
+    closures: Rc<Vec<Closure<dyn FnMut(JsValue)>>>,
+    mutations: Rc<RefCell<Vec<AnnotatedMutation>>>,
+}
+
+impl DynamicVariance for AnnotatedVariance {
+    fn box_clone(&self) -> Box<dyn DynamicVariance> {
+        Box::new(self.clone())
+    }
+
+    fn debug(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        <AnnotatedVariance as Debug>::fmt(self, f)
+    }
+
+    fn focus(&self) {
+        focus_element(&self.span, false)
+    }
+
+    fn remove(&self) {
+        self.mutations.borrow_mut().push(AnnotatedMutation::Remove);
+        self.container.remove();
+    }
+
+    fn focused_el(&self) -> Cow<'_, Element> {
+        Cow::Borrowed(&self.span)
+    }
+}
+
+impl DynamicUi<()> for Annotated<()> {
+    fn add_ui(
+        self: Box<Self>,
+        sender: &Sender<()>,
+        editable: bool,
+    ) -> (UiSection, Box<dyn DynamicTerm<UiSection>>) {
+        let document = web_sys::window().unwrap().document().unwrap();
+
+        let mutations = Rc::new(RefCell::new(vec![]));
+        let sender = RefCell::new(sender.clone());
+
+        let container = document.create_element("div").unwrap();
+        container.class_list().add_1("annotated").unwrap();
+
+        let term = document.create_element("span").unwrap();
+        term.class_list().add_1("annotated-term").unwrap();
+
+        // the separator stands for the annotation itself, so it is what is focused and removed
+        let span = document.create_element("span").unwrap();
+        span.class_list().add_1("annotated-inner").unwrap();
+        span.set_attribute("tabindex", "0").unwrap();
+
+        let ty = document.create_element("span").unwrap();
+        ty.class_list().add_1("annotated-type").unwrap();
+
+        let focus_closure = Closure::wrap(Box::new({
+            let mutations = mutations.clone();
+            let span = span.clone();
+            let sender = sender.clone();
+            move |_| {
+                mutations.borrow_mut().push(AnnotatedMutation::Focus);
+                focus_element(&span, true);
+                let _ = sender.borrow_mut().try_send(());
+            }
+        }) as Box<dyn FnMut(JsValue)>);
+
+        span.add_event_listener_with_callback("focus", focus_closure.as_ref().unchecked_ref())
+            .unwrap();
+
+        let keydown_closure = Closure::wrap(Box::new({
+            let mutations = mutations.clone();
+            let container = container.clone();
+            let sender = sender.clone();
+            move |e: JsValue| {
+                let e: KeyboardEvent = e.dyn_into().unwrap();
+                if editable && (e.code() == "Backspace" || e.code() == "Delete") {
+                    mutations.borrow_mut().push(AnnotatedMutation::Remove);
+                    container.remove();
+                    let _ = sender.borrow_mut().try_send(());
+                    e.stop_propagation();
+                }
+            }
+        }) as Box<dyn FnMut(JsValue)>);
+
+        span.add_event_listener_with_callback("keydown", keydown_closure.as_ref().unchecked_ref())
+            .unwrap();
+
+        container.append_child(&term).unwrap();
+        container.append_child(&span).unwrap();
+        container.append_child(&ty).unwrap();
+
+        let section = UiSection::new(UiSectionVariance::Dynamic(Box::new(AnnotatedVariance {
+            container,
+            span,
+            closures: Rc::new(vec![keydown_closure, focus_closure]),
+            mutations,
+        })));
+
+        let sender = &sender.borrow().clone();
+
+        (
+            section,
+            Box::new(Annotated::new(
+                add_ui(self.term, sender, editable),
+                add_ui(self.ty, sender, editable),
+            )),
+        )
+    }
+
+    fn apply_mutations(
+        self: Box<Self>,
+        _: Path<UiSection>,
+        _: Box<dyn DynamicVariance>,
+        _: &mut Option<Cursor<UiSection>>,
+        _: &mut Edit,
+        _: &Sender<()>,
+    ) -> Result<Cursor<UiSection>, JsValue> {
+        // mutations are only ever applied to terms `add_ui` has given an editing interface
+        unreachable!("annotated term without an editing interface")
+    }
+
+    fn render_to(
+        &self,
+        _: &Path<UiSection>,
+        _: &dyn DynamicVariance,
+        _: &web_sys::Node,
+    ) -> Result<(), JsValue> {
+        // as with mutations, only terms with an editing interface are rendered
+        unreachable!("annotated term without an editing interface")
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum AnnotatedBranch<T> {
+    Term { ty: Term<T> },
+    Type { term: Term<T> },
+}
+
+impl BranchWrapper<UiSection> for AnnotatedBranch<UiSection> {
+    fn reconstruct(self: Box<Self>, term: Term<UiSection>) -> Box<dyn DynamicTerm<UiSection>> {
+        Box::new(match *self {
+            AnnotatedBranch::Term { ty } => Annotated::new(term, ty),
+            AnnotatedBranch::Type { term: annotated } => Annotated::new(annotated, term),
+        })
+    }
+
+    fn box_clone(&self) -> Box<dyn BranchWrapper<UiSection>> {
+        Box::new(<Self as Clone>::clone(self))
+    }
+
+    fn debug(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        <Self as Debug>::fmt(self, f)
+    }
+}
+
+impl DynamicUi<UiSection> for Annotated<UiSection> {
+    /// Replaces the editing interface the term already has with a new one.
+    fn add_ui(
+        self: Box<Self>,
+        sender: &Sender<()>,
+        editable: bool,
+    ) -> (UiSection, Box<dyn DynamicTerm<UiSection>>) {
+        let Annotated { term, ty } = *self;
+
+        Box::new(Annotated::new(
+            term.clear_annotation(),
+            ty.clear_annotation(),
+        ))
+        .add_ui(sender, editable)
+    }
+
+    fn apply_mutations(
+        self: Box<Self>,
+        up: Path<UiSection>,
+        annotation: Box<dyn DynamicVariance>,
+        focused: &mut Option<Cursor<UiSection>>,
+        edit: &mut Edit,
+        sender: &Sender<()>,
+    ) -> Result<Cursor<UiSection>, JsValue> {
+        let Annotated { term, ty } = *self;
+
+        let annotation_data: &AnnotatedVariance = annotation.downcast_ref().unwrap();
+        let mutations = annotation_data
+            .mutations
+            .borrow_mut()
+            .drain(..)
+            .collect::<Vec<_>>();
+
+        let branch_annotation = UiSection::new(UiSectionVariance::Dynamic(annotation.clone()));
+
+        let new_term = apply_mutations(
+            Cursor::from_term_and_path(
+                term.clone(),
+                Path::Dynamic {
+                    up: Box::new(up.clone()),
+                    branch: Box::new(AnnotatedBranch::Term { ty: ty.clone() }),
+                    annotation: branch_annotation.clone(),
+                },
+            ),
+            focused,
+            edit,
+            sender,
+        )?;
+
+        let ty = apply_mutations(
+            Cursor::from_term_and_path(
+                ty,
+                Path::Dynamic {
+                    up: Box::new(up.clone()),
+                    branch: Box::new(AnnotatedBranch::Type { term }),
+                    annotation: branch_annotation,
+                },
+            ),
+            focused,
+            edit,
+            sender,
+        )?;
+
+        let mut cursor = Cursor::Dynamic(DynamicCursor {
+            up: up.clone(),
+            term: Box::new(Annotated::new(new_term.into(), ty.into())),
+            annotation: UiSection::new(UiSectionVariance::Dynamic(annotation)),
+        });
+
+        for mutation in &mutations {
+            match mutation {
+                AnnotatedMutation::Remove => {
+                    edit.structure();
+                    cursor = Cursor::Hole(HoleCursor::new(
+                        up.clone(),
+                        ui_section(Term::Hole(()), sender),
+                    ));
+                    break;
+                }
+                _ => {}
+            }
+        }
+
+        for mutation in mutations {
+            match mutation {
+                AnnotatedMutation::Focus => {
+                    *focused = Some(cursor.clone());
+                }
+                _ => {}
+            }
+        }
+
+        Ok(cursor)
+    }
+
+    fn render_to(
+        &self,
+        up: &Path<UiSection>,
+        annotation: &dyn DynamicVariance,
+        node: &web_sys::Node,
+    ) -> Result<(), JsValue> {
+        let annotation: &AnnotatedVariance = annotation.downcast_ref().unwrap();
+        let container = &annotation.container;
+
+        if !node.contains(Some(container)) {
+            node.append_child(container)?;
+        }
+
+        let term_node = container.child_nodes().get(0).unwrap();
+        let ty_node = container.child_nodes().get(2).unwrap();
+        render_to(
+            &Cursor::from_term_and_path(
+                self.term.clone(),
+                Path::Dynamic {
+                    up: Box::new(up.clone()),
+                    branch: Box::new(AnnotatedBranch::Term {
+                        ty: self.ty.clone(),
+                    }),
+                    annotation: UiSection::new(UiSectionVariance::Dynamic(Box::new(
+                        annotation.clone(),
+                    ))),
+                },
+            ),
+            &term_node,
+        )?;
+        render_to(
+            &Cursor::from_term_and_path(
+                self.ty.clone(),
+                Path::Dynamic {
+                    up: Box::new(up.clone()),
+                    branch: Box::new(AnnotatedBranch::Type {
+                        term: self.term.clone(),
+                    }),
+                    annotation: UiSection::new(UiSectionVariance::Dynamic(Box::new(
+                        annotation.clone(),
+                    ))),
+                },
+            ),
+            &ty_node,
+        )?;
+
+        Ok(())
+    }
+}
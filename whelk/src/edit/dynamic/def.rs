@@ -1,4 +1,5 @@
 use std::{
+    any::Any,
     borrow::Cow,
     cell::RefCell,
     fmt::{self, Debug},
@@ -15,11 +16,13 @@ use crate::{
     edit::{
         apply_mutations, configure_contenteditable, focus_contenteditable, render_to, ui_section,
         zipper::{encode, BranchWrapper, Cursor, DynamicCursor, HoleCursor, Path, Term, TermData},
-        DynamicVariance, UiSection, UiSectionVariance,
+        DynamicVariance, Edit, UiSection, UiSectionVariance,
     },
     zipper::dynamic::DynamicTerm,
 };
 
+use super::DynamicUi;
+
 #[derive(Debug, Clone)]
 pub struct Def<T> {
     expression: Term<T>,
@@ -81,6 +84,45 @@ impl DynamicTerm<()> for Def<()> {
         Box::new(self.clone())
     }
 
+    fn clear_annotation(self: Box<Self>) -> Box<dyn DynamicTerm<()>> {
+        self
+    }
+
+    fn index(&self) -> u8 {
+        'D' as u8
+    }
+
+    fn encode(self: Box<Self>) -> Vec<u8> {
+        if let Ok(data) = encode(DefData {
+            expression: self.expression.clear_annotation().into(),
+            body: self.body.clear_annotation().into(),
+            binder: self.binder,
+        }) {
+            data
+        } else {
+            panic!()
+        }
+    }
+
+    fn expand(self: Box<Self>) -> Term<()> {
+        Term::Duplication {
+            binder: self.binder,
+            expression: Box::new(self.expression.clear_annotation()),
+            body: Box::new(self.body.clear_annotation()),
+            annotation: (),
+        }
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn into_any(self: Box<Self>) -> Box<dyn Any> {
+        self
+    }
+}
+
+impl DynamicUi<()> for Def<()> {
     fn add_ui(
         self: Box<Self>,
         sender: &Sender<()>,
@@ -185,6 +227,7 @@ impl DynamicTerm<()> for Def<()> {
         _: Path<UiSection>,
         _: Box<dyn DynamicVariance>,
         _: &mut Option<Cursor<UiSection>>,
+        _: &mut Edit,
         _: &Sender<()>,
     ) -> Result<Cursor<UiSection>, JsValue> {
         unimplemented!()
@@ -198,35 +241,6 @@ impl DynamicTerm<()> for Def<()> {
     ) -> Result<(), JsValue> {
         unimplemented!()
     }
-
-    fn clear_annotation(self: Box<Self>) -> Box<dyn DynamicTerm<()>> {
-        self
-    }
-
-    fn index(&self) -> u8 {
-        'D' as u8
-    }
-
-    fn encode(self: Box<Self>) -> Vec<u8> {
-        if let Ok(data) = encode(DefData {
-            expression: self.expression.clear_annotation().into(),
-            body: self.body.clear_annotation().into(),
-            binder: self.binder,
-        }) {
-            data
-        } else {
-            panic!()
-        }
-    }
-
-    fn expand(self: Box<Self>) -> Term<()> {
-        Term::Duplication {
-            binder: self.binder,
-            expression: Box::new(self.expression.clear_annotation()),
-            body: Box::new(self.body.clear_annotation()),
-            annotation: (),
-        }
-    }
 }
 
 #[derive(Debug, Clone)]
@@ -275,6 +289,41 @@ impl DynamicTerm<UiSection> for Def<UiSection> {
         Box::new(self.clone())
     }
 
+    fn clear_annotation(self: Box<Self>) -> Box<dyn DynamicTerm<()>> {
+        Box::new(Def {
+            expression: self.expression.clear_annotation(),
+            body: self.body.clear_annotation(),
+            binder: self.binder,
+        })
+    }
+
+    fn index(&self) -> u8 {
+        todo!()
+    }
+
+    fn encode(self: Box<Self>) -> Vec<u8> {
+        todo!()
+    }
+
+    fn expand(self: Box<Self>) -> Term<()> {
+        Term::Duplication {
+            binder: self.binder,
+            expression: Box::new(self.expression.clear_annotation()),
+            body: Box::new(self.body.clear_annotation()),
+            annotation: (),
+        }
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn into_any(self: Box<Self>) -> Box<dyn Any> {
+        self
+    }
+}
+
+impl DynamicUi<UiSection> for Def<UiSection> {
     fn add_ui(
         self: Box<Self>,
         _: &Sender<()>,
@@ -288,6 +337,7 @@ impl DynamicTerm<UiSection> for Def<UiSection> {
         up: Path<UiSection>,
         annotation: Box<dyn DynamicVariance>,
         focused: &mut Option<Cursor<UiSection>>,
+        edit: &mut Edit,
         sender: &Sender<()>,
     ) -> Result<Cursor<UiSection>, JsValue> {
         let c_expression = self.expression;
@@ -313,6 +363,7 @@ impl DynamicTerm<UiSection> for Def<UiSection> {
                 },
             ),
             focused,
+            edit,
             sender,
         )?;
 
@@ -329,12 +380,14 @@ impl DynamicTerm<UiSection> for Def<UiSection> {
                 },
             ),
             focused,
+            edit,
             sender,
         )?;
 
         for mutation in &mutations {
             match mutation {
                 DefMutation::Update(name) => {
+                    edit.text();
                     self.binder = if name.is_empty() {
                         None
                     } else {
@@ -359,6 +412,7 @@ impl DynamicTerm<UiSection> for Def<UiSection> {
         for mutation in &mutations {
             match mutation {
                 DefMutation::Remove => {
+                    edit.structure();
                     cursor = Cursor::Hole(match cursor {
                         Cursor::Dynamic(_) => {
                             HoleCursor::new(up.clone(), ui_section(Term::Hole(()), sender))
@@ -439,31 +493,6 @@ impl DynamicTerm<UiSection> for Def<UiSection> {
 
         Ok(())
     }
-
-    fn clear_annotation(self: Box<Self>) -> Box<dyn DynamicTerm<()>> {
-        Box::new(Def {
-            expression: self.expression.clear_annotation(),
-            body: self.body.clear_annotation(),
-            binder: self.binder,
-        })
-    }
-
-    fn index(&self) -> u8 {
-        todo!()
-    }
-
-    fn encode(self: Box<Self>) -> Vec<u8> {
-        todo!()
-    }
-
-    fn expand(self: Box<Self>) -> Term<()> {
-        Term::Duplication {
-            binder: self.binder,
-            expression: Box::new(self.expression.clear_annotation()),
-            body: Box::new(self.body.clear_annotation()),
-            annotation: (),
-        }
-    }
 }
 
 impl<T> Def<T> {
@@ -1,3 +1,6 @@
+mod annotated;
 mod def;
 pub use def::{Def, DefData};
 pub mod abst;
+mod ui;
+pub use ui::{register_dynamics, DynamicUi, HasDynamicUi};
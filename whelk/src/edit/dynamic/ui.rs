@@ -0,0 +1,140 @@
+use futures::channel::mpsc::Sender;
+use wasm_bindgen::JsValue;
+use web_sys::Node;
+
+use crate::edit::{
+    zipper::{
+        decode,
+        dynamic::{
+            register_dynamic, Annotated, Dynamic, DynamicReadError, DynamicTerm, VariableTerm,
+        },
+        Cursor, Path, Term,
+    },
+    DynamicVariance, Edit, UiSection,
+};
+
+use super::{
+    abst::{
+        controls::{ControlData, Variable},
+        implementation::Root,
+    },
+    Def, DefData,
+};
+
+/// The editing interface of a dynamic term, which the platform-independent `DynamicTerm` leaves
+/// out.
+pub trait DynamicUi<T>: DynamicTerm<T> {
+    fn add_ui(
+        self: Box<Self>,
+        sender: &Sender<()>,
+        editable: bool,
+    ) -> (UiSection, Box<dyn DynamicTerm<UiSection>>);
+
+    fn apply_mutations(
+        self: Box<Self>,
+        up: Path<UiSection>,
+        annotation: Box<dyn DynamicVariance>,
+        focused: &mut Option<Cursor<UiSection>>,
+        edit: &mut Edit,
+        sender: &Sender<()>,
+    ) -> Result<Cursor<UiSection>, JsValue>
+    where
+        Term<T>: Into<Term<UiSection>>;
+
+    fn render_to(
+        &self,
+        up: &Path<UiSection>,
+        annotation: &dyn DynamicVariance,
+        node: &Node,
+    ) -> Result<(), JsValue>;
+}
+
+/// Annotations whose dynamic terms can be given an editing interface.
+pub trait HasDynamicUi: Sized {
+    fn into_ui(term: Box<dyn DynamicTerm<Self>>) -> Box<dyn DynamicUi<Self>>;
+
+    fn as_ui(term: &dyn DynamicTerm<Self>) -> &dyn DynamicUi<Self>;
+}
+
+impl HasDynamicUi for () {
+    fn into_ui(term: Box<dyn DynamicTerm<()>>) -> Box<dyn DynamicUi<()>> {
+        let term = match term.into_any().downcast::<Def<()>>() {
+            Ok(term) => return term,
+            Err(term) => term,
+        };
+        let term = match term.downcast::<Root>() {
+            Ok(term) => return term,
+            Err(term) => term,
+        };
+        let term = match term.downcast::<Annotated<()>>() {
+            Ok(term) => return term,
+            Err(term) => term,
+        };
+        match term.downcast::<VariableTerm>() {
+            Ok(term) => Box::new(Root::new(Variable::new(term.idx()))),
+            Err(_) => panic!("dynamic term has no editing interface"),
+        }
+    }
+
+    fn as_ui(term: &dyn DynamicTerm<()>) -> &dyn DynamicUi<()> {
+        let term = term.as_any();
+        if let Some(term) = term.downcast_ref::<Def<()>>() {
+            term
+        } else if let Some(term) = term.downcast_ref::<Root>() {
+            term
+        } else if let Some(term) = term.downcast_ref::<Annotated<()>>() {
+            term
+        } else {
+            panic!("dynamic term has no editing interface")
+        }
+    }
+}
+
+impl HasDynamicUi for UiSection {
+    fn into_ui(term: Box<dyn DynamicTerm<UiSection>>) -> Box<dyn DynamicUi<UiSection>> {
+        let term = match term.into_any().downcast::<Def<UiSection>>() {
+            Ok(term) => return term,
+            Err(term) => term,
+        };
+        let term = match term.downcast::<Root>() {
+            Ok(term) => return term,
+            Err(term) => term,
+        };
+        match term.downcast::<Annotated<UiSection>>() {
+            Ok(term) => term,
+            Err(_) => panic!("dynamic term has no editing interface"),
+        }
+    }
+
+    fn as_ui(term: &dyn DynamicTerm<UiSection>) -> &dyn DynamicUi<UiSection> {
+        let term = term.as_any();
+        if let Some(term) = term.downcast_ref::<Def<UiSection>>() {
+            term
+        } else if let Some(term) = term.downcast_ref::<Root>() {
+            term
+        } else if let Some(term) = term.downcast_ref::<Annotated<UiSection>>() {
+            term
+        } else {
+            panic!("dynamic term has no editing interface")
+        }
+    }
+}
+
+/// Makes the dynamic terms defined here readable from their encoding on this thread.
+pub fn register_dynamics() {
+    register_dynamic(b'D', |data| {
+        let DefData {
+            binder,
+            expression,
+            body,
+        } = decode(data).map_err(|_| DynamicReadError::Invalid)?;
+        Ok(Dynamic::new(
+            (),
+            Def::new(body.into(), expression.into(), binder),
+        ))
+    });
+    register_dynamic(b'i', |data| {
+        let data: ControlData = decode(data).map_err(|_| DynamicReadError::Invalid)?;
+        Ok(Dynamic::new((), Root::new(data.to_control())))
+    });
+}
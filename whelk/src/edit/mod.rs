@@ -14,14 +14,18 @@ mod ui_section;
 #[doc(inline)]
 pub use ui_section::*;
 
+mod completions;
+pub use completions::*;
+
 mod apply_mutations;
+pub use apply_mutations::Edit;
 use apply_mutations::apply_mutations;
 
 mod render_to;
 use render_to::render_to;
 
 pub mod dynamic;
-pub mod zipper;
+pub use whelk_core::zipper;
 
 #[allow(dead_code)]
 fn focus_contenteditable(p: &Element, always: bool) {
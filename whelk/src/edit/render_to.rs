@@ -1,7 +1,7 @@
 use wasm_bindgen::JsValue;
 use web_sys::Node;
 
-use crate::edit::UiSectionVariance;
+use crate::edit::{dynamic::HasDynamicUi, UiSectionVariance};
 
 use super::{zipper::Cursor, UiSection};
 
@@ -80,7 +80,7 @@ pub fn render_to(data: &Cursor<UiSection>, node: &Node) -> Result<(), JsValue> {
         }
 
         Cursor::Dynamic(cursor) => {
-            cursor.term.render_to(
+            UiSection::as_ui(cursor.term.as_ref()).render_to(
                 &cursor.up,
                 match &cursor.annotation.variant {
                     UiSectionVariance::Dynamic(variance) => variance.as_ref(),
@@ -8,18 +8,70 @@ use futures::{
 use wasm_bindgen::{prelude::Closure, JsCast, JsValue};
 use wasm_bindgen_futures::{spawn_local, JsFuture};
 use web_sys::{ClipboardEvent, Element, FocusEvent, KeyboardEvent, Node};
+use whelk_core::history::{location, History};
 
-use crate::edit::{apply_mutations, mutations::HoleMutation, render_to, zipper::TermData};
+use crate::edit::{apply_mutations, mutations::HoleMutation, render_to, zipper::TermData, Edit};
 
 use super::{
     add_ui,
-    zipper::{Cursor, Term},
+    zipper::{
+        source::{from_source, to_source},
+        Cursor, Term,
+    },
     OnChangeWrapper, UiSection, UiSectionVariance,
 };
 
+/// The bytes of snapshots each scratchpad keeps to undo to.
+const HISTORY_BUDGET: usize = 1 << 20;
+
+/// The term under a cursor without its UI.
+fn bare_term(data: &Cursor<UiSection>) -> Term<()> {
+    Term::<UiSection>::from(data.clone()).clear_annotation()
+}
+
+/// Encodes the term under a cursor for the clipboard, as `TermData::decode` reads it back.
+fn encode(data: &Cursor<UiSection>) -> Option<String> {
+    let waker = noop_waker();
+    let mut context = Context::from_waker(&waker);
+
+    let mut fut = Box::pin(TermData::from(bare_term(data)).encode());
+
+    loop {
+        match fut.as_mut().poll(&mut context) {
+            std::task::Poll::Ready(data) => break data.ok(),
+            std::task::Poll::Pending => {}
+        }
+    }
+}
+
+/// Writes text to the system clipboard, reporting failure to the console, as when the page is
+/// denied access.
+fn write_clipboard(text: String) {
+    spawn_local(async move {
+        let written = match web_sys::window().unwrap().navigator().clipboard() {
+            Some(clipboard) => JsFuture::from(clipboard.write_text(&text))
+                .await
+                .map(|_| ()),
+            None => Err(JsValue::from_str("the clipboard is unavailable")),
+        };
+
+        if let Err(e) = written {
+            web_sys::console::error_2(&"could not write to the clipboard:".into(), &e);
+        }
+    });
+}
+
+fn copy_source(data: &Cursor<UiSection>) {
+    match to_source(&bare_term(data)) {
+        Ok(source) => write_clipboard(source),
+        Err(e) => web_sys::console::error_1(&format!("could not copy as source: {}", e).into()),
+    }
+}
+
 #[derive(Debug)]
 pub struct Scratchpad {
     data: Rc<RefCell<Cursor<UiSection>>>,
+    history: Rc<RefCell<History>>,
     needs_update: Receiver<()>,
     sender: Sender<()>,
     clipboard_event_handler: Closure<dyn FnMut(JsValue)>,
@@ -33,10 +85,12 @@ pub struct Scratchpad {
 impl Scratchpad {
     pub fn new_static(term: Term, target_node: Node) -> Self {
         let (mut sender, receiver) = channel(0);
+        let history = Rc::new(RefCell::new(History::new(&term, HISTORY_BUDGET)));
         let data = Rc::new(RefCell::new(add_ui(term, &sender, false).into()));
 
         let scratchpad = Scratchpad {
             data: data.clone(),
+            history,
             needs_update: receiver,
             target_node,
             has_focus: Rc::new(RefCell::new(false)),
@@ -47,39 +101,16 @@ impl Scratchpad {
             clipboard_event_handler: Closure::wrap(Box::new({
                 let mut sender = sender.clone();
                 move |e: JsValue| {
-                    let copy = |f: Box<dyn Fn(String)>| {
-                        let data = &mut *data.borrow_mut();
-
-                        let term: Term<()> =
-                            Term::<UiSection>::from(data.clone()).clear_annotation();
-
-                        let waker = noop_waker();
-                        let mut context = Context::from_waker(&waker);
-
-                        let mut fut = Box::pin(TermData::from(term.clear_annotation()).encode());
-
-                        let data = loop {
-                            match fut.as_mut().poll(&mut context) {
-                                std::task::Poll::Ready(data) => break data,
-                                std::task::Poll::Pending => {}
-                            }
-                        };
-
-                        if let Ok(data) = data {
-                            f(data);
-                        }
-                    };
-
                     if let Some(e) = e.dyn_ref::<KeyboardEvent>() {
                         if e.ctrl_key() {
                             if e.key() == "c" {
-                                copy(Box::new(|data| {
-                                    spawn_local(async move {
-                                        let navigator = web_sys::window().unwrap().navigator();
-                                        let clipboard = navigator.clipboard().unwrap();
-                                        JsFuture::from(clipboard.write_text(&data)).await.unwrap();
-                                    });
-                                }));
+                                if let Some(text) = encode(&data.borrow()) {
+                                    write_clipboard(text);
+                                }
+                                e.prevent_default();
+                                e.stop_propagation();
+                            } else if e.key() == "C" {
+                                copy_source(&data.borrow());
                                 e.prevent_default();
                                 e.stop_propagation();
                             }
@@ -92,9 +123,9 @@ impl Scratchpad {
 
                     match e.type_().as_str() {
                         "copy" => {
-                            copy(Box::new(|data| {
-                                c_data.set_data("text/plain", &data).unwrap();
-                            }));
+                            if let Some(text) = encode(&data.borrow()) {
+                                c_data.set_data("text/plain", &text).unwrap();
+                            }
                             e.prevent_default();
                         }
                         _ => {}
@@ -108,6 +139,7 @@ impl Scratchpad {
 
     pub fn new(term: Term, target_node: Node) -> (Sender<()>, Self) {
         let (mut sender, receiver) = channel(0);
+        let history = Rc::new(RefCell::new(History::new(&term, HISTORY_BUDGET)));
         let data = Rc::new(RefCell::new(add_ui(term, &sender, true).into()));
 
         let has_focus = Rc::new(RefCell::new(false));
@@ -146,8 +178,9 @@ impl Scratchpad {
 
         let scratchpad = Scratchpad {
             data: data.clone(),
+            history: history.clone(),
             needs_update: receiver,
-            target_node,
+            target_node: target_node.clone(),
             has_focus,
             editable: true,
             sender: sender.clone(),
@@ -156,52 +189,36 @@ impl Scratchpad {
             clipboard_event_handler: Closure::wrap(Box::new({
                 let mut sender = sender.clone();
                 move |e: JsValue| {
-                    let copy = |f: Box<dyn Fn(String)>| {
-                        let data = &mut *data.borrow_mut();
-
-                        let term: Term<()> =
-                            Term::<UiSection>::from(data.clone()).clear_annotation();
-
-                        let waker = noop_waker();
-                        let mut context = Context::from_waker(&waker);
-
-                        let mut fut = Box::pin(TermData::from(term.clear_annotation()).encode());
-
-                        let data = loop {
-                            match fut.as_mut().poll(&mut context) {
-                                std::task::Poll::Ready(data) => break data,
-                                std::task::Poll::Pending => {}
-                            }
-                        };
-
-                        if let Ok(data) = data {
-                            f(data);
-                        }
-                    };
-
                     if let Some(e) = e.dyn_ref::<KeyboardEvent>() {
                         if e.ctrl_key() {
                             if e.key() == "c" {
-                                copy(Box::new(|data| {
-                                    spawn_local(async move {
-                                        let navigator = web_sys::window().unwrap().navigator();
-                                        let clipboard = navigator.clipboard().unwrap();
-                                        JsFuture::from(clipboard.write_text(&data)).await.unwrap();
-                                    });
-                                }));
+                                if let Some(text) = encode(&data.borrow()) {
+                                    write_clipboard(text);
+                                }
                                 e.prevent_default();
                                 e.stop_propagation();
                             } else if e.key() == "x" {
-                                copy(Box::new(|data| {
-                                    spawn_local(async move {
-                                        let navigator = web_sys::window().unwrap().navigator();
-                                        let clipboard = navigator.clipboard().unwrap();
-                                        JsFuture::from(clipboard.write_text(&data)).await.unwrap();
-                                    });
-                                }));
+                                if let Some(text) = encode(&data.borrow()) {
+                                    write_clipboard(text);
+                                }
                                 data.borrow().annotation().trigger_remove(&sender);
                                 e.prevent_default();
                                 e.stop_propagation();
+                            } else if e.key() == "C" {
+                                copy_source(&data.borrow());
+                                e.prevent_default();
+                                e.stop_propagation();
+                            } else if e.key() == "z" || e.key() == "Z" {
+                                let term = if e.shift_key() {
+                                    history.borrow_mut().redo()
+                                } else {
+                                    history.borrow_mut().undo()
+                                };
+                                if let Some(term) = term {
+                                    replace(&data, &target_node, term, &mut sender, true);
+                                }
+                                e.prevent_default();
+                                e.stop_propagation();
                             }
                         }
 
@@ -212,16 +229,16 @@ impl Scratchpad {
 
                     match e.type_().as_str() {
                         "cut" => {
-                            copy(Box::new(|data| {
-                                c_data.set_data("text/plain", &data).unwrap();
-                            }));
+                            if let Some(text) = encode(&data.borrow()) {
+                                c_data.set_data("text/plain", &text).unwrap();
+                            }
                             data.borrow().annotation().trigger_remove(&sender);
                             e.prevent_default();
                         }
                         "copy" => {
-                            copy(Box::new(|data| {
-                                c_data.set_data("text/plain", &data).unwrap();
-                            }));
+                            if let Some(text) = encode(&data.borrow()) {
+                                c_data.set_data("text/plain", &text).unwrap();
+                            }
                             e.prevent_default();
                         }
                         "paste" => {
@@ -229,11 +246,11 @@ impl Scratchpad {
 
                             if let Cursor::Hole(cursor) = data {
                                 e.prevent_default();
-                                if let Ok(data) = c_data.get_data("text/plain") {
+                                if let Ok(text) = c_data.get_data("text/plain") {
                                     let waker = noop_waker();
                                     let mut context = Context::from_waker(&waker);
 
-                                    let mut fut = Box::pin(TermData::decode(data));
+                                    let mut fut = Box::pin(TermData::decode(text.clone()));
 
                                     let data = loop {
                                         match fut.as_mut().poll(&mut context) {
@@ -242,12 +259,18 @@ impl Scratchpad {
                                         }
                                     };
 
-                                    if let Ok(Some(data)) = data {
+                                    let data = match data {
+                                        Ok(Some(data)) => Some(data.into()),
+                                        Ok(None) => from_source(&text).ok(),
+                                        Err(_) => None,
+                                    };
+
+                                    if let Some(data) = data {
                                         match &mut cursor.annotation_mut().variant {
                                             UiSectionVariance::Hole { p, mutations, .. } => {
                                                 p.remove();
                                                 mutations.borrow_mut().push(HoleMutation::Replace(
-                                                    add_ui(data.into(), &sender, true),
+                                                    add_ui(data, &sender, true),
                                                 ));
                                             }
                                             _ => panic!(),
@@ -269,19 +292,13 @@ impl Scratchpad {
     }
 
     pub fn force_update(&mut self, data: Term) {
-        *self.data.borrow_mut() = add_ui(data, &self.sender, self.editable).into();
-        for i in 0..self.target_node.child_nodes().length() {
-            if let Some(el) = self
-                .target_node
-                .child_nodes()
-                .get(i)
-                .unwrap()
-                .dyn_ref::<Element>()
-            {
-                el.remove()
-            }
-        }
-        let _ = self.sender.try_send(());
+        replace(
+            &self.data,
+            &self.target_node,
+            data,
+            &mut self.sender,
+            self.editable,
+        );
     }
 
     pub fn cursor(&self) -> Cursor<UiSection> {
@@ -332,6 +349,7 @@ impl Scratchpad {
         let time = perf.now();
 
         let mut focused = None;
+        let mut edit = Edit::None;
         let mut data = self.data.borrow().clone();
 
         let el = self.root_el();
@@ -352,14 +370,14 @@ impl Scratchpad {
             self.clipboard_event_handler.as_ref().unchecked_ref(),
         )?;
 
-        data = apply_mutations(data, &mut focused, &self.sender)?;
+        data = apply_mutations(data, &mut focused, &mut edit, &self.sender)?;
 
         {
             let mut data = data.clone();
             while !data.is_top() {
                 data = data.ascend();
             }
-            apply_mutations(data, &mut focused, &self.sender)?;
+            apply_mutations(data, &mut focused, &mut edit, &self.sender)?;
         }
 
         if let Some(focused) = focused {
@@ -370,6 +388,24 @@ impl Scratchpad {
 
         *self.data.borrow_mut() = data;
 
+        {
+            let data = self.data.borrow();
+            let mut top = data.clone();
+            while !top.is_top() {
+                top = top.ascend();
+            }
+            // only a run of typing into one name is undone at once, so that each removal,
+            // replacement or toggle can be undone on its own
+            let group = if edit == Edit::Text {
+                Some(location(&data))
+            } else {
+                None
+            };
+            self.history
+                .borrow_mut()
+                .record(&Term::<UiSection>::from(top).clear_annotation(), group);
+        }
+
         self.on_change.call(&*self.data.borrow());
 
         console_log!("update took {:.1}ms", perf.now() - time);
@@ -415,3 +451,25 @@ impl Scratchpad {
         });
     }
 }
+
+/// Replaces the whole term of a scratchpad, discarding the interface of the old one.
+fn replace(
+    data: &Rc<RefCell<Cursor<UiSection>>>,
+    target_node: &Node,
+    term: Term,
+    sender: &mut Sender<()>,
+    editable: bool,
+) {
+    *data.borrow_mut() = add_ui(term, sender, editable).into();
+    for i in 0..target_node.child_nodes().length() {
+        if let Some(el) = target_node
+            .child_nodes()
+            .get(i)
+            .unwrap()
+            .dyn_ref::<Element>()
+        {
+            el.remove()
+        }
+    }
+    let _ = sender.try_send(());
+}
@@ -12,7 +12,7 @@ use crate::edit::{
             controls::{Adt, CompressedSize, Invoke, SizeLiteral, Zero},
             implementation::Root,
         },
-        Def,
+        Def, HasDynamicUi,
     },
     focus_contenteditable, focus_element,
     zipper::{analysis::AnalysisTerm, dynamic::Dynamic, Term},
@@ -790,7 +790,7 @@ pub fn add_ui<T: Zero + Clone + 'static>(
         Term::Dynamic(cursor) => {
             let (_, term) = cursor.into_inner();
 
-            let (annotation, term) = term.add_ui(sender, editable);
+            let (annotation, term) = <() as HasDynamicUi>::into_ui(term).add_ui(sender, editable);
 
             Term::Dynamic(Dynamic::new(annotation, term))
         }
@@ -0,0 +1,48 @@
+use welkin_core::term::EqualityCache;
+
+use crate::edit::{
+    zipper::{
+        analysis::{AnalysisTerm, TypedDefinitions},
+        Cursor,
+    },
+    UiSection,
+};
+
+/// Checks the whole term containing `cursor` against `root_ty`, returning the type inferred for
+/// the focused subterm.
+pub fn infer<T: TypedDefinitions<Option<UiSection>>>(
+    cursor: &Cursor<UiSection>,
+    root_ty: AnalysisTerm<()>,
+    defs: &T,
+    cache: &mut impl EqualityCache,
+) -> Option<AnalysisTerm<()>> {
+    let annotation = cursor.annotation().annotation.clone();
+
+    *annotation.borrow_mut() = None;
+
+    let mut cursor = cursor.clone();
+
+    while !cursor.is_top() {
+        cursor = cursor.ascend();
+    }
+
+    let term: AnalysisTerm<Option<UiSection>> = cursor.into();
+
+    let root_ty: AnalysisTerm<Option<UiSection>> = root_ty.map_annotation(&mut |_| None);
+
+    let _ = term.check_in(
+        &root_ty,
+        defs,
+        &mut |annotation, ty| {
+            if let Some(annotation) = annotation {
+                let annotation = &annotation.annotation;
+                *annotation.borrow_mut() = Some(ty.clone().clear_annotation());
+            }
+        },
+        &mut |_, _| {},
+        cache,
+    );
+
+    let annotation = &*annotation.borrow();
+    annotation.clone()
+}
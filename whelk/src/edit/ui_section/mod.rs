@@ -20,6 +20,9 @@ mod add_ui;
 #[doc(inline)]
 pub use add_ui::*;
 
+mod infer;
+pub use infer::infer;
+
 #[derive(Debug, Clone)]
 pub struct UiSection {
     pub(crate) variant: UiSectionVariance,
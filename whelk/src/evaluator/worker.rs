@@ -1,5 +1,6 @@
 use futures::Future;
 use welkin_core::term::Term;
+use whelk_core::worker::Interruption;
 
 use crate::{edit::zipper::analysis::NormalizationError, worker::WorkerWrapper};
 
@@ -20,10 +21,10 @@ pub struct CoreWorkerEvaluatorError(welkin_core::term::NormalizationError);
 
 impl CoreEvaluator for WorkerEvaluator {
     type Future = Pin<Box<dyn Future<Output = Result<Term<String>, Self::Error>>>>;
-    type Error = CoreWorkerEvaluatorError;
+    type Error = Interruption;
 
     fn evaluate(&self, mut term: Term<String>) -> Self::Future {
         let worker = self.0.clone();
-        Box::pin(async move { Ok(worker.evaluate(term).await) })
+        Box::pin(async move { worker.evaluate(term).await })
     }
 }
@@ -7,11 +7,12 @@ use std::{cell::RefCell, collections::HashMap, error::Error, mem::replace, panic
 
 use async_recursion::async_recursion;
 use bindings::{io::iter::LoopRequest, w};
+use console::Console;
 use edit::{
-    dynamic::abst::controls::Zero,
+    dynamic::register_dynamics,
     zipper::{
         self,
-        analysis::{self, AnalysisError, AnalysisTerm},
+        analysis::{AnalysisError, AnalysisTerm},
         Cursor, TermData,
     },
     Scratchpad, UiSection,
@@ -24,36 +25,60 @@ use futures::{
     },
     stream, Stream, StreamExt,
 };
-use serde::{Deserialize, Serialize};
 use wasm_bindgen::{
     prelude::{wasm_bindgen, Closure},
     JsCast, JsValue,
 };
 use wasm_bindgen_futures::spawn_local;
-use web_sys::{Element, KeyboardEvent, MessageEvent, Node, Worker};
-use welkin::Terms;
-use welkin_binding::{FromAnalogue, FromWelkin, ToWelkin};
-use welkin_core::term::{DefinitionResult, MapCache, Term, TypedDefinitions};
+use web_sys::{Element, KeyboardEvent, MessageEvent, Node};
+use welkin::{container, Terms};
+use welkin_binding::{Diagnose, FromAnalogue, FromWelkin, ToWelkin};
+use welkin_core::term::{MapCache, Term};
+use whelk_core::{
+    history::location,
+    worker::{CheckError, DefWrapper, LoadedTerms},
+    zipper::{at_location, RefactorError},
+};
 
 use crate::{
-    edit::{add_ui, mutations::HoleMutation, UiSectionVariance},
+    edit::{
+        accept_completion, add_ui, close_completions, completion_prefix, infer,
+        mutations::HoleMutation, show_completions, UiSectionVariance,
+    },
     evaluator::WorkerEvaluator,
-    worker::{CheckError, WorkerWrapper},
+    worker::WorkerWrapper,
 };
 
 mod bindings;
+mod console;
 mod edit;
 mod evaluator;
 mod worker;
 
+pub use edit::zipper::{
+    source::{from_source, to_source, SourceError},
+    Term as ScratchpadTerm, TermData,
+};
+
 thread_local! {
     pub static CACHE: RefCell<MapCache> = RefCell::new(MapCache::new());
 }
 
+/// How long, in milliseconds, the worker searches for terms to fill a hole.
+const SEARCH_BUDGET: u64 = 2000;
+
+/// The most workers scratchpads are checked and evaluated in parallel on.
+const MAX_WORKERS: usize = 4;
+
 #[wasm_bindgen]
-pub fn entry(terms: Vec<u8>, worker: Worker) -> Result<(), JsValue> {
+pub fn entry(terms: Vec<u8>, spawn_worker: js_sys::Function) -> Result<(), JsValue> {
+    register_dynamics();
+
+    let terms: Terms = container::decode(&terms)
+        .map_err(|e| JsValue::from_str(&format!("could not load terms: {}", e)))?;
+
     spawn_local(async move {
-        main(terms, worker).await.unwrap();
+        main(terms, spawn_worker).await.unwrap();
     });
     Ok(())
 }
@@ -84,14 +109,17 @@ enum Block {
     },
 }
 
-async fn main(terms: Vec<u8>, worker: Worker) -> Result<(), JsValue> {
+async fn main(terms: Terms, spawn_worker: js_sys::Function) -> Result<(), JsValue> {
     panic::set_hook(Box::new(console_error_panic_hook::hook));
 
-    let worker = WorkerWrapper::new(worker);
+    let window = web_sys::window().unwrap();
 
-    let terms: Terms = bincode::deserialize(&terms).unwrap();
+    // leaves a thread for the page itself
+    let workers = (window.navigator().hardware_concurrency() as usize)
+        .saturating_sub(1)
+        .clamp(1, MAX_WORKERS);
+    let worker = WorkerWrapper::new(spawn_worker, workers);
 
-    let window = web_sys::window().unwrap();
     let document = window.document().unwrap();
 
     let container = document.get_element_by_id("container").unwrap();
@@ -118,7 +146,7 @@ async fn main(terms: Vec<u8>, worker: Worker) -> Result<(), JsValue> {
         data.push(TermData::Hole);
     }
 
-    let terms = Rc::new(terms);
+    let terms = Rc::new(LoadedTerms::from(terms));
 
     let defs = Rc::new(RefCell::new(HashMap::new()));
 
@@ -302,11 +330,88 @@ fn push_paragraph(data: Block, container: &Element) {
     }
 }
 
+/// Replaces the focused term of a scratchpad by removing it and filling the hole left behind.
+fn replace_focus(
+    data: &Rc<RefCell<Cursor<UiSection>>>,
+    term: zipper::Term<()>,
+    sender: &Sender<()>,
+) {
+    data.borrow().annotation().trigger_remove(sender);
+
+    spawn_local({
+        let data = data.clone();
+        let mut sender = sender.clone();
+        async move {
+            if let Cursor::Hole(cursor) = &*data.borrow() {
+                let annotation = cursor.annotation();
+                if let UiSection {
+                    variant: UiSectionVariance::Hole { mutations, p, .. },
+                    ..
+                } = annotation
+                {
+                    p.remove();
+                    mutations
+                        .borrow_mut()
+                        .push(HoleMutation::Replace(add_ui(term, &sender, true)));
+                    let _ = sender.try_send(());
+                }
+            }
+        }
+    });
+}
+
+/// Refactors the focused term of a scratchpad, reporting to `output` when it can't be.
+fn refactor(
+    data: &Rc<RefCell<Cursor<UiSection>>>,
+    sender: &Sender<()>,
+    output: &Element,
+    refactor: impl FnOnce(Cursor<()>) -> Result<Cursor<()>, RefactorError>,
+) {
+    // refactorings work on the term without its interface, at the same place in it
+    let cursor = {
+        let data = data.borrow();
+        let mut top = data.clone();
+        while !top.is_top() {
+            top = top.ascend();
+        }
+        at_location(
+            zipper::Term::<UiSection>::from(top).clear_annotation(),
+            &location(&data),
+        )
+    };
+
+    let refactored = match cursor {
+        Some(cursor) => refactor(cursor).map_err(|e| e.to_string()),
+        None => Err("terms within this one can't be refactored".into()),
+    };
+
+    match refactored {
+        Ok(cursor) => replace_focus(data, cursor.into(), sender),
+        Err(e) => push_paragraph(
+            Block::Info {
+                header: "refactor".into(),
+                content: e,
+            },
+            output,
+        ),
+    }
+}
+
+/// Asks for a name to give a new binder or definition, if one is given.
+fn prompt_name(purpose: &str) -> Option<String> {
+    web_sys::window()
+        .unwrap()
+        .prompt_with_message(&format!("name to {}", purpose))
+        .ok()
+        .flatten()
+        .filter(|name| !name.is_empty())
+}
+
 async fn add_scratchpad(
     term: zipper::Term,
     pads: Rc<RefCell<Vec<ScratchpadContainer>>>,
     defs: Rc<RefCell<HashMap<String, (Term<String>, Term<String>)>>>,
-    terms: Rc<Terms>,
+    terms: Rc<LoadedTerms>,
     worker: WorkerWrapper,
 ) -> Result<ScratchpadContainer, JsValue> {
     let call: Rc<RefCell<Box<dyn FnMut(JsValue)>>> = Rc::new(RefCell::new(Box::new(|_| {})));
@@ -344,6 +449,7 @@ async fn add_scratchpad(
         let data = pad.data.clone();
         let defs = defs.clone();
         let worker = worker.clone();
+        let output = output.clone();
         move |e| {
             let e: KeyboardEvent = e.dyn_into().unwrap();
             let code = e.code();
@@ -378,7 +484,8 @@ async fn add_scratchpad(
                     let defs = DefWrapper(defs.clone(), terms.clone());
 
                     CACHE.with(|cache| {
-                        let ty = data.borrow().infer(
+                        let ty = infer(
+                            &data.borrow(),
                             AnalysisTerm::Reference("Whelk".into(), ()),
                             &defs,
                             &mut *cache.borrow_mut(),
@@ -416,30 +523,226 @@ async fn add_scratchpad(
                             };
                         }
 
-                        data.borrow().annotation().trigger_remove(&sender);
-
-                        spawn_local({
-                            let data = data.clone();
-                            let mut sender = sender.clone();
-                            async move {
-                                if let Cursor::Hole(cursor) = &*data.borrow() {
-                                    let annotation = cursor.annotation();
-                                    if let UiSection {
-                                        variant: UiSectionVariance::Hole { mutations, p, .. },
-                                        ..
-                                    } = annotation
-                                    {
-                                        p.remove();
-                                        mutations.borrow_mut().push(HoleMutation::Replace(add_ui(
-                                            term, &sender, true,
-                                        )));
-                                        let _ = sender.try_send(());
-                                    }
+                        replace_focus(&data, term, &sender);
+                    });
+
+                    e.prevent_default();
+                    e.stop_propagation();
+                }
+                "Space" if e.ctrl_key() => {
+                    let prefix = match completion_prefix(&data.borrow()) {
+                        Some(prefix) => prefix,
+                        None => return,
+                    };
+                    let hole = match &*data.borrow() {
+                        Cursor::Hole(cursor) => cursor.annotation().annotation.clone(),
+                        _ => return,
+                    };
+
+                    let mut top = data.borrow().clone();
+                    while !top.is_top() {
+                        top = top.ascend();
+                    }
+                    let term: AnalysisTerm<Option<UiSection>> = top.into();
+
+                    spawn_local({
+                        let data = data.clone();
+                        let worker = worker.clone();
+                        let sender = sender.clone();
+                        async move {
+                            let completions = worker
+                                .complete(
+                                    term,
+                                    AnalysisTerm::Reference("Whelk".into(), None),
+                                    |section| Rc::ptr_eq(&section.annotation, &hole),
+                                    prefix,
+                                )
+                                .await;
+
+                            let data = data.borrow();
+                            if let Cursor::Hole(cursor) = &*data {
+                                // the focus may have moved on while completing
+                                if Rc::ptr_eq(&cursor.annotation().annotation, &hole) {
+                                    show_completions(&data, completions, &sender);
                                 }
                             }
+                        }
+                    });
+
+                    e.prevent_default();
+                    e.stop_propagation();
+                }
+                "Tab" => {
+                    if accept_completion() {
+                        e.prevent_default();
+                        e.stop_propagation();
+                    }
+                }
+                "Escape" => close_completions(),
+                "Enter" if e.ctrl_key() => {
+                    let hole = match &*data.borrow() {
+                        Cursor::Hole(cursor) => cursor.annotation().annotation.clone(),
+                        _ => return,
+                    };
+
+                    let mut top = data.borrow().clone();
+                    while !top.is_top() {
+                        top = top.ascend();
+                    }
+                    let term: AnalysisTerm<Option<UiSection>> = top.into();
+
+                    spawn_local({
+                        let data = data.clone();
+                        let output = output.clone();
+                        let worker = worker.clone();
+                        let mut sender = sender.clone();
+                        async move {
+                            let mut solutions = worker
+                                .search(
+                                    term,
+                                    AnalysisTerm::Reference("Whelk".into(), None),
+                                    |section| Rc::ptr_eq(&section.annotation, &hole),
+                                    SEARCH_BUDGET,
+                                )
+                                .await
+                                .into_iter();
+
+                            let solution = match solutions.next() {
+                                Some(solution) => solution,
+                                None => {
+                                    push_paragraph(
+                                        Block::Info {
+                                            header: "search".into(),
+                                            content: "no solutions found".into(),
+                                        },
+                                        &output,
+                                    );
+                                    return;
+                                }
+                            };
+                            for alternative in solutions {
+                                push_paragraph(
+                                    Block::Term {
+                                        prefix: "alternative".into(),
+                                        data: alternative,
+                                    },
+                                    &output,
+                                );
+                            }
+
+                            if let Cursor::Hole(cursor) = &*data.borrow() {
+                                let section = cursor.annotation();
+                                // the focus may have moved on while searching
+                                if !Rc::ptr_eq(&section.annotation, &hole) {
+                                    return;
+                                }
+                                if let UiSectionVariance::Hole { mutations, p, .. } =
+                                    &section.variant
+                                {
+                                    p.remove();
+                                    mutations.borrow_mut().push(HoleMutation::Replace(add_ui(
+                                        zipper::Term::from(solution),
+                                        &sender,
+                                        true,
+                                    )));
+                                    let _ = sender.try_send(());
+                                }
+                            }
+                        }
+                    });
+
+                    e.prevent_default();
+                    e.stop_propagation();
+                }
+                "KeyL" if e.alt_key() => {
+                    if let Some(name) = prompt_name("bind") {
+                        let erased = e.shift_key();
+                        refactor(&data, &sender, &output, |cursor| {
+                            cursor.wrap_lambda(erased, name)
                         });
+                    }
+                    e.prevent_default();
+                    e.stop_propagation();
+                }
+                "KeyP" if e.alt_key() => {
+                    let erased = e.shift_key();
+                    refactor(&data, &sender, &output, |cursor| {
+                        Ok(cursor.wrap_application(erased))
                     });
+                    e.prevent_default();
+                    e.stop_propagation();
+                }
+                "KeyA" if e.alt_key() => {
+                    refactor(&data, &sender, &output, |cursor| {
+                        Ok(cursor.wrap_annotation(zipper::Term::Hole(())))
+                    });
+                    e.prevent_default();
+                    e.stop_propagation();
+                }
+                "KeyB" if e.alt_key() => {
+                    refactor(&data, &sender, &output, |cursor| cursor.beta_reduce());
+                    e.prevent_default();
+                    e.stop_propagation();
+                }
+                "KeyE" if e.alt_key() => {
+                    let defs = DefWrapper(defs.clone(), terms.clone());
+                    let ty = CACHE.with(|cache| {
+                        infer(
+                            &data.borrow(),
+                            AnalysisTerm::Reference("Whelk".into(), ()),
+                            &defs,
+                            &mut *cache.borrow_mut(),
+                        )
+                    });
+                    refactor(&data, &sender, &output, |cursor| {
+                        ty.ok_or(RefactorError::NotAFunction)
+                            .and_then(|ty| cursor.eta_expand(&ty))
+                    });
+                    e.prevent_default();
+                    e.stop_propagation();
+                }
+                "KeyX" if e.alt_key() => {
+                    if let Some(name) = prompt_name("define") {
+                        let mut definition = None;
+                        refactor(&data, &sender, &output, |cursor| {
+                            let (cursor, term) = cursor.extract(name)?;
+                            definition = Some(term);
+                            Ok(cursor)
+                        });
 
+                        // the new definition goes in a scratchpad of its own after this one
+                        if let Some(definition) = definition {
+                            let wrapper = wrapper.clone();
+                            let pads = pads.clone();
+                            let terms = terms.clone();
+                            let defs = defs.clone();
+                            let worker = worker.clone();
+                            spawn_local(async move {
+                                let pad = add_scratchpad(
+                                    definition,
+                                    pads.clone(),
+                                    defs.clone(),
+                                    terms.clone(),
+                                    worker.clone(),
+                                )
+                                .await
+                                .unwrap();
+                                let idx = pads
+                                    .borrow()
+                                    .iter()
+                                    .position(|pad| pad.wrapper == wrapper)
+                                    .unwrap();
+                                wrapper.after_with_node_1(&pad.wrapper).unwrap();
+                                pads.borrow_mut().insert(idx + 1, pad);
+                            });
+                        }
+                    }
+                    e.prevent_default();
+                    e.stop_propagation();
+                }
+                "KeyI" if e.alt_key() => {
+                    let defs = DefWrapper(defs.clone(), terms.clone());
+                    refactor(&data, &sender, &output, |cursor| cursor.inline(&defs));
                     e.prevent_default();
                     e.stop_propagation();
                 }
@@ -484,8 +787,14 @@ async fn add_scratchpad(
         let worker = worker.clone();
         async move {
             let mut receiver = Box::pin(stream::once(async { () }).chain(receiver));
+            let mut running: Option<(Rc<Console>, WorkerWrapper)> = None;
 
             while let Some(()) = receiver.next().await {
+                if let Some((console, session)) = running.take() {
+                    console.close();
+                    session.cancel();
+                }
+
                 output.set_inner_html("");
 
                 status.remove_attribute("class").unwrap();
@@ -509,6 +818,9 @@ async fn add_scratchpad(
 
                 {
                     let (sender, receiver) = oneshot::channel();
+                    let session = worker.session();
+                    let (io_console, mut lines) = Console::new(output.clone(), session.clone());
+                    running = Some((io_console.clone(), session.clone()));
 
                     CACHE.with(|cache| {
                         let defs = DefWrapper(defs.clone(), terms.clone());
@@ -520,6 +832,7 @@ async fn add_scratchpad(
                         let worker = worker.clone();
 
                         spawn_local(async move {
+                            let mut done = Some(sender);
                             let complete = term.is_complete();
                             let time = perf.now();
                             let check = worker
@@ -592,18 +905,33 @@ async fn add_scratchpad(
                                         .add_3("scratchpad", "status", "def-ok")
                                         .unwrap();
 
-                                    let evaluator = WorkerEvaluator(worker.clone());
+                                    let evaluator = WorkerEvaluator(session.clone());
 
                                     if term.is_complete() {
                                         let time = perf.now();
 
-                                        let whelk = {
-                                            let term = worker
-                                                .expand_evaluate(term.clone().clear_annotation())
-                                                .await;
-                                            w::Whelk::from_welkin(term).unwrap()
+                                        // evaluation and IO run in the background, cancelled by
+                                        // the next edit
+                                        done.take().unwrap().send(()).unwrap();
+
+                                        let whelk = match session
+                                            .expand_evaluate(term.clone().clear_annotation())
+                                            .await
+                                        {
+                                            Ok(term) => w::Whelk::from_welkin(term).unwrap(),
+                                            Err(e) => {
+                                                io_console.push(Block::Info {
+                                                    header: "EVAL ERR".into(),
+                                                    content: e.to_string(),
+                                                });
+                                                return;
+                                            }
                                         };
 
+                                        if io_console.is_closed() {
+                                            return;
+                                        }
+
                                         let io = match whelk {
                                             w::Whelk::new { data } => match data {
                                                 w::BoxPoly::new { data } => data,
@@ -616,17 +944,30 @@ async fn add_scratchpad(
 
                                         let mut new_defs = vec![];
 
-                                        run_io(
+                                        let ran = run_io(
                                             io,
-                                            &|block| push_paragraph(block, &output),
-                                            &mut stream::pending(),
+                                            &|block| io_console.push(block),
+                                            &|| io_console.prompt(),
+                                            &|| io_console.progress(),
+                                            &mut lines,
                                             &defs,
                                             &evaluator,
-                                            &worker,
+                                            &session,
                                             &mut new_defs,
                                         )
-                                        .await
-                                        .unwrap();
+                                        .await;
+
+                                        if io_console.is_closed() {
+                                            return;
+                                        }
+
+                                        if let Err(e) = ran {
+                                            io_console.push(Block::Info {
+                                                header: "IO ERR".into(),
+                                                content: e.to_string(),
+                                            });
+                                            return;
+                                        }
 
                                         let mut errors = vec![];
 
@@ -647,7 +988,7 @@ async fn add_scratchpad(
                                         for (name, ty, term) in &new_defs {
                                             let mut t_error = None;
 
-                                            if let Err(e) = worker
+                                            if let Err(e) = session
                                                 .check(
                                                     ty.clone().map_annotation(&mut |_| None::<()>),
                                                     AnalysisTerm::Universe(None),
@@ -663,7 +1004,7 @@ async fn add_scratchpad(
                                             }
 
                                             if t_error.is_none() {
-                                                if let Err(e) = worker
+                                                if let Err(e) = session
                                                     .check(
                                                         term.clone()
                                                             .map_annotation(&mut |_| None::<()>),
@@ -695,10 +1036,10 @@ async fn add_scratchpad(
                                         let errored = !errors.is_empty();
 
                                         for error in errors {
-                                            push_paragraph(error, &output);
+                                            io_console.push(error);
                                         }
 
-                                        if !errored {
+                                        if !errored && !io_console.is_closed() {
                                             for (name, ty, term) in new_defs {
                                                 let term: Term<String> = term.into();
                                                 let ty: Term<String> = ty.into();
@@ -734,7 +1075,9 @@ async fn add_scratchpad(
                                     status.class_list().add_2("scratchpad", "status").unwrap();
                                 }
                             }
-                            sender.send(()).unwrap();
+                            if let Some(done) = done {
+                                done.send(()).unwrap();
+                            }
                         });
                     });
                     receiver.await.unwrap();
@@ -746,66 +1089,6 @@ async fn add_scratchpad(
     Ok(pad)
 }
 
-#[derive(Clone)]
-pub struct DefWrapper(
-    Rc<RefCell<HashMap<String, (Term<String>, Term<String>)>>>,
-    Rc<Terms>,
-);
-
-#[derive(Serialize, Deserialize)]
-pub struct DefWrapperData(HashMap<String, (Term<String>, Term<String>)>, Terms);
-
-impl From<DefWrapper> for DefWrapperData {
-    fn from(data: DefWrapper) -> Self {
-        DefWrapperData(data.0.borrow().clone(), (&*data.1).clone())
-    }
-}
-
-impl From<DefWrapperData> for DefWrapper {
-    fn from(data: DefWrapperData) -> Self {
-        DefWrapper(Rc::new(RefCell::new(data.0)), Rc::new(data.1))
-    }
-}
-
-impl<T: Zero> analysis::TypedDefinitions<T> for DefWrapper {
-    fn get_typed(
-        &self,
-        name: &str,
-    ) -> Option<analysis::DefinitionResult<(AnalysisTerm<T>, AnalysisTerm<T>)>> {
-        TypedDefinitions::get_typed(self, &name.to_owned()).map(|defs| match defs {
-            DefinitionResult::Borrowed((ty, term)) => {
-                analysis::DefinitionResult::Owned((ty.clone().into(), term.clone().into()))
-            }
-            DefinitionResult::Owned((ty, term)) => {
-                analysis::DefinitionResult::Owned((ty.into(), term.into()))
-            }
-        })
-    }
-}
-
-impl TypedDefinitions<String> for DefWrapper {
-    fn get_typed(&self, n: &String) -> Option<DefinitionResult<(Term<String>, Term<String>)>> {
-        self.0
-            .borrow()
-            .get(n)
-            .map(|(ty, term)| DefinitionResult::Owned((ty.clone(), term.clone())))
-            .or_else(|| {
-                self.1.data.iter().find_map(|(name, ty, term)| {
-                    if &format!("{:?}", name) == n {
-                        Some(DefinitionResult::Owned((
-                            ty.clone()
-                                .map_reference(|a| Term::Reference(format!("{:?}", a))),
-                            term.clone()
-                                .map_reference(|a| Term::Reference(format!("{:?}", a))),
-                        )))
-                    } else {
-                        None
-                    }
-                })
-            })
-    }
-}
-
 fn read_definition(term: &Term<String>) -> Option<(String, Term<String>, Term<String>)> {
     if let Term::Apply {
         function,
@@ -945,12 +1228,17 @@ fn make_scratchpad(
 #[async_recursion(?Send)]
 async fn run_io<
     F: Fn(Block),
+    P: Fn(),
+    L: Fn() -> G,
+    G: Fn(usize) -> bool,
     R: Stream<Item = String> + Unpin,
     E: CoreEvaluator + 'static,
     D: Clone + FromAnalogue + 'static,
 >(
     mut io: w::WhelkIO<D>,
     push_paragraph: &F,
+    prompt: &P,
+    progress: &L,
     receive: &mut R,
     defs: &DefWrapper,
     evaluator: &E,
@@ -959,7 +1247,7 @@ async fn run_io<
 ) -> Result<D, anyhow::Error>
 where
     E::Error: Error + Send + Sync,
-    <<D as FromAnalogue>::Analogue as FromWelkin>::Error: Send + Sync + Error,
+    <<D as FromAnalogue>::Analogue as FromWelkin>::Error: Send + Sync + Error + Diagnose,
 {
     match io {
         w::IO::end { value } => Ok(value),
@@ -988,12 +1276,16 @@ where
                         replace(&mut r#continue.0, Term::Universe),
                         replace(&mut step.0, Term::Universe),
                     );
+                    let update = progress();
+                    let mut iterations = 0;
                     loop {
                         request
                             .step(&*evaluator, |io| async {
                                 run_io(
                                     io,
                                     &*push_paragraph,
+                                    &*prompt,
+                                    &*progress,
                                     &mut *receive,
                                     &*defs,
                                     &*evaluator,
@@ -1003,11 +1295,14 @@ where
                                 .await
                             })
                             .await?;
-                        if {
-                            // TODO allow loops later in background
-                            request.proceed(&*evaluator).await?;
-                            false
-                        } {
+                        iterations += 1;
+                        if !update(iterations) {
+                            break Err(anyhow::anyhow!(
+                                "loop cancelled after {} iterations",
+                                iterations
+                            ));
+                        }
+                        if request.proceed(&*evaluator).await? {
                             continue;
                         } else {
                             break Ok(FromAnalogue::from_analogue(FromWelkin::from_welkin(
@@ -1017,9 +1312,10 @@ where
                     }
                 }
                 w::WhelkRequest::prompt { .. } => {
-                    // TODO reinstate actual message input
-                    // let message = receive.next().await.unwrap();
-                    let message: String = "placeholder".into();
+                    prompt();
+                    let message = receive.next().await.ok_or_else(|| {
+                        anyhow::anyhow!("prompt closed before a line was entered")
+                    })?;
                     push_paragraph(Block::Info {
                         header: "FUL".into(),
                         content: format!("{:?}", message.clone()),
@@ -1040,6 +1336,8 @@ where
                     run_io(
                         io,
                         &*push_paragraph,
+                        &*prompt,
+                        &*progress,
                         &mut *receive,
                         &*defs,
                         &*evaluator,
@@ -1062,6 +1360,8 @@ where
                     run_io(
                         io,
                         &*push_paragraph,
+                        &*prompt,
+                        &*progress,
                         &mut *receive,
                         &*defs,
                         &*evaluator,
@@ -1093,6 +1393,8 @@ where
                     run_io(
                         io,
                         &*push_paragraph,
+                        &*prompt,
+                        &*progress,
                         &mut *receive,
                         &*defs,
                         &*evaluator,
@@ -1,126 +1,266 @@
-use std::{cell::RefCell, collections::HashMap, panic, rc::Rc};
+use std::{
+    cell::RefCell,
+    collections::{HashMap, HashSet},
+    mem::replace,
+    panic,
+    rc::Rc,
+};
 
-use futures::channel::oneshot::{channel, Sender};
-use js_sys::Uint8Array;
-use serde::{Deserialize, Serialize};
+use futures::{
+    channel::oneshot::{channel, Sender},
+    future::{select, Either},
+};
+use js_sys::{Function, Promise, Uint8Array};
 use uuid::Uuid;
 use wasm_bindgen::{prelude::Closure, JsCast, JsValue};
+use wasm_bindgen_futures::JsFuture;
 use web_sys::{DedicatedWorkerGlobalScope, MessageEvent, Worker};
-use welkin_core::term::{MapCache, Term};
-
-use crate::{
-    edit::{
-        dynamic::abst::controls::Zero,
-        zipper::analysis::{
-            AnalysisError, AnalysisTerm, DefinitionResult, StratificationError, TypedDefinitions,
-        },
+use welkin_core::term::Term;
+use whelk_core::{
+    completion::Completion,
+    pool::Pool,
+    worker::{
+        CheckError, DefWrapper, Interruption, WorkerRequest, WorkerRequestVariant, WorkerResponse,
+        WorkerState,
     },
-    evaluator::{CoreEvaluator, Inet},
-    DefWrapper, DefWrapperData,
 };
 
+use crate::edit::zipper::analysis::AnalysisTerm;
+
 thread_local! {
-    pub static DEFS: RefCell<Option<DefWrapper>> = RefCell::new(None);
-    pub static CACHE: RefCell<MapCache> = RefCell::new(MapCache::new());
+    pub static STATE: WorkerState = WorkerState::new().with_clock(js_sys::Date::now);
     pub static INITIALIZED: RefCell<bool> = RefCell::new(false);
-    pub static TEMP_DEFS: RefCell<HashMap<Uuid, Vec<(String, Term<String>, Term<String>)>>> = RefCell::new(HashMap::new());
 }
 
-#[derive(Clone)]
-pub struct WorkerWrapper {
-    worker: Worker,
-    channels: Rc<RefCell<HashMap<Uuid, Sender<WorkerResponse>>>>,
-    on_message: Rc<Closure<dyn FnMut(JsValue)>>,
-}
+/// How long, in milliseconds, a check may take before the worker is replaced.
+const CHECK_LIMIT: u64 = 10_000;
 
-#[derive(Debug, Serialize, Deserialize)]
-pub enum CheckError<T> {
-    Analysis(AnalysisError<T>),
-    Stratification(StratificationError),
-    Recursive,
+/// How long, in milliseconds, an evaluation may take before the worker is replaced.
+const EVALUATE_LIMIT: u64 = 30_000;
+
+/// How long, in milliseconds, a search may overrun its budget before the worker is replaced.
+const SEARCH_GRACE: u64 = 2_000;
+
+/// One worker of a pool.
+struct Slot {
+    worker: RefCell<Worker>,
+    on_message: Closure<dyn FnMut(JsValue)>,
 }
 
-impl<T> CheckError<T> {
-    pub fn map_annotation<U, F: FnMut(T) -> U>(self, call: &mut F) -> CheckError<U> {
-        match self {
-            CheckError::Analysis(e) => CheckError::Analysis(e.map_annotation(call)),
-            CheckError::Stratification(e) => CheckError::Stratification(e),
-            CheckError::Recursive => CheckError::Recursive,
-        }
+impl Slot {
+    fn post(&self, request: &[u8]) {
+        self.worker
+            .borrow()
+            .post_message(&Uint8Array::from(request).into())
+            .unwrap();
     }
 }
 
-impl<T> From<AnalysisError<T>> for CheckError<T> {
-    fn from(e: AnalysisError<T>) -> Self {
-        CheckError::Analysis(e)
+/// Tells the request the worker `slot` is handling that it has started on it.
+fn start(pool: &RefCell<Pool>, started: &RefCell<HashMap<Uuid, Sender<()>>>, slot: usize) {
+    if let Some(idx) = pool.borrow().running(slot) {
+        if let Some(sender) = started.borrow_mut().remove(&idx) {
+            let _ = sender.send(());
+        }
     }
 }
 
-impl<T> From<StratificationError> for CheckError<T> {
-    fn from(e: StratificationError) -> Self {
-        CheckError::Stratification(e)
-    }
+/// A pool of workers, each holding every registered definition, that requests are shared
+/// between.
+#[derive(Clone)]
+pub struct WorkerWrapper {
+    /// Starts a fresh worker, for the pool and for when one of it is stuck.
+    spawn: Function,
+    slots: Rc<Vec<Slot>>,
+    pool: Rc<RefCell<Pool>>,
+    channels: Rc<RefCell<HashMap<Uuid, Sender<WorkerResponse>>>>,
+    /// Told when the request of each id is the one its worker is handling.
+    started: Rc<RefCell<HashMap<Uuid, Sender<()>>>>,
+    /// The requests made through this wrapper, cancelled together, if it is a session.
+    session: Option<Rc<RefCell<HashSet<Uuid>>>>,
 }
 
 pub struct TempDefs(Uuid);
 
-pub struct MergeDefs<'a>(
-    &'a DefWrapper,
-    &'a Option<Vec<(String, Term<String>, Term<String>)>>,
-);
-
-impl<'a, T: Zero> TypedDefinitions<T> for MergeDefs<'a> {
-    fn get_typed(
-        &self,
-        name: &str,
-    ) -> Option<DefinitionResult<(AnalysisTerm<T>, AnalysisTerm<T>)>> {
-        self.1
-            .as_ref()
-            .and_then(|defs| defs.iter().find(|(n, _, _)| n.as_str() == name).cloned())
-            .map(|(_, ty, term)| DefinitionResult::Owned((ty.into(), term.into())))
-            .or_else(|| self.0.get_typed(name))
-    }
-}
-
 impl WorkerWrapper {
-    pub fn new(worker: Worker) -> Self {
+    /// A pool of `workers` workers, each started by calling `spawn`.
+    pub fn new(spawn: Function, workers: usize) -> Self {
+        let pool = Rc::new(RefCell::new(Pool::new(workers)));
         let channels: Rc<RefCell<HashMap<Uuid, Sender<WorkerResponse>>>> =
             Rc::new(RefCell::new(HashMap::new()));
+        let started: Rc<RefCell<HashMap<Uuid, Sender<()>>>> = Rc::new(RefCell::new(HashMap::new()));
+
+        let slots = (0..workers.max(1))
+            .map(|slot| {
+                let on_message = Closure::wrap(Box::new({
+                    let pool = pool.clone();
+                    let channels = channels.clone();
+                    let started = started.clone();
+                    move |e: JsValue| {
+                        let data: MessageEvent = e.dyn_into().unwrap();
+                        let data: Uint8Array = data.data().dyn_into().unwrap();
+                        let data = data.to_vec();
+                        let data: WorkerResponse = bincode::deserialize(data.as_slice()).unwrap();
+
+                        pool.borrow_mut().answered(slot, data.idx);
+                        start(&pool, &started, slot);
+                        // requests given up on, replayed to a fresh worker or sent to every
+                        // worker and already answered by another are answered to no one
+                        if let Some(sender) = channels.borrow_mut().remove(&data.idx) {
+                            let _ = sender.send(data);
+                        }
+                    }
+                }) as Box<dyn FnMut(JsValue)>);
 
-        let on_message = Closure::wrap(Box::new({
-            let channels = channels.clone();
-            move |e: JsValue| {
-                let data: MessageEvent = e.dyn_into().unwrap();
-                let data: Uint8Array = data.data().dyn_into().unwrap();
-                let data = data.to_vec();
-                let data: WorkerResponse = bincode::deserialize(data.as_slice()).unwrap();
-
-                let sender = channels.borrow_mut().remove(&data.idx).unwrap();
-                sender.send(data).unwrap();
-            }
-        }) as Box<dyn FnMut(JsValue)>);
+                let worker: Worker = spawn.call0(&JsValue::NULL).unwrap().dyn_into().unwrap();
+                worker.set_onmessage(Some(on_message.as_ref().unchecked_ref()));
 
-        worker.set_onmessage(Some(on_message.as_ref().unchecked_ref()));
+                Slot {
+                    worker: RefCell::new(worker),
+                    on_message,
+                }
+            })
+            .collect();
 
         WorkerWrapper {
-            worker,
+            spawn,
+            slots: Rc::new(slots),
+            pool,
             channels,
-            on_message: Rc::new(on_message),
+            started,
+            session: None,
         }
     }
 
-    async fn make_request(&self, variant: WorkerRequestVariant) -> WorkerResponse {
+    /// A wrapper for the same workers whose requests can be cancelled together with `cancel`.
+    pub fn session(&self) -> Self {
+        WorkerWrapper {
+            session: Some(Rc::new(RefCell::new(HashSet::new()))),
+            ..self.clone()
+        }
+    }
+
+    /// Gives up on every request made through this session and not yet answered, replacing any
+    /// worker busy with one of them.
+    pub fn cancel(&self) {
+        if let Some(session) = &self.session {
+            let requests = session.borrow_mut().drain().collect::<Vec<_>>();
+            for idx in requests {
+                self.interrupt(idx);
+            }
+        }
+    }
+
+    async fn make_request(
+        &self,
+        variant: WorkerRequestVariant,
+        limit: Option<u64>,
+    ) -> WorkerResponse {
+        let idx = Uuid::new_v4();
+        let target = match self.pool.borrow().route(&variant) {
+            Ok(target) => target,
+            Err(error) => return WorkerResponse::failed(idx, error),
+        };
+
         let (sender, receiver) = channel();
-        let uuid = Uuid::new_v4();
-        self.channels.borrow_mut().insert(uuid.clone(), sender);
+        self.channels.borrow_mut().insert(idx, sender);
+
+        // the limit runs from when the worker starts on the request, not from when it is queued
+        let started = match (limit, target) {
+            (Some(_), Some(_)) => {
+                let (sender, receiver) = channel();
+                self.started.borrow_mut().insert(idx, sender);
+                Some(receiver)
+            }
+            _ => None,
+        };
+
+        let request = WorkerRequest {
+            idx,
+            variant,
+            limit,
+        };
+        let data = bincode::serialize(&request).unwrap();
+        let slots = self.pool.borrow_mut().send(&request, target, data.clone());
+        for slot in slots {
+            self.slots[slot].post(&data);
+            start(&self.pool, &self.started, slot);
+        }
 
-        let request = WorkerRequest { idx: uuid, variant };
-        let request = bincode::serialize(&request).unwrap();
-        self.worker
-            .post_message(&Uint8Array::from(request.as_slice()).into())
+        if let Some(session) = &self.session {
+            session.borrow_mut().insert(idx);
+        }
+
+        let response = match limit {
+            Some(limit) => match select(
+                receiver,
+                Box::pin(async move {
+                    if let Some(started) = started {
+                        let _ = started.await;
+                    }
+                    sleep(limit).await
+                }),
+            )
+            .await
+            {
+                Either::Left((response, _)) => response.ok(),
+                Either::Right(_) => {
+                    self.interrupt(idx);
+                    Some(WorkerResponse::interrupted(idx, Interruption::TimedOut))
+                }
+            },
+            None => receiver.await.ok(),
+        };
+
+        if let Some(session) = &self.session {
+            session.borrow_mut().remove(&idx);
+        }
+
+        // the sender is dropped where the request is given up on
+        response.unwrap_or_else(|| WorkerResponse::interrupted(idx, Interruption::Cancelled))
+    }
+
+    /// Gives up on the request `idx`, replacing each worker busy with it and having the others
+    /// it was sent to skip it.
+    fn interrupt(&self, idx: Uuid) {
+        if self.channels.borrow_mut().remove(&idx).is_none() {
+            return;
+        }
+        self.started.borrow_mut().remove(&idx);
+
+        let sent = self.pool.borrow_mut().interrupt(idx);
+        for (slot, running) in sent {
+            if running {
+                self.respawn(slot);
+                start(&self.pool, &self.started, slot);
+            } else {
+                let request = WorkerRequest {
+                    idx: Uuid::new_v4(),
+                    variant: WorkerRequestVariant::Cancel(idx),
+                    limit: None,
+                };
+                self.slots[slot].post(&bincode::serialize(&request).unwrap());
+            }
+        }
+    }
+
+    /// Terminates the worker `index` for a fresh one, which is brought to the same state and
+    /// sent the requests still waiting on an answer.
+    fn respawn(&self, index: usize) {
+        let slot = &self.slots[index];
+
+        let worker: Worker = self
+            .spawn
+            .call0(&JsValue::NULL)
+            .unwrap()
+            .dyn_into()
             .unwrap();
+        worker.set_onmessage(Some(slot.on_message.as_ref().unchecked_ref()));
+        replace(&mut *slot.worker.borrow_mut(), worker).terminate();
 
-        receiver.await.unwrap()
+        for request in self.pool.borrow().replay(index) {
+            slot.post(&request);
+        }
     }
 
     pub async fn check<T: Clone>(
@@ -133,23 +273,26 @@ impl WorkerWrapper {
     ) -> Result<(), CheckError<Option<T>>> {
         let mut annotations = HashMap::new();
         let resp = self
-            .make_request(WorkerRequestVariant::Check(
-                term.map_annotation(&mut |annotation| {
-                    annotation.map(|annotation| {
-                        let idx = annotations.len();
-                        annotations.insert(idx, annotation);
-                        idx as _
-                    })
-                }),
-                ty.map_annotation(&mut |annotation| {
-                    annotation.map(|annotation| {
-                        let idx = annotations.len();
-                        annotations.insert(idx, annotation);
-                        idx as _
-                    })
-                }),
-                temp_defs.map(|a| a.0.clone()),
-            ))
+            .make_request(
+                WorkerRequestVariant::Check(
+                    term.map_annotation(&mut |annotation| {
+                        annotation.map(|annotation| {
+                            let idx = annotations.len();
+                            annotations.insert(idx, annotation);
+                            idx as _
+                        })
+                    }),
+                    ty.map_annotation(&mut |annotation| {
+                        annotation.map(|annotation| {
+                            let idx = annotations.len();
+                            annotations.insert(idx, annotation);
+                            idx as _
+                        })
+                    }),
+                    temp_defs.map(|a| a.0.clone()),
+                ),
+                Some(CHECK_LIMIT),
+            )
             .await;
 
         for (idx, ty) in resp.inferred {
@@ -177,81 +320,151 @@ impl WorkerWrapper {
         })
     }
 
+    /// Searches for up to `budget` milliseconds for terms to fill the hole in `term` whose
+    /// annotation `is_hole` picks out.
+    pub async fn search<T>(
+        &self,
+        term: AnalysisTerm<Option<T>>,
+        ty: AnalysisTerm<Option<T>>,
+        is_hole: impl Fn(&T) -> bool,
+        budget: u64,
+    ) -> Vec<AnalysisTerm<()>> {
+        let (term, hole) = match label_hole(term, is_hole) {
+            Some(labelled) => labelled,
+            None => return vec![],
+        };
+
+        self.make_request(
+            WorkerRequestVariant::Search(term, ty.map_annotation(&mut |_| None), hole, budget),
+            Some(budget + SEARCH_GRACE),
+        )
+        .await
+        .solutions
+    }
+
+    /// Suggests what to fill the hole in `term` whose annotation `is_hole` picks out with,
+    /// starting with `prefix`.
+    pub async fn complete<T>(
+        &self,
+        term: AnalysisTerm<Option<T>>,
+        ty: AnalysisTerm<Option<T>>,
+        is_hole: impl Fn(&T) -> bool,
+        prefix: String,
+    ) -> Vec<Completion> {
+        let (term, hole) = match label_hole(term, is_hole) {
+            Some(labelled) => labelled,
+            None => return vec![],
+        };
+
+        self.make_request(
+            WorkerRequestVariant::Complete(term, ty.map_annotation(&mut |_| None), hole, prefix),
+            Some(CHECK_LIMIT),
+        )
+        .await
+        .completions
+    }
+
     pub async fn initialize(&self, defs: &DefWrapper) {
-        self.make_request(WorkerRequestVariant::Initialize(defs.clone().into()))
+        self.make_request(WorkerRequestVariant::Initialize(defs.clone().into()), None)
             .await
             .data
             .unwrap();
     }
 
     pub async fn register(&self, name: String, ty: Term<String>, term: Term<String>) {
-        self.make_request(WorkerRequestVariant::Register(name, ty, term))
+        self.make_request(WorkerRequestVariant::Register(name, ty, term), None)
             .await
             .data
             .unwrap()
     }
 
-    pub async fn evaluate(&self, term: Term<String>) -> Term<String> {
-        let data = self
-            .make_request(WorkerRequestVariant::Evaluate(term))
-            .await;
-        data.data.unwrap();
-        data.evaluated.unwrap()
+    pub async fn evaluate(&self, term: Term<String>) -> Result<Term<String>, Interruption> {
+        evaluated(
+            self.make_request(WorkerRequestVariant::Evaluate(term), Some(EVALUATE_LIMIT))
+                .await,
+        )
     }
 
-    pub async fn expand_evaluate(&self, term: AnalysisTerm<()>) -> Term<String> {
-        let data = self
-            .make_request(WorkerRequestVariant::ExpandEvaluate(term))
-            .await;
-        data.data.unwrap();
-        data.evaluated.unwrap()
+    pub async fn expand_evaluate(
+        &self,
+        term: AnalysisTerm<()>,
+    ) -> Result<Term<String>, Interruption> {
+        evaluated(
+            self.make_request(
+                WorkerRequestVariant::ExpandEvaluate(term),
+                Some(EVALUATE_LIMIT),
+            )
+            .await,
+        )
     }
 
     pub async fn register_temp(&self, defs: Vec<(String, Term<String>, Term<String>)>) -> TempDefs {
         let data = self
-            .make_request(WorkerRequestVariant::TempDefs(defs))
+            .make_request(WorkerRequestVariant::TempDefs(defs), None)
             .await;
         data.data.unwrap();
         TempDefs(data.id.unwrap())
     }
 
     pub async fn clear_temp(&self, defs: TempDefs) {
-        self.make_request(WorkerRequestVariant::ClearTempDefs(defs.0))
+        self.make_request(WorkerRequestVariant::ClearTempDefs(defs.0), None)
             .await
             .data
             .unwrap();
     }
 }
 
-#[derive(Serialize, Deserialize)]
-pub enum WorkerRequestVariant {
-    Check(
-        AnalysisTerm<Option<u64>>,
-        AnalysisTerm<Option<u64>>,
-        Option<Uuid>,
-    ),
-    Register(String, Term<String>, Term<String>),
-    Initialize(DefWrapperData),
-    Evaluate(Term<String>),
-    ExpandEvaluate(AnalysisTerm<()>),
-    TempDefs(Vec<(String, Term<String>, Term<String>)>),
-    ClearTempDefs(Uuid),
+/// `term` with each annotation numbered, and the number of the hole whose annotation `is_hole`
+/// picks out, if there is one.
+fn label_hole<T>(
+    term: AnalysisTerm<Option<T>>,
+    is_hole: impl Fn(&T) -> bool,
+) -> Option<(AnalysisTerm<Option<u64>>, u64)> {
+    let mut hole = None;
+    let mut next = 0;
+    let term = term.map_annotation(&mut |annotation| {
+        annotation.map(|annotation| {
+            let idx = next;
+            next += 1;
+            if is_hole(&annotation) {
+                hole = Some(idx);
+            }
+            idx
+        })
+    });
+
+    hole.map(|hole| (term, hole))
 }
 
-#[derive(Serialize, Deserialize)]
-pub struct WorkerRequest {
-    variant: WorkerRequestVariant,
-    idx: Uuid,
+fn evaluated(response: WorkerResponse) -> Result<Term<String>, Interruption> {
+    match response.data {
+        Err(CheckError::Interrupted(interruption)) => Err(interruption),
+        data => {
+            data.unwrap();
+            Ok(response.evaluated.unwrap())
+        }
+    }
 }
 
-#[derive(Serialize, Deserialize, Debug)]
-pub struct WorkerResponse {
-    idx: Uuid,
-    inferred: Vec<(u64, AnalysisTerm<Option<u64>>)>,
-    filled: Vec<(u64, AnalysisTerm<Option<u64>>)>,
-    evaluated: Option<Term<String>>,
-    data: Result<(), CheckError<Option<u64>>>,
-    id: Option<Uuid>,
+/// Resolves after `ms` milliseconds.
+async fn sleep(ms: u64) {
+    let promise = Promise::new(&mut |resolve, _| {
+        web_sys::window()
+            .unwrap()
+            .set_timeout_with_callback_and_timeout_and_arguments_0(&resolve, ms as i32)
+            .unwrap();
+    });
+    let _ = JsFuture::from(promise).await;
+}
+
+/// Resolves once the worker has taken in the messages already posted to it.
+async fn yield_to_messages(worker: &DedicatedWorkerGlobalScope) {
+    let promise = Promise::new(&mut |resolve, _| {
+        worker
+            .set_timeout_with_callback_and_timeout_and_arguments_0(&resolve, 0)
+            .unwrap();
+    });
+    let _ = JsFuture::from(promise).await;
 }
 
 pub async fn worker(event: MessageEvent) -> Result<(), JsValue> {
@@ -265,118 +478,19 @@ pub async fn worker(event: MessageEvent) -> Result<(), JsValue> {
 
     let data = event.data().dyn_into::<Uint8Array>().unwrap().to_vec();
     let data: WorkerRequest = bincode::deserialize(&data).unwrap();
-    let mut inferred = vec![];
-    let mut filled = vec![];
     let worker = js_sys::global()
         .dyn_into::<DedicatedWorkerGlobalScope>()
         .unwrap();
 
-    let mut evaluated = None;
-    let mut id = None;
-
-    let response = match data.variant {
-        WorkerRequestVariant::Check(term, ty, temp_defs) => {
-            let temp_id = temp_defs.clone();
-            let temp_defs =
-                temp_defs.map(|id| TEMP_DEFS.with(|defs| defs.borrow_mut().remove(&id).unwrap()));
-
-            let res = DEFS.with(|defs| {
-                CACHE.with(|cache| {
-                    let defs = defs.borrow();
-                    let defs = defs.as_ref().unwrap();
-                    let cache = &mut *cache.borrow_mut();
-                    let defs = MergeDefs(defs, &temp_defs);
-                    term.check_in(
-                        &ty,
-                        &defs,
-                        &mut |annotation, ty| {
-                            if let Some(annotation) = annotation {
-                                inferred.push((*annotation, ty.clone()))
-                            }
-                        },
-                        &mut |annotation, ty| {
-                            if let Some(annotation) = annotation {
-                                filled.push((*annotation, ty.clone()))
-                            }
-                        },
-                        cache,
-                    )?;
-                    term.is_stratified()?;
-                    if term.is_recursive_in(&defs) {
-                        Err(CheckError::Recursive)
-                    } else {
-                        Ok(())
-                    }
-                })
-            });
-
-            if let Some(temp_id) = temp_id {
-                TEMP_DEFS.with(move |defs| defs.borrow_mut().insert(temp_id, temp_defs.unwrap()));
-            }
-
-            res
-        }
-        WorkerRequestVariant::Initialize(data) => {
-            DEFS.with(|defs| {
-                *defs.borrow_mut() = Some(data.into());
-            });
-            Ok(())
-        }
-        WorkerRequestVariant::Register(name, ty, term) => {
-            DEFS.with(|defs| {
-                defs.borrow()
-                    .as_ref()
-                    .unwrap()
-                    .0
-                    .borrow_mut()
-                    .insert(name, (ty, term));
-            });
-            Ok(())
-        }
-        WorkerRequestVariant::Evaluate(term) => {
-            let e = DEFS.with(|defs| {
-                let defs = defs.borrow();
-                let defs = defs.as_ref().unwrap();
-                let evaluator = Inet(defs.clone());
-                evaluator.evaluate(term)
-            });
-            evaluated = Some(e.await.unwrap());
-            Ok(())
-        }
-        WorkerRequestVariant::ExpandEvaluate(term) => {
-            let term: Term<String> = term.into();
-            let e = DEFS.with(|defs| {
-                let defs = defs.borrow();
-                let defs = defs.as_ref().unwrap();
-                let evaluator = Inet(defs.clone());
-                evaluator.evaluate(term)
-            });
-            evaluated = Some(e.await.unwrap());
-            Ok(())
-        }
-        WorkerRequestVariant::TempDefs(temp_defs) => {
-            let uuid = Uuid::new_v4();
-            id = Some(uuid.clone());
-            TEMP_DEFS.with(|defs| {
-                defs.borrow_mut().insert(uuid, temp_defs);
-            });
-            Ok(())
-        }
-        WorkerRequestVariant::ClearTempDefs(uuid) => {
-            TEMP_DEFS.with(|defs| {
-                defs.borrow_mut().remove(&uuid);
-            });
-            Ok(())
-        }
-    };
+    let state = STATE.with(|state| state.clone());
+    state.receive(data);
 
-    let response = WorkerResponse {
-        data: response,
-        idx: data.idx,
-        evaluated,
-        inferred,
-        filled,
-        id,
+    // let the messages already posted arrive first, so that cancellations among them are applied
+    // before the next request is started
+    yield_to_messages(&worker).await;
+    let response = match state.next().await {
+        Some(response) => response,
+        None => return Ok(()),
     };
 
     let data = bincode::serialize(&response).unwrap();